@@ -0,0 +1,92 @@
+//! The cost of redrawing the decorations with `cargo bench --features headless`.
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use sctk_adwaita::{theme::ColorTheme, ButtonKind, FrameConfig, HeadlessFrame, PartId};
+use smithay_client_toolkit::reexports::csd_frame::WindowState;
+
+const WIDTH: u32 = 800;
+const HEIGHT: u32 = 600;
+
+fn config() -> FrameConfig {
+    FrameConfig::new(ColorTheme::light())
+}
+
+/// Every part drawn from scratch, with the shadow and the buttons rendered again.
+fn full_redraw(c: &mut Criterion) {
+    let state = WindowState::ACTIVATED;
+    for scale in 1..=3 {
+        c.bench_function(&format!("full redraw @{scale}x"), |b| {
+            b.iter(|| HeadlessFrame::new(config()).render_to_pixmaps(WIDTH, HEIGHT, scale, state))
+        });
+    }
+}
+
+/// The parts drawn again with the cached shadow and buttons, like on the focus change.
+fn cached_redraw(c: &mut Criterion) {
+    for scale in 1..=3 {
+        let mut frame = HeadlessFrame::new(config());
+        let mut active = false;
+        c.bench_function(&format!("cached redraw @{scale}x"), |b| {
+            b.iter(|| {
+                active = !active;
+                let mut state = WindowState::empty();
+                state.set(WindowState::ACTIVATED, active);
+                frame.render_to_pixmaps(WIDTH, HEIGHT, scale, state)
+            })
+        });
+    }
+}
+
+/// The shadow of the top edge generated for the new frame.
+fn shadow(c: &mut Criterion) {
+    let state = WindowState::ACTIVATED;
+    for scale in 1..=3 {
+        c.bench_function(&format!("shadow @{scale}x"), |b| {
+            b.iter_batched(
+                || HeadlessFrame::new(config()),
+                |mut frame| frame.render_part(PartId::Top, WIDTH, HEIGHT, scale, state),
+                BatchSize::SmallInput,
+            )
+        });
+    }
+}
+
+/// The headerbar with the title changed on every redraw.
+fn title(c: &mut Criterion) {
+    let state = WindowState::ACTIVATED;
+    for scale in 1..=3 {
+        let mut frame = HeadlessFrame::new(config());
+        let mut count = 0u32;
+        c.bench_function(&format!("title @{scale}x"), |b| {
+            b.iter(|| {
+                count = count.wrapping_add(1);
+                frame.set_title(format!("Document {count}"));
+                frame.render_part(PartId::Header, WIDTH, HEIGHT, scale, state)
+            })
+        });
+    }
+}
+
+/// The headerbar with the pointer moving between the buttons.
+fn hover(c: &mut Criterion) {
+    let state = WindowState::ACTIVATED;
+    let buttons = [
+        None,
+        Some(ButtonKind::Minimize),
+        Some(ButtonKind::Maximize),
+        Some(ButtonKind::Close),
+    ];
+    for scale in 1..=3 {
+        let mut frame = HeadlessFrame::new(config());
+        frame.set_title("Document");
+        let mut hovered = buttons.iter().cycle();
+        c.bench_function(&format!("hover @{scale}x"), |b| {
+            b.iter(|| {
+                frame.set_hovered(hovered.next().copied().flatten());
+                frame.render_part(PartId::Header, WIDTH, HEIGHT, scale, state)
+            })
+        });
+    }
+}
+
+criterion_group!(benches, full_redraw, cached_redraw, shadow, title, hover);
+criterion_main!(benches);
@@ -1,4 +1,12 @@
 // Based on https://github.com/Smithay/client-toolkit/blob/master/examples/themed_window.rs.
+//
+// The left click on the window contents starts and stops the animation, the right click switches
+// between the light, the dark and the high contrast theme.
+//
+// With `--features headless` the middle click saves the frame into `frame.png`, and
+// `cargo run --example window --features headless -- --screenshot <path>` saves the frame of the
+// default window without connecting to the compositor, to compare it with the earlier
+// screenshots.
 
 use std::sync::Arc;
 use std::time::Duration;
@@ -10,7 +18,7 @@ use smithay_client_toolkit::reexports::client::{
     Connection, Proxy, QueueHandle,
 };
 use smithay_client_toolkit::reexports::csd_frame::{
-    CursorIcon, DecorationsFrame, FrameAction, FrameClick, ResizeEdge,
+    CursorIcon, DecorationsFrame, FrameAction, FrameClick, ResizeEdge, WindowState,
 };
 use smithay_client_toolkit::reexports::protocols::xdg::shell::client::xdg_toplevel::ResizeEdge as XdgResizeEdge;
 use smithay_client_toolkit::{
@@ -40,9 +48,35 @@ use smithay_client_toolkit::{
     subcompositor::SubcompositorState,
 };
 
-use sctk_adwaita::{AdwaitaFrame, FrameConfig};
+use sctk_adwaita::{
+    theme::ColorTheme, AdwaitaFrame, FrameConfig, FrameGlobals, HeaderbarAction, ScrollAction,
+};
+
+/// The themes switched with the right click.
+const THEMES: [fn() -> ColorTheme; 3] = [
+    ColorTheme::light,
+    ColorTheme::dark,
+    ColorTheme::high_contrast,
+];
 
 fn main() {
+    let mut args = std::env::args().skip(1);
+    if args.next().as_deref() == Some("--screenshot") {
+        let path = args.next().unwrap_or_else(|| String::from("frame.png"));
+        #[cfg(feature = "headless")]
+        save_screenshot(
+            &path,
+            &FrameConfig::light(),
+            256,
+            256,
+            1,
+            WindowState::ACTIVATED,
+        );
+        #[cfg(not(feature = "headless"))]
+        eprintln!("Saving {path} requires `--features headless`");
+        return;
+    }
+
     let conn = Connection::connect_to_env().unwrap();
 
     let (globals, mut event_queue) = registry_queue_init(&conn).unwrap();
@@ -52,9 +86,9 @@ fn main() {
     let output_state = OutputState::new(&globals, &qh);
     let compositor_state =
         CompositorState::bind(&globals, &qh).expect("wl_compositor not available");
+    // The frame reports the missing subcompositor when it's created.
     let subcompositor_state =
-        SubcompositorState::bind(compositor_state.wl_compositor().clone(), &globals, &qh)
-            .expect("wl_subcompositor not available");
+        SubcompositorState::bind(compositor_state.wl_compositor().clone(), &globals, &qh).ok();
     let shm_state = Shm::bind(&globals, &qh).expect("wl_shm not available");
     let xdg_shell_state = XdgShell::bind(&globals, &qh).expect("xdg shell not available");
 
@@ -85,7 +119,7 @@ fn main() {
         seat_state,
         output_state,
         compositor_state: Arc::new(compositor_state),
-        subcompositor_state: Arc::new(subcompositor_state),
+        subcompositor_state: subcompositor_state.map(Arc::new),
         shm_state,
         _xdg_shell_state: xdg_shell_state,
 
@@ -98,6 +132,12 @@ fn main() {
         buffer: None,
         window,
         window_frame: None,
+        frame_config: FrameConfig::auto()
+            .scroll_action(ScrollAction::Maximize)
+            .globals(FrameGlobals::bind(&globals, &conn)),
+        theme: 0,
+        window_state: WindowState::empty(),
+        scale: 1,
         themed_pointer: None,
         set_cursor: false,
         cursor_icon: CursorIcon::Crosshair,
@@ -121,7 +161,7 @@ struct SimpleWindow {
     seat_state: SeatState,
     output_state: OutputState,
     compositor_state: Arc<CompositorState>,
-    subcompositor_state: Arc<SubcompositorState>,
+    subcompositor_state: Option<Arc<SubcompositorState>>,
     shm_state: Shm,
     _xdg_shell_state: XdgShell,
 
@@ -134,6 +174,11 @@ struct SimpleWindow {
     buffer: Option<Buffer>,
     window: Window,
     window_frame: Option<AdwaitaFrame<Self>>,
+    frame_config: FrameConfig,
+    /// The index of the theme in the [`THEMES`] switched to last.
+    theme: usize,
+    window_state: WindowState,
+    scale: u32,
     themed_pointer: Option<ThemedPointer>,
     set_cursor: bool,
     cursor_icon: CursorIcon,
@@ -147,10 +192,16 @@ impl CompositorHandler for SimpleWindow {
         surface: &wl_surface::WlSurface,
         new_factor: i32,
     ) {
+        let Some(frame) = self.window_frame.as_mut() else {
+            return;
+        };
+
         if self.window.wl_surface() == surface {
-            if let Some(frame) = self.window_frame.as_mut() {
-                frame.set_scaling_factor(new_factor as f64);
-            }
+            self.scale = new_factor as u32;
+            frame.set_scaling_factor(new_factor as f64);
+        } else {
+            // The window spans outputs with different scales.
+            frame.set_surface_scaling_factor(&surface.id(), new_factor as f64);
         }
     }
 
@@ -251,7 +302,7 @@ impl WindowHandler for SimpleWindow {
                     self.compositor_state.clone(),
                     self.subcompositor_state.clone(),
                     qh.clone(),
-                    FrameConfig::auto(),
+                    self.frame_config.clone(),
                 )
                 .expect("failed to create client side decorations frame.");
                 frame.set_title(self.title.clone());
@@ -263,6 +314,7 @@ impl WindowHandler for SimpleWindow {
 
             // Configure state before touching any resizing.
             window_frame.update_state(configure.state);
+            self.window_state = configure.state;
 
             // Configure the button state.
             window_frame.update_wm_capabilities(configure.capabilities);
@@ -428,15 +480,25 @@ impl PointerHandler for SimpleWindow {
                     serial,
                     time,
                 } => {
-                    let pressed = if matches!(event.kind, Press { .. }) {
-                        true
-                    } else {
-                        false
-                    };
+                    let pressed = matches!(event.kind, Press { .. });
                     if &event.surface != self.window.wl_surface() {
                         let click = match button {
                             0x110 => FrameClick::Normal,
                             0x111 => FrameClick::Alternate,
+                            0x112 => {
+                                let action = self
+                                    .window_frame
+                                    .as_mut()
+                                    .and_then(|frame| frame.on_middle_click(pressed));
+                                match action {
+                                    Some(HeaderbarAction::Frame(action)) => {
+                                        self.frame_action(pointer, serial, action)
+                                    }
+                                    Some(HeaderbarAction::Lower) => println!("Lower the window"),
+                                    _ => (),
+                                }
+                                continue;
+                            }
                             _ => continue,
                         };
 
@@ -447,7 +509,11 @@ impl PointerHandler for SimpleWindow {
                         }
                     } else if pressed {
                         println!("Press {:x} @ {:?}", button, event.position);
-                        self.shift = self.shift.xor(Some(0));
+                        match button {
+                            0x111 => self.switch_theme(),
+                            0x112 => self.screenshot(),
+                            _ => self.shift = self.shift.xor(Some(0)),
+                        }
                     }
                 }
                 Axis {
@@ -457,6 +523,12 @@ impl PointerHandler for SimpleWindow {
                 } => {
                     if &event.surface == self.window.wl_surface() {
                         println!("Scroll H:{horizontal:?}, V:{vertical:?}");
+                    } else if let Some(HeaderbarAction::Frame(action)) = self
+                        .window_frame
+                        .as_mut()
+                        .and_then(|frame| frame.on_scroll(vertical.absolute))
+                    {
+                        self.frame_action(pointer, 0, action);
                     }
                 }
             }
@@ -494,6 +566,58 @@ impl SimpleWindow {
     }
 }
 
+impl SimpleWindow {
+    /// Switch the frame to the next theme of the [`THEMES`].
+    fn switch_theme(&mut self) {
+        self.theme = (self.theme + 1) % THEMES.len();
+        self.frame_config =
+            FrameConfig::new(THEMES[self.theme]()).scroll_action(ScrollAction::Maximize);
+        if let Some(frame) = self.window_frame.as_mut() {
+            frame.set_config(self.frame_config.clone());
+        }
+    }
+
+    /// Save the frame in the current state of the window into `frame.png`.
+    fn screenshot(&self) {
+        #[cfg(feature = "headless")]
+        save_screenshot(
+            "frame.png",
+            &self.frame_config,
+            self.width.get(),
+            self.height.get(),
+            self.scale,
+            self.window_state,
+        );
+        #[cfg(not(feature = "headless"))]
+        println!(
+            "Saving the frame in {:?} requires `--features headless`",
+            self.window_state
+        );
+    }
+}
+
+/// Render the frame around the window contents of the `width` and `height` into the PNG at
+/// `path`.
+#[cfg(feature = "headless")]
+fn save_screenshot(
+    path: &str,
+    config: &FrameConfig,
+    width: u32,
+    height: u32,
+    scale: u32,
+    state: WindowState,
+) {
+    let Some(pixmap) = sctk_adwaita::render_to_pixmap(config, width, height, scale, state) else {
+        println!("The frame is hidden in {state:?}");
+        return;
+    };
+
+    match pixmap.save_png(path) {
+        Ok(()) => println!("Saved the frame into {path}"),
+        Err(err) => eprintln!("Failed to save {path}: {err}"),
+    }
+}
+
 impl ShmHandler for SimpleWindow {
     fn shm_state(&mut self) -> &mut Shm {
         &mut self.shm_state
@@ -572,11 +696,11 @@ impl SimpleWindow {
         }
 
         // Draw the decorations frame.
-        self.window_frame.as_mut().map(|frame| {
+        if let Some(frame) = self.window_frame.as_mut() {
             if frame.is_dirty() && !frame.is_hidden() {
                 frame.draw();
             }
-        });
+        }
 
         // Damage the entire window
         self.window.wl_surface().damage_buffer(
@@ -0,0 +1,174 @@
+//! Animations of the window state transitions.
+//!
+//! The animations are driven by the redraws: while any of them runs the frame stays dirty, so
+//! the application redraws it on every frame callback until the transition is over.
+use std::time::{Duration, Instant};
+
+/// The duration of the window state transitions, matching mutter.
+pub(crate) const TRANSITION_DURATION: Duration = Duration::from_millis(150);
+
+/// The value moving towards its target over [`TRANSITION_DURATION`].
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Animated {
+    from: f32,
+    to: f32,
+    /// The start of the transition, `None` when the value is at its target.
+    start: Option<Instant>,
+}
+
+impl Animated {
+    pub fn new(value: f32) -> Self {
+        Self {
+            from: value,
+            to: value,
+            start: None,
+        }
+    }
+
+    /// Move towards `to` starting from the current value, or jump to it without `animate`.
+    pub fn set(&mut self, to: f32, now: Instant, animate: bool) {
+        if to == self.to {
+            return;
+        }
+
+        self.from = if animate { self.value(now) } else { to };
+        self.to = to;
+        self.start = animate.then_some(now);
+    }
+
+    /// The value at the time `now`.
+    pub fn value(&self, now: Instant) -> f32 {
+        let progress = ease_out_cubic(self.progress(now));
+        self.from + (self.to - self.from) * progress
+    }
+
+    /// Jump to the target, e.g. when the window isn't shown.
+    pub fn finish(&mut self) {
+        self.from = self.to;
+        self.start = None;
+    }
+
+    /// Whether the value still moves at the time `now`.
+    pub fn is_running(&self, now: Instant) -> bool {
+        self.progress(now) < 1.
+    }
+
+    fn progress(&self, now: Instant) -> f32 {
+        let Some(start) = self.start else {
+            return 1.;
+        };
+
+        let elapsed = now.saturating_duration_since(start);
+        (elapsed.as_secs_f32() / TRANSITION_DURATION.as_secs_f32()).min(1.)
+    }
+}
+
+/// The duration of the flash of the headerbar, see [`Pulse`].
+pub(crate) const FLASH_DURATION: Duration = Duration::from_millis(600);
+
+/// The value rising from `0` to `1` and back twice over [`FLASH_DURATION`], resetting once over.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct Pulse {
+    /// The start of the pulse, `None` when it's over.
+    start: Option<Instant>,
+    /// Whether the value is eased, otherwise it stays at `1` until the pulse is over.
+    animate: bool,
+}
+
+impl Pulse {
+    /// Stop the pulse, e.g. when the window isn't shown.
+    pub fn stop(&mut self) {
+        self.start = None;
+    }
+
+    /// Start the pulse over at the time `now`.
+    pub fn start(&mut self, now: Instant, animate: bool) {
+        self.start = Some(now);
+        self.animate = animate;
+    }
+
+    /// The value at the time `now`, `0` once the pulse is over.
+    pub fn value(&self, now: Instant) -> f32 {
+        let Some(progress) = self.progress(now) else {
+            return 0.;
+        };
+
+        if self.animate {
+            (progress * 2. * std::f32::consts::PI).sin().powi(2)
+        } else {
+            1.
+        }
+    }
+
+    /// Whether the pulse still runs at the time `now`, forgetting its start once it's over.
+    ///
+    /// It's still running on the first update after it's over, so the frame is redrawn
+    /// without it.
+    pub fn update(&mut self, now: Instant) -> bool {
+        let running = self.start.is_some();
+        if self.progress(now).is_none() {
+            self.start = None;
+        }
+        running
+    }
+
+    fn progress(&self, now: Instant) -> Option<f32> {
+        let elapsed = now.saturating_duration_since(self.start?);
+        (elapsed < FLASH_DURATION).then(|| elapsed.as_secs_f32() / FLASH_DURATION.as_secs_f32())
+    }
+}
+
+fn ease_out_cubic(t: f32) -> f32 {
+    1. - (1. - t).powi(3)
+}
+
+#[test]
+fn animated_moves_towards_target() {
+    let start = Instant::now();
+    let mut value = Animated::new(0.);
+    value.set(1., start, true);
+
+    assert_eq!(value.value(start), 0.);
+    let middle = value.value(start + TRANSITION_DURATION / 2);
+    assert!(middle > 0.5 && middle < 1.);
+    assert!(value.is_running(start + TRANSITION_DURATION / 2));
+
+    assert_eq!(value.value(start + TRANSITION_DURATION), 1.);
+    assert!(!value.is_running(start + TRANSITION_DURATION));
+
+    // Reversing in the middle starts from the current value.
+    value.set(0., start + TRANSITION_DURATION / 2, true);
+    assert_eq!(value.value(start + TRANSITION_DURATION / 2), middle);
+
+    value.set(1., start, false);
+    assert_eq!(value.value(start), 1.);
+    assert!(!value.is_running(start));
+}
+
+#[test]
+fn pulse_resets_after_flash() {
+    let start = Instant::now();
+    let mut pulse = Pulse::default();
+    assert!(!pulse.update(start));
+
+    pulse.start(start, true);
+    assert_eq!(pulse.value(start), 0.);
+    assert!(pulse.value(start + FLASH_DURATION / 4) > 0.99);
+    assert!(pulse.update(start + FLASH_DURATION / 2));
+
+    assert_eq!(pulse.value(start + FLASH_DURATION), 0.);
+    assert!(pulse.update(start + FLASH_DURATION));
+    assert!(!pulse.update(start + FLASH_DURATION));
+    assert_eq!(pulse.value(start + FLASH_DURATION / 4), 0.);
+}
+
+#[test]
+fn finished_animation_jumps_to_target() {
+    let start = Instant::now();
+    let mut value = Animated::new(0.);
+    value.set(1., start, true);
+    value.finish();
+
+    assert_eq!(value.value(start), 1.);
+    assert!(!value.is_running(start));
+}
@@ -0,0 +1,177 @@
+//! Blurring the windows behind the translucent headerbar with the `org_kde_kwin_blur_manager`,
+//! see the `blur` feature.
+use smithay_client_toolkit::compositor::{CompositorState, Region};
+use smithay_client_toolkit::reexports::client::globals::GlobalList;
+use smithay_client_toolkit::reexports::client::protocol::wl_surface::WlSurface;
+use smithay_client_toolkit::reexports::client::{delegate_noop, Connection, QueueHandle};
+use wayland_protocols_plasma::blur::client::{
+    org_kde_kwin_blur::OrgKdeKwinBlur, org_kde_kwin_blur_manager::OrgKdeKwinBlurManager,
+};
+
+use crate::parts::Rect;
+
+/// The blur manager of KWin the header surface is registered with.
+///
+/// The objects live on their own queue, so the state of the application doesn't need to handle
+/// them. They have no events, so the queue is never dispatched.
+#[derive(Debug)]
+pub struct BlurManager {
+    qh: QueueHandle<BlurState>,
+    manager: OrgKdeKwinBlurManager,
+}
+
+impl BlurManager {
+    /// Bind the manager from the advertised `globals` on the `connection`, `None` when the
+    /// compositor doesn't support it.
+    pub fn bind(globals: &GlobalList, connection: &Connection) -> Option<Self> {
+        let qh = connection.new_event_queue().handle();
+        let manager = globals.bind(&qh, 1..=1, ()).ok()?;
+        Some(Self { qh, manager })
+    }
+
+    /// Blur behind the `rects` of the `surface` in its coordinates from its next commit, or stop
+    /// blurring it with `None`.
+    ///
+    /// Nothing is sent when the `rects` didn't change since the last update of the `blur`.
+    pub fn update(
+        &self,
+        blur: &mut Option<SurfaceBlur>,
+        surface: &WlSurface,
+        compositor: &CompositorState,
+        rects: Option<Vec<Rect>>,
+    ) {
+        let Some(rects) = rects else {
+            if blur.take().is_some() {
+                self.manager.unset(surface);
+            }
+            return;
+        };
+        if blur.as_ref().is_some_and(|blur| blur.rects == rects) {
+            return;
+        }
+
+        let Ok(region) = Region::new(compositor) else {
+            return;
+        };
+        for rect in &rects {
+            region.add(rect.x, rect.y, rect.width as i32, rect.height as i32);
+        }
+
+        // The object of the blur is kept while the blur is on, only its region is updated.
+        let blur = blur.get_or_insert_with(|| SurfaceBlur {
+            object: self.manager.create(surface, &self.qh, ()),
+            rects: Vec::new(),
+        });
+        blur.object.set_region(Some(region.wl_region()));
+        blur.object.commit();
+        blur.rects = rects;
+    }
+}
+
+/// The blur behind the surface, see [`BlurManager::update`].
+#[derive(Debug)]
+pub struct SurfaceBlur {
+    object: OrgKdeKwinBlur,
+    /// The blurred rectangles in the coordinates of the surface.
+    rects: Vec<Rect>,
+}
+
+impl Drop for SurfaceBlur {
+    fn drop(&mut self) {
+        self.object.release();
+    }
+}
+
+/// The rectangles of the header of the `width` and the `height` in logical points without the
+/// rounded top corners of the `radii` on the left and on the right.
+///
+/// The upright header is turned counterclockwise when it's placed along the left edge.
+pub fn header_rects(
+    width: u32,
+    height: u32,
+    (left, right): (u32, u32),
+    upright: bool,
+) -> Vec<Rect> {
+    let (length, thickness) = if upright {
+        (height, width)
+    } else {
+        (width, height)
+    };
+    let rects = [
+        Rect {
+            x: left as i32,
+            y: 0,
+            width: length.saturating_sub(left + right),
+            height: thickness,
+        },
+        Rect {
+            x: 0,
+            y: left as i32,
+            width: left,
+            height: thickness.saturating_sub(left),
+        },
+        Rect {
+            x: length.saturating_sub(right) as i32,
+            y: right as i32,
+            width: right,
+            height: thickness.saturating_sub(right),
+        },
+    ];
+
+    rects
+        .into_iter()
+        .filter(|rect| rect.width > 0 && rect.height > 0)
+        .map(|rect| {
+            if upright {
+                Rect {
+                    x: rect.y,
+                    y: length as i32 - rect.x - rect.width as i32,
+                    width: rect.height,
+                    height: rect.width,
+                }
+            } else {
+                rect
+            }
+        })
+        .collect()
+}
+
+#[derive(Debug)]
+struct BlurState;
+
+delegate_noop!(BlurState: ignore OrgKdeKwinBlurManager);
+delegate_noop!(BlurState: ignore OrgKdeKwinBlur);
+
+#[test]
+fn header_rects_skip_rounded_corners() {
+    let rects = header_rects(100, 40, (8, 0), false);
+    assert_eq!(
+        rects,
+        [
+            Rect {
+                x: 8,
+                y: 0,
+                width: 92,
+                height: 40
+            },
+            Rect {
+                x: 0,
+                y: 8,
+                width: 8,
+                height: 32
+            },
+        ]
+    );
+
+    // Turned counterclockwise, the top left corner ends up at the bottom left.
+    let rects = header_rects(40, 100, (8, 0), true);
+    assert_eq!(
+        rects[1],
+        Rect {
+            x: 8,
+            y: 92,
+            width: 32,
+            height: 8
+        }
+    );
+}
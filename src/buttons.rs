@@ -1,20 +1,79 @@
 use log::{debug, warn};
-use smithay_client_toolkit::reexports::csd_frame::{WindowManagerCapabilities, WindowState};
-use tiny_skia::{FillRule, PathBuilder, PixmapMut, Rect, Stroke, Transform};
+use smithay_client_toolkit::reexports::csd_frame::{
+    FrameAction, WindowManagerCapabilities, WindowState,
+};
+use std::{collections::HashMap, fmt, sync::Arc};
 
-use crate::{theme::ColorMap, Location, SkiaResult};
+use tiny_skia::{
+    FillRule, FilterQuality, Mask, Path, PathBuilder, Pixmap, PixmapMut, PixmapPaint, Rect, Stroke,
+    Transform,
+};
+
+use crate::{
+    icon::{self, Icon, IconSource, ICON_SIZE},
+    parts,
+    theme::{ColorMap, HEADER_SIZE},
+    Location, SkiaResult,
+};
 
 /// The size of the button on the header bar in logical points.
 const BUTTON_SIZE: f32 = 24.;
 const BUTTON_MARGIN: f32 = 5.;
 const BUTTON_SPACING: f32 = 13.;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub(crate) struct Buttons {
     // Sorted by order vec of buttons for the left and right sides
     buttons_left: Vec<Button>,
     buttons_right: Vec<Button>,
     layout_config: Option<(String, String)>,
+    /// The button focused with the keyboard.
+    focused: Option<ButtonKind>,
+    /// The buttons the application allows to show.
+    allowed: ButtonSet,
+    supports_maximize: bool,
+    supports_minimize: bool,
+    /// The width and the margin of the last arrangement, to rearrange the rebuilt buttons.
+    arrangement: Option<(u32, f32)>,
+    /// The buttons dropped by the last arrangement, since the header is too narrow for them.
+    collapsed: Vec<ButtonKind>,
+    /// The margin above the buttons, centering them in the headerbar.
+    margin_v: f32,
+    /// The buttons added by the application, placed inwards of the window controls.
+    custom: Vec<CustomButton>,
+    /// The symbolic icons loaded from the icon theme by their names, `None` when the built-in
+    /// icons are drawn.
+    themed_icons: Option<HashMap<&'static str, Option<Path>>>,
+    /// The glyphs of the buttons without the custom or the themed icons.
+    icons: ButtonIcons,
+    /// The rendered buttons, reused until their colors change.
+    cache: HashMap<CacheKey, Pixmap>,
+    /// The colors of the cached buttons of the inactive and the active window.
+    cache_colors: [Option<ColorMap>; 2],
+}
+
+/// The state the cached button was rendered in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct CacheKey {
+    kind: ButtonKind,
+    hovered: bool,
+    pressed: bool,
+    focused: bool,
+    resizable: bool,
+    maximized: bool,
+    outlined: bool,
+    active: bool,
+    /// The bits of the scale.
+    scale: u32,
+    /// The subpixel position of the button in 1/64 of the pixel.
+    offset: (u8, u8),
+}
+
+#[derive(Debug, Clone)]
+struct CustomButton {
+    id: u32,
+    side: Side,
+    icon: Icon,
 }
 
 type ButtonLayout = (Vec<Button>, Vec<Button>);
@@ -27,6 +86,18 @@ impl Default for Buttons {
             buttons_left,
             buttons_right,
             layout_config: None,
+            focused: None,
+            allowed: ButtonSet::ALL,
+            supports_maximize: true,
+            supports_minimize: true,
+            arrangement: None,
+            collapsed: Vec::new(),
+            margin_v: BUTTON_MARGIN,
+            custom: Vec::new(),
+            themed_icons: None,
+            icons: ButtonIcons::default(),
+            cache: HashMap::new(),
+            cache_colors: Default::default(),
         }
     }
 }
@@ -38,42 +109,118 @@ impl Buttons {
                 buttons_left,
                 buttons_right,
                 layout_config,
+                ..Self::default()
             },
             _ => Self::default(),
         }
     }
 
+    /// Center the buttons in the headerbar of the `height`.
+    pub fn set_header_height(&mut self, height: u32) {
+        self.margin_v = BUTTON_MARGIN + height.saturating_sub(HEADER_SIZE) as f32 / 2.;
+        if let Some((width, margin_h)) = self.arrangement {
+            self.arrange(width, margin_h);
+        }
+    }
+
     /// Rearrange the buttons with the new width.
+    ///
+    /// The buttons the header is too narrow for are dropped: the custom ones first, then
+    /// minimize, then maximize. The close button is always kept.
     pub fn arrange(&mut self, width: u32, margin_h: f32) {
+        self.arrangement = Some((width, margin_h));
+        let margin_v = self.margin_v;
+
+        self.collapsed.clear();
+        let droppable: Vec<_> = self
+            .custom
+            .iter()
+            .rev()
+            .map(|custom| ButtonKind::Custom(custom.id))
+            .chain([ButtonKind::Minimize, ButtonKind::Maximize])
+            .filter(|kind| self.visual_order().any(|button| button.kind == *kind))
+            .collect();
+        for kind in droppable {
+            if self.shown_width(margin_h) <= width as f32 {
+                break;
+            }
+            self.collapsed.push(kind);
+        }
+        if self
+            .focused
+            .is_some_and(|kind| self.collapsed.contains(&kind))
+        {
+            self.focused = None;
+        }
+
         let mut left_x = BUTTON_MARGIN + margin_h;
         let mut right_x = width as f32 - BUTTON_MARGIN;
 
-        for button in &mut self.buttons_left {
+        let collapsed = &self.collapsed;
+        let shown = |button: &&mut Button| !collapsed.contains(&button.kind);
+        for button in self.buttons_left.iter_mut().filter(shown) {
             button.offset = left_x;
+            button.offset_y = margin_v;
 
             // Add the button size plus spacing
             left_x += BUTTON_SIZE + BUTTON_SPACING;
         }
 
-        for button in &mut self.buttons_right {
+        for button in self.buttons_right.iter_mut().filter(shown) {
             // Subtract the button size.
             right_x -= BUTTON_SIZE;
 
-            // Update it
-            button.offset = right_x;
+            // Update it, without overlapping the left edge when even the close button
+            // doesn't fit.
+            button.offset = right_x.max(BUTTON_MARGIN + margin_h);
+            button.offset_y = margin_v;
 
             // Subtract spacing for the next button.
             right_x -= BUTTON_SPACING;
         }
     }
 
+    /// The minimal width of the header in logical points, with the buttons [`Self::arrange`]
+    /// drops in the narrow headers left out.
+    ///
+    /// Doesn't depend on the current arrangement, only the close button is counted.
+    pub fn minimum_width(&self, margin_h: f32) -> f32 {
+        self.buttons_width(margin_h, |button| button.kind == ButtonKind::Close)
+    }
+
+    /// The width of the header in logical points before the shown buttons on the left and on
+    /// the right collide.
+    fn shown_width(&self, margin_h: f32) -> f32 {
+        self.buttons_width(margin_h, |button| !self.collapsed.contains(&button.kind))
+    }
+
+    /// The width of the header in logical points before the `counted` buttons on the left and
+    /// on the right collide.
+    fn buttons_width(&self, margin_h: f32, counted: impl Fn(&Button) -> bool) -> f32 {
+        let side_width = |buttons: &[Button]| match buttons.iter().filter(|b| counted(b)).count() {
+            0 => 0.,
+            len => len as f32 * BUTTON_SIZE + (len - 1) as f32 * BUTTON_SPACING,
+        };
+        let (left_width, right_width) = (
+            side_width(&self.buttons_left),
+            side_width(&self.buttons_right),
+        );
+
+        let gap = if left_width == 0. || right_width == 0. {
+            0.
+        } else {
+            BUTTON_SPACING
+        };
+
+        BUTTON_MARGIN + margin_h + left_width + gap + right_width + BUTTON_MARGIN
+    }
+
     /// Find the coordinate of the button.
     pub fn find_button(&self, x: f64, y: f64) -> Location {
         let x = x as f32;
         let y = y as f32;
-        let buttons = self.buttons_left.iter().chain(self.buttons_right.iter());
 
-        for button in buttons {
+        for button in self.visual_order() {
             if button.contains(x, y) {
                 return Location::Button(button.kind);
             }
@@ -89,11 +236,77 @@ impl Buttons {
         self.update_buttons(supports_maximize, supports_minimize);
     }
 
+    /// Add the button with the `icon` on the `side` of the headerbar, replacing the custom
+    /// button with the same `id`.
+    pub fn add_custom(&mut self, id: u32, icon: IconSource, side: Side) {
+        let button = CustomButton {
+            id,
+            side,
+            icon: Icon::new(icon),
+        };
+        match self.custom.iter_mut().find(|button| button.id == id) {
+            Some(existing) => *existing = button,
+            None => self.custom.push(button),
+        }
+        self.update_buttons(self.supports_maximize, self.supports_minimize);
+    }
+
+    /// Remove the custom button, returns `false` when there's no button with the `id`.
+    pub fn remove_custom(&mut self, id: u32) -> bool {
+        let len = self.custom.len();
+        self.custom.retain(|button| button.id != id);
+        let removed = self.custom.len() != len;
+        if removed {
+            self.update_buttons(self.supports_maximize, self.supports_minimize);
+        }
+        removed
+    }
+
+    /// The `button-layout` sides the buttons are ordered with, `None` for the default layout.
+    pub fn layout_config(&self) -> Option<&(String, String)> {
+        self.layout_config.as_ref()
+    }
+
+    /// Order the buttons with the `button-layout` sides, `None` for the default layout.
+    pub fn set_layout_config(&mut self, layout_config: Option<(String, String)>) {
+        self.layout_config = layout_config;
+        self.update_buttons(self.supports_maximize, self.supports_minimize);
+    }
+
+    /// Draw the symbolic icons from the icon theme instead of the built-in ones, reloading
+    /// them when enabled.
+    pub fn set_themed_icons(&mut self, enabled: bool) {
+        self.themed_icons = enabled.then(HashMap::new);
+        self.cache.clear();
+    }
+
+    /// Draw the glyphs of the buttons with the `icons`.
+    pub fn set_icons(&mut self, icons: ButtonIcons) {
+        self.icons = icons;
+        self.cache.clear();
+    }
+
+    /// Show only the `allowed` buttons, regardless of the window manager capabilities.
+    pub fn set_allowed(&mut self, allowed: ButtonSet) {
+        self.allowed = allowed;
+        self.update_buttons(self.supports_maximize, self.supports_minimize);
+    }
+
     pub fn update_buttons(&mut self, supports_maximize: bool, supports_minimize: bool) {
-        let is_supported = |button: &Button| match button.kind {
-            ButtonKind::Close => true,
-            ButtonKind::Maximize => supports_maximize,
-            ButtonKind::Minimize => supports_minimize,
+        // The custom buttons might have changed.
+        self.cache.clear();
+        self.supports_maximize = supports_maximize;
+        self.supports_minimize = supports_minimize;
+
+        let allowed = self.allowed;
+        let is_supported = |button: &Button| {
+            allowed.contains(button.kind)
+                && match button.kind {
+                    ButtonKind::Close => true,
+                    ButtonKind::Maximize => supports_maximize,
+                    ButtonKind::Minimize => supports_minimize,
+                    ButtonKind::Custom(_) => true,
+                }
         };
 
         let (buttons_left, buttons_right) =
@@ -102,48 +315,211 @@ impl Buttons {
 
         self.buttons_left = buttons_left.into_iter().filter(is_supported).collect();
         self.buttons_right = buttons_right.into_iter().filter(is_supported).collect();
+
+        for custom in &self.custom {
+            let button = Button::new(ButtonKind::Custom(custom.id));
+            match custom.side {
+                Side::Left => self.buttons_left.push(button),
+                Side::Right => self.buttons_right.push(button),
+            }
+        }
+
+        // Drop the focus if the focused button is gone.
+        if self
+            .focused
+            .is_some_and(|kind| !self.visual_order().any(|button| button.kind == kind))
+        {
+            self.focused = None;
+        }
+
+        if let Some((width, margin_h)) = self.arrangement {
+            self.arrange(width, margin_h);
+        }
+    }
+
+    /// The buttons of the side not dropped by the last arrangement.
+    fn shown<'a>(&'a self, buttons: &'a [Button]) -> impl DoubleEndedIterator<Item = &'a Button> {
+        buttons
+            .iter()
+            .filter(|button| !self.collapsed.contains(&button.kind))
+    }
+
+    /// The shown buttons in the order they appear on screen, from left to right.
+    fn visual_order(&self) -> impl DoubleEndedIterator<Item = &Button> {
+        self.shown(&self.buttons_left)
+            .chain(self.shown(&self.buttons_right).rev())
+    }
+
+    pub fn focused(&self) -> Option<ButtonKind> {
+        self.focused
+    }
+
+    /// Move the focus to the next button, wrapping around at the end.
+    pub fn focus_next(&mut self) -> Option<ButtonKind> {
+        let order: Vec<_> = self.visual_order().map(|button| button.kind).collect();
+        self.focused = Self::cycle(&order, self.focused);
+        self.focused
+    }
+
+    /// Move the focus to the previous button, wrapping around at the start.
+    pub fn focus_prev(&mut self) -> Option<ButtonKind> {
+        let order: Vec<_> = self
+            .visual_order()
+            .rev()
+            .map(|button| button.kind)
+            .collect();
+        self.focused = Self::cycle(&order, self.focused);
+        self.focused
+    }
+
+    pub fn clear_focus(&mut self) {
+        self.focused = None;
+    }
+
+    fn cycle(order: &[ButtonKind], current: Option<ButtonKind>) -> Option<ButtonKind> {
+        let next = match current.and_then(|kind| order.iter().position(|k| *k == kind)) {
+            Some(pos) => pos + 1,
+            None => 0,
+        };
+
+        order.get(next).or_else(|| order.first()).copied()
+    }
+
+    /// The buttons with their rectangles relative to the header.
+    pub fn rects(&self) -> impl Iterator<Item = (ButtonKind, parts::Rect)> + '_ {
+        self.visual_order()
+            .map(|button| (button.kind, button.rect()))
     }
 
     pub fn right_buttons_start_x(&self) -> Option<f32> {
-        self.buttons_right.last().map(|button| button.x())
+        self.shown(&self.buttons_right)
+            .next_back()
+            .map(|button| button.x())
     }
 
     pub fn left_buttons_end_x(&self) -> Option<f32> {
-        self.buttons_left.last().map(|button| button.end_x())
+        self.shown(&self.buttons_left)
+            .next_back()
+            .map(|button| button.end_x())
     }
 
     #[allow(clippy::too_many_arguments)]
     pub fn draw(
-        &self,
+        &mut self,
         start_x: f32,
         end_x: f32,
         scale: f32,
         colors: &ColorMap,
-        mouse_location: Location,
+        hovered: &[ButtonKind],
+        pressed: &[ButtonKind],
         pixmap: &mut PixmapMut,
         resizable: bool,
         state: &WindowState,
+        outlined: bool,
     ) {
+        let active = state.contains(WindowState::ACTIVATED);
+        let cache_colors = &mut self.cache_colors[active as usize];
+        if cache_colors.as_ref() != Some(colors) {
+            *cache_colors = Some(colors.clone());
+            self.cache.retain(|key, _| key.active != active);
+        }
+
         let left_buttons_right_limit =
             self.right_buttons_start_x().unwrap_or(end_x).min(end_x) - BUTTON_SPACING;
         let buttons_left = self.buttons_left.iter().map(|x| (x, Side::Left));
         let buttons_right = self.buttons_right.iter().map(|x| (x, Side::Right));
 
         for (button, side) in buttons_left.chain(buttons_right) {
+            if self.collapsed.contains(&button.kind) {
+                continue;
+            }
+
             let is_visible = button.x() > start_x && button.end_x() < end_x
                 // If we have buttons from both sides and they overlap, prefer the right side
                 && (side == Side::Right || button.end_x() < left_buttons_right_limit);
 
-            if is_visible {
-                button.draw(scale, colors, mouse_location, pixmap, resizable, state);
+            if !is_visible {
+                continue;
+            }
+
+            // The button with the focus ring and the antialiasing around it, in pixels.
+            let x = button.center_x() * scale;
+            let y = button.center_y() * scale;
+            let extent = button.radius() * scale + 2. * scale + 1.;
+            let (left, top) = ((x - extent).floor(), (y - extent).floor());
+            let subpixel = |position: f32| ((position - extent).fract() * 64.) as u8;
+
+            let key = CacheKey {
+                kind: button.kind,
+                hovered: hovered.contains(&button.kind),
+                pressed: pressed.contains(&button.kind),
+                focused: self.focused == Some(button.kind),
+                resizable,
+                maximized: state.contains(WindowState::MAXIMIZED),
+                outlined,
+                active,
+                scale: scale.to_bits(),
+                offset: (subpixel(x), subpixel(y)),
+            };
+
+            if !self.cache.contains_key(&key) {
+                let icon = match button.kind {
+                    ButtonKind::Custom(id) => self
+                        .custom
+                        .iter_mut()
+                        .find(|custom| custom.id == id)
+                        .and_then(|custom| custom.icon.pixmap(scale as u32))
+                        .map(ButtonIcon::Pixmap),
+                    kind => self
+                        .themed_icons
+                        .as_mut()
+                        .zip(kind.symbolic_icon_name(state))
+                        .and_then(|(icons, name)| {
+                            icons
+                                .entry(name)
+                                .or_insert_with(|| icon::symbolic(name))
+                                .as_ref()
+                        })
+                        .map(ButtonIcon::Symbolic),
+                };
+
+                let size = (2. * extent).ceil() as u32 + 1;
+                let Some(mut rendered) = Pixmap::new(size, size) else {
+                    continue;
+                };
+                button.draw(
+                    x - left,
+                    y - top,
+                    scale,
+                    colors,
+                    key.hovered,
+                    key.pressed,
+                    key.focused,
+                    icon,
+                    &*self.icons.0,
+                    &mut rendered.as_mut(),
+                    resizable,
+                    state,
+                    outlined,
+                );
+                self.cache.insert(key, rendered);
+            }
+
+            if let Some(rendered) = self.cache.get(&key) {
+                pixmap.draw_pixmap(
+                    left as i32,
+                    top as i32,
+                    rendered.as_ref(),
+                    &PixmapPaint::default(),
+                    Transform::identity(),
+                    None,
+                );
             }
         }
     }
 
     fn parse_button_layout(sides: Option<(String, String)>) -> Option<ButtonLayout> {
-        let Some((left_side, right_side)) = sides else {
-            return None;
-        };
+        let (left_side, right_side) = sides?;
 
         let buttons_left = Buttons::parse_button_layout_side(left_side, Side::Left);
         let buttons_right = Buttons::parse_button_layout_side(right_side, Side::Right);
@@ -201,13 +577,19 @@ impl Buttons {
 pub(crate) struct Button {
     /// The button offset into the header bar canvas.
     offset: f32,
+    /// The vertical offset of the button into the header bar canvas.
+    offset_y: f32,
     /// The kind of the button.
     kind: ButtonKind,
 }
 
 impl Button {
     pub fn new(kind: ButtonKind) -> Self {
-        Self { offset: 0., kind }
+        Self {
+            offset: 0.,
+            offset_y: BUTTON_MARGIN,
+            kind,
+        }
     }
 
     pub fn radius(&self) -> f32 {
@@ -223,40 +605,54 @@ impl Button {
     }
 
     pub fn center_y(&self) -> f32 {
-        BUTTON_MARGIN + self.radius()
+        self.offset_y + self.radius()
     }
 
     pub fn end_x(&self) -> f32 {
         self.offset + BUTTON_SIZE
     }
 
+    /// The hit box of the button, relative to the header.
+    pub fn rect(&self) -> parts::Rect {
+        parts::Rect {
+            x: self.offset.round() as i32,
+            y: self.offset_y.round() as i32,
+            width: BUTTON_SIZE as u32,
+            height: BUTTON_SIZE as u32,
+        }
+    }
+
     fn contains(&self, x: f32, y: f32) -> bool {
         x > self.offset
             && x < self.offset + BUTTON_SIZE
-            && y > BUTTON_MARGIN
-            && y < BUTTON_MARGIN + BUTTON_SIZE
+            && y > self.offset_y
+            && y < self.offset_y + BUTTON_SIZE
     }
 
+    /// Draw the button centered at `x` and `y` in pixels.
+    #[allow(clippy::too_many_arguments)]
     pub fn draw(
         &self,
+        x: f32,
+        y: f32,
         scale: f32,
         colors: &ColorMap,
-        mouse_location: Location,
+        hovered: bool,
+        pressed: bool,
+        focused: bool,
+        icon: Option<ButtonIcon<'_>>,
+        icons: &dyn ButtonIconProvider,
         pixmap: &mut PixmapMut,
         resizable: bool,
         state: &WindowState,
+        outlined: bool,
     ) -> SkiaResult {
-        let button_bg = if mouse_location == Location::Button(self.kind)
-            && (resizable || self.kind != ButtonKind::Maximize)
-        {
-            colors.button_hover_paint()
-        } else {
-            colors.button_idle_paint()
+        let button_bg = match resizable || self.kind != ButtonKind::Maximize {
+            true if pressed => colors.button_pressed_paint(),
+            true if hovered => colors.button_hover_paint(),
+            _ => colors.button_idle_paint(),
         };
 
-        // Convert to pixels.
-        let x = self.center_x() * scale;
-        let y = self.center_y() * scale;
         let radius = self.radius() * scale;
 
         // Draw the button background.
@@ -269,12 +665,123 @@ impl Button {
             None,
         );
 
+        // Outline the button for the high contrast.
+        if outlined {
+            let mut outline_paint = colors.button_icon_paint();
+            outline_paint.anti_alias = true;
+            pixmap.stroke_path(
+                &circle,
+                &outline_paint,
+                &Stroke {
+                    width: scale,
+                    ..Default::default()
+                },
+                Transform::identity(),
+                None,
+            );
+        }
+
+        // Draw the keyboard focus ring around the button.
+        if focused {
+            let ring = PathBuilder::from_circle(x, y, radius + scale)?;
+            pixmap.stroke_path(
+                &ring,
+                &colors.focus_ring_paint(),
+                &Stroke {
+                    width: 2.0 * scale,
+                    ..Default::default()
+                },
+                Transform::identity(),
+                None,
+            );
+        }
+
+        let size = ICON_SIZE as f32 * scale;
+        let icon_transform = |width: f32, height: f32| {
+            Transform::from_scale(size / width, size / height)
+                .post_translate((x - size / 2.).round(), (y - size / 2.).round())
+        };
+        match icon {
+            Some(ButtonIcon::Pixmap(icon)) => {
+                let paint = PixmapPaint {
+                    quality: FilterQuality::Bilinear,
+                    ..Default::default()
+                };
+                let transform = icon_transform(icon.width() as f32, icon.height() as f32);
+                pixmap.draw_pixmap(0, 0, icon.as_ref(), &paint, transform, None);
+                return Some(());
+            }
+            Some(ButtonIcon::Symbolic(path)) => {
+                let mut paint = colors.button_icon_paint();
+                paint.anti_alias = true;
+                let transform = icon_transform(1., 1.);
+                pixmap.fill_path(path, &paint, FillRule::Winding, transform, None);
+                return Some(());
+            }
+            None => (),
+        }
+
+        let rect = Rect::from_xywh(x - size / 2., y - size / 2., size, size)?;
+        match self.kind {
+            // The icon failed to load.
+            ButtonKind::Custom(_) => None,
+            kind => icons.draw(
+                kind,
+                state.contains(WindowState::MAXIMIZED),
+                pixmap,
+                rect,
+                scale,
+                colors,
+            ),
+        }
+    }
+}
+
+/// Draws the glyphs of the titlebar buttons over their backgrounds, e.g. to follow another
+/// icon style, see [`FrameConfig::button_icons`].
+///
+/// The icons of the custom buttons and the symbolic icons from the icon theme are drawn
+/// instead when they're set.
+///
+/// [`FrameConfig::button_icons`]: crate::FrameConfig::button_icons
+pub trait ButtonIconProvider {
+    /// Draw the glyph of the button of the `kind` into the `rect` of the icon in pixels, with
+    /// the [`ColorMap::button_icon`] of the window state usually.
+    ///
+    /// The `toggled` buttons show their other state, like the restore glyph of the maximize
+    /// button of the maximized window. Returns `None` when the glyph can't be drawn.
+    fn draw(
+        &self,
+        kind: ButtonKind,
+        toggled: bool,
+        pixmap: &mut PixmapMut,
+        rect: Rect,
+        scale: f32,
+        colors: &ColorMap,
+    ) -> Option<()>;
+}
+
+/// The built-in Adwaita glyphs of the buttons, the default [`ButtonIconProvider`].
+#[derive(Debug, Default, Clone, Copy)]
+pub struct AdwaitaButtonIcons;
+
+impl ButtonIconProvider for AdwaitaButtonIcons {
+    fn draw(
+        &self,
+        kind: ButtonKind,
+        toggled: bool,
+        pixmap: &mut PixmapMut,
+        rect: Rect,
+        scale: f32,
+        colors: &ColorMap,
+    ) -> Option<()> {
+        let (x, y) = (rect.x() + rect.width() / 2., rect.y() + rect.height() / 2.);
         let mut button_icon_paint = colors.button_icon_paint();
         // Do AA only for diagonal lines.
-        button_icon_paint.anti_alias = self.kind == ButtonKind::Close;
+        button_icon_paint.anti_alias = kind == ButtonKind::Close;
 
         // Draw the icon.
-        match self.kind {
+        match kind {
             ButtonKind::Close => {
                 let x_icon = {
                     let size = 3.5 * scale;
@@ -316,28 +823,56 @@ impl Button {
                     None,
                 );
             }
+            ButtonKind::Maximize if toggled => {
+                // The front window over the bottom left of the back one, like the
+                // `window-restore-symbolic` icon.
+                let size = 8.0 * scale;
+                let offset = 2.0 * scale;
+                let (left, top) = (x - size / 2., y - size / 2.);
+                let front = Rect::from_xywh(left, top + offset, size - offset, size - offset)?;
+                let back = Rect::from_xywh(left + offset, top, size - offset, size - offset)?;
+                let window = |rect: Rect| {
+                    let mut pb = PathBuilder::new();
+                    crate::push_rounded_rect(&mut pb, rect, [scale; 4]);
+                    pb.finish()
+                };
+                let stroke = Stroke {
+                    width: 1.0 * scale,
+                    ..Default::default()
+                };
+                button_icon_paint.anti_alias = true;
+
+                // Hide the back window behind the front one with its stroke.
+                let mut mask = Mask::new(pixmap.width(), pixmap.height())?;
+                mask.fill_path(
+                    &window(front.outset(scale, scale)?)?,
+                    FillRule::Winding,
+                    true,
+                    Transform::identity(),
+                );
+                mask.invert();
+
+                pixmap.stroke_path(
+                    &window(back)?,
+                    &button_icon_paint,
+                    &stroke,
+                    Transform::identity(),
+                    Some(&mask),
+                );
+                pixmap.stroke_path(
+                    &window(front)?,
+                    &button_icon_paint,
+                    &stroke,
+                    Transform::identity(),
+                    None,
+                );
+            }
             ButtonKind::Maximize => {
                 let path2 = {
                     let size = 8.0 * scale;
                     let hsize = size / 2.0;
                     let mut pb = PathBuilder::new();
-
-                    let x = x - hsize;
-                    let y = y - hsize;
-                    if state.contains(WindowState::MAXIMIZED) {
-                        let offset = 2.0 * scale;
-                        if let Some(rect) =
-                            Rect::from_xywh(x, y + offset, size - offset, size - offset)
-                        {
-                            pb.push_rect(rect);
-                            pb.move_to(rect.left() + offset, rect.top() - offset);
-                            pb.line_to(rect.right() + offset, rect.top() - offset);
-                            pb.line_to(rect.right() + offset, rect.bottom() - offset + 0.5);
-                        }
-                    } else if let Some(rect) = Rect::from_xywh(x, y, size, size) {
-                        pb.push_rect(rect);
-                    }
-
+                    pb.push_rect(Rect::from_xywh(x - hsize, y - hsize, size, size)?);
                     pb.finish()?
                 };
 
@@ -362,21 +897,446 @@ impl Button {
                     None,
                 );
             }
+            ButtonKind::Custom(_) => return None,
         }
 
         Some(())
     }
 }
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+/// The shared [`ButtonIconProvider`] of the frame.
+#[derive(Clone)]
+pub(crate) struct ButtonIcons(pub Arc<dyn ButtonIconProvider + Send + Sync>);
+
+impl Default for ButtonIcons {
+    fn default() -> Self {
+        Self(Arc::new(AdwaitaButtonIcons))
+    }
+}
+
+impl fmt::Debug for ButtonIcons {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ButtonIcons").finish_non_exhaustive()
+    }
+}
+
+/// The icon drawn on the button instead of the built-in one.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum ButtonIcon<'a> {
+    Pixmap(&'a Pixmap),
+    /// The outline in the unit square filled with the icon color.
+    Symbolic(&'a Path),
+}
+
+/// The kind of the titlebar button.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum ButtonKind {
     Close,
     Maximize,
     Minimize,
+    /// The button added with [`AdwaitaFrame::add_custom_button`], with its id.
+    ///
+    /// [`AdwaitaFrame::add_custom_button`]: crate::AdwaitaFrame::add_custom_button
+    Custom(u32),
+}
+
+impl ButtonKind {
+    /// The action triggered by activating the button, `None` for the custom buttons.
+    pub(crate) fn action(self, state: &WindowState) -> Option<FrameAction> {
+        let action = match self {
+            ButtonKind::Close => FrameAction::Close,
+            ButtonKind::Maximize if state.contains(WindowState::MAXIMIZED) => {
+                FrameAction::UnMaximize
+            }
+            ButtonKind::Maximize => FrameAction::Maximize,
+            ButtonKind::Minimize => FrameAction::Minimize,
+            ButtonKind::Custom(_) => return None,
+        };
+
+        Some(action)
+    }
+
+    /// The name of the symbolic icon of the button in the icon theme.
+    fn symbolic_icon_name(self, state: &WindowState) -> Option<&'static str> {
+        let name = match self {
+            ButtonKind::Close => "window-close-symbolic",
+            ButtonKind::Maximize if state.contains(WindowState::MAXIMIZED) => {
+                "window-restore-symbolic"
+            }
+            ButtonKind::Maximize => "window-maximize-symbolic",
+            ButtonKind::Minimize => "window-minimize-symbolic",
+            ButtonKind::Custom(_) => return None,
+        };
+
+        Some(name)
+    }
+}
+
+/// The callback invoked with the id of the activated custom button.
+pub(crate) struct CustomButtonCallback(pub Box<dyn FnMut(u32)>);
+
+impl fmt::Debug for CustomButtonCallback {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CustomButtonCallback")
+            .finish_non_exhaustive()
+    }
+}
+
+/// The titlebar buttons the application allows to show.
+///
+/// The buttons not supported by the window manager are hidden regardless.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ButtonSet {
+    pub close: bool,
+    pub maximize: bool,
+    pub minimize: bool,
+}
+
+impl ButtonSet {
+    /// All the buttons, the default.
+    pub const ALL: Self = Self {
+        close: true,
+        maximize: true,
+        minimize: true,
+    };
+
+    /// Only the close button, like for the dialogs and the utility windows.
+    pub const CLOSE: Self = Self {
+        close: true,
+        maximize: false,
+        minimize: false,
+    };
+
+    /// No buttons at all.
+    pub const NONE: Self = Self {
+        close: false,
+        maximize: false,
+        minimize: false,
+    };
+
+    pub fn contains(self, kind: ButtonKind) -> bool {
+        match kind {
+            ButtonKind::Close => self.close,
+            ButtonKind::Maximize => self.maximize,
+            ButtonKind::Minimize => self.minimize,
+            ButtonKind::Custom(_) => true,
+        }
+    }
+}
+
+impl Default for ButtonSet {
+    fn default() -> Self {
+        Self::ALL
+    }
 }
 
+/// The side of the headerbar.
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum Side {
     Left,
     Right,
 }
+
+#[test]
+fn minimum_width_keeps_buttons_apart() {
+    let mut buttons = Buttons::new(Some(("close".into(), "minimize,maximize".into())));
+    let width = buttons.shown_width(1.);
+    buttons.arrange(width.ceil() as u32, 1.);
+
+    #[allow(clippy::unwrap_used)]
+    let gap = buttons.right_buttons_start_x().unwrap() - buttons.left_buttons_end_x().unwrap();
+    assert!(gap >= BUTTON_SPACING);
+}
+
+#[test]
+fn minimum_width_ignores_arrangement() {
+    let mut buttons = Buttons::new(Some(("minimize".into(), "maximize,close".into())));
+    let minimum = buttons.minimum_width(1.);
+    for width in [1, 60, 200] {
+        buttons.arrange(width, 1.);
+        assert_eq!(buttons.minimum_width(1.), minimum);
+    }
+
+    // The close button still fits once the other buttons are dropped.
+    buttons.arrange(minimum.ceil() as u32, 1.);
+    assert_eq!(buttons.rects().count(), 1);
+}
+
+#[test]
+fn narrow_header_drops_buttons() {
+    let mut buttons = Buttons::new(Some(("close".into(), "minimize,maximize".into())));
+    let icon = IconSource::Rgba {
+        width: 1,
+        height: 1,
+        pixels: vec![0; 4],
+    };
+    buttons.add_custom(1, icon, Side::Right);
+
+    for width in 1..=200 {
+        buttons.arrange(width, 1.);
+        let rects: Vec<_> = buttons.rects().collect();
+        let shown = |kind| rects.iter().any(|(k, _)| *k == kind);
+
+        assert!(shown(ButtonKind::Close), "close dropped at {width}");
+        // The custom buttons go first, then minimize, then maximize.
+        assert!(!shown(ButtonKind::Custom(1)) || shown(ButtonKind::Minimize));
+        assert!(!shown(ButtonKind::Minimize) || shown(ButtonKind::Maximize));
+
+        for (kind, rect) in &rects {
+            assert!(
+                rect.x as f32 >= 1. + BUTTON_MARGIN,
+                "{kind:?} over the edge at {width}"
+            );
+        }
+        for pair in rects.windows(2) {
+            let ((_, left), (_, right)) = (pair[0], pair[1]);
+            assert!(left.x + left.width as i32 <= right.x, "overlap at {width}");
+        }
+    }
+    assert_eq!(buttons.rects().count(), 4);
+
+    buttons.arrange(1, 1.);
+    assert_eq!(buttons.rects().count(), 1);
+    assert_eq!(buttons.find_button(45., 10.), Location::Head);
+}
+
+#[test]
+fn button_set_hides_buttons() {
+    let mut buttons = Buttons::new(Some(("close".into(), "minimize,maximize".into())));
+    buttons.arrange(200, 1.);
+    buttons.set_allowed(ButtonSet::CLOSE);
+
+    let kinds: Vec<_> = buttons.rects().map(|(kind, _)| kind).collect();
+    assert_eq!(kinds, [ButtonKind::Close]);
+    assert_eq!(buttons.right_buttons_start_x(), None);
+
+    buttons.update_wm_capabilities(WindowManagerCapabilities::all());
+    assert_eq!(buttons.rects().count(), 1);
+}
+
+#[test]
+fn custom_buttons_are_placed_inwards() {
+    let mut buttons = Buttons::new(Some(("close".into(), "minimize,maximize".into())));
+    let icon = IconSource::Rgba {
+        width: 1,
+        height: 1,
+        pixels: vec![0; 4],
+    };
+    buttons.add_custom(1, icon.clone(), Side::Left);
+    buttons.add_custom(2, icon.clone(), Side::Right);
+    buttons.add_custom(2, icon, Side::Right);
+
+    let kinds: Vec<_> = buttons.rects().map(|(kind, _)| kind).collect();
+    assert_eq!(
+        kinds,
+        [
+            ButtonKind::Close,
+            ButtonKind::Custom(1),
+            ButtonKind::Custom(2),
+            ButtonKind::Minimize,
+            ButtonKind::Maximize,
+        ]
+    );
+
+    assert!(buttons.remove_custom(1));
+    assert!(!buttons.remove_custom(1));
+    assert_eq!(buttons.rects().count(), 4);
+}
+
+#[test]
+fn focus_wraps_around() {
+    let mut buttons = Buttons::new(Some(("close".into(), "minimize,maximize".into())));
+    buttons.arrange(200, 1.);
+    let order: Vec<_> = buttons.rects().map(|(kind, _)| kind).collect();
+    assert_eq!(order.len(), 3);
+
+    for kind in order.iter().chain(order.first()) {
+        assert_eq!(buttons.focus_next(), Some(*kind));
+    }
+    assert_eq!(buttons.focus_prev(), order.last().copied());
+
+    buttons.clear_focus();
+    assert_eq!(buttons.focus_prev(), order.last().copied());
+
+    // The only button keeps the focus.
+    let mut buttons = Buttons::new(Some(("".into(), "close".into())));
+    assert_eq!(buttons.focus_next(), Some(ButtonKind::Close));
+    assert_eq!(buttons.focus_next(), Some(ButtonKind::Close));
+    assert_eq!(buttons.focus_prev(), Some(ButtonKind::Close));
+}
+
+#[test]
+fn focus_skips_empty_layout() {
+    let mut buttons = Buttons::default();
+    buttons.set_allowed(ButtonSet::NONE);
+    assert_eq!(buttons.rects().count(), 0);
+    assert_eq!(buttons.focus_next(), None);
+    assert_eq!(buttons.focus_prev(), None);
+    assert_eq!(buttons.focused(), None);
+}
+
+#[test]
+fn icon_provider_draws_glyphs() {
+    struct Squares;
+
+    impl ButtonIconProvider for Squares {
+        fn draw(
+            &self,
+            kind: ButtonKind,
+            toggled: bool,
+            pixmap: &mut PixmapMut,
+            rect: Rect,
+            _scale: f32,
+            _colors: &ColorMap,
+        ) -> Option<()> {
+            let mut paint = tiny_skia::Paint::default();
+            let red = if toggled { 128 } else { 255 };
+            paint.set_color_rgba8(red, 0, (kind == ButtonKind::Close) as u8, 255);
+            pixmap.fill_rect(rect, &paint, Transform::identity(), None);
+            Some(())
+        }
+    }
+
+    let mut buttons = Buttons::new(Some(("".into(), "close".into())));
+    buttons.arrange(100, 0.);
+    buttons.set_icons(ButtonIcons(Arc::new(Squares)));
+
+    #[allow(clippy::unwrap_used)]
+    let mut pixmap = Pixmap::new(100, 35).unwrap();
+    let colors = crate::theme::ColorTheme::light().active;
+    #[allow(clippy::unwrap_used)]
+    let (_, rect) = buttons.rects().next().unwrap();
+    let center = |pixmap: &Pixmap| {
+        let x = rect.x as u32 + rect.width / 2;
+        let y = rect.y as u32 + rect.height / 2;
+        pixmap.pixel(x, y).map(|pixel| (pixel.red(), pixel.blue()))
+    };
+
+    let state = WindowState::ACTIVATED;
+    buttons.draw(
+        0.,
+        100.,
+        1.,
+        &colors,
+        &[],
+        &[],
+        &mut pixmap.as_mut(),
+        true,
+        &state,
+        false,
+    );
+    assert_eq!(center(&pixmap), Some((255, 1)));
+
+    let state = WindowState::ACTIVATED | WindowState::MAXIMIZED;
+    buttons.draw(
+        0.,
+        100.,
+        1.,
+        &colors,
+        &[],
+        &[],
+        &mut pixmap.as_mut(),
+        true,
+        &state,
+        false,
+    );
+    assert_eq!(center(&pixmap), Some((128, 1)));
+}
+
+#[test]
+fn rendered_buttons_are_cached() {
+    let mut buttons = Buttons::new(Some(("".into(), "minimize,maximize,close".into())));
+    buttons.arrange(200, 0.);
+
+    #[allow(clippy::unwrap_used)]
+    let mut pixmap = Pixmap::new(200, 35).unwrap();
+    let colors = crate::theme::ColorTheme::light().active;
+    let state = WindowState::ACTIVATED;
+    let mut draw = |buttons: &mut Buttons, hovered: &[ButtonKind]| {
+        let pixmap = &mut pixmap.as_mut();
+        buttons.draw(
+            0.,
+            200.,
+            1.,
+            &colors,
+            hovered,
+            &[],
+            pixmap,
+            true,
+            &state,
+            false,
+        );
+    };
+
+    draw(&mut buttons, &[]);
+    assert_eq!(buttons.cache.len(), 3);
+    draw(&mut buttons, &[]);
+    assert_eq!(buttons.cache.len(), 3);
+
+    // Only the hovered button is rendered again.
+    draw(&mut buttons, &[ButtonKind::Close]);
+    assert_eq!(buttons.cache.len(), 4);
+}
+
+#[test]
+fn buttons_match_golden_images() {
+    const CELL: f32 = 32.;
+
+    let kinds = [
+        (ButtonKind::Close, false),
+        (ButtonKind::Maximize, false),
+        (ButtonKind::Maximize, true),
+        (ButtonKind::Minimize, false),
+    ];
+    // Idle, hovered, pressed and focused.
+    let looks = [
+        (false, false, false),
+        (true, false, false),
+        (true, true, false),
+        (false, false, true),
+    ];
+
+    for scale in [1., 2.] {
+        let cell = CELL * scale;
+        let (width, height) = (looks.len() as f32 * cell, 2. * kinds.len() as f32 * cell);
+        #[allow(clippy::unwrap_used)]
+        let mut pixmap = Pixmap::new(width as u32, height as u32).unwrap();
+        let theme = crate::theme::ColorTheme::light();
+
+        for (row, (active, (kind, maximized))) in [true, false]
+            .into_iter()
+            .flat_map(|active| kinds.map(|kind| (active, kind)))
+            .enumerate()
+        {
+            let colors = theme.for_state(active);
+            let top = row as f32 * cell;
+            if let Some(rect) = Rect::from_xywh(0., top, width, cell) {
+                let paint = colors.headerbar_paint(cell, scale);
+                pixmap.fill_rect(rect, &paint, Transform::identity(), None);
+            }
+
+            let mut state = WindowState::empty();
+            state.set(WindowState::ACTIVATED, active);
+            state.set(WindowState::MAXIMIZED, maximized);
+            for (column, (hovered, pressed, focused)) in looks.into_iter().enumerate() {
+                Button::new(kind).draw(
+                    (column as f32 + 0.5) * cell,
+                    top + cell / 2.,
+                    scale,
+                    colors,
+                    hovered,
+                    pressed,
+                    focused,
+                    None,
+                    &AdwaitaButtonIcons,
+                    &mut pixmap.as_mut(),
+                    true,
+                    &state,
+                    false,
+                );
+            }
+        }
+
+        crate::golden::assert_golden(&format!("buttons@{scale}x"), &pixmap);
+    }
+}
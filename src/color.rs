@@ -0,0 +1,192 @@
+//! Tagging the decorations with their color space, see [`FrameConfig::color_space`].
+//!
+//! [`FrameConfig::color_space`]: crate::FrameConfig::color_space
+use std::sync::Mutex;
+
+use smithay_client_toolkit::reexports::client::globals::GlobalList;
+use smithay_client_toolkit::reexports::client::protocol::wl_surface::WlSurface;
+use smithay_client_toolkit::reexports::client::{
+    delegate_noop, Connection, Dispatch, EventQueue, QueueHandle, WEnum,
+};
+use smithay_client_toolkit::reexports::protocols::wp::color_management::v1::client::{
+    wp_color_management_surface_v1::WpColorManagementSurfaceV1,
+    wp_color_manager_v1::{self, Feature, Primaries, RenderIntent, TransferFunction},
+    wp_image_description_creator_params_v1::WpImageDescriptionCreatorParamsV1,
+    wp_image_description_v1::{self, WpImageDescriptionV1},
+};
+
+/// The color space the decorations are drawn in, as told to the compositor.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ColorSpace {
+    /// Leave the decorations untagged, the compositor decides how to show them.
+    #[default]
+    Untagged,
+    /// Tag the decorations as sRGB with the `wp_color_manager_v1`, so the compositor maps them
+    /// like the sRGB contents next to the HDR or the wide-gamut ones.
+    ///
+    /// The decorations stay untagged when the compositor doesn't support it.
+    Srgb,
+}
+
+/// The sRGB image description of the `wp_color_manager_v1` the part surfaces are tagged with.
+///
+/// The objects live on their own queue, so the state of the application doesn't need to handle
+/// them. The description is created once the compositor listed the supported features, and
+/// used once it's ready, both found out without blocking by [`Self::is_ready`].
+#[derive(Debug)]
+pub struct ColorManagement {
+    state: Mutex<(EventQueue<ColorManagementState>, ColorManagementState)>,
+    qh: QueueHandle<ColorManagementState>,
+    manager: wp_color_manager_v1::WpColorManagerV1,
+}
+
+impl ColorManagement {
+    /// Bind the manager from the advertised `globals` on the `connection`, `None` when the
+    /// compositor doesn't support it.
+    pub fn bind(globals: &GlobalList, connection: &Connection) -> Option<Self> {
+        let queue = connection.new_event_queue();
+        let qh = queue.handle();
+        let manager = globals.bind(&qh, 1..=1, ()).ok()?;
+
+        Some(Self {
+            state: Mutex::new((queue, ColorManagementState::default())),
+            qh,
+            manager,
+        })
+    }
+
+    /// Whether the sRGB image description is ready to [`Self::tag`] the surfaces with.
+    ///
+    /// Dispatches the events received so far without blocking, creating the description once
+    /// the supported features are known.
+    pub fn is_ready(&self) -> bool {
+        let Ok(mut guard) = self.state.lock() else {
+            return false;
+        };
+        let (queue, state) = &mut *guard;
+        if let Err(err) = queue.dispatch_pending(state) {
+            log::warn!("Failed to dispatch the color management: {err}");
+            return false;
+        }
+
+        if state.done && state.description.is_none() && state.ready.is_none() {
+            if !state.supports_srgb() {
+                state.ready = Some(false);
+                return false;
+            }
+
+            let transfer_function = if state
+                .transfer_functions
+                .contains(&TransferFunction::Gamma22)
+            {
+                TransferFunction::Gamma22
+            } else {
+                TransferFunction::Srgb
+            };
+            let creator = self.manager.create_parametric_creator(&self.qh, ());
+            creator.set_primaries_named(Primaries::Srgb);
+            creator.set_tf_named(transfer_function);
+            state.description = Some(creator.create(&self.qh, ()));
+        }
+
+        state.ready == Some(true)
+    }
+
+    /// Tag the `surface` with the sRGB image description from the next commit, until the
+    /// returned object is destroyed, `None` until [`Self::is_ready`].
+    pub fn tag(&self, surface: &WlSurface) -> Option<WpColorManagementSurfaceV1> {
+        let guard = self.state.lock().ok()?;
+        let description = guard
+            .1
+            .description
+            .as_ref()
+            .filter(|_| guard.1.ready == Some(true))?;
+        let color_surface = self.manager.get_surface(surface, &self.qh, ());
+        color_surface.set_image_description(description, RenderIntent::Perceptual);
+        Some(color_surface)
+    }
+}
+
+impl Drop for ColorManagement {
+    fn drop(&mut self) {
+        if let Ok((_, state)) = self.state.get_mut() {
+            if let Some(description) = state.description.take() {
+                description.destroy();
+            }
+        }
+        self.manager.destroy();
+    }
+}
+
+#[derive(Debug, Default)]
+struct ColorManagementState {
+    parametric: bool,
+    primaries: Vec<Primaries>,
+    transfer_functions: Vec<TransferFunction>,
+    /// Whether all the supported features were listed.
+    done: bool,
+    /// The sRGB image description, created once the features are listed.
+    description: Option<WpImageDescriptionV1>,
+    /// Whether the image description is ready, `None` until it's known.
+    ready: Option<bool>,
+}
+
+impl ColorManagementState {
+    fn supports_srgb(&self) -> bool {
+        self.parametric
+            && self.primaries.contains(&Primaries::Srgb)
+            && [TransferFunction::Gamma22, TransferFunction::Srgb]
+                .iter()
+                .any(|tf| self.transfer_functions.contains(tf))
+    }
+}
+
+impl Dispatch<wp_color_manager_v1::WpColorManagerV1, ()> for ColorManagementState {
+    fn event(
+        state: &mut Self,
+        _: &wp_color_manager_v1::WpColorManagerV1,
+        event: wp_color_manager_v1::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        match event {
+            wp_color_manager_v1::Event::SupportedFeature {
+                feature: WEnum::Value(Feature::Parametric),
+            } => state.parametric = true,
+            wp_color_manager_v1::Event::SupportedPrimariesNamed {
+                primaries: WEnum::Value(primaries),
+            } => state.primaries.push(primaries),
+            wp_color_manager_v1::Event::SupportedTfNamed {
+                tf: WEnum::Value(transfer_function),
+            } => state.transfer_functions.push(transfer_function),
+            wp_color_manager_v1::Event::Done => state.done = true,
+            _ => (),
+        }
+    }
+}
+
+impl Dispatch<WpImageDescriptionV1, ()> for ColorManagementState {
+    fn event(
+        state: &mut Self,
+        _: &WpImageDescriptionV1,
+        event: wp_image_description_v1::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        match event {
+            wp_image_description_v1::Event::Ready { .. }
+            | wp_image_description_v1::Event::Ready2 { .. } => state.ready = Some(true),
+            wp_image_description_v1::Event::Failed { msg, .. } => {
+                log::warn!("the compositor failed the sRGB image description: {msg}");
+                state.ready = Some(false);
+            }
+            _ => (),
+        }
+    }
+}
+
+delegate_noop!(ColorManagementState: ignore WpImageDescriptionCreatorParamsV1);
+delegate_noop!(ColorManagementState: ignore WpColorManagementSurfaceV1);
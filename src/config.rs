@@ -1,5 +1,88 @@
 //! System configuration.
-use std::process::Command;
+use std::{
+    env, fmt,
+    io::{BufRead, BufReader},
+    process::{Child, Command, Stdio},
+    sync::{Arc, Mutex, Weak},
+    thread,
+    time::Duration,
+};
+
+use tiny_skia::Color;
+
+use crate::{
+    pointer::{DoubleClickAction, MiddleClickAction},
+    title::{Hinting, SubpixelOrder},
+};
+
+/// The snapshot of the system configuration the frame follows, to reuse it e.g. for the theming
+/// of the toolkit drawing the window contents.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct SystemPrefs {
+    /// Whether the dark theme is preferred.
+    pub prefer_dark: bool,
+    /// The font of the window titles, like `Cantarell Bold 11`, resolved the way GNOME does
+    /// from the settings below.
+    pub titlebar_font: Option<String>,
+    /// The raw `titlebar-font` of `org.gnome.desktop.wm.preferences`.
+    pub wm_titlebar_font: Option<String>,
+    /// The raw `font-name` of `org.gnome.desktop.interface`, the font of the user interface.
+    pub system_font: Option<String>,
+    /// Whether the window titles use the [`Self::system_font`] rather than the
+    /// [`Self::wm_titlebar_font`], the `titlebar-uses-system-font` of
+    /// `org.gnome.desktop.wm.preferences`.
+    pub titlebar_uses_system_font: bool,
+    /// The order of the titlebar buttons in the format of the `button-layout` setting, like
+    /// `appmenu:minimize,maximize,close`.
+    pub button_layout: Option<String>,
+    /// The action on the double click on the titlebar.
+    pub double_click_action: Option<DoubleClickAction>,
+    /// The action on the middle click on the titlebar.
+    pub middle_click_action: Option<MiddleClickAction>,
+    /// The time to register the next click as a double click.
+    pub double_click_time: Option<Duration>,
+    /// The factor the sizes of the fonts are scaled by, `1` by default.
+    pub text_scale: f32,
+    /// Whether the animations are enabled.
+    pub enable_animations: bool,
+    /// The hinting of the fonts.
+    pub font_hinting: Option<Hinting>,
+    /// The subpixel order of the LCD the fonts are rendered for.
+    pub subpixel_order: Option<SubpixelOrder>,
+}
+
+impl SystemPrefs {
+    /// Query the system configuration, like the frame does when it's created.
+    ///
+    /// Each setting is read with the separate call to the XDG Settings Portal or `gsettings`,
+    /// so the snapshot should be reused rather than loaded often.
+    pub fn load() -> Self {
+        let wm_titlebar_font = wm_titlebar_font();
+        let system_font = system_font();
+        let titlebar_uses_system_font = titlebar_uses_system_font();
+        Self {
+            prefer_dark: prefer_dark(),
+            titlebar_font: resolve_titlebar_font(
+                titlebar_uses_system_font,
+                || system_font.clone(),
+                || wm_titlebar_font.clone(),
+            ),
+            wm_titlebar_font,
+            system_font,
+            titlebar_uses_system_font,
+            button_layout: get_button_layout_config()
+                .map(|(left, right)| format!("{left}:{right}")),
+            double_click_action: double_click_action(),
+            middle_click_action: middle_click_action(),
+            double_click_time: double_click_time(),
+            text_scale: text_scale(),
+            enable_animations: enable_animations(),
+            font_hinting: font_hinting(),
+            subpixel_order: subpixel_order(),
+        }
+    }
+}
 
 /// Query system to see if dark theming should be preferred.
 pub(crate) fn prefer_dark() -> bool {
@@ -54,3 +137,445 @@ pub(crate) fn get_button_layout_config() -> Option<(String, String)> {
         _ => None,
     }
 }
+
+/// The layout read after the `button-layout` change, `Some(None)` when it can't be read.
+type ChangedLayout = Option<Option<(String, String)>>;
+
+/// The callback waking the event loop of the frame once the layout changed.
+pub(crate) type LayoutWaker = Arc<dyn Fn() + Send + Sync>;
+
+/// The `dbus-monitor` of the process shared by the watchers, see [`ButtonLayoutWatcher`].
+static MONITOR: Mutex<Weak<LayoutMonitor>> = Mutex::new(Weak::new());
+
+/// The watcher of the changes of the buttons layout in the system configuration, e.g. made with
+/// the GNOME Tweaks.
+///
+/// The `SettingChanged` signals of the XDG Settings Portal are read from `dbus-monitor`, which
+/// is started once for all the watchers of the process and killed with the last of them.
+#[derive(Debug)]
+pub(crate) struct ButtonLayoutWatcher {
+    /// Keeps the shared monitor running.
+    _monitor: Arc<LayoutMonitor>,
+    subscriber: Arc<Subscriber>,
+}
+
+impl ButtonLayoutWatcher {
+    /// Start watching, `None` when `dbus-monitor` can't be started.
+    pub fn new() -> Option<Self> {
+        let monitor = LayoutMonitor::shared()?;
+        let subscriber = Arc::new(Subscriber::default());
+        monitor
+            .subscribers
+            .lock()
+            .ok()?
+            .push(Arc::downgrade(&subscriber));
+
+        Some(Self {
+            _monitor: monitor,
+            subscriber,
+        })
+    }
+
+    /// Set the callback invoked on the thread of the monitor once the layout changed.
+    pub fn set_waker(&self, waker: Option<LayoutWaker>) {
+        if let Ok(mut wake) = self.subscriber.wake.lock() {
+            *wake = waker;
+        }
+    }
+
+    /// The layout changed since the last call, `None` when it hasn't changed.
+    pub fn take_changed(&self) -> Option<Option<(String, String)>> {
+        self.subscriber.changed.lock().ok()?.take()
+    }
+
+    /// Whether the layout changed since the last [`Self::take_changed`].
+    pub fn has_changed(&self) -> bool {
+        self.subscriber
+            .changed
+            .lock()
+            .is_ok_and(|changed| changed.is_some())
+    }
+}
+
+/// The layout changed for one watcher, with the callback waking its frame.
+#[derive(Default)]
+struct Subscriber {
+    changed: Mutex<ChangedLayout>,
+    wake: Mutex<Option<LayoutWaker>>,
+}
+
+impl fmt::Debug for Subscriber {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Subscriber")
+            .field("changed", &self.changed)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The `dbus-monitor` notifying the watchers, killed and reaped once they're all dropped.
+#[derive(Debug)]
+struct LayoutMonitor {
+    monitor: Mutex<Child>,
+    subscribers: Arc<Mutex<Vec<Weak<Subscriber>>>>,
+}
+
+impl LayoutMonitor {
+    /// The monitor of the process, started when there's none.
+    fn shared() -> Option<Arc<Self>> {
+        let mut shared = MONITOR.lock().ok()?;
+        if let Some(monitor) = shared.upgrade() {
+            return Some(monitor);
+        }
+
+        let monitor = Arc::new(Self::spawn()?);
+        *shared = Arc::downgrade(&monitor);
+        Some(monitor)
+    }
+
+    fn spawn() -> Option<Self> {
+        let mut monitor = Command::new("dbus-monitor")
+            .arg("--session")
+            .arg(concat!(
+                "type='signal',",
+                "interface='org.freedesktop.portal.Settings',",
+                "member='SettingChanged',",
+                "arg0='org.gnome.desktop.wm.preferences',",
+                "arg1='button-layout'"
+            ))
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .ok()?;
+        let stdout = monitor.stdout.take();
+        let monitor = Self {
+            monitor: Mutex::new(monitor),
+            subscribers: Default::default(),
+        };
+
+        let subscribers = monitor.subscribers.clone();
+        thread::Builder::new()
+            .name("button-layout".to_owned())
+            .spawn(move || {
+                // The signal is printed with its arguments, the namespace, the key and the
+                // value, one per line.
+                for line in BufReader::new(stdout?).lines().map_while(Result::ok) {
+                    if line.trim() == "string \"button-layout\"" {
+                        let layout = get_button_layout_config();
+                        let mut subscribers = subscribers.lock().ok()?;
+                        subscribers.retain(|subscriber| subscriber.strong_count() > 0);
+                        for subscriber in subscribers.iter().filter_map(Weak::upgrade) {
+                            *subscriber.changed.lock().ok()? = Some(layout.clone());
+                            if let Some(wake) = subscriber.wake.lock().ok()?.as_ref() {
+                                wake();
+                            }
+                        }
+                    }
+                }
+                Some(())
+            })
+            .ok()?;
+
+        Some(monitor)
+    }
+}
+
+impl Drop for LayoutMonitor {
+    fn drop(&mut self) {
+        if let Ok(monitor) = self.monitor.get_mut() {
+            let _ = monitor.kill();
+            let _ = monitor.wait();
+        }
+    }
+}
+
+/// Query system for which font to use for window titles.
+///
+/// Like GNOME, the font of the user interface is used when `titlebar-uses-system-font` is set,
+/// and the `titlebar-font` otherwise.
+pub(crate) fn titlebar_font() -> Option<String> {
+    resolve_titlebar_font(titlebar_uses_system_font(), system_font, wm_titlebar_font)
+}
+
+/// Pick the font of the window titles, falling back to the other font when the picked one
+/// isn't set.
+fn resolve_titlebar_font(
+    uses_system_font: bool,
+    system_font: impl FnOnce() -> Option<String>,
+    wm_titlebar_font: impl FnOnce() -> Option<String>,
+) -> Option<String> {
+    if uses_system_font {
+        system_font().or_else(wm_titlebar_font)
+    } else {
+        wm_titlebar_font().or_else(system_font)
+    }
+}
+
+/// Query system for the `titlebar-font` of the window manager preferences.
+fn wm_titlebar_font() -> Option<String> {
+    gsettings("org.gnome.desktop.wm.preferences", "titlebar-font")
+}
+
+/// Query system for the font of the user interface.
+fn system_font() -> Option<String> {
+    gsettings("org.gnome.desktop.interface", "font-name")
+}
+
+/// Query system for whether the window titles use the font of the user interface.
+fn titlebar_uses_system_font() -> bool {
+    gsettings(
+        "org.gnome.desktop.wm.preferences",
+        "titlebar-uses-system-font",
+    )
+    .is_some_and(|value| value == "true")
+}
+
+/// Read the `key` of the `schema` with `gsettings`, without the quotes around the strings.
+fn gsettings(schema: &str, key: &str) -> Option<String> {
+    // outputs something like: `'Cantarell Bold 12'`
+    let stdout = Command::new("gsettings")
+        .args(["get", schema, key])
+        .output()
+        .ok()
+        .and_then(|out| String::from_utf8(out.stdout).ok())?;
+
+    let value = stdout
+        .trim()
+        .trim_end_matches('\'')
+        .trim_start_matches('\'');
+    (!value.is_empty()).then(|| value.to_owned())
+}
+
+/// Query system configuration for the action on the double click on the titlebar.
+pub(crate) fn double_click_action() -> Option<DoubleClickAction> {
+    DoubleClickAction::from_gsettings(&wm_preference("action-double-click-titlebar")?)
+}
+
+/// Query system configuration for the action on the middle click on the titlebar.
+pub(crate) fn middle_click_action() -> Option<MiddleClickAction> {
+    MiddleClickAction::from_gsettings(&wm_preference("action-middle-click-titlebar")?)
+}
+
+/// Query system configuration for the time to register the next click as a double click.
+pub(crate) fn double_click_time() -> Option<Duration> {
+    setting("org.gnome.desktop.peripherals.mouse", "double-click")?
+        .parse()
+        .ok()
+        .map(Duration::from_millis)
+}
+
+/// The DPI the font sizes are given for.
+const DEFAULT_DPI: f32 = 96.;
+
+/// The factor of the desktop scaling the font sizes, `1` by default.
+///
+/// The `text-scaling-factor` takes precedence over the `Xft.dpi`, which GNOME derives from it.
+pub(crate) fn text_scale() -> f32 {
+    text_scaling_factor()
+        .or_else(|| xft_dpi().map(|dpi| dpi / DEFAULT_DPI))
+        .map_or(1., |factor| factor.clamp(0.5, 3.))
+}
+
+/// Query system configuration for the factor the sizes of the fonts are scaled by, like the
+/// large text of the accessibility settings.
+fn text_scaling_factor() -> Option<f32> {
+    setting("org.gnome.desktop.interface", "text-scaling-factor")?
+        .parse::<f32>()
+        .ok()
+        .filter(|factor| factor.is_finite() && *factor > 0.)
+}
+
+/// Query the X resources for the `Xft.dpi` the fonts are sized for, set instead of the
+/// `text-scaling-factor` by the other desktops and the X resources of the user.
+fn xft_dpi() -> Option<f32> {
+    let resources = Command::new("xrdb")
+        .arg("-query")
+        .stderr(Stdio::null())
+        .output()
+        .ok()
+        .and_then(|out| String::from_utf8(out.stdout).ok())?;
+
+    parse_xft_dpi(&resources)
+}
+
+/// The `Xft.dpi` of the `xrdb -query` output.
+fn parse_xft_dpi(resources: &str) -> Option<f32> {
+    resources
+        .lines()
+        .filter_map(|line| line.split_once(':'))
+        .find(|(name, _)| name.trim() == "Xft.dpi")?
+        .1
+        .trim()
+        .parse::<f32>()
+        .ok()
+        .filter(|dpi| dpi.is_finite() && *dpi > 0.)
+}
+
+/// Query system configuration for the hinting of the fonts.
+pub(crate) fn font_hinting() -> Option<Hinting> {
+    Hinting::from_gsettings(&setting("org.gnome.desktop.interface", "font-hinting")?)
+}
+
+/// Query fontconfig for the subpixel order of the LCD the fonts are rendered for.
+pub(crate) fn subpixel_order() -> Option<SubpixelOrder> {
+    // outputs the `FC_RGBA_*` constant of the default pattern, like `1`
+    let stdout = Command::new("fc-match")
+        .args(["--format=%{rgba}", ""])
+        .output()
+        .ok()
+        .and_then(|out| String::from_utf8(out.stdout).ok())?;
+
+    SubpixelOrder::from_fontconfig(stdout.trim())
+}
+
+/// Query system configuration for the name of the icon theme.
+pub(crate) fn icon_theme() -> Option<String> {
+    setting("org.gnome.desktop.interface", "icon-theme")
+}
+
+/// Query system configuration for whether the animations are enabled, `true` by default.
+pub(crate) fn enable_animations() -> bool {
+    setting("org.gnome.desktop.interface", "enable-animations")
+        .map_or(true, |value| value != "false")
+}
+
+/// Query system configuration for whether the high contrast is requested.
+///
+/// The `contrast` of the XDG Settings Portal is preferred over the GNOME setting.
+pub(crate) fn high_contrast() -> bool {
+    match setting("org.freedesktop.appearance", "contrast") {
+        Some(contrast) => contrast == "1",
+        None => setting("org.gnome.desktop.a11y.interface", "high-contrast")
+            .is_some_and(|value| value == "true"),
+    }
+}
+
+/// Query system configuration for the accent color, `None` when it isn't set.
+pub(crate) fn accent_color() -> Option<Color> {
+    let stdout = Command::new("dbus-send")
+        .arg("--reply-timeout=100")
+        .arg("--print-reply=literal")
+        .arg("--dest=org.freedesktop.portal.Desktop")
+        .arg("/org/freedesktop/portal/desktop")
+        .arg("org.freedesktop.portal.Settings.Read")
+        .arg("string:org.freedesktop.appearance")
+        .arg("string:accent-color")
+        .output()
+        .ok()
+        .and_then(|out| String::from_utf8(out.stdout).ok())?;
+
+    parse_accent_color(&stdout)
+}
+
+/// Parse the `(ddd)` accent color, the channels out of the range are unset.
+///
+/// The output looks like: `variant       struct {         double 0.207843 ... }`
+fn parse_accent_color(reply: &str) -> Option<Color> {
+    let mut channels = reply
+        .split_whitespace()
+        .skip_while(|word| *word != "double")
+        .filter(|word| *word != "double" && *word != "}")
+        .map(|word| word.parse::<f32>().ok());
+    let mut channel = || channels.next().flatten().filter(|c| (0. ..=1.).contains(c));
+
+    Color::from_rgba(channel()?, channel()?, channel()?, 1.)
+}
+
+/// Read the string `key` of `org.gnome.desktop.wm.preferences` from the XDG Settings Portal.
+fn wm_preference(key: &str) -> Option<String> {
+    setting("org.gnome.desktop.wm.preferences", key)
+}
+
+/// Read the string `key` of the `namespace` from the XDG Settings Portal.
+fn setting(namespace: &str, key: &str) -> Option<String> {
+    let config_string = Command::new("dbus-send")
+        .arg("--reply-timeout=100")
+        .arg("--print-reply=literal")
+        .arg("--dest=org.freedesktop.portal.Desktop")
+        .arg("/org/freedesktop/portal/desktop")
+        .arg("org.freedesktop.portal.Settings.Read")
+        .arg(format!("string:{namespace}"))
+        .arg(format!("string:{key}"))
+        .output()
+        .ok()
+        .and_then(|out| String::from_utf8(out.stdout).ok())?;
+
+    // Taking last word
+    config_string
+        .trim()
+        .rsplit(' ')
+        .next()
+        .filter(|value| !value.is_empty())
+        .map(|value| value.to_owned())
+}
+
+/// Whether the language of the user interface is written right-to-left.
+pub(crate) fn locale_is_rtl() -> bool {
+    const RTL_LANGUAGES: &[&str] = &[
+        "ar", "dv", "fa", "he", "iw", "ks", "ps", "sd", "ug", "ur", "yi",
+    ];
+
+    ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .into_iter()
+        .filter_map(|var| env::var(var).ok())
+        .find(|value| !value.is_empty())
+        .is_some_and(|locale| {
+            let language = locale.split(['_', '.', '@']).next().unwrap_or_default();
+            RTL_LANGUAGES.contains(&language)
+        })
+}
+
+#[test]
+fn titlebar_font_follows_system_font_setting() {
+    let system = || Some("Inter 10".to_owned());
+    let titlebar = || Some("Cantarell Bold 11".to_owned());
+    assert_eq!(
+        resolve_titlebar_font(true, system, titlebar).as_deref(),
+        Some("Inter 10")
+    );
+    assert_eq!(
+        resolve_titlebar_font(false, system, titlebar).as_deref(),
+        Some("Cantarell Bold 11")
+    );
+    assert_eq!(
+        resolve_titlebar_font(true, || None, titlebar).as_deref(),
+        Some("Cantarell Bold 11")
+    );
+    assert_eq!(resolve_titlebar_font(false, || None, || None), None);
+}
+
+#[test]
+fn accent_color_parses_portal_reply() {
+    let reply = "   variant       struct {\n         double 0.2\n         double 0.4\n         double 1\n      }\n";
+    assert_eq!(
+        parse_accent_color(reply),
+        Color::from_rgba(0.2, 0.4, 1., 1.)
+    );
+
+    let unset = "   variant       struct {\n         double -1\n         double -1\n         double -1\n      }\n";
+    assert_eq!(parse_accent_color(unset), None);
+    assert_eq!(parse_accent_color(""), None);
+}
+
+#[test]
+fn xft_dpi_parses_resources() {
+    let resources = "Xft.antialias:\t1\nXft.dpi:\t120\nXft.hinting:\t1\n";
+    assert_eq!(parse_xft_dpi(resources), Some(120.));
+    assert_eq!(parse_xft_dpi("Xft.dpi:\t0\n"), None);
+    assert_eq!(parse_xft_dpi("Xcursor.size:\t24\n"), None);
+    assert_eq!(parse_xft_dpi(""), None);
+}
+
+#[test]
+fn button_layout_watchers_share_monitor() {
+    // Without `dbus-monitor` there's nothing to share.
+    let (Some(first), Some(second)) = (ButtonLayoutWatcher::new(), ButtonLayoutWatcher::new())
+    else {
+        return;
+    };
+    assert!(Arc::ptr_eq(&first._monitor, &second._monitor));
+
+    drop((first, second));
+    #[allow(clippy::unwrap_used)]
+    let shared = MONITOR.lock().unwrap();
+    assert!(shared.upgrade().is_none());
+}
@@ -0,0 +1,146 @@
+//! The overlay drawn over the decorations to diagnose the layout, enabled with
+//! `SCTK_ADWAITA_DEBUG=1` when built with `--features debug-overlay`.
+use std::env;
+
+use tiny_skia::{Paint, PathBuilder, PixmapMut, Stroke, Transform};
+
+use crate::{
+    buttons::ButtonKind,
+    parts::{DecorationParts, Rect},
+};
+
+/// The tints of the parts by their index in the [`DecorationParts`].
+const PART_TINTS: [[u8; 3]; 5] = [
+    [255, 0, 0],
+    [0, 192, 0],
+    [0, 0, 255],
+    [255, 160, 0],
+    [192, 0, 192],
+];
+
+/// Whether the overlay is requested with `SCTK_ADWAITA_DEBUG=1`.
+pub(crate) fn enabled() -> bool {
+    env::var("SCTK_ADWAITA_DEBUG").is_ok_and(|value| value == "1")
+}
+
+/// The geometry of the frame outlined by the overlay.
+#[derive(Debug, Clone)]
+pub(crate) struct DebugOverlay {
+    /// The input rectangles of the parts, positioned relative to the main surface.
+    input_rects: [Rect; 5],
+}
+
+impl DebugOverlay {
+    pub fn new(decorations: &DecorationParts) -> Self {
+        Self {
+            input_rects: [0, 1, 2, 3, 4].map(|idx| decorations.input_rect(idx)),
+        }
+    }
+
+    /// Tint the part of the buffer `rect` and outline its input rectangle.
+    pub fn draw_part(&self, idx: usize, rect: Rect, scale: u32, pixmap: &mut PixmapMut) {
+        let [red, green, blue] = PART_TINTS[idx];
+        let mut tint = Paint::default();
+        tint.set_color_rgba8(red, green, blue, 48);
+        if let Some(area) =
+            tiny_skia::Rect::from_xywh(0., 0., pixmap.width() as f32, pixmap.height() as f32)
+        {
+            pixmap.fill_rect(area, &tint, Transform::identity(), None);
+        }
+
+        let input = self.input_rects[idx];
+        let input = Rect {
+            x: input.x - rect.x,
+            y: input.y - rect.y,
+            ..input
+        };
+        outline(pixmap, input, scale, [red, green, blue]);
+    }
+
+    /// Outline the hit boxes of the buttons and the clip of the title in the upright header,
+    /// whose buffer starts at `origin_x` along the header.
+    pub fn draw_header(
+        &self,
+        buttons: impl Iterator<Item = (ButtonKind, Rect)>,
+        title_clip: Option<tiny_skia::Rect>,
+        origin_x: i32,
+        scale: u32,
+        pixmap: &mut PixmapMut,
+    ) {
+        for (_, button) in buttons {
+            let button = Rect {
+                x: button.x - origin_x,
+                ..button
+            };
+            outline(pixmap, button, scale, [0, 160, 255]);
+        }
+
+        if let Some(clip) = title_clip.and_then(|clip| {
+            // Keep the outline of the clip inside of the buffer.
+            tiny_skia::Rect::from_ltrb(
+                clip.left().max(0.) + 0.5,
+                clip.top() + 0.5,
+                clip.right().min(pixmap.width() as f32) - 0.5,
+                clip.bottom() - 0.5,
+            )
+        }) {
+            stroke(pixmap, clip, [255, 0, 255]);
+        }
+    }
+}
+
+/// Outline the `rect` in logical points inside of its edges.
+fn outline(pixmap: &mut PixmapMut, rect: Rect, scale: u32, color: [u8; 3]) {
+    let scale = scale as f32;
+    if let Some(rect) = tiny_skia::Rect::from_xywh(
+        rect.x as f32 * scale + 0.5,
+        rect.y as f32 * scale + 0.5,
+        rect.width as f32 * scale - 1.,
+        rect.height as f32 * scale - 1.,
+    ) {
+        stroke(pixmap, rect, color);
+    }
+}
+
+fn stroke(pixmap: &mut PixmapMut, rect: tiny_skia::Rect, [red, green, blue]: [u8; 3]) {
+    let mut paint = Paint::default();
+    paint.set_color_rgba8(red, green, blue, 255);
+    paint.anti_alias = false;
+    pixmap.stroke_path(
+        &PathBuilder::from_rect(rect),
+        &paint,
+        &Stroke::default(),
+        Transform::identity(),
+        None,
+    );
+}
+
+#[test]
+fn overlay_outlines_input_rect() {
+    use tiny_skia::Pixmap;
+
+    let rect = Rect {
+        x: -4,
+        y: -4,
+        width: 8,
+        height: 8,
+    };
+    let mut input_rects = [rect; 5];
+    input_rects[DecorationParts::TOP] = Rect {
+        x: -2,
+        y: -2,
+        width: 4,
+        height: 4,
+    };
+    let overlay = DebugOverlay { input_rects };
+
+    #[allow(clippy::unwrap_used)]
+    let mut pixmap = Pixmap::new(8, 8).unwrap();
+    overlay.draw_part(DecorationParts::TOP, rect, 1, &mut pixmap.as_mut());
+
+    let alpha = |x, y| pixmap.pixel(x, y).map(|pixel| pixel.alpha());
+    // The tint everywhere, the outline along the input rectangle.
+    assert_eq!(alpha(0, 0), Some(48));
+    assert_eq!(alpha(2, 2), Some(255));
+    assert_eq!(alpha(4, 4), Some(48));
+}
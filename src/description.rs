@@ -0,0 +1,123 @@
+//! The structured description of what the frame draws, to introspect it outside of the client.
+
+use crate::{theme::ColorTheme, FrameConfig, FrameMetrics, HeaderPosition, TitleAlignment};
+
+/// The description of the frame drawn by the client, see [`AdwaitaFrame::description`].
+///
+/// With `--features serde` it can be serialized for the compositors or the tools, and read back
+/// into the [`FrameConfig`] with [`FrameConfig::from_value`].
+///
+/// [`AdwaitaFrame::description`]: crate::AdwaitaFrame::description
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FrameDescription {
+    /// The theme the frame is drawn with.
+    pub theme: ColorTheme,
+    /// The sizes of the decorations in the current window state.
+    pub metrics: FrameMetrics,
+    /// The order of the buttons in the format of the `button-layout` setting, like
+    /// `appmenu:minimize,maximize,close`.
+    pub button_layout: String,
+    /// The radius of the top corners of the floating window.
+    pub corner_radius: u32,
+    /// Whether only the borders are drawn, without the headerbar.
+    pub hide_titlebar: bool,
+    /// The horizontal alignment of the title in the headerbar.
+    pub title_alignment: TitleAlignment,
+    /// The edge of the window the headerbar is placed along.
+    pub header_position: HeaderPosition,
+}
+
+impl FrameConfig {
+    /// Create the configuration drawing the frame of the `description`, e.g. one deserialized
+    /// from another client.
+    pub fn from_value(description: &FrameDescription) -> Self {
+        FrameConfig::new(description.theme.clone())
+            .hide_titlebar(description.hide_titlebar)
+            .button_layout(&description.button_layout)
+            .corner_radius(description.corner_radius)
+            .title_alignment(description.title_alignment)
+            .header_position(description.header_position)
+    }
+}
+
+/// Serialize the colors as the `#rrggbbaa` strings.
+#[cfg(feature = "serde")]
+pub(crate) mod serde_color {
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+    use tiny_skia::Color;
+
+    pub fn serialize<S: Serializer>(color: &Color, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&to_hex(*color))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Color, D::Error> {
+        let hex = String::deserialize(deserializer)?;
+        from_hex(&hex).ok_or_else(|| D::Error::custom(format!("invalid color: {hex}")))
+    }
+
+    pub(crate) fn to_hex(color: Color) -> String {
+        let color = color.to_color_u8();
+        format!(
+            "#{:02x}{:02x}{:02x}{:02x}",
+            color.red(),
+            color.green(),
+            color.blue(),
+            color.alpha()
+        )
+    }
+
+    /// Parse the `#rrggbbaa` or the opaque `#rrggbb` color.
+    pub(crate) fn from_hex(hex: &str) -> Option<Color> {
+        let hex = hex.strip_prefix('#')?;
+        if !matches!(hex.len(), 6 | 8) || !hex.is_ascii() {
+            return None;
+        }
+
+        let channel = |idx: usize| u8::from_str_radix(hex.get(idx * 2..idx * 2 + 2)?, 16).ok();
+        let alpha = if hex.len() == 8 { channel(3)? } else { 255 };
+        Some(Color::from_rgba8(
+            channel(0)?,
+            channel(1)?,
+            channel(2)?,
+            alpha,
+        ))
+    }
+
+    #[test]
+    fn hex_colors_round_trip() {
+        let color = Color::from_rgba8(53, 132, 228, 128);
+        assert_eq!(to_hex(color), "#3584e480");
+        assert_eq!(from_hex("#3584e480"), Some(color));
+        assert_eq!(
+            from_hex("#3584e4"),
+            Some(Color::from_rgba8(53, 132, 228, 255))
+        );
+        assert_eq!(from_hex("3584e4"), None);
+        assert_eq!(from_hex("#3584e"), None);
+    }
+}
+
+#[test]
+fn frame_config_from_value() {
+    let description = FrameDescription {
+        theme: ColorTheme::dark(),
+        metrics: FrameMetrics::default(),
+        button_layout: "close:minimize".to_owned(),
+        corner_radius: 4,
+        hide_titlebar: true,
+        title_alignment: TitleAlignment::Left,
+        header_position: HeaderPosition::Left,
+    };
+
+    let config = FrameConfig::from_value(&description);
+    assert_eq!(config.theme, ColorTheme::dark());
+    assert_eq!(config.corner_radius, 4);
+    assert!(config.hide_titlebar);
+    assert_eq!(
+        config.button_layout,
+        Some(("close".to_owned(), "minimize".to_owned()))
+    );
+    assert_eq!(config.title_alignment, TitleAlignment::Left);
+    assert_eq!(config.header_position, HeaderPosition::Left);
+}
@@ -0,0 +1,45 @@
+//! The optional globals the frames use when the compositor advertises them.
+use std::sync::Arc;
+
+use smithay_client_toolkit::reexports::client::globals::GlobalList;
+use smithay_client_toolkit::reexports::client::Connection;
+
+#[cfg(feature = "blur")]
+use crate::blur::BlurManager;
+use crate::color::ColorManagement;
+use crate::parts::SolidBuffers;
+
+/// The optional protocols of the compositor the frames draw with, bound once by the app and
+/// shared by all its frames, like the [`Shm`] and the [`CompositorState`].
+///
+/// - `wp_single_pixel_buffer_manager_v1` with `wp_viewporter` for the parts of a single color.
+/// - `wp_color_manager_v1` for [`FrameConfig::color_space`].
+/// - `org_kde_kwin_blur_manager` for the blur behind the translucent headerbar, with the `blur`
+///   feature.
+///
+/// Binding them doesn't block on the connection, their events are received on their own queues
+/// and dispatched without blocking while the frames are drawn. The [`Default`] has none of
+/// them, so the frames are drawn only into the shared memory buffers.
+///
+/// [`Shm`]: smithay_client_toolkit::shm::Shm
+/// [`CompositorState`]: smithay_client_toolkit::compositor::CompositorState
+/// [`FrameConfig::color_space`]: crate::FrameConfig::color_space
+#[derive(Debug, Clone, Default)]
+pub struct FrameGlobals {
+    pub(crate) solid_buffers: Option<Arc<SolidBuffers>>,
+    pub(crate) color_management: Option<Arc<ColorManagement>>,
+    #[cfg(feature = "blur")]
+    pub(crate) blur_manager: Option<Arc<BlurManager>>,
+}
+
+impl FrameGlobals {
+    /// Bind the supported globals from the ones advertised on the `connection`.
+    pub fn bind(globals: &GlobalList, connection: &Connection) -> Self {
+        Self {
+            solid_buffers: SolidBuffers::bind(globals, connection).map(Arc::new),
+            color_management: ColorManagement::bind(globals, connection).map(Arc::new),
+            #[cfg(feature = "blur")]
+            blur_manager: BlurManager::bind(globals, connection).map(Arc::new),
+        }
+    }
+}
@@ -0,0 +1,109 @@
+//! The golden image tests of the rendering, see [`assert_golden`].
+
+use smithay_client_toolkit::reexports::csd_frame::WindowState;
+use tiny_skia::{Pixmap, PremultipliedColorU8};
+
+use crate::{
+    buttons::Buttons,
+    draw_headerbar, get_margin_h_lp,
+    theme::{ColorTheme, CORNER_RADIUS, HEADER_SIZE},
+    TitleAlignment,
+};
+
+/// Compare the `pixmap` with the golden image `tests/golden/<name>.png`, allowing the small
+/// rounding differences between the SIMD backends. `SCTK_ADWAITA_BLESS=1` writes the golden
+/// image instead.
+#[allow(clippy::unwrap_used)]
+pub(crate) fn assert_golden(name: &str, pixmap: &Pixmap) {
+    let path = format!("{}/tests/golden/{name}.png", env!("CARGO_MANIFEST_DIR"));
+    if std::env::var_os("SCTK_ADWAITA_BLESS").is_some() {
+        pixmap.save_png(&path).unwrap();
+        return;
+    }
+
+    let golden = Pixmap::load_png(&path).unwrap();
+    assert_eq!(golden.width(), pixmap.width(), "{name}");
+    assert_eq!(golden.height(), pixmap.height(), "{name}");
+    for (i, (expected, actual)) in golden.pixels().iter().zip(pixmap.pixels()).enumerate() {
+        let channels = |pixel: &PremultipliedColorU8| {
+            [pixel.red(), pixel.green(), pixel.blue(), pixel.alpha()]
+        };
+        let close = channels(expected)
+            .into_iter()
+            .zip(channels(actual))
+            .all(|(expected, actual)| expected.abs_diff(actual) <= 2);
+        assert!(close, "{name} differs at the pixel {i}");
+    }
+}
+
+#[test]
+fn headerbar_matches_golden_images() {
+    const WIDTH: u32 = 240;
+
+    let states = [
+        WindowState::ACTIVATED,
+        WindowState::empty(),
+        WindowState::ACTIVATED | WindowState::MAXIMIZED,
+        WindowState::ACTIVATED | WindowState::TILED,
+    ];
+    let layouts = [
+        ("", "minimize,maximize,close"),
+        ("close", ""),
+        ("close", "minimize,maximize"),
+    ];
+    let theme = ColorTheme::light();
+
+    for scale in [1, 2] {
+        let (width, height) = (WIDTH * scale, HEADER_SIZE * scale);
+        let rows = (states.len() * layouts.len()) as u32;
+        #[allow(clippy::unwrap_used)]
+        let mut sheet = Pixmap::new(width, rows * height).unwrap();
+        // The stand-in for the title text, which depends on the installed fonts.
+        #[allow(clippy::unwrap_used)]
+        let mut title = Pixmap::new(60 * scale, 12 * scale).unwrap();
+        title.fill(theme.active.font_color);
+
+        for (row, (state, (left, right))) in states
+            .into_iter()
+            .flat_map(|state| layouts.map(|layout| (state, layout)))
+            .enumerate()
+        {
+            let mut buttons = Buttons::new(Some((left.into(), right.into())));
+            buttons.arrange(WIDTH, get_margin_h_lp(&state));
+            let corner_radius = if state.intersects(WindowState::MAXIMIZED | WindowState::TILED) {
+                0.
+            } else {
+                CORNER_RADIUS as f32
+            };
+
+            #[allow(clippy::unwrap_used)]
+            let mut pixmap = Pixmap::new(width, height).unwrap();
+            draw_headerbar(
+                &mut pixmap.as_mut(),
+                Some((&title).into()),
+                None,
+                None,
+                scale as f32,
+                true,
+                &state,
+                &theme,
+                &mut buttons,
+                &[],
+                &[],
+                false,
+                TitleAlignment::Center,
+                corner_radius,
+            );
+            sheet.draw_pixmap(
+                0,
+                (row as u32 * height) as i32,
+                pixmap.as_ref(),
+                &Default::default(),
+                Default::default(),
+                None,
+            );
+        }
+
+        assert_golden(&format!("headerbar@{scale}x"), &sheet);
+    }
+}
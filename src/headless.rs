@@ -0,0 +1,472 @@
+//! Rendering of the decorations without the Wayland connection.
+//!
+//! Runs the same drawing code as [`AdwaitaFrame`], so it could be used to render the golden
+//! images of the whole decorations in the tests.
+//!
+//! [`AdwaitaFrame`]: crate::AdwaitaFrame
+use smithay_client_toolkit::reexports::csd_frame::WindowState;
+use tiny_skia::Pixmap;
+
+use crate::{
+    buttons::Buttons,
+    get_margin_h_lp,
+    parts::{DecorationParts, PartId},
+    shadow::Shadow,
+    theme::HEADER_SIZE,
+    title::TitleText,
+    ButtonKind, DecorationMode, FrameConfig, HeaderPosition, PartPainter, Rect,
+};
+
+/// Render the visible parts of the decorations around the main surface of the given size in
+/// logical points, in the order they're stacked.
+///
+/// The title isn't drawn, since the result would depend on the fonts installed in the system,
+/// and the buttons follow the default layout with the built-in icons.
+pub fn render_to_pixmaps(
+    config: &FrameConfig,
+    width: u32,
+    height: u32,
+    scale: u32,
+    state: WindowState,
+) -> Vec<(PartId, Pixmap)> {
+    HeadlessFrame::new(config.clone()).render_to_pixmaps(width, height, scale, state)
+}
+
+/// Render the whole decorations around the main surface of the given size in logical points
+/// into the single pixmap, with the main surface area left transparent.
+///
+/// The result matches the buffer of the [`DecorationLayout::SingleSurface`] layout.
+///
+/// [`DecorationLayout::SingleSurface`]: crate::DecorationLayout::SingleSurface
+pub fn render_to_pixmap(
+    config: &FrameConfig,
+    width: u32,
+    height: u32,
+    scale: u32,
+    state: WindowState,
+) -> Option<Pixmap> {
+    HeadlessFrame::new(config.clone()).render_to_pixmap(width, height, scale, state)
+}
+
+/// The decorations rendered repeatedly without the Wayland connection.
+///
+/// Keeps the rendered title, buttons and shadow between the renders like [`AdwaitaFrame`], so
+/// the cost of the redraws could be measured, see `benches/redraw.rs`.
+///
+/// [`AdwaitaFrame`]: crate::AdwaitaFrame
+#[derive(Debug)]
+pub struct HeadlessFrame {
+    config: FrameConfig,
+    buttons: Buttons,
+    shadow: Shadow,
+    title_text: Option<TitleText>,
+    hovered: Vec<ButtonKind>,
+}
+
+impl HeadlessFrame {
+    pub fn new(config: FrameConfig) -> Self {
+        let mut buttons = Buttons::new(None);
+        buttons.set_allowed(config.buttons);
+        buttons.set_icons(config.button_icons.clone());
+        let mut shadow = Shadow::default();
+        shadow.set_corner_radius(config.corner_radius);
+
+        Self {
+            config,
+            buttons,
+            shadow,
+            title_text: None,
+            hovered: Vec::new(),
+        }
+    }
+
+    /// Draw the `title`, rendered with the fonts installed in the system.
+    pub fn set_title(&mut self, title: impl Into<String>) {
+        let font_color = self.config.theme.active.font_color;
+        if self.title_text.is_none() {
+            let fonts = self.config.title_font_cache.clone().unwrap_or_default();
+            self.title_text =
+                TitleText::new(font_color, self.config.resolve_text_scale(), &fonts).ok();
+        }
+        if let Some(title_text) = self.title_text.as_mut() {
+            title_text.update_title(title);
+        }
+    }
+
+    /// Highlight the `button` as if the pointer was over it.
+    pub fn set_hovered(&mut self, button: Option<ButtonKind>) {
+        self.hovered = button.into_iter().collect();
+    }
+
+    /// Render the `part` of the decorations, `None` when it isn't visible.
+    ///
+    /// See [`render_to_pixmaps`].
+    pub fn render_part(
+        &mut self,
+        part: PartId,
+        width: u32,
+        height: u32,
+        scale: u32,
+        state: WindowState,
+    ) -> Option<Pixmap> {
+        let idx = PartId::ALL.iter().position(|id| *id == part)?;
+        self.with_painter(width, height, state, |painter, rects| {
+            if !painter.is_visible(idx) {
+                return None;
+            }
+
+            let rect = painter.buffer_rect(idx, rects[idx]);
+            let mut pixmap = Pixmap::new(rect.width * scale, rect.height * scale)?;
+            painter.draw(idx, rect, scale, &mut pixmap.as_mut());
+            Some(pixmap)
+        })
+        .flatten()
+    }
+
+    /// See [`render_to_pixmaps`].
+    pub fn render_to_pixmaps(
+        &mut self,
+        width: u32,
+        height: u32,
+        scale: u32,
+        state: WindowState,
+    ) -> Vec<(PartId, Pixmap)> {
+        PartId::ALL
+            .into_iter()
+            .filter_map(|part| {
+                let pixmap = self.render_part(part, width, height, scale, state)?;
+                Some((part, pixmap))
+            })
+            .collect()
+    }
+
+    /// See [`render_to_pixmap`].
+    pub fn render_to_pixmap(
+        &mut self,
+        width: u32,
+        height: u32,
+        scale: u32,
+        state: WindowState,
+    ) -> Option<Pixmap> {
+        self.with_painter(width, height, state, |painter, rects| {
+            let bounds = painter.bounds(&rects)?;
+            let mut pixmap = Pixmap::new(bounds.width * scale, bounds.height * scale)?;
+            painter.draw_composed(&rects, bounds, scale, &mut pixmap.as_mut());
+            Some(pixmap)
+        })
+        .flatten()
+    }
+
+    /// Run `f` with the painter of the decorations and the surface rectangles of the parts,
+    /// `None` when the decorations are hidden.
+    fn with_painter<T>(
+        &mut self,
+        width: u32,
+        height: u32,
+        state: WindowState,
+        f: impl FnOnce(&mut PartPainter, [Rect; 5]) -> T,
+    ) -> Option<T> {
+        if state.contains(WindowState::FULLSCREEN) {
+            return None;
+        }
+
+        let config = &self.config;
+        let full = config.decoration_mode == DecorationMode::Full;
+        let draw_header = !config.hide_titlebar && full;
+        let header_height = if draw_header { HEADER_SIZE } else { 0 };
+        let border_size =
+            DecorationParts::border_size_for(config.decoration_mode, config.resize_handle_size);
+        let rects = DecorationParts::surface_rects(
+            width,
+            height,
+            header_height,
+            config.header_position,
+            border_size,
+        );
+        let header_length = match config.header_position {
+            HeaderPosition::Top => width,
+            HeaderPosition::Left => height,
+        };
+        self.buttons.arrange(header_length, get_margin_h_lp(&state));
+
+        let mut painter = PartPainter {
+            state,
+            theme: &config.theme,
+            buttons: &mut self.buttons,
+            shadow: &mut self.shadow,
+            title_text: self.title_text.as_mut(),
+            title_pixmap: None,
+            subtitle_text: None,
+            icon: None,
+            hovered: self.hovered.clone(),
+            pressed: Vec::new(),
+            resize_hint: Vec::new(),
+            progress: None,
+            snap_preview: None,
+            resizable: true,
+            rtl: false,
+            title_alignment: config.title_alignment,
+            header_position: config.header_position,
+            draw_header,
+            draw_borders: !config.shadow_policy.hides_edges(&state),
+            draw_shadow: full,
+            shadow_policy: config.shadow_policy,
+            floating: if state.contains(WindowState::MAXIMIZED) {
+                0.
+            } else {
+                1.
+            },
+            activation: if state.contains(WindowState::ACTIVATED) {
+                1.
+            } else {
+                0.
+            },
+            corner_radius: config.corner_radius,
+            outline: None,
+            #[cfg(feature = "debug-overlay")]
+            debug: None,
+        };
+        if let Some(outline) = config.outline {
+            painter.outline = Some((outline, painter.outline_bounds(&rects)));
+        }
+
+        Some(f(&mut painter, rects))
+    }
+}
+
+/// Render the whole decorations with [`render_to_pixmap`] and sample the pixels at the points
+/// picked by `at` for the size of the pixmap, returned along with the samples.
+#[cfg(test)]
+fn sample_pixels<const N: usize>(
+    config: &FrameConfig,
+    (width, height, scale): (u32, u32, u32),
+    state: WindowState,
+    at: impl FnOnce(u32, u32) -> [(u32, u32); N],
+) -> ((u32, u32), [tiny_skia::PremultipliedColorU8; N]) {
+    let pixmap = render_to_pixmap(config, width, height, scale, state)
+        .expect("the decorations should be rendered");
+    let size = (pixmap.width(), pixmap.height());
+    let pixels = at(size.0, size.1).map(|(x, y)| {
+        pixmap
+            .pixel(x, y)
+            .expect("the sampled point should be inside the decorations")
+    });
+    (size, pixels)
+}
+
+/// The alpha of the pixels sampled with [`sample_pixels`].
+#[cfg(test)]
+fn sample_alpha<const N: usize>(
+    config: &FrameConfig,
+    size: (u32, u32, u32),
+    state: WindowState,
+    at: impl FnOnce(u32, u32) -> [(u32, u32); N],
+) -> ((u32, u32), [u8; N]) {
+    let (size, pixels) = sample_pixels(config, size, state, at);
+    (size, pixels.map(|pixel| pixel.alpha()))
+}
+
+#[test]
+fn render_whole_decorations() {
+    use crate::theme::{ColorTheme, BORDER_SIZE};
+
+    let config = FrameConfig::new(ColorTheme::light());
+    let parts = render_to_pixmaps(&config, 200, 100, 1, WindowState::ACTIVATED);
+    assert_eq!(parts.len(), 5);
+
+    // The middle of the headerbar is opaque and the main surface area is transparent.
+    let (size, alpha) = sample_alpha(
+        &config,
+        (200, 100, 2),
+        WindowState::ACTIVATED,
+        |width, _| {
+            let x = width / 2;
+            let header_y = (BORDER_SIZE + HEADER_SIZE / 2) * 2;
+            let main_y = (BORDER_SIZE + HEADER_SIZE + 50) * 2;
+            [(x, header_y), (x, main_y)]
+        },
+    );
+    assert_eq!(
+        size,
+        (
+            (200 + 2 * BORDER_SIZE) * 2,
+            (100 + HEADER_SIZE + 2 * BORDER_SIZE) * 2
+        )
+    );
+    assert_eq!(alpha, [255, 0]);
+
+    let maximized = WindowState::ACTIVATED | WindowState::MAXIMIZED;
+    let parts = render_to_pixmaps(&config, 200, 100, 1, maximized);
+    assert_eq!(parts.len(), 1);
+    assert_eq!(parts[0].0, PartId::Header);
+}
+
+#[test]
+fn render_border_only() {
+    use crate::theme::{ColorTheme, RESIZE_HANDLE_SIZE};
+
+    let config = FrameConfig::new(ColorTheme::light()).decoration_mode(DecorationMode::BorderOnly);
+    let parts = render_to_pixmaps(&config, 200, 100, 1, WindowState::ACTIVATED);
+    assert!(parts.iter().all(|(part, _)| *part != PartId::Header));
+
+    // The borders are as thick as the resize handles, without the header and the shadow.
+    let (size, [border, outside]) = sample_alpha(
+        &config,
+        (200, 100, 1),
+        WindowState::ACTIVATED,
+        |_, height| [(RESIZE_HANDLE_SIZE - 1, height / 2), (0, height / 2)],
+    );
+    assert_eq!(
+        size,
+        (200 + 2 * RESIZE_HANDLE_SIZE, 100 + 2 * RESIZE_HANDLE_SIZE)
+    );
+    assert_ne!(border, 0);
+    assert_eq!(outside, 0);
+}
+
+#[test]
+fn shadow_policy_leaves_out_tiled_edges() {
+    use crate::theme::ColorTheme;
+    use crate::ShadowPolicy;
+
+    let tiled = WindowState::ACTIVATED | WindowState::TILED_LEFT;
+    let config = FrameConfig::new(ColorTheme::light());
+    assert_eq!(render_to_pixmaps(&config, 200, 100, 1, tiled).len(), 5);
+
+    let config = config.shadow_policy(ShadowPolicy::NotWhenTiled);
+    let parts = render_to_pixmaps(&config, 200, 100, 1, tiled);
+    assert_eq!(parts.len(), 1);
+    assert_eq!(parts[0].0, PartId::Header);
+    assert_eq!(
+        render_to_pixmaps(&config, 200, 100, 1, WindowState::ACTIVATED).len(),
+        5
+    );
+
+    // Without the shadow only the visible border is left along the edge.
+    let config = FrameConfig::new(ColorTheme::light()).shadow_policy(ShadowPolicy::Never);
+    let (_, alpha) = sample_alpha(
+        &config,
+        (200, 100, 1),
+        WindowState::ACTIVATED,
+        |_, height| [(1, height / 2)],
+    );
+    assert_eq!(alpha, [0]);
+}
+
+#[test]
+fn render_header_on_the_left() {
+    use crate::theme::{ColorTheme, BORDER_SIZE};
+
+    // The headerbar runs along the left edge, next to the transparent main surface area.
+    let config = FrameConfig::new(ColorTheme::light()).header_position(HeaderPosition::Left);
+    let (size, alpha) = sample_alpha(
+        &config,
+        (200, 100, 1),
+        WindowState::ACTIVATED,
+        |_, height| {
+            let header_x = BORDER_SIZE + HEADER_SIZE / 2;
+            let main_x = BORDER_SIZE + HEADER_SIZE + 100;
+            [(header_x, height / 2), (main_x, height / 2)]
+        },
+    );
+    assert_eq!(
+        size,
+        (200 + HEADER_SIZE + 2 * BORDER_SIZE, 100 + 2 * BORDER_SIZE)
+    );
+    assert_eq!(alpha, [255, 0]);
+}
+
+#[test]
+fn stretched_edges_match_the_whole_parts() {
+    use crate::{stretch_segments, theme::ColorTheme};
+
+    let draw = |painter: &mut PartPainter, idx: usize, rect: Rect| {
+        let mut pixmap =
+            Pixmap::new(rect.width * 2, rect.height * 2).expect("the part shouldn't be empty");
+        painter.draw(idx, rect, 2, &mut pixmap.as_mut());
+        pixmap
+    };
+
+    for position in [HeaderPosition::Top, HeaderPosition::Left] {
+        let config = FrameConfig::new(ColorTheme::light()).header_position(position);
+        HeadlessFrame::new(config).with_painter(
+            300,
+            200,
+            WindowState::ACTIVATED,
+            |painter, rects| {
+                for (idx, surface_rect) in
+                    rects.into_iter().enumerate().take(DecorationParts::HEADER)
+                {
+                    let rect = painter.buffer_rect(idx, surface_rect);
+                    let caps = painter
+                        .stretch_caps(idx, rect)
+                        .expect("the edge should be stretched");
+                    let segments = stretch_segments(idx, rect, caps);
+                    let compressed = Rect {
+                        width: segments[2].0.x as u32 + segments[2].0.width,
+                        height: segments[2].0.y as u32 + segments[2].0.height,
+                        ..rect
+                    };
+
+                    let whole = draw(painter, idx, rect);
+                    let compressed = draw(painter, idx, compressed);
+                    for (source, destination) in segments {
+                        for y in 0..destination.height * 2 {
+                            for x in 0..destination.width * 2 {
+                                let source_x = (source.x * 2) as u32 + x.min(source.width * 2 - 1);
+                                let source_y = (source.y * 2) as u32 + y.min(source.height * 2 - 1);
+                                let whole_x = (destination.x * 2) as u32 + x;
+                                let whole_y = (destination.y * 2) as u32 + y;
+                                assert_eq!(
+                                    compressed.pixel(source_x, source_y),
+                                    whole.pixel(whole_x, whole_y),
+                                    "part {idx} at {whole_x}x{whole_y}"
+                                );
+                            }
+                        }
+                    }
+                }
+            },
+        );
+    }
+}
+
+#[test]
+fn outline_surrounds_visible_frame() {
+    use crate::theme::{ColorTheme, OutlineStyle, BORDER_SIZE};
+    use tiny_skia::PremultipliedColorU8;
+
+    // Contrasting with the light borders.
+    let black = PremultipliedColorU8::from_rgba(0, 0, 0, 255);
+    let config = FrameConfig::new(ColorTheme::light());
+    let outlined = config.clone().outline(OutlineStyle::new(2));
+    for (config, expected) in [(config, false), (outlined, true)] {
+        // Next to the left border and above the headerbar.
+        let (_, [side, top]) = sample_pixels(
+            &config,
+            (200, 100, 1),
+            WindowState::ACTIVATED,
+            |width, height| [(BORDER_SIZE - 2, height / 2), (width / 2, BORDER_SIZE - 1)],
+        );
+        assert_eq!(Some(side) == black, expected);
+        assert_eq!(Some(top) == black, expected);
+    }
+}
+
+#[test]
+fn headless_frame_redraws_hovered_button() {
+    use crate::theme::ColorTheme;
+
+    let mut frame = HeadlessFrame::new(FrameConfig::new(ColorTheme::light()));
+    let render = |frame: &mut HeadlessFrame| {
+        frame.render_part(PartId::Header, 200, 100, 1, WindowState::ACTIVATED)
+    };
+
+    let idle = render(&mut frame);
+    frame.set_hovered(Some(ButtonKind::Close));
+    let hovered = render(&mut frame);
+    frame.set_hovered(None);
+    assert!(idle.is_some());
+    assert_ne!(idle, hovered);
+    assert_eq!(idle, render(&mut frame));
+}
@@ -0,0 +1,146 @@
+//! The application icon drawn in the headerbar.
+use std::{fs, path::PathBuf};
+
+use tiny_skia::{ColorU8, Pixmap};
+
+mod lookup;
+mod svg;
+
+/// The size of the icon in the headerbar in logical points.
+pub(crate) const ICON_SIZE: u32 = 16;
+
+/// The largest width and height of the PNG icon decoded, larger ones are rejected before
+/// decoding them.
+const MAX_PNG_SIZE: u32 = 512;
+
+/// The source of the icon drawn in the headerbar.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IconSource {
+    /// Non-premultiplied RGBA8 pixels, row by row.
+    Rgba {
+        width: u32,
+        height: u32,
+        pixels: Vec<u8>,
+    },
+    /// The icon name, like `org.gnome.Nautilus`, resolved with the icon theme.
+    Themed(String),
+    /// The path to the PNG file.
+    Path(PathBuf),
+}
+
+/// The icon loaded for the scale of the headerbar.
+#[derive(Debug, Clone)]
+pub(crate) struct Icon {
+    source: IconSource,
+    /// The scale the pixmap was loaded for, with the pixmap, `None` when loading failed.
+    loaded: Option<(u32, Option<Pixmap>)>,
+}
+
+impl Icon {
+    pub fn new(source: IconSource) -> Self {
+        Self {
+            source,
+            loaded: None,
+        }
+    }
+
+    /// The icon pixmap for the given scale, in its natural size.
+    pub fn pixmap(&mut self, scale: u32) -> Option<&Pixmap> {
+        let reload = self
+            .loaded
+            .as_ref()
+            .map_or(true, |(loaded_scale, _)| *loaded_scale != scale);
+
+        if reload {
+            let pixmap = match &self.source {
+                // Independent from the scale.
+                IconSource::Rgba { .. } | IconSource::Path(_) if self.loaded.is_some() => {
+                    self.loaded.take().and_then(|(_, pixmap)| pixmap)
+                }
+                IconSource::Rgba {
+                    width,
+                    height,
+                    pixels,
+                } => from_rgba(*width, *height, pixels),
+                IconSource::Themed(name) => {
+                    let pixmap = lookup::lookup(name, ICON_SIZE * scale, &["png"])
+                        .and_then(|path| fs::read(path).ok())
+                        .and_then(|data| decode_png(&data));
+                    if pixmap.is_none() {
+                        log::warn!("failed to load the icon {name:?}");
+                    }
+                    pixmap
+                }
+                IconSource::Path(path) => {
+                    let pixmap = fs::read(path).ok().and_then(|data| decode_png(&data));
+                    if pixmap.is_none() {
+                        log::warn!("failed to load the icon {}", path.display());
+                    }
+                    pixmap
+                }
+            };
+            self.loaded = Some((scale, pixmap));
+        }
+
+        self.loaded.as_ref().and_then(|(_, pixmap)| pixmap.as_ref())
+    }
+}
+
+/// The outline of the symbolic icon `name` from the icon theme in the unit square, to be
+/// filled with the single color.
+pub(crate) fn symbolic(name: &str) -> Option<tiny_skia::Path> {
+    let path = lookup::lookup(name, ICON_SIZE, &["svg"])?;
+    let data = fs::read_to_string(&path).ok()?;
+    let icon = svg::parse_symbolic(&data);
+    if icon.is_none() {
+        log::warn!("unsupported symbolic icon {}", path.display());
+    }
+    icon
+}
+
+/// Decode the PNG icon into the premultiplied pixmap, `None` when it's larger than
+/// [`MAX_PNG_SIZE`].
+fn decode_png(data: &[u8]) -> Option<Pixmap> {
+    // The width and the height of the IHDR chunk right after the signature.
+    let size = |range: std::ops::Range<usize>| {
+        data.get(range)
+            .and_then(|bytes| bytes.try_into().ok())
+            .map(u32::from_be_bytes)
+    };
+    let (width, height) = (size(16..20)?, size(20..24)?);
+    if width > MAX_PNG_SIZE || height > MAX_PNG_SIZE {
+        log::warn!("the icon of {width}x{height} is too large");
+        return None;
+    }
+
+    Pixmap::decode_png(data).ok()
+}
+
+fn from_rgba(width: u32, height: u32, pixels: &[u8]) -> Option<Pixmap> {
+    let mut pixmap = Pixmap::new(width, height)?;
+    if pixels.len() != pixmap.pixels().len() * 4 {
+        log::error!(
+            "invalid icon size: {width}x{height}, {} bytes",
+            pixels.len()
+        );
+        return None;
+    }
+
+    for (dst, src) in pixmap.pixels_mut().iter_mut().zip(pixels.chunks_exact(4)) {
+        *dst = ColorU8::from_rgba(src[0], src[1], src[2], src[3]).premultiply();
+    }
+
+    Some(pixmap)
+}
+
+#[test]
+#[allow(clippy::unwrap_used)]
+fn decode_png_rejects_large_icons() {
+    let mut pixmap = Pixmap::new(3, 2).unwrap();
+    pixmap.fill(tiny_skia::Color::from_rgba8(10, 20, 30, 128));
+    let decoded = decode_png(&pixmap.encode_png().unwrap()).unwrap();
+    assert_eq!(decoded.data(), pixmap.data());
+
+    let large = Pixmap::new(MAX_PNG_SIZE + 1, 1).unwrap();
+    assert!(decode_png(&large.encode_png().unwrap()).is_none());
+}
@@ -0,0 +1,248 @@
+//! Icon lookup following the freedesktop Icon Theme Specification.
+//!
+//! Only the icons with the requested file extensions are considered.
+use std::{
+    collections::HashMap,
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+use crate::config;
+
+/// The theme every other theme implicitly inherits from.
+const FALLBACK_THEME: &str = "hicolor";
+
+/// Find the path of the icon `name` closest to `size` in pixels, with one of the `extensions`
+/// in the order of the preference.
+pub fn lookup(name: &str, size: u32, extensions: &[&str]) -> Option<PathBuf> {
+    let base_dirs = base_dirs();
+
+    let mut themes: Vec<String> = config::icon_theme().into_iter().collect();
+    let mut idx = 0;
+    while let Some(theme) = themes.get(idx) {
+        let Some(index) = ThemeIndex::load(&base_dirs, theme) else {
+            idx += 1;
+            continue;
+        };
+
+        if let Some(path) = index.lookup(&base_dirs, theme, name, size, extensions) {
+            return Some(path);
+        }
+
+        for parent in index.inherits {
+            if !themes.contains(&parent) {
+                themes.push(parent);
+            }
+        }
+        idx += 1;
+    }
+
+    if !themes.iter().any(|theme| theme == FALLBACK_THEME) {
+        let index = ThemeIndex::load(&base_dirs, FALLBACK_THEME);
+        if let Some(path) =
+            index.and_then(|index| index.lookup(&base_dirs, FALLBACK_THEME, name, size, extensions))
+        {
+            return Some(path);
+        }
+    }
+
+    extensions
+        .iter()
+        .map(|extension| Path::new("/usr/share/pixmaps").join(format!("{name}.{extension}")))
+        .find(|path| path.is_file())
+}
+
+/// The directories with the icon themes in the order of the precedence.
+fn base_dirs() -> Vec<PathBuf> {
+    let home = env::var_os("HOME").map(PathBuf::from);
+
+    let data_home = env::var_os("XDG_DATA_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| home.as_ref().map(|home| home.join(".local/share")));
+    let data_dirs = env::var("XDG_DATA_DIRS")
+        .ok()
+        .filter(|dirs| !dirs.is_empty())
+        .unwrap_or_else(|| String::from("/usr/local/share:/usr/share"));
+
+    home.map(|home| home.join(".icons"))
+        .into_iter()
+        .chain(data_home.map(|dir| dir.join("icons")))
+        .chain(data_dirs.split(':').map(|dir| Path::new(dir).join("icons")))
+        .collect()
+}
+
+/// The subdirectory of the theme.
+#[derive(Debug, Default)]
+struct Directory {
+    name: String,
+    size: u32,
+    scale: u32,
+    kind: String,
+    min_size: Option<u32>,
+    max_size: Option<u32>,
+    threshold: Option<u32>,
+}
+
+impl Directory {
+    /// The distance between the icons of the directory and the `size` in pixels.
+    fn distance(&self, size: u32) -> u32 {
+        let scale = self.scale.max(1);
+        let (min, max) = match self.kind.as_str() {
+            "Fixed" => (self.size, self.size),
+            "Scalable" => (
+                self.min_size.unwrap_or(self.size),
+                self.max_size.unwrap_or(self.size),
+            ),
+            _ => {
+                let threshold = self.threshold.unwrap_or(2);
+                (self.size.saturating_sub(threshold), self.size + threshold)
+            }
+        };
+
+        let (min, max) = (min * scale, max * scale);
+        if size < min {
+            min - size
+        } else {
+            size.saturating_sub(max)
+        }
+    }
+}
+
+/// The parsed `index.theme` of the theme.
+#[derive(Debug, Default)]
+struct ThemeIndex {
+    inherits: Vec<String>,
+    directories: Vec<Directory>,
+}
+
+impl ThemeIndex {
+    fn load(base_dirs: &[PathBuf], theme: &str) -> Option<Self> {
+        let content = base_dirs
+            .iter()
+            .find_map(|dir| fs::read_to_string(dir.join(theme).join("index.theme")).ok())?;
+        Some(Self::parse(&content))
+    }
+
+    fn parse(content: &str) -> Self {
+        let mut sections: HashMap<&str, HashMap<&str, &str>> = HashMap::new();
+        let mut section = "";
+        for line in content.lines().map(str::trim) {
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                section = name;
+            } else if let Some((key, value)) = line.split_once('=') {
+                sections
+                    .entry(section)
+                    .or_default()
+                    .insert(key.trim(), value.trim());
+            }
+        }
+
+        let list = |value: Option<&&str>| -> Vec<String> {
+            value
+                .map(|value| {
+                    value
+                        .split(',')
+                        .map(str::trim)
+                        .filter(|item| !item.is_empty())
+                        .map(String::from)
+                        .collect()
+                })
+                .unwrap_or_default()
+        };
+
+        let Some(theme) = sections.get("Icon Theme") else {
+            return Self::default();
+        };
+
+        let directories = list(theme.get("Directories"))
+            .into_iter()
+            .filter_map(|name| {
+                let keys = sections.get(name.as_str())?;
+                let number = |key: &str| keys.get(key).and_then(|value| value.parse().ok());
+                Some(Directory {
+                    size: number("Size")?,
+                    scale: number("Scale").unwrap_or(1),
+                    kind: keys.get("Type").unwrap_or(&"Threshold").to_string(),
+                    min_size: number("MinSize"),
+                    max_size: number("MaxSize"),
+                    threshold: number("Threshold"),
+                    name,
+                })
+            })
+            .collect();
+
+        Self {
+            inherits: list(theme.get("Inherits")),
+            directories,
+        }
+    }
+
+    /// Find the icon in the theme with the directory closest to `size`.
+    fn lookup(
+        &self,
+        base_dirs: &[PathBuf],
+        theme: &str,
+        name: &str,
+        size: u32,
+        extensions: &[&str],
+    ) -> Option<PathBuf> {
+        let file_names: Vec<_> = extensions
+            .iter()
+            .map(|extension| format!("{name}.{extension}"))
+            .collect();
+        let mut best: Option<(u32, PathBuf)> = None;
+        for directory in &self.directories {
+            let distance = directory.distance(size);
+            if best
+                .as_ref()
+                .is_some_and(|(best_distance, _)| *best_distance <= distance)
+            {
+                continue;
+            }
+
+            let path = file_names
+                .iter()
+                .flat_map(|file_name| {
+                    base_dirs
+                        .iter()
+                        .map(move |dir| dir.join(theme).join(&directory.name).join(file_name))
+                })
+                .find(|path| path.is_file());
+            if let Some(path) = path {
+                best = Some((distance, path));
+            }
+        }
+
+        best.map(|(_, path)| path)
+    }
+}
+
+#[test]
+fn theme_index_parse() {
+    let index = ThemeIndex::parse(
+        "[Icon Theme]\n\
+         Name=Test\n\
+         Inherits=Adwaita, hicolor\n\
+         Directories=16x16/apps,scalable/apps,48x48/apps\n\
+         \n\
+         [16x16/apps]\n\
+         Size=16\n\
+         Type=Fixed\n\
+         \n\
+         [scalable/apps]\n\
+         Size=128\n\
+         MinSize=8\n\
+         MaxSize=512\n\
+         Type=Scalable\n\
+         \n\
+         [48x48/apps]\n\
+         Size=48\n",
+    );
+
+    assert_eq!(index.inherits, ["Adwaita", "hicolor"]);
+    assert_eq!(index.directories.len(), 3);
+    assert_eq!(index.directories[0].distance(32), 16);
+    assert_eq!(index.directories[1].distance(32), 0);
+    assert_eq!(index.directories[2].distance(32), 14);
+}
@@ -0,0 +1,257 @@
+//! Minimal SVG reader for the symbolic icons.
+//!
+//! The symbolic icons are drawn with the single color, so only the outlines of the `<path>`
+//! elements are read, without their styles. The icons with the arcs or the transforms aren't
+//! supported.
+use tiny_skia::{Path, PathBuilder, Transform};
+
+/// Parse the outline of the symbolic icon, scaled into the unit square.
+pub fn parse_symbolic(data: &str) -> Option<Path> {
+    let svg = elements(data, "svg").next()?;
+    let (min_x, min_y, width, height) = view_box(svg)?;
+    if data.contains("transform=") {
+        return None;
+    }
+
+    let mut pb = PathBuilder::new();
+    for element in elements(data, "path") {
+        parse_path_data(attribute(element, "d")?, &mut pb)?;
+    }
+
+    pb.finish()?
+        .transform(Transform::from_translate(-min_x, -min_y).post_scale(1. / width, 1. / height))
+}
+
+/// The `viewBox` of the `<svg>` element, or its size when it's missing.
+fn view_box(svg: &str) -> Option<(f32, f32, f32, f32)> {
+    let (min_x, min_y, width, height) = match attribute(svg, "viewBox") {
+        Some(view_box) => {
+            let mut numbers = view_box
+                .split(|c: char| c.is_whitespace() || c == ',')
+                .filter(|number| !number.is_empty())
+                .map(|number| number.parse::<f32>().ok());
+            (
+                numbers.next()??,
+                numbers.next()??,
+                numbers.next()??,
+                numbers.next()??,
+            )
+        }
+        None => {
+            let length = |name| {
+                let value = attribute(svg, name)?;
+                value.strip_suffix("px").unwrap_or(value).parse().ok()
+            };
+            (0., 0., length("width")?, length("height")?)
+        }
+    };
+
+    (width > 0. && height > 0.).then_some((min_x, min_y, width, height))
+}
+
+/// The start tags of the elements with the `name`, without the `<` and `>`.
+fn elements<'a>(data: &'a str, name: &'a str) -> impl Iterator<Item = &'a str> {
+    data.match_indices('<').filter_map(move |(start, _)| {
+        let tag = data[start + 1..].strip_prefix(name)?;
+        if !tag.starts_with(|c: char| c.is_whitespace() || c == '/' || c == '>') {
+            return None;
+        }
+
+        let end = tag.find('>')?;
+        Some(&tag[..end])
+    })
+}
+
+/// The value of the attribute `name` in the start tag.
+fn attribute<'a>(tag: &'a str, name: &str) -> Option<&'a str> {
+    tag.match_indices(name).find_map(|(start, _)| {
+        if !tag[..start].ends_with(char::is_whitespace) {
+            return None;
+        }
+
+        let value = tag[start + name.len()..].trim_start().strip_prefix('=')?;
+        let value = value.trim_start();
+        let quote = value.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+        let value = &value[1..];
+        value.find(quote).map(|end| &value[..end])
+    })
+}
+
+/// Append the outline of the path data to the `pb`.
+fn parse_path_data(data: &str, pb: &mut PathBuilder) -> Option<()> {
+    let mut tokens = Tokens { data, pos: 0 };
+    let mut command = None;
+    let mut current = (0., 0.);
+    let mut start = (0., 0.);
+    // The control points to reflect for the smooth curves.
+    let mut last_cubic = None;
+    let mut last_quad = None;
+
+    while let Some(next) = tokens.command(command) {
+        let relative = next.is_ascii_lowercase();
+        let point = |tokens: &mut Tokens, current: (f32, f32)| -> Option<(f32, f32)> {
+            let (x, y) = (tokens.number()?, tokens.number()?);
+            Some(if relative {
+                (current.0 + x, current.1 + y)
+            } else {
+                (x, y)
+            })
+        };
+        let reflect = |control: Option<(f32, f32)>, current: (f32, f32)| {
+            control.map_or(current, |(x, y)| (2. * current.0 - x, 2. * current.1 - y))
+        };
+
+        let (mut cubic, mut quad) = (None, None);
+        match next.to_ascii_uppercase() {
+            'M' => {
+                current = point(&mut tokens, current)?;
+                start = current;
+                pb.move_to(current.0, current.1);
+                // The following coordinates are the lines.
+                command = Some(if relative { 'l' } else { 'L' });
+                last_cubic = None;
+                last_quad = None;
+                continue;
+            }
+            'L' => {
+                current = point(&mut tokens, current)?;
+                pb.line_to(current.0, current.1);
+            }
+            'H' => {
+                let x = tokens.number()?;
+                current.0 = if relative { current.0 + x } else { x };
+                pb.line_to(current.0, current.1);
+            }
+            'V' => {
+                let y = tokens.number()?;
+                current.1 = if relative { current.1 + y } else { y };
+                pb.line_to(current.0, current.1);
+            }
+            'C' => {
+                let control1 = point(&mut tokens, current)?;
+                let control2 = point(&mut tokens, current)?;
+                let end = point(&mut tokens, current)?;
+                pb.cubic_to(control1.0, control1.1, control2.0, control2.1, end.0, end.1);
+                cubic = Some(control2);
+                current = end;
+            }
+            'S' => {
+                let control1 = reflect(last_cubic, current);
+                let control2 = point(&mut tokens, current)?;
+                let end = point(&mut tokens, current)?;
+                pb.cubic_to(control1.0, control1.1, control2.0, control2.1, end.0, end.1);
+                cubic = Some(control2);
+                current = end;
+            }
+            'Q' => {
+                let control = point(&mut tokens, current)?;
+                let end = point(&mut tokens, current)?;
+                pb.quad_to(control.0, control.1, end.0, end.1);
+                quad = Some(control);
+                current = end;
+            }
+            'T' => {
+                let control = reflect(last_quad, current);
+                let end = point(&mut tokens, current)?;
+                pb.quad_to(control.0, control.1, end.0, end.1);
+                quad = Some(control);
+                current = end;
+            }
+            'Z' => {
+                pb.close();
+                current = start;
+            }
+            // The arcs aren't supported.
+            _ => return None,
+        }
+
+        last_cubic = cubic;
+        last_quad = quad;
+        command = Some(next);
+    }
+
+    tokens.is_empty().then_some(())
+}
+
+/// The tokens of the path data.
+struct Tokens<'a> {
+    data: &'a str,
+    pos: usize,
+}
+
+impl Tokens<'_> {
+    fn skip_separators(&mut self) {
+        let rest = &self.data[self.pos..];
+        let trimmed = rest.trim_start_matches(|c: char| c.is_whitespace() || c == ',');
+        self.pos += rest.len() - trimmed.len();
+    }
+
+    fn is_empty(&mut self) -> bool {
+        self.skip_separators();
+        self.pos == self.data.len()
+    }
+
+    /// The next command, or the `current` one repeated when the numbers follow.
+    fn command(&mut self, current: Option<char>) -> Option<char> {
+        self.skip_separators();
+        let next = self.data[self.pos..].chars().next()?;
+        if next.is_ascii_alphabetic() {
+            self.pos += 1;
+            Some(next)
+        } else {
+            // Nothing follows the close path.
+            current.filter(|command| !command.eq_ignore_ascii_case(&'z'))
+        }
+    }
+
+    fn number(&mut self) -> Option<f32> {
+        self.skip_separators();
+        let bytes = self.data.as_bytes();
+        let start = self.pos;
+        let mut end = start;
+
+        let digits = |end: &mut usize| {
+            while bytes.get(*end).is_some_and(u8::is_ascii_digit) {
+                *end += 1;
+            }
+        };
+
+        if matches!(bytes.get(end), Some(b'+' | b'-')) {
+            end += 1;
+        }
+        digits(&mut end);
+        if bytes.get(end) == Some(&b'.') {
+            end += 1;
+            digits(&mut end);
+        }
+        if matches!(bytes.get(end), Some(b'e' | b'E')) {
+            end += 1;
+            if matches!(bytes.get(end), Some(b'+' | b'-')) {
+                end += 1;
+            }
+            digits(&mut end);
+        }
+
+        let number = self.data[start..end].parse().ok()?;
+        self.pos = end;
+        Some(number)
+    }
+}
+
+#[test]
+fn parse_symbolic_icon() {
+    let icon = r##"<?xml version="1.0" encoding="UTF-8"?>
+        <svg height="16px" viewBox="0 0 16 16" width="16px" xmlns="http://www.w3.org/2000/svg">
+            <path d="m 4 10 h 8 v 2 h -8 z m 0 0" fill="#2e3436"/>
+        </svg>"##;
+
+    #[allow(clippy::unwrap_used)]
+    let bounds = parse_symbolic(icon).unwrap().bounds();
+    assert_eq!(bounds.left(), 0.25);
+    assert_eq!(bounds.right(), 0.75);
+    assert_eq!(bounds.top(), 0.625);
+    assert_eq!(bounds.bottom(), 0.75);
+
+    let arc = r#"<svg width="16" height="16"><path d="M 0 0 a 4 4 0 0 0 8 8"/></svg>"#;
+    assert!(parse_symbolic(arc).is_none());
+}
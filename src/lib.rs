@@ -1,12 +1,14 @@
 use std::error::Error;
+use std::fmt;
+use std::marker::PhantomData;
 use std::mem;
 use std::num::NonZeroU32;
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use tiny_skia::{
-    Color, FillRule, Mask, Path, PathBuilder, Pixmap, PixmapMut, PixmapPaint, Point, Rect,
-    Transform,
+    BlendMode, Color, FillRule, FilterQuality, Paint, Path, PathBuilder, Pattern, Pixmap,
+    PixmapMut, PixmapPaint, PixmapRef, Point, SpreadMode, Transform,
 };
 
 use smithay_client_toolkit::reexports::client::backend::ObjectId;
@@ -19,31 +21,74 @@ use smithay_client_toolkit::reexports::csd_frame::{
 };
 
 use smithay_client_toolkit::compositor::{CompositorState, Region, SurfaceData};
+use smithay_client_toolkit::error::GlobalError;
+use smithay_client_toolkit::globals::ProvidesBoundGlobal;
 use smithay_client_toolkit::shell::WaylandSurface;
 use smithay_client_toolkit::shm::{slot::SlotPool, Shm};
 use smithay_client_toolkit::subcompositor::SubcompositorState;
 use smithay_client_toolkit::subcompositor::SubsurfaceData;
 
+mod animation;
+#[cfg(feature = "blur")]
+mod blur;
 mod buttons;
+mod color;
 mod config;
+#[cfg(feature = "debug-overlay")]
+mod debug;
+mod description;
+mod globals;
+#[cfg(test)]
+mod golden;
+#[cfg(feature = "headless")]
+mod headless;
+mod icon;
+mod negotiation;
 mod parts;
 mod pointer;
+mod render_thread;
 mod shadow;
 pub mod theme;
 mod title;
 mod wl_typed;
 
 use crate::theme::{
-    ColorMap, ColorTheme, BORDER_SIZE, CORNER_RADIUS, HEADER_SIZE, RESIZE_HANDLE_CORNER_SIZE,
-    VISIBLE_BORDER_SIZE,
+    ColorMap, ColorTheme, OutlineStyle, ThemeState, CORNER_RADIUS, RESIZE_HANDLE_CORNER_SIZE,
+    RESIZE_HANDLE_SIZE, RESIZE_HINT_SIZE, VISIBLE_BORDER_SIZE,
 };
 
-use buttons::Buttons;
-use config::get_button_layout_config;
-use parts::DecorationParts;
-use pointer::{Location, MouseState};
-use shadow::Shadow;
-use title::TitleText;
+pub use buttons::{AdwaitaButtonIcons, ButtonIconProvider, ButtonKind, ButtonSet, Side};
+pub use color::ColorSpace;
+pub use config::SystemPrefs;
+pub use description::FrameDescription;
+pub use globals::FrameGlobals;
+#[cfg(feature = "headless")]
+pub use headless::{render_to_pixmap, render_to_pixmaps, HeadlessFrame};
+pub use icon::IconSource;
+pub use negotiation::DecorationNegotiator;
+pub use parts::{
+    DecorationLayout, DecorationMode, HeaderPosition, Insets, PartId, Rect, ShadowPolicy,
+    SurfaceFactory,
+};
+pub use pointer::{
+    cursor_shape, CursorMap, DoubleClickAction, DragModifier, HeaderbarAction, Location,
+    MiddleClickAction, Modifiers, ScrollAction, SeatId, SnapRegion,
+};
+pub use title::{Hinting, SubpixelOrder, TitleAlignment, TitleCacheStats, TitleFontCache};
+
+use animation::{Animated, Pulse};
+use buttons::{ButtonIcons, Buttons, CustomButtonCallback};
+use color::ColorManagement;
+use config::{get_button_layout_config, ButtonLayoutWatcher};
+use icon::{Icon, ICON_SIZE};
+use parts::{
+    opaque_format, uniform_color, DecorationParts, PartSurface, SctkSurfaceFactory, SolidBuffers,
+    SurfaceFactoryBox,
+};
+use pointer::{ClickConfig, Pointers, DEFAULT_SEAT, DOUBLE_CLICK_DURATION};
+use render_thread::{RenderJob, RenderReadyCallback, RenderTarget, RenderThread};
+use shadow::{Shadow, SHADOW_SIZE};
+use title::{TextRendering, TitleLine, TitlePixmap, TitleText};
 use wl_typed::WlTyped;
 
 /// XXX this is not result, so `must_use` when needed.
@@ -57,21 +102,50 @@ pub struct AdwaitaFrame<State> {
 
     compositor: Arc<CompositorState>,
 
-    /// Subcompositor to create/drop subsurfaces ondemand.
-    subcompositor: Arc<SubcompositorState>,
+    /// Creates the subsurfaces of the parts ondemand.
+    surface_factory: SurfaceFactoryBox,
 
-    /// Queue handle to perform object creation.
-    queue_handle: QueueHandle<State>,
+    /// The `State` dispatching the events of the base surface.
+    _state: PhantomData<State>,
 
     /// The drawable decorations, `None` when hidden.
     decorations: Option<DecorationParts>,
+    /// The last size of the main surface in logical points, restored once unhidden.
+    last_size: Option<(NonZeroU32, NonZeroU32)>,
+
+    /// How the decorations are split into the subsurfaces.
+    layout: DecorationLayout,
+    /// Which of the decorations are drawn.
+    mode: DecorationMode,
+    /// Where the shadow is drawn, and whether the border parts are left out when tiled.
+    shadow_policy: ShadowPolicy,
 
     /// Memory pool to allocate the buffers for the decorations.
     pool: SlotPool,
+    /// The `wl_shm` the `pool` is created with, to recreate it smaller.
+    shm: ShmGlobal,
+    /// Whether the buffers are released while the window is suspended.
+    release_suspended_buffers: bool,
+    /// The cursors shown over the parts of the frame.
+    cursor_map: CursorMap,
+
+    /// The buffer format for the parts without the transparency.
+    opaque_format: wl_shm::Format,
+
+    /// The optional globals shared with the other frames of the app.
+    globals: FrameGlobals,
+    /// The color space the decorations are tagged with.
+    color_space: ColorSpace,
+    /// Whether the parts are tagged with the image description of the `color_space`.
+    color_tagged: bool,
 
     /// Whether the frame should be redrawn.
     dirty: bool,
 
+    /// The unfinished [`AdwaitaFrame::freeze`] calls, the frame isn't drawn until all of them
+    /// are thawed.
+    frozen: Freeze,
+
     /// Whether the drawing should be synced with the main surface.
     should_sync: bool,
 
@@ -81,520 +155,3952 @@ pub struct AdwaitaFrame<State> {
     /// Wether the frame is resizable.
     resizable: bool,
 
+    /// Whether only the borders should be drawn, without the headerbar.
+    hide_titlebar: bool,
+
+    /// The height of the headerbar fitting the titlebar font.
+    header_size: u32,
+
+    /// Whether the layout is outlined over the decorations, see the `debug` module.
+    #[cfg(feature = "debug-overlay")]
+    debug_overlay: bool,
+
+    /// Whether the user interface language is written right-to-left.
+    rtl: bool,
+
+    title_alignment: TitleAlignment,
+
+    /// The edge of the window the headerbar is placed along.
+    header_position: HeaderPosition,
+
+    /// Whether to hint the resize edge under the pointer.
+    resize_hint: bool,
+
+    /// The line outside the visible borders, `None` when it's not drawn.
+    outline: Option<OutlineStyle>,
+
+    /// The size of the resize handles along the edges.
+    resize_handle_size: u32,
+    /// The size of the resize handles of the corners along the edges.
+    resize_corner_size: u32,
+
+    /// The progress shown along the bottom of the headerbar.
+    progress: Option<f32>,
+
+    /// The snap preview drawn over the borders.
+    snap_preview: Option<SnapRegion>,
+
+    /// The error of the last redraw.
+    last_error: Option<FrameError>,
+
+    /// Whether the window needs attention.
+    needs_attention: bool,
+    /// The attention tint of the headerbar, `0` without it.
+    attention: Animated,
+    /// The accent flash of the headerbar, see [`Self::flash`].
+    flash: Pulse,
+
+    /// The handling of the clicks on the headerbar.
+    click_config: ClickConfig,
+
+    /// The action on the middle click on the headerbar.
+    middle_click_action: MiddleClickAction,
+    /// The actions on the clicks of the other buttons on the headerbar, by their codes.
+    button_actions: Vec<(u32, MiddleClickAction)>,
+    /// The action on the scroll over the headerbar.
+    scroll_action: ScrollAction,
+
+    /// Whether the window state transitions are animated.
+    animations: bool,
+    /// The roundness of the corners and the opacity of the shadow, `0` when maximized.
+    floating: Animated,
+
+    /// The radius of the top corners in logical points when floating.
+    corner_radius: u32,
+    /// The shadow crossfading between the inactive one at `0` and the active one at `1`.
+    activation: Animated,
+
     buttons: Buttons,
+    /// The watcher of the buttons layout in the system configuration, `None` when the layout
+    /// is set by the application.
+    button_layout_watcher: Option<ButtonLayoutWatcher>,
     state: WindowState,
     wm_capabilities: WindowManagerCapabilities,
-    mouse: MouseState,
+    /// The pointers of the seats inside the frame.
+    pointers: Pointers,
     theme: ColorTheme,
+    /// The theme fading into [`Self::theme`] after it changed, with the fade at `0` at it.
+    theme_fade: Option<(ColorTheme, Animated)>,
     title: Option<String>,
     title_text: Option<TitleText>,
+    /// The title pre-rendered by the application, drawn in place of the `title_text`.
+    title_pixmap: Option<TitlePixmap>,
+    /// The smaller line under the title, `None` when there's no subtitle.
+    subtitle_text: Option<TitleText>,
+    /// The hinting and the subpixel rendering of the title and the subtitle.
+    text_rendering: TextRendering,
+    /// The factor the sizes of the fonts are scaled by, see [`SystemPrefs::text_scale`].
+    text_scale: f32,
+    /// The font the title and the subtitle are created with.
+    title_fonts: TitleFontCache,
+    icon: Option<Icon>,
+    /// The callback invoked when the custom button is clicked.
+    custom_button_callback: Option<CustomButtonCallback>,
+    /// The callback invoked when the frame becomes dirty.
+    dirty_callback: Option<DirtyCallback>,
+    /// The callback invoked when the maximize button is held down.
+    tile_menu_callback: Option<TileMenuCallback>,
+    /// The callback invoked on the render thread once the parts are drawn.
+    render_ready_callback: Option<RenderReadyCallback>,
+    /// Whether the parts are drawn on the render thread.
+    threaded_rendering: bool,
+    /// The thread drawing the parts, spawned with the first job.
+    render_thread: Option<RenderThread>,
+    /// The parts drawn on the render thread waiting to be presented.
+    rendered_parts: Vec<(RenderTarget, Option<Pixmap>)>,
+    /// Whether the title was truncated and the statistics of the title caches when the job was
+    /// sent to the render thread, answered while the title is moved to it.
+    sent_title_metrics: (bool, TitleCacheStats),
     shadow: Shadow,
 }
 
+/// The `wl_shm` global to recreate the buffer pool with.
+#[derive(Debug)]
+struct ShmGlobal(wl_shm::WlShm);
+
+impl ProvidesBoundGlobal<wl_shm::WlShm, 1> for ShmGlobal {
+    fn bound_global(&self) -> Result<wl_shm::WlShm, GlobalError> {
+        Ok(self.0.clone())
+    }
+}
+
+/// The callback invoked when the frame becomes dirty, see [`AdwaitaFrame::on_dirty`].
+/// The number of the nested batches of updates, see [`AdwaitaFrame::freeze`].
+#[derive(Debug, Default, Clone, Copy)]
+struct Freeze(u32);
+
+impl Freeze {
+    fn freeze(&mut self) {
+        self.0 += 1;
+    }
+
+    /// Finish the innermost batch, the unmatched calls are ignored.
+    fn thaw(&mut self) {
+        self.0 = self.0.saturating_sub(1);
+    }
+
+    fn is_frozen(self) -> bool {
+        self.0 > 0
+    }
+}
+
+#[derive(Clone)]
+struct DirtyCallback(Arc<dyn Fn() + Send + Sync>);
+
+impl DirtyCallback {
+    /// Invoke the callback when the frame became dirty since `was_dirty`.
+    fn notify(&self, was_dirty: bool, is_dirty: bool) {
+        if !was_dirty && is_dirty {
+            (self.0)();
+        }
+    }
+}
+
+impl fmt::Debug for DirtyCallback {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DirtyCallback").finish_non_exhaustive()
+    }
+}
+
+/// The callback invoked when the maximize button is held down, see
+/// [`AdwaitaFrame::on_tile_menu`].
+struct TileMenuCallback(Box<dyn FnMut(Rect)>);
+
+impl fmt::Debug for TileMenuCallback {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TileMenuCallback").finish_non_exhaustive()
+    }
+}
+
 impl<State> AdwaitaFrame<State>
 where
     State: Dispatch<WlSurface, SurfaceData> + Dispatch<WlSubsurface, SubsurfaceData> + 'static,
 {
+    /// Create the frame around the `base_surface`.
+    ///
+    /// The decorations are drawn into the subsurfaces, so the frame can't be created without
+    /// the `subcompositor`, e.g. when the compositor doesn't advertise `wl_subcompositor`. It
+    /// fails with [`FrameError::MissingGlobal`] then, the decorations could still be drawn into
+    /// the buffers of the caller with the `headless` feature.
     pub fn new(
         base_surface: &impl WaylandSurface,
         shm: &Shm,
         compositor: Arc<CompositorState>,
-        subcompositor: Arc<SubcompositorState>,
+        subcompositor: impl Into<Option<Arc<SubcompositorState>>>,
         queue_handle: QueueHandle<State>,
         frame_config: FrameConfig,
-    ) -> Result<Self, Box<dyn Error>> {
+    ) -> Result<Self, FrameError> {
+        let subcompositor = subcompositor
+            .into()
+            .ok_or(FrameError::MissingGlobal("wl_subcompositor"))?;
+        let surface_factory = SctkSurfaceFactory {
+            subcompositor,
+            queue_handle,
+        };
+
+        Self::with_surface_factory(base_surface, shm, compositor, surface_factory, frame_config)
+    }
+
+    /// Create the frame around the `base_surface` with the subsurfaces of the parts created by
+    /// the `surface_factory`, instead of the SCTK `wl_subcompositor` of [`Self::new`].
+    ///
+    /// Fails with [`FrameError::PoolCreation`] when the memory pool of the buffers can't be
+    /// created. The frame is still created when the titlebar font can't be loaded, only without
+    /// the title.
+    pub fn with_surface_factory(
+        base_surface: &impl WaylandSurface,
+        shm: &Shm,
+        compositor: Arc<CompositorState>,
+        surface_factory: impl SurfaceFactory + 'static,
+        frame_config: FrameConfig,
+    ) -> Result<Self, FrameError> {
+        let surface_factory = SurfaceFactoryBox(Box::new(surface_factory));
         let base_surface = WlTyped::wrap::<State>(base_surface.wl_surface().clone());
 
-        let pool = SlotPool::new(1, shm)?;
+        let pool =
+            SlotPool::new(1, shm).map_err(|err| FrameError::PoolCreation(err.to_string()))?;
+        let shm_global = ShmGlobal(shm.wl_shm().clone());
 
-        let decorations = Some(DecorationParts::new(
+        let hide_titlebar = frame_config.hide_titlebar;
+        let text_scale = frame_config.resolve_text_scale();
+        let header_size = frame_config.resolve_header_height(text_scale);
+        let layout = frame_config.layout;
+        let mode = frame_config.decoration_mode;
+        let has_header = !hide_titlebar && mode == DecorationMode::Full;
+        let mut decorations = DecorationParts::new(
             &base_surface,
-            &subcompositor,
-            &queue_handle,
-        ));
+            &*surface_factory.0,
+            if has_header { header_size } else { 0 },
+            layout,
+            mode,
+        );
+        decorations.set_handle_size(frame_config.resize_handle_size);
+        decorations.set_header_position(frame_config.header_position);
+
+        let click_config = frame_config.resolve_click_config();
+        let middle_click_action = frame_config.resolve_middle_click_action();
+        let text_rendering = frame_config.resolve_text_rendering();
+        let animations = frame_config.resolve_animations();
+        let mut buttons = Buttons::new(frame_config.resolve_button_layout());
+        buttons.set_allowed(frame_config.buttons);
+        buttons.set_themed_icons(frame_config.themed_button_icons);
+        buttons.set_icons(frame_config.button_icons.clone());
+        buttons.set_header_height(header_size);
+
+        let mut shadow = Shadow::default();
+        shadow.set_corner_radius(frame_config.corner_radius);
+        shadow.prerender(1.);
 
         let theme = frame_config.theme;
+        let title_fonts = frame_config.title_font_cache.clone().unwrap_or_default();
+        let mut title_text = TitleText::new(theme.active.font_color, text_scale, &title_fonts)
+            .map_err(|err| log::warn!("{err}"))
+            .ok();
+        if let Some(title_text) = title_text.as_mut() {
+            title_text.update_rendering(text_rendering);
+        }
 
         Ok(AdwaitaFrame {
             base_surface,
-            decorations,
+            decorations: Some(decorations),
+            last_size: None,
+            layout,
+            mode,
+            shadow_policy: frame_config.shadow_policy,
             pool,
+            shm: shm_global,
+            release_suspended_buffers: frame_config.release_suspended_buffers,
+            cursor_map: frame_config.cursor_map,
+            opaque_format: opaque_format(shm.formats()),
+            globals: frame_config.globals.clone(),
+            color_space: frame_config.color_space,
+            color_tagged: false,
             compositor,
-            subcompositor,
-            queue_handle,
+            surface_factory,
+            _state: PhantomData,
             dirty: true,
+            frozen: Freeze::default(),
             scale_factor: 1,
             should_sync: true,
             title: None,
-            title_text: TitleText::new(theme.active.font_color),
+            title_text,
+            title_pixmap: None,
+            subtitle_text: None,
+            text_rendering,
+            text_scale,
+            title_fonts,
+            icon: None,
+            custom_button_callback: None,
+            dirty_callback: None,
+            tile_menu_callback: None,
+            render_ready_callback: None,
+            threaded_rendering: frame_config.threaded_rendering,
+            render_thread: None,
+            rendered_parts: Vec::new(),
+            sent_title_metrics: (false, TitleCacheStats::default()),
             theme,
-            buttons: Buttons::new(get_button_layout_config()),
-            mouse: Default::default(),
+            theme_fade: None,
+            buttons,
+            button_layout_watcher: frame_config
+                .button_layout
+                .is_none()
+                .then(ButtonLayoutWatcher::new)
+                .flatten(),
+            pointers: Default::default(),
             state: WindowState::empty(),
             wm_capabilities: WindowManagerCapabilities::all(),
             resizable: true,
-            shadow: Shadow::default(),
+            hide_titlebar,
+            header_size,
+            #[cfg(feature = "debug-overlay")]
+            debug_overlay: debug::enabled(),
+            rtl: config::locale_is_rtl(),
+            title_alignment: frame_config.title_alignment,
+            header_position: frame_config.header_position,
+            resize_hint: frame_config.resize_hint,
+            outline: frame_config.outline,
+            resize_handle_size: frame_config.resize_handle_size,
+            resize_corner_size: frame_config.resize_corner_size,
+            progress: None,
+            snap_preview: None,
+            last_error: None,
+            needs_attention: false,
+            attention: Animated::new(0.),
+            flash: Pulse::default(),
+            click_config,
+            middle_click_action,
+            button_actions: frame_config.button_actions,
+            scroll_action: frame_config.scroll_action,
+            animations,
+            floating: Animated::new(1.),
+            activation: Animated::new(0.),
+            corner_radius: frame_config.corner_radius,
+            shadow,
         })
     }
 
     /// Update the current frame config.
+    ///
+    /// When [`FrameConfig::hide_titlebar`] or [`FrameConfig::decoration_mode`] changes, the
+    /// size of the borders changes as well, so the window geometry should be recomputed with
+    /// [`DecorationsFrame::add_borders`].
     pub fn set_config(&mut self, config: FrameConfig) {
+        self.join_render();
+        self.text_rendering = config.resolve_text_rendering();
+        for text in [self.title_text.as_mut(), self.subtitle_text.as_mut()]
+            .into_iter()
+            .flatten()
+        {
+            text.update_rendering(self.text_rendering);
+        }
+        self.click_config = config.resolve_click_config();
+        self.middle_click_action = config.resolve_middle_click_action();
+        self.button_actions = config.button_actions.clone();
+        self.scroll_action = config.scroll_action;
+        self.buttons
+            .set_layout_config(config.resolve_button_layout());
+        if config.button_layout.is_some() {
+            self.button_layout_watcher = None;
+        } else if self.button_layout_watcher.is_none() {
+            self.button_layout_watcher = ButtonLayoutWatcher::new();
+            if let Some(watcher) = self.button_layout_watcher.as_ref() {
+                watcher.set_waker(
+                    self.dirty_callback
+                        .as_ref()
+                        .map(|callback| callback.0.clone()),
+                );
+            }
+        }
+        if self.animations && self.theme != config.theme {
+            // Fade from the colors shown right now, even in the middle of the previous fade.
+            let now = Instant::now();
+            let from = self.faded_theme(now).unwrap_or_else(|| self.theme.clone());
+            let mut fade = Animated::new(0.);
+            fade.set(1., now, true);
+            self.theme_fade = Some((from, fade));
+        }
         self.theme = config.theme;
-        self.dirty = true;
-    }
-
-    fn precise_location(
-        &self,
-        location: Location,
-        decoration: &DecorationParts,
-        x: f64,
-        y: f64,
-    ) -> Location {
-        let header_width = decoration.header().surface_rect.width;
-        let side_height = decoration.side_height();
+        self.hide_titlebar = config.hide_titlebar;
+        self.title_alignment = config.title_alignment;
+        self.header_position = config.header_position;
+        self.release_suspended_buffers = config.release_suspended_buffers;
+        self.cursor_map = config.cursor_map;
+        self.shadow_policy = config.shadow_policy;
+        self.threaded_rendering = config.threaded_rendering;
+        if !self.threaded_rendering {
+            self.render_thread = None;
+        }
+        if self.color_space != config.color_space {
+            self.color_space = config.color_space;
+            self.mark_dirty();
+        }
+        self.resize_hint = config.resize_hint;
+        self.outline = config.outline;
+        self.resize_handle_size = config.resize_handle_size;
+        self.resize_corner_size = config.resize_corner_size;
+        self.buttons.set_allowed(config.buttons);
+        self.buttons.set_themed_icons(config.themed_button_icons);
+        self.buttons.set_icons(config.button_icons.clone());
+        self.corner_radius = config.corner_radius;
+        self.shadow.set_corner_radius(config.corner_radius);
 
-        let left_corner_x = BORDER_SIZE + RESIZE_HANDLE_CORNER_SIZE;
-        let right_corner_x = (header_width + BORDER_SIZE).saturating_sub(RESIZE_HANDLE_CORNER_SIZE);
-        let top_corner_y = RESIZE_HANDLE_CORNER_SIZE;
-        let bottom_corner_y = side_height.saturating_sub(RESIZE_HANDLE_CORNER_SIZE);
-        match location {
-            Location::Head | Location::Button(_) => self.buttons.find_button(x, y),
-            Location::Top | Location::TopLeft | Location::TopRight => {
-                if x <= f64::from(left_corner_x) {
-                    Location::TopLeft
-                } else if x >= f64::from(right_corner_x) {
-                    Location::TopRight
-                } else {
-                    Location::Top
-                }
-            }
-            Location::Bottom | Location::BottomLeft | Location::BottomRight => {
-                if x <= f64::from(left_corner_x) {
-                    Location::BottomLeft
-                } else if x >= f64::from(right_corner_x) {
-                    Location::BottomRight
-                } else {
-                    Location::Bottom
-                }
-            }
-            Location::Left => {
-                if y <= f64::from(top_corner_y) {
-                    Location::TopLeft
-                } else if y >= f64::from(bottom_corner_y) {
-                    Location::BottomLeft
-                } else {
-                    Location::Left
-                }
-            }
-            Location::Right => {
-                if y <= f64::from(top_corner_y) {
-                    Location::TopRight
-                } else if y >= f64::from(bottom_corner_y) {
-                    Location::BottomRight
-                } else {
-                    Location::Right
-                }
+        // The parts are created again, e.g. to drop the header surface with the header.
+        if self.layout != config.layout || self.mode != config.decoration_mode {
+            self.layout = config.layout;
+            self.mode = config.decoration_mode;
+            let header_height = self.header_height();
+            if let Some(decorations) = self.decorations.as_mut() {
+                let (width, height) = decorations.size();
+                *decorations = DecorationParts::new(
+                    &self.base_surface,
+                    &*self.surface_factory.0,
+                    header_height,
+                    self.layout,
+                    self.mode,
+                );
+                decorations.resize(width, height);
+                self.should_sync = true;
             }
-            other => other,
         }
-    }
 
-    fn redraw_inner(&mut self) -> Option<bool> {
-        let decorations = self.decorations.as_mut()?;
-
-        // Reset the dirty bit.
-        self.dirty = false;
-        let should_sync = mem::take(&mut self.should_sync);
-
-        // Don't draw borders if the frame explicitly hidden or fullscreened.
-        if self.state.contains(WindowState::FULLSCREEN) {
-            decorations.hide();
-            return Some(true);
+        let header_height = self.header_height();
+        if let Some(decorations) = self.decorations.as_mut() {
+            decorations.set_header_height(header_height);
+            decorations.set_handle_size(self.resize_handle_size);
+            decorations.set_header_position(self.header_position);
+            self.buttons
+                .arrange(decorations.header_length(), get_margin_h_lp(&self.state));
+            self.should_sync = true;
         }
 
-        let colors = if self.state.contains(WindowState::ACTIVATED) {
-            &self.theme.active
-        } else {
-            &self.theme.inactive
-        };
+        self.mark_dirty();
+    }
 
-        let draw_borders = if self.state.contains(WindowState::MAXIMIZED) {
-            // Don't draw the borders.
-            decorations.hide_borders();
-            false
-        } else {
-            true
+    /// The sizes of the decorations in logical points in the current window state.
+    ///
+    /// Everything is `0` when the decorations are hidden, and only the headerbar remains when
+    /// the window is maximized, or tiled with [`ShadowPolicy::NotWhenTiled`].
+    pub fn metrics(&self) -> FrameMetrics {
+        let Some(decorations) = self
+            .decorations
+            .as_ref()
+            .filter(|_| !self.state.contains(WindowState::FULLSCREEN))
+        else {
+            return FrameMetrics::default();
         };
-        let border_paint = colors.border_paint();
 
-        // Draw the borders.
-        for (idx, part) in decorations
-            .parts()
-            .filter(|(idx, _)| *idx == DecorationParts::HEADER || draw_borders)
-        {
-            let scale = self.scale_factor;
-
-            let mut rect = part.surface_rect;
-            // XXX to perfectly align the visible borders we draw them with
-            // the header, otherwise rounded corners won't look 'smooth' at the
-            // start. To achieve that, we enlargen the width of the header by
-            // 2 * `VISIBLE_BORDER_SIZE`, and move `x` by `VISIBLE_BORDER_SIZE`
-            // to the left.
-            if idx == DecorationParts::HEADER && draw_borders {
-                rect.width += 2 * VISIBLE_BORDER_SIZE;
-                rect.x -= VISIBLE_BORDER_SIZE as i32;
-            }
-
-            rect.width *= scale;
-            rect.height *= scale;
-
-            let (buffer, canvas) = match self.pool.create_buffer(
-                rect.width as i32,
-                rect.height as i32,
-                rect.width as i32 * 4,
-                wl_shm::Format::Argb8888,
-            ) {
-                Ok((buffer, canvas)) => (buffer, canvas),
-                Err(_) => continue,
+        if self.hides_edges() {
+            return FrameMetrics {
+                header_height: self.header_height(),
+                ..Default::default()
             };
+        }
 
-            // Create the pixmap and fill with transparent color.
-            let mut pixmap = PixmapMut::from_bytes(canvas, rect.width, rect.height)?;
+        // Only the header has the rounded corners, and the shadow is left out with it.
+        let full = self.mode == DecorationMode::Full;
+        FrameMetrics {
+            header_height: self.header_height(),
+            border_size: decorations.border_size(),
+            visible_border: self.theme.visible_border_size(),
+            corner_radius: if full { self.corner_radius } else { 0 },
+            shadow_size: if full { SHADOW_SIZE } else { 0 },
+        }
+    }
 
-            // Fill everything with transparent background, since we draw rounded corners and
-            // do invisible borders to enlarge the input zone.
-            pixmap.fill(Color::TRANSPARENT);
+    /// The error which left some parts of the frame undrawn in the last redraw, `None` when it
+    /// was drawn fully.
+    ///
+    /// The parts whose buffers can't be created at the preferred scale are drawn at the lower
+    /// scales first, so the error means the frame can't be drawn even at the scale `1`.
+    pub fn last_error(&self) -> Option<&FrameError> {
+        self.last_error.as_ref()
+    }
 
-            if !self.state.intersects(WindowState::TILED) {
-                self.shadow.draw(
-                    &mut pixmap,
-                    scale,
-                    self.state.contains(WindowState::ACTIVATED),
-                    idx,
-                );
-            }
+    /// Encode the visible parts of the decorations into the PNG images, e.g. to attach them to
+    /// the bug reports, in the order they're stacked.
+    ///
+    /// The parts are drawn again the way the last redraw did, at the scale of their surfaces,
+    /// since the buffers attached to them aren't kept around. Empty when the frame is hidden.
+    #[cfg(feature = "screenshot")]
+    pub fn screenshot_decorations(&mut self) -> Vec<(PartId, Vec<u8>)> {
+        self.join_render();
+        let now = Instant::now();
+        let theme = self.faded_theme(now);
+        let Some(decorations) = self.decorations.as_ref() else {
+            return Vec::new();
+        };
+        if self.state.contains(WindowState::FULLSCREEN) {
+            return Vec::new();
+        }
 
-            match idx {
-                DecorationParts::HEADER => {
-                    if let Some(title_text) = self.title_text.as_mut() {
-                        title_text.update_scale(scale);
-                        title_text.update_color(colors.font_color);
-                    }
+        let draw_header = self.has_header();
+        let draw_borders = !self.hides_edges();
+        let mut painter = PartPainter {
+            state: self.state,
+            theme: theme.as_ref().unwrap_or(&self.theme),
+            buttons: &mut self.buttons,
+            shadow: &mut self.shadow,
+            title_text: self.title_text.as_mut(),
+            title_pixmap: self.title_pixmap.as_mut(),
+            subtitle_text: self.subtitle_text.as_mut(),
+            icon: self.icon.as_mut(),
+            hovered: self.pointers.hovered_buttons(),
+            pressed: self.pointers.pressed_buttons(),
+            resize_hint: if self.resize_hint && self.resizable {
+                self.pointers.hovered_edges()
+            } else {
+                Vec::new()
+            },
+            progress: self.progress,
+            snap_preview: self.snap_preview,
+            resizable: self.resizable,
+            rtl: self.rtl,
+            title_alignment: self.title_alignment,
+            header_position: self.header_position,
+            draw_header,
+            draw_borders,
+            draw_shadow: self.mode == DecorationMode::Full,
+            shadow_policy: self.shadow_policy,
+            floating: self.floating.value(now),
+            activation: self.activation.value(now),
+            corner_radius: self.corner_radius,
+            outline: None,
+            #[cfg(feature = "debug-overlay")]
+            debug: None,
+        };
+        if let Some(outline) = self.outline {
+            painter.outline = Some((outline, painter.outline_bounds(&decorations.rects())));
+        }
 
-                    draw_headerbar(
-                        &mut pixmap,
-                        self.title_text.as_ref().map(|t| t.pixmap()).unwrap_or(None),
-                        scale as f32,
-                        self.resizable,
-                        &self.state,
-                        &self.theme,
-                        &self.buttons,
-                        self.mouse.location,
-                    );
-                }
-                border => {
-                    // The visible border is one pt.
-                    let visible_border_size = VISIBLE_BORDER_SIZE * scale;
-
-                    // XXX we do all the match using integral types and then convert to f32 in the
-                    // end to ensure that result is finite.
-                    let border_rect = match border {
-                        DecorationParts::LEFT => {
-                            let x = (rect.x.unsigned_abs() * scale) - visible_border_size;
-                            let y = rect.y.unsigned_abs() * scale;
-                            Rect::from_xywh(
-                                x as f32,
-                                y as f32,
-                                visible_border_size as f32,
-                                (rect.height - y) as f32,
-                            )
-                        }
-                        DecorationParts::RIGHT => {
-                            let y = rect.y.unsigned_abs() * scale;
-                            Rect::from_xywh(
-                                0.,
-                                y as f32,
-                                visible_border_size as f32,
-                                (rect.height - y) as f32,
-                            )
-                        }
-                        // We draw small visible border only bellow the window surface, no need to
-                        // handle `TOP`.
-                        DecorationParts::BOTTOM => {
-                            let x = (rect.x.unsigned_abs() * scale) - visible_border_size;
-                            Rect::from_xywh(
-                                x as f32,
-                                0.,
-                                (rect.width - 2 * x) as f32,
-                                visible_border_size as f32,
-                            )
-                        }
-                        _ => None,
-                    };
+        let mut screenshots = Vec::new();
+        for (idx, part) in decorations.parts() {
+            if !painter.is_visible(idx) {
+                continue;
+            }
 
-                    // Fill the visible border, if present.
-                    if let Some(border_rect) = border_rect {
-                        pixmap.fill_rect(border_rect, &border_paint, Transform::identity(), None);
-                    }
-                }
+            let scale = part
+                .surface
+                .as_ref()
+                .and_then(|part_surface| part_surface.scale)
+                .unwrap_or(self.scale_factor);
+            let rect = painter.buffer_rect(idx, part.surface_rect);
+            let Some(mut pixmap) = Pixmap::new(rect.width * scale, rect.height * scale) else {
+                continue;
             };
+            painter.draw(idx, rect, scale, &mut pixmap.as_mut());
 
-            if should_sync {
-                part.subsurface.set_sync();
-            } else {
-                part.subsurface.set_desync();
+            let part_id = PartId::ALL[idx];
+            match pixmap.encode_png() {
+                Ok(png) => screenshots.push((part_id, png)),
+                Err(err) => log::warn!("failed to encode the screenshot of {part_id:?}: {err}"),
             }
+        }
+        screenshots
+    }
 
-            part.surface.set_buffer_scale(scale as i32);
-
-            part.subsurface.set_position(rect.x, rect.y);
-            buffer.attach_to(&part.surface).ok()?;
+    /// The full title, e.g. to show it in the tooltip over the headerbar when
+    /// [`Self::title_is_truncated`].
+    pub fn title(&self) -> Option<&str> {
+        self.title.as_deref()
+    }
 
-            if part.surface.version() >= 4 {
-                part.surface.damage_buffer(0, 0, i32::MAX, i32::MAX);
-            } else {
-                part.surface.damage(0, 0, i32::MAX, i32::MAX);
-            }
+    /// Whether the title was ellipsized to fit between the buttons in the last redraw.
+    pub fn title_is_truncated(&self) -> bool {
+        self.has_header() && self.title_metrics().0
+    }
 
-            if let Some(input_rect) = part.input_rect {
-                let input_region = Region::new(&*self.compositor).ok()?;
-                input_region.add(
-                    input_rect.x,
-                    input_rect.y,
-                    input_rect.width as i32,
-                    input_rect.height as i32,
-                );
+    /// Draw the title pre-rendered by the application in place of the text, centered and
+    /// clipped between the buttons the way the text is, until the next
+    /// [`DecorationsFrame::set_title`].
+    ///
+    /// The `pixmap` is resized to the `logical_size` multiplied by the scale, so it's drawn
+    /// without resizing when it's rendered at the scale of the frame.
+    pub fn set_title_pixmap(&mut self, pixmap: Pixmap, logical_size: (u32, u32)) {
+        self.join_render();
+        self.title_pixmap = Some(TitlePixmap::new(pixmap, logical_size));
+        self.mark_dirty();
+    }
 
-                part.surface
-                    .set_input_region(Some(input_region.wl_region()));
-            }
+    /// The statistics of the caches of the rendered title and subtitle, e.g. to check that
+    /// toggling the activation doesn't re-render them.
+    pub fn title_cache_stats(&self) -> TitleCacheStats {
+        self.title_metrics().1
+    }
 
-            part.surface.commit();
+    /// Whether the title is truncated and the statistics of the title caches.
+    ///
+    /// While the job is on the render thread, the ones from when it was sent, so the answer
+    /// doesn't depend on how far the thread got.
+    fn title_metrics(&self) -> (bool, TitleCacheStats) {
+        if self
+            .render_thread
+            .as_ref()
+            .is_some_and(RenderThread::in_flight)
+        {
+            return self.sent_title_metrics;
         }
 
-        Some(should_sync)
-    }
-}
-
-impl<State> DecorationsFrame for AdwaitaFrame<State>
-where
-    State: Dispatch<WlSurface, SurfaceData> + Dispatch<WlSubsurface, SubsurfaceData> + 'static,
-{
-    fn update_state(&mut self, state: WindowState) {
-        let difference = self.state.symmetric_difference(state);
-        self.state = state;
-        self.dirty |= difference.intersects(
-            WindowState::ACTIVATED
-                | WindowState::FULLSCREEN
-                | WindowState::MAXIMIZED
-                | WindowState::TILED,
-        );
+        let truncated = match self.title_pixmap.as_ref() {
+            Some(title_pixmap) => title_pixmap.is_truncated(),
+            None => self
+                .title_text
+                .as_ref()
+                .is_some_and(TitleText::is_truncated),
+        };
+        let stats = [self.title_text.as_ref(), self.subtitle_text.as_ref()]
+            .into_iter()
+            .flatten()
+            .map(TitleText::cache_stats)
+            .fold(TitleCacheStats::default(), |sum, stats| sum + stats);
+        (truncated, stats)
     }
 
-    fn update_wm_capabilities(&mut self, wm_capabilities: WindowManagerCapabilities) {
-        self.dirty |= self.wm_capabilities != wm_capabilities;
-        self.wm_capabilities = wm_capabilities;
-        self.buttons.update_wm_capabilities(wm_capabilities);
-    }
+    /// Describe the frame drawn right now, e.g. to introspect it in the compositor, see
+    /// [`FrameDescription`].
+    pub fn description(&self) -> FrameDescription {
+        let button_layout = match self.buttons.layout_config() {
+            Some((left, right)) => format!("{left}:{right}"),
+            None => DEFAULT_BUTTON_LAYOUT.to_owned(),
+        };
 
-    fn set_hidden(&mut self, hidden: bool) {
-        if hidden {
-            self.dirty = false;
-            let _ = self.pool.resize(1);
-            self.decorations = None;
-        } else if self.decorations.is_none() {
-            self.decorations = Some(DecorationParts::new(
-                &self.base_surface,
-                &self.subcompositor,
-                &self.queue_handle,
-            ));
-            self.dirty = true;
-            self.should_sync = true;
+        FrameDescription {
+            theme: self.theme.clone(),
+            metrics: self.metrics(),
+            button_layout,
+            corner_radius: self.corner_radius,
+            hide_titlebar: self.hide_titlebar,
+            title_alignment: self.title_alignment,
+            header_position: self.header_position,
         }
     }
 
-    fn set_resizable(&mut self, resizable: bool) {
-        self.dirty |= self.resizable != resizable;
-        self.resizable = resizable;
+    /// The theme in the middle of the fade at the time `now`, `None` when it's over.
+    fn faded_theme(&self, now: Instant) -> Option<ColorTheme> {
+        let (from, fade) = self.theme_fade.as_ref()?;
+        fade.is_running(now)
+            .then(|| from.lerp(&self.theme, fade.value(now)))
     }
 
-    fn resize(&mut self, width: NonZeroU32, height: NonZeroU32) {
-        let Some(decorations) = self.decorations.as_mut() else {
-            log::error!("trying to resize the hidden frame.");
+    /// Rearrange the buttons with the layout changed in the system configuration since the
+    /// last redraw.
+    fn reload_button_layout(&mut self) {
+        let Some(layout) = self
+            .button_layout_watcher
+            .as_ref()
+            .and_then(ButtonLayoutWatcher::take_changed)
+        else {
             return;
         };
 
-        decorations.resize(width.get(), height.get());
-        self.buttons
-            .arrange(width.get(), get_margin_h_lp(&self.state));
-        self.dirty = true;
-        self.should_sync = true;
+        self.buttons.set_layout_config(layout);
+        self.mark_dirty();
     }
 
-    fn draw(&mut self) -> bool {
-        self.redraw_inner().unwrap_or(true)
+    /// The last size of the main surface in logical points passed to `resize`, also while the
+    /// decorations are hidden, `None` before the first one.
+    pub fn last_size(&self) -> Option<(NonZeroU32, NonZeroU32)> {
+        self.last_size
     }
 
-    fn subtract_borders(
-        &self,
-        width: NonZeroU32,
-        height: NonZeroU32,
-    ) -> (Option<NonZeroU32>, Option<NonZeroU32>) {
+    /// The space the decorations take around the main surface within the window geometry, to
+    /// offset it by in `xdg_surface.set_window_geometry`.
+    ///
+    /// Only the headerbar is inside the window geometry, while the borders and the shadow are
+    /// drawn outside of it. The insets are empty while the decorations are hidden, in the
+    /// fullscreen, or with [`FrameConfig::hide_titlebar`].
+    pub fn decoration_insets(&self) -> Insets {
         if self.decorations.is_none() || self.state.contains(WindowState::FULLSCREEN) {
-            (Some(width), Some(height))
-        } else {
-            (
-                Some(width),
-                NonZeroU32::new(height.get().saturating_sub(HEADER_SIZE)),
-            )
+            return Insets::default();
         }
-    }
 
-    fn add_borders(&self, width: u32, height: u32) -> (u32, u32) {
-        if self.decorations.is_none() || self.state.contains(WindowState::FULLSCREEN) {
-            (width, height)
-        } else {
-            (width, height + HEADER_SIZE)
+        let header_height = self.header_height();
+        match self.header_position {
+            HeaderPosition::Top => Insets {
+                top: header_height,
+                ..Default::default()
+            },
+            HeaderPosition::Left => Insets {
+                left: header_height,
+                ..Default::default()
+            },
         }
     }
 
-    fn location(&self) -> (i32, i32) {
-        if self.decorations.is_none() || self.state.contains(WindowState::FULLSCREEN) {
-            (0, 0)
+    /// The height of the headerbar, `0` when the titlebar is hidden.
+    ///
+    /// The headerbar grows with the titlebar font from the default height.
+    fn header_height(&self) -> u32 {
+        if self.has_header() {
+            self.header_size
         } else {
-            (0, -(HEADER_SIZE as i32))
+            0
         }
     }
 
-    fn set_title(&mut self, title: impl Into<String>) {
-        let new_title = title.into();
-        if let Some(title_text) = self.title_text.as_mut() {
-            title_text.update_title(new_title.clone());
-        }
+    /// Whether the border parts are left out, when maximized or following the
+    /// [`ShadowPolicy::NotWhenTiled`].
+    fn hides_edges(&self) -> bool {
+        self.shadow_policy.hides_edges(&self.state)
+    }
 
-        self.title = Some(new_title);
-        self.dirty = true;
+    /// Whether the headerbar is drawn, unless it's hidden or only the borders are drawn.
+    fn has_header(&self) -> bool {
+        !self.hide_titlebar && self.mode == DecorationMode::Full
     }
 
-    fn on_click(
-        &mut self,
+    /// The widening of the turned headerbar by the visible borders, see
+    /// `PartPainter::buffer_rect`.
+    fn header_widening(&self) -> u32 {
+        if self.hides_edges() {
+            0
+        } else {
+            2 * VISIBLE_BORDER_SIZE
+        }
+    }
+
+    /// Move the point of the window menu from the upright headerbar to the base surface.
+    fn menu_action(&self, action: Option<FrameAction>) -> Option<FrameAction> {
+        let Some(FrameAction::ShowMenu(x, y)) = action else {
+            return action;
+        };
+
+        let header_height = self.header_height() as i32;
+        let (x, y) = match (self.header_position, self.decorations.as_ref()) {
+            (HeaderPosition::Left, Some(decorations)) => {
+                let length = (decorations.header_length() + self.header_widening()) as i32;
+                (y - header_height, length - x)
+            }
+            _ => (x, y - header_height),
+        };
+        Some(FrameAction::ShowMenu(x, y))
+    }
+
+    /// Move the keyboard focus to the next titlebar button, wrapping around.
+    ///
+    /// Returns the newly focused button, `None` when there're no buttons.
+    pub fn focus_next_button(&mut self) -> Option<ButtonKind> {
+        let previous = self.buttons.focused();
+        let focused = self.buttons.focus_next();
+        if focused != previous {
+            self.mark_dirty();
+        }
+        focused
+    }
+
+    /// Move the keyboard focus to the previous titlebar button, wrapping around.
+    ///
+    /// Returns the newly focused button, `None` when there're no buttons.
+    pub fn focus_prev_button(&mut self) -> Option<ButtonKind> {
+        let previous = self.buttons.focused();
+        let focused = self.buttons.focus_prev();
+        if focused != previous {
+            self.mark_dirty();
+        }
+        focused
+    }
+
+    /// Remove the keyboard focus from the titlebar buttons.
+    pub fn clear_button_focus(&mut self) {
+        if self.buttons.focused().is_some() {
+            self.mark_dirty();
+        }
+        self.buttons.clear_focus();
+    }
+
+    /// The titlebar button currently focused with the keyboard.
+    pub fn focused_button(&self) -> Option<ButtonKind> {
+        self.buttons.focused()
+    }
+
+    /// Activate the focused titlebar button, like when pressing Enter or Space in GTK.
+    ///
+    /// Returns the action the button would trigger when clicked, the custom buttons invoke the
+    /// callback instead.
+    pub fn activate_focused(&mut self) -> Option<FrameAction> {
+        let kind = self.buttons.focused()?;
+        self.activate_button(kind)
+    }
+
+    fn activate_button(&mut self, kind: ButtonKind) -> Option<FrameAction> {
+        if let ButtonKind::Custom(id) = kind {
+            if let Some(callback) = self.custom_button_callback.as_mut() {
+                (callback.0)(id);
+            }
+        }
+
+        kind.action(&self.state)
+    }
+
+    /// Add the button with the `icon` to the `side` of the headerbar, next to the window
+    /// controls, replacing the custom button with the same `id`.
+    ///
+    /// Clicks on the button are reported to the callback set with
+    /// [`Self::on_custom_button`].
+    pub fn add_custom_button(&mut self, id: u32, icon: IconSource, side: Side) {
+        self.buttons.add_custom(id, icon, side);
+        self.mark_dirty();
+    }
+
+    /// Remove the custom button, returns `false` when there's no button with the `id`.
+    pub fn remove_custom_button(&mut self, id: u32) -> bool {
+        let removed = self.buttons.remove_custom(id);
+        if removed {
+            self.mark_dirty();
+        }
+        removed
+    }
+
+    /// Set the callback invoked with the id of the clicked custom button.
+    pub fn on_custom_button(&mut self, callback: impl FnMut(u32) + 'static) {
+        self.custom_button_callback = Some(CustomButtonCallback(Box::new(callback)));
+    }
+
+    /// Set the callback invoked once the frame becomes dirty, e.g. after the state or the hover
+    /// changed, to schedule the redraw without polling [`DecorationsFrame::is_dirty`].
+    ///
+    /// It's invoked again only after the frame was drawn, or when it's still dirty after the
+    /// draw, e.g. while animating. It's not invoked while frozen, but once thawed.
+    ///
+    /// It's also invoked on the thread watching the button layout once the layout changes in
+    /// the system configuration, to wake the idle event loop.
+    pub fn on_dirty(&mut self, callback: impl Fn() + Send + Sync + 'static) {
+        let callback = DirtyCallback(Arc::new(callback));
+        if let Some(watcher) = self.button_layout_watcher.as_ref() {
+            watcher.set_waker(Some(callback.0.clone()));
+        }
+        self.dirty_callback = Some(callback);
+    }
+
+    /// Set the callback invoked with the rect of the maximize button, relative to the main
+    /// surface, once it's held down for a while, like the tiling popover of GNOME.
+    ///
+    /// The app or the compositor could offer the tiling options next to the button, and the
+    /// release of the button doesn't maximize the window then. The press is checked on the
+    /// pointer motion and the release, or with [`Self::long_press`] from a timer.
+    pub fn on_tile_menu(&mut self, callback: impl FnMut(Rect) + 'static) {
+        self.tile_menu_callback = Some(TileMenuCallback(Box::new(callback)));
+    }
+
+    /// Set the callback invoked on the render thread once the parts are drawn with
+    /// [`FrameConfig::threaded_rendering`], to wake the event loop for the
+    /// [`DecorationsFrame::draw`] presenting them.
+    ///
+    /// The frame isn't dirty while the parts are being drawn, so [`Self::on_dirty`] isn't
+    /// invoked until they're presented.
+    pub fn on_render_ready(&mut self, callback: impl Fn() + Send + Sync + 'static) {
+        self.render_ready_callback = Some(RenderReadyCallback(Arc::new(callback)));
+    }
+
+    /// Invoke the [`Self::on_tile_menu`] callback when the maximize button was held down long
+    /// enough at the `timestamp`.
+    ///
+    /// Returns whether the callback was invoked.
+    pub fn long_press(&mut self, timestamp: Duration) -> bool {
+        self.long_press_for_seat(DEFAULT_SEAT, timestamp)
+    }
+
+    /// [`Self::long_press`] with the pointer of the `seat`.
+    pub fn long_press_for_seat(&mut self, seat: SeatId, timestamp: Duration) -> bool {
+        if self.tile_menu_callback.is_none() || !self.pointers.seat(seat).long_press(timestamp) {
+            return false;
+        }
+
+        let Some((_, rect)) = self
+            .button_rects()
+            .find(|(kind, _)| *kind == ButtonKind::Maximize)
+        else {
+            return false;
+        };
+        if let Some(TileMenuCallback(callback)) = self.tile_menu_callback.as_mut() {
+            callback(rect);
+        }
+        true
+    }
+
+    /// The regions of the frame reacting to the pointer input, positioned relative to the main
+    /// surface.
+    ///
+    /// The regions could overlap, in which case the latter one takes precedence, e.g. the
+    /// buttons are placed on top of the [`Location::Head`].
+    pub fn input_regions(&self) -> Vec<(Rect, Location)> {
+        let mut regions = Vec::new();
+        let Some(decorations) = self.decorations.as_ref() else {
+            return regions;
+        };
+
+        if self.state.contains(WindowState::FULLSCREEN) {
+            return regions;
+        }
+
+        if self.resizable && !self.hides_edges() {
+            let top_width = decorations.top_width();
+            let side_height = decorations.side_height();
+
+            // Keep in sync with `Self::precise_location`.
+            let corner_size = self.resize_corner_size;
+            let border_size = decorations.border_size();
+            let left_corner_x = border_size + corner_size;
+            let right_corner_x = (top_width - border_size).saturating_sub(corner_size);
+            let top_corner_y = corner_size;
+            let bottom_corner_y = side_height.saturating_sub(corner_size);
+
+            for (idx, part) in decorations.parts() {
+                let rect = decorations.input_rect(idx);
+                let origin = part.surface_rect;
+                let (start, end) = match idx {
+                    DecorationParts::TOP => (Location::TopLeft, Location::TopRight),
+                    DecorationParts::BOTTOM => (Location::BottomLeft, Location::BottomRight),
+                    DecorationParts::LEFT => (Location::TopLeft, Location::BottomLeft),
+                    DecorationParts::RIGHT => (Location::TopRight, Location::BottomRight),
+                    _ => continue,
+                };
+                let middle = DecorationParts::location(idx);
+
+                if idx == DecorationParts::TOP || idx == DecorationParts::BOTTOM {
+                    let split_start = origin.x + left_corner_x as i32;
+                    let split_end = origin.x + right_corner_x as i32;
+                    regions.extend(rect.split_x(split_start, split_end, [start, middle, end]));
+                } else {
+                    let split_start = origin.y + top_corner_y as i32;
+                    let split_end = origin.y + bottom_corner_y as i32;
+                    regions.extend(rect.split_y(split_start, split_end, [start, middle, end]));
+                }
+            }
+        }
+
+        if !self.has_header() {
+            return regions;
+        }
+
+        regions.push((decorations.header().surface_rect, Location::Head));
+        regions.extend(
+            self.button_rects()
+                .map(|(kind, rect)| (rect, Location::Button(kind))),
+        );
+
+        regions
+    }
+
+    /// Handle the middle click on the decorations frame, see [`DecorationsFrame::on_click`].
+    ///
+    /// The returned action should be performed by the user.
+    pub fn on_middle_click(&mut self, pressed: bool) -> Option<HeaderbarAction> {
+        self.on_middle_click_for_seat(DEFAULT_SEAT, pressed)
+    }
+
+    /// [`Self::on_middle_click`] with the pointer of the `seat`.
+    pub fn on_middle_click_for_seat(
+        &mut self,
+        seat: SeatId,
+        pressed: bool,
+    ) -> Option<HeaderbarAction> {
+        self.pointers.seat(seat).button_click(
+            pressed,
+            &self.state,
+            &self.wm_capabilities,
+            self.middle_click_action,
+        )
+    }
+
+    /// Handle the vertical scroll by `delta` in the `wl_pointer` axis units, growing downwards,
+    /// over the decorations frame with the [`FrameConfig::scroll_action`].
+    ///
+    /// The returned action should be performed by the user.
+    pub fn on_scroll(&mut self, delta: f64) -> Option<HeaderbarAction> {
+        self.on_scroll_for_seat(DEFAULT_SEAT, delta)
+    }
+
+    /// [`Self::on_scroll`] with the pointer of the `seat`.
+    pub fn on_scroll_for_seat(&mut self, seat: SeatId, delta: f64) -> Option<HeaderbarAction> {
+        self.pointers.seat(seat).scroll(
+            delta,
+            &self.state,
+            &self.wm_capabilities,
+            self.scroll_action,
+        )
+    }
+
+    /// Handle the click of the pointer `button` with the Linux input event code, like the ones
+    /// of `wl_pointer`, on the decorations frame.
+    ///
+    /// The left and the right buttons are the [`FrameClick::Normal`] and the
+    /// [`FrameClick::Alternate`] clicks of [`DecorationsFrame::on_click`]. The other buttons
+    /// perform the actions set with [`FrameConfig::button_action`], and the middle one falls
+    /// back to the [`FrameConfig::middle_click_action`].
+    ///
+    /// With the [`FrameConfig::drag_modifier`] held in the keyboard `modifiers`, the left
+    /// button moves the window from anywhere on the decorations and the right one resizes it
+    /// from the nearest edge.
+    ///
+    /// The returned action should be performed by the user.
+    pub fn on_button(
+        &mut self,
+        timestamp: Duration,
+        button: u32,
+        pressed: bool,
+        modifiers: Modifiers,
+    ) -> Option<HeaderbarAction> {
+        self.on_button_for_seat(DEFAULT_SEAT, timestamp, button, pressed, modifiers)
+    }
+
+    /// [`Self::on_button`] with the pointer of the `seat`.
+    pub fn on_button_for_seat(
+        &mut self,
+        seat: SeatId,
+        timestamp: Duration,
+        button: u32,
+        pressed: bool,
+        modifiers: Modifiers,
+    ) -> Option<HeaderbarAction> {
+        let click = match button {
+            BTN_LEFT => FrameClick::Normal,
+            BTN_RIGHT => FrameClick::Alternate,
+            _ => {
+                let action = self
+                    .button_actions
+                    .iter()
+                    .find(|(code, _)| *code == button)
+                    .map(|(_, action)| *action)
+                    .or_else(|| (button == BTN_MIDDLE).then_some(self.middle_click_action))?;
+                let action = self.pointers.seat(seat).button_click(
+                    pressed,
+                    &self.state,
+                    &self.wm_capabilities,
+                    action,
+                );
+                return match action {
+                    Some(HeaderbarAction::Frame(action)) => {
+                        self.menu_action(Some(action)).map(HeaderbarAction::Frame)
+                    }
+                    action => action,
+                };
+            }
+        };
+
+        self.on_click_for_seat(seat, timestamp, click, pressed, modifiers)
+            .map(HeaderbarAction::Frame)
+    }
+
+    /// [`DecorationsFrame::on_click`] with the pointer of the `seat`.
+    ///
+    /// The pointers of the different seats keep their own hover and click state, while the
+    /// [`DecorationsFrame`] methods use the seat `0`, without the `modifiers` held.
+    pub fn on_click_for_seat(
+        &mut self,
+        seat: SeatId,
         timestamp: Duration,
         click: FrameClick,
         pressed: bool,
+        modifiers: Modifiers,
     ) -> Option<FrameAction> {
-        match click {
-            FrameClick::Normal => self.mouse.click(
-                timestamp,
+        if matches!(click, FrameClick::Normal | FrameClick::Alternate) {
+            let header = (
+                self.decorations
+                    .as_ref()
+                    .map_or(0, DecorationParts::header_length),
+                self.header_position,
+            );
+            if let Some(action) = self.pointers.seat(seat).modifier_click(
+                matches!(click, FrameClick::Alternate),
                 pressed,
+                modifiers,
                 self.resizable,
-                &self.state,
-                &self.wm_capabilities,
-            ),
-            FrameClick::Alternate => self.mouse.alternate_click(pressed, &self.wm_capabilities),
-            _ => None,
+                &self.click_config,
+                header,
+            ) {
+                return action;
+            }
+        }
+
+        if matches!(click, FrameClick::Normal) {
+            if !pressed {
+                self.long_press_for_seat(seat, timestamp);
+            }
+
+            // Draw the button under the pointer pressed until the release.
+            let mouse = self.pointers.seat(seat);
+            let pressed_button = mouse.pressed_button();
+            mouse.held = pressed;
+            if mouse.pressed_button() != pressed_button {
+                self.mark_dirty();
+            }
         }
+
+        let action = match click {
+            FrameClick::Normal => match self.pointers.seat(seat).location {
+                Location::Button(kind @ ButtonKind::Custom(_)) if !pressed => {
+                    self.activate_button(kind)
+                }
+                _ => self.pointers.seat(seat).click(
+                    timestamp,
+                    pressed,
+                    self.resizable,
+                    &self.state,
+                    &self.wm_capabilities,
+                    &self.click_config,
+                ),
+            },
+            FrameClick::Alternate => self
+                .pointers
+                .seat(seat)
+                .alternate_click(pressed, &self.wm_capabilities),
+            _ => None,
+        };
+        self.menu_action(action)
     }
 
-    fn set_scaling_factor(&mut self, scale_factor: f64) {
-        // NOTE: Clamp it just in case to some ok-ish range.
-        self.scale_factor = scale_factor.clamp(0.1, 64.).ceil() as u32;
-        self.dirty = true;
-        self.should_sync = true;
+    /// The move of the window once the pointer pressed on the headerbar travelled past the
+    /// [`FrameConfig::drag_threshold`], to check after [`DecorationsFrame::click_point_moved`].
+    ///
+    /// The move should be started with the serial of the press.
+    pub fn drag_action(&mut self) -> Option<FrameAction> {
+        self.drag_action_for_seat(DEFAULT_SEAT)
     }
 
-    fn click_point_moved(
+    /// [`Self::drag_action`] with the pointer of the `seat`.
+    pub fn drag_action_for_seat(&mut self, seat: SeatId) -> Option<FrameAction> {
+        let drag_threshold = self.click_config.drag_threshold;
+        self.pointers.seat(seat).drag(drag_threshold)
+    }
+
+    /// [`DecorationsFrame::click_point_moved`] with the pointer of the `seat`.
+    pub fn click_point_moved_for_seat(
         &mut self,
-        _timestamp: Duration,
+        seat: SeatId,
+        timestamp: Duration,
         surface: &ObjectId,
         x: f64,
         y: f64,
     ) -> Option<CursorIcon> {
+        let (location, x, y) = self.locate(surface, x, y)?;
+        self.long_press_for_seat(seat, timestamp);
+        Some(self.hover(seat, location, x, y))
+    }
+
+    /// Move the pointer of the `seat` to the `location`, redrawing the parts whose hover
+    /// changed.
+    fn hover(&mut self, seat: SeatId, location: Location, x: f64, y: f64) -> CursorIcon {
+        let mouse = self.pointers.seat(seat);
+        let old_location = mouse.location;
+        let new_cursor = mouse.moved(location, x, y, self.resizable, &self.cursor_map);
+
+        // Set dirty if we moved the cursor between the buttons.
+        if (matches!(old_location, Location::Button(_)) || matches!(location, Location::Button(_)))
+            && old_location != location
+        {
+            self.mark_dirty();
+        }
+
+        if old_location != location {
+            self.redraw_resize_hint(old_location);
+            self.redraw_resize_hint(location);
+        }
+
+        new_cursor
+    }
+
+    /// Hover the `location` with the pointer, without the Wayland pointer events, for the
+    /// tests and the accessibility tooling driving the frame.
+    ///
+    /// [`Location::None`] leaves the frame like [`DecorationsFrame::click_point_left`].
+    #[cfg(feature = "automation")]
+    pub fn set_hover_for_test(&mut self, location: Location) {
+        if location == Location::None {
+            self.click_point_left_for_seat(DEFAULT_SEAT);
+        } else {
+            self.hover(DEFAULT_SEAT, location, 0., 0.);
+        }
+    }
+
+    /// Hold the primary button of the pointer down or release it, drawing the hovered button
+    /// pressed, without performing its action.
+    #[cfg(feature = "automation")]
+    pub fn set_pressed_for_test(&mut self, pressed: bool) {
+        let mouse = self.pointers.seat(DEFAULT_SEAT);
+        let pressed_button = mouse.pressed_button();
+        mouse.held = pressed;
+        if mouse.pressed_button() != pressed_button {
+            self.mark_dirty();
+        }
+    }
+
+    /// The location hovered by the pointer, [`Location::None`] when it's not on the frame.
+    #[cfg(feature = "automation")]
+    pub fn current_hover(&self) -> Location {
+        self.pointers.location(DEFAULT_SEAT)
+    }
+
+    /// What the point of the `surface` would hit, without moving any of the pointers like
+    /// [`DecorationsFrame::click_point_moved`] does.
+    ///
+    /// Returns [`Location::None`] when the surface isn't a part of the frame.
+    pub fn hit_test(&self, surface: &ObjectId, x: f64, y: f64) -> Location {
+        self.locate(surface, x, y)
+            .map_or(Location::None, |(location, _, _)| location)
+    }
+
+    /// The name of the cursor theme's cursor over the `location`, like the one from
+    /// [`Self::hit_test`], for the embedders loading the cursors by the name.
+    ///
+    /// Follows the names of the [`FrameConfig::cursor_map`], or the [`CursorIcon::name`] of the
+    /// cursor returned by [`DecorationsFrame::click_point_moved`].
+    pub fn cursor_name(&self, location: Location) -> &str {
+        self.cursor_map.name(location, self.resizable)
+    }
+
+    /// The location under the point of the `surface`, with the point in the coordinates of the
+    /// part, along the upright headerbar for the header.
+    fn locate(&self, surface: &ObjectId, x: f64, y: f64) -> Option<(Location, f64, f64)> {
         let decorations = self.decorations.as_ref()?;
-        let location = decorations.find_surface(surface);
+        let (location, x, y) = decorations.locate(surface, x, y)?;
         if location == Location::None {
             return None;
         }
 
-        let old_location = self.mouse.location;
+        // The buttons are found along the headerbar drawn upright, before it's turned.
+        let (x, y) = match self.header_position {
+            HeaderPosition::Left if location == Location::Head => (
+                f64::from(decorations.header_length() + self.header_widening()) - y,
+                x,
+            ),
+            _ => (x, y),
+        };
+
+        let location = self.precise_location(location, decorations, x, y);
+        Some((location, x, y))
+    }
+
+    /// [`DecorationsFrame::click_point_left`] with the pointer of the `seat`.
+    pub fn click_point_left_for_seat(&mut self, seat: SeatId) {
+        // Clear the hover highlight of the button the pointer left from.
+        let location = self.pointers.left(seat);
+        if matches!(location, Location::Button(_)) {
+            self.mark_dirty();
+        }
+        self.redraw_resize_hint(location);
+    }
+
+    /// Redraw only the border parts with the resize hint at the `location`.
+    fn redraw_resize_hint(&mut self, location: Location) {
+        if !self.resize_hint {
+            return;
+        }
+
+        let was_dirty = self.is_dirty();
+        if let Some(decorations) = self.decorations.as_mut() {
+            for idx in location.edge_parts() {
+                decorations.redraw_part(*idx);
+            }
+        }
+        self.notify_dirty(was_dirty);
+    }
+
+    /// Mark the whole frame to be redrawn, see [`Self::on_dirty`].
+    fn mark_dirty(&mut self) {
+        let was_dirty = self.is_dirty();
+        self.dirty = true;
+        self.notify_dirty(was_dirty);
+    }
+
+    /// Invoke the callback of [`Self::on_dirty`] when the frame became dirty since `was_dirty`.
+    fn notify_dirty(&mut self, was_dirty: bool) {
+        if let Some(callback) = self.dirty_callback.as_ref() {
+            callback.notify(was_dirty, self.is_dirty());
+        }
+    }
+
+    /// The minimal size of the window geometry before the titlebar buttons collide, with the
+    /// minimize, the maximize and the custom buttons dropped from the narrow headers.
+    ///
+    /// Doesn't depend on the current size of the window. Useful for `xdg_toplevel.set_min_size`.
+    pub fn minimum_size(&self) -> (u32, u32) {
+        let length = if !self.has_header() {
+            1
+        } else {
+            let margin_h = get_margin_h_lp(&self.state);
+            (self.buttons.minimum_width(margin_h).ceil() as u32).max(1)
+        };
+
+        match self.header_position {
+            HeaderPosition::Top => self.add_borders(length, 1),
+            HeaderPosition::Left => self.add_borders(1, length),
+        }
+    }
+
+    /// Set the smaller line drawn under the title, `None` removes it.
+    pub fn set_subtitle(&mut self, subtitle: Option<String>) {
+        self.join_render();
+        match subtitle {
+            Some(subtitle) => {
+                let subtitle_text = match self.subtitle_text.as_mut() {
+                    Some(subtitle_text) => Some(subtitle_text),
+                    None => {
+                        self.subtitle_text = TitleText::new_subtitle(
+                            self.theme.active.font_color,
+                            self.text_scale,
+                            &self.title_fonts,
+                        )
+                        .map_err(|err| log::warn!("{err}"))
+                        .ok();
+                        if let Some(subtitle_text) = self.subtitle_text.as_mut() {
+                            subtitle_text.update_rendering(self.text_rendering);
+                        }
+                        self.subtitle_text.as_mut()
+                    }
+                };
+
+                if let Some(subtitle_text) = subtitle_text {
+                    subtitle_text.update_title(subtitle);
+                }
+            }
+            None => self.subtitle_text = None,
+        }
+
+        self.mark_dirty();
+    }
+
+    /// Stop drawing the frame until the matching [`Self::thaw`], so the batch of updates, e.g.
+    /// the title, the theme and the size, is drawn once after it.
+    ///
+    /// While frozen, [`DecorationsFrame::draw`] does nothing and [`DecorationsFrame::is_dirty`]
+    /// returns `false`. The calls nest.
+    pub fn freeze(&mut self) {
+        self.frozen.freeze();
+    }
+
+    /// Finish the batch started with [`Self::freeze`].
+    ///
+    /// Once the last batch finished, [`DecorationsFrame::is_dirty`] tells whether any of the
+    /// updates in it needs the frame to be drawn.
+    pub fn thaw(&mut self) {
+        self.frozen.thaw();
+        self.notify_dirty(false);
+    }
+
+    /// Whether the window isn't shown with the [`WindowState::SUSPENDED`], the drawing is
+    /// deferred until it's shown again then.
+    pub fn is_suspended(&self) -> bool {
+        self.state.contains(WindowState::SUSPENDED)
+    }
+
+    /// Stop the animations and release the buffers once the window is suspended.
+    fn suspend(&mut self) {
+        self.floating.finish();
+        self.activation.finish();
+        self.attention.finish();
+        self.flash.stop();
+        self.theme_fade = None;
+
+        if !self.release_suspended_buffers {
+            return;
+        }
+
+        if let Some(decorations) = self.decorations.as_mut() {
+            decorations.release_buffers();
+        }
+        match SlotPool::new(1, &self.shm) {
+            Ok(pool) => self.pool = pool,
+            Err(err) => log::warn!("failed to shrink the buffer pool: {err}"),
+        }
+        // The released buffers are redrawn once shown again.
+        self.dirty = true;
+    }
+
+    /// Whether the drawing is deferred by [`Self::freeze`].
+    pub fn is_frozen(&self) -> bool {
+        self.frozen.is_frozen()
+    }
+
+    /// Run the updates in `f` as one batch, see [`Self::freeze`].
+    pub fn batch<T>(&mut self, f: impl FnOnce(&mut Self) -> T) -> T {
+        self.freeze();
+        let result = f(self);
+        self.thaw();
+        result
+    }
+
+    /// Show the progress from `0.` to `1.` as the strip along the bottom of the headerbar,
+    /// `None` removes it.
+    ///
+    /// Only the strip is redrawn when the progress changes.
+    pub fn set_progress(&mut self, progress: Option<f32>) {
+        let progress = progress.map(|progress| progress.clamp(0., 1.));
+        if self.progress == progress {
+            return;
+        }
+
+        self.progress = progress;
+        if !self.has_header() {
+            return;
+        }
+
+        let was_dirty = self.is_dirty();
+        if let Some(decorations) = self.decorations.as_mut() {
+            let header = decorations.header().surface_rect;
+            let strip = match self.header_position {
+                HeaderPosition::Top => Rect {
+                    x: header.x,
+                    y: header.y + (header.height - PROGRESS_HEIGHT) as i32,
+                    width: header.width,
+                    height: PROGRESS_HEIGHT,
+                },
+                HeaderPosition::Left => Rect {
+                    x: header.x + (header.width - PROGRESS_HEIGHT) as i32,
+                    y: header.y,
+                    width: PROGRESS_HEIGHT,
+                    height: header.height,
+                },
+            };
+            decorations.redraw_area(DecorationParts::HEADER, strip);
+        }
+        self.notify_dirty(was_dirty);
+    }
+
+    /// Preview the snapping of the window to the `region` of the screen with the translucent
+    /// overlay on the borders facing it, `None` removes it.
+    ///
+    /// Useful while the window is dragged by the headerbar near the edges of the screen, when the
+    /// embedder knows where the window would snap to.
+    pub fn set_snap_preview(&mut self, region: Option<SnapRegion>) {
+        if self.snap_preview == region {
+            return;
+        }
+
+        let old_region = mem::replace(&mut self.snap_preview, region);
+        let was_dirty = self.is_dirty();
+        if let Some(decorations) = self.decorations.as_mut() {
+            for region in [old_region, region].into_iter().flatten() {
+                for idx in region.edge_parts() {
+                    decorations.redraw_part(*idx);
+                }
+            }
+        }
+        self.notify_dirty(was_dirty);
+    }
+
+    /// Tint the headerbar to hint that the window needs attention, like after the urgent
+    /// notification.
+    ///
+    /// The tint fades in and out when the animations are enabled.
+    pub fn set_needs_attention(&mut self, needs_attention: bool) {
+        if self.needs_attention == needs_attention {
+            return;
+        }
+
+        self.needs_attention = needs_attention;
+        let attention = if needs_attention { 1. } else { 0. };
+        self.attention
+            .set(attention, Instant::now(), self.animations);
+        self.mark_dirty();
+    }
+
+    /// Flash the headerbar with the accent color briefly, e.g. when the window is activated
+    /// with the `xdg_activation_v1` token, to show where the focus went.
+    ///
+    /// The flash pulses when the animations are enabled, and is drawn on the following
+    /// redraws until it's over.
+    pub fn flash(&mut self) {
+        self.flash.start(Instant::now(), self.animations);
+        self.mark_dirty();
+    }
+
+    /// Set the icon drawn next to the title, `None` removes it.
+    pub fn set_icon(&mut self, icon: Option<IconSource>) {
+        self.join_render();
+        self.icon = icon.map(Icon::new);
+        self.mark_dirty();
+    }
+
+    /// Set the scale factor of the decorations surface.
+    ///
+    /// When the window spans outputs with different scales, each surface of the frame can
+    /// prefer its own scale, as reported by `CompositorHandler::scale_factor_changed`. Only the
+    /// surfaces with the changed scale are redrawn.
+    ///
+    /// The scales tracked by the toolkit for the surfaces of the frame are followed on the draw
+    /// as well, so the parts aren't drawn at the stale scale when the events arrive after the
+    /// resize and before this is called.
+    ///
+    /// Returns `false` when the surface doesn't belong to the frame.
+    pub fn set_surface_scaling_factor(&mut self, surface: &ObjectId, scale_factor: f64) -> bool {
+        let was_dirty = self.is_dirty();
+        let Some(decorations) = self.decorations.as_mut() else {
+            return false;
+        };
+
+        // NOTE: Clamp it just in case to some ok-ish range.
+        let scale = scale_factor.clamp(0.1, 64.).ceil() as u32;
+        if !decorations.set_scale(surface, scale) {
+            return false;
+        }
+
+        self.should_sync |= decorations.needs_redraw();
+        self.notify_dirty(was_dirty);
+        true
+    }
+
+    /// The rects occupied by the titlebar buttons in the visual order.
+    ///
+    /// The rects are in the logical coordinates relative to the window surface and are updated
+    /// on every resize of the frame. Nothing is returned when the titlebar isn't drawn.
+    pub fn button_rects(&self) -> impl Iterator<Item = (ButtonKind, Rect)> + '_ {
+        let header = self
+            .decorations
+            .as_ref()
+            .filter(|_| self.has_header() && !self.state.contains(WindowState::FULLSCREEN))
+            .map(|decorations| decorations.header().surface_rect);
+
+        header.into_iter().flat_map(move |header| {
+            self.buttons.rects().map(move |(kind, rect)| {
+                let rect = match self.header_position {
+                    HeaderPosition::Top => Rect {
+                        x: header.x + rect.x,
+                        y: header.y + rect.y,
+                        ..rect
+                    },
+                    // Turned with the headerbar, the start of it ends up at the bottom.
+                    HeaderPosition::Left => Rect {
+                        x: header.x + rect.y,
+                        y: header.y + header.height as i32 - rect.x - rect.width as i32,
+                        width: rect.height,
+                        height: rect.width,
+                    },
+                };
+                (kind, rect)
+            })
+        })
+    }
+
+    fn precise_location(
+        &self,
+        location: Location,
+        decoration: &DecorationParts,
+        x: f64,
+        y: f64,
+    ) -> Location {
+        let top_width = decoration.top_width();
+        let side_height = decoration.side_height();
+
+        let corner_size = self.resize_corner_size;
+        let border_size = decoration.border_size();
+        let left_corner_x = border_size + corner_size;
+        let right_corner_x = (top_width - border_size).saturating_sub(corner_size);
+        let top_corner_y = corner_size;
+        let bottom_corner_y = side_height.saturating_sub(corner_size);
+        match location {
+            // The buttons take precedence on either side, the transparent area around the
+            // rounded corners resizes the window.
+            Location::Head | Location::Button(_) => match self.buttons.find_button(x, y) {
+                Location::Head => self.header_corner(decoration, x, y),
+                button => button,
+            },
+            Location::Top | Location::TopLeft | Location::TopRight => {
+                if x <= f64::from(left_corner_x) {
+                    Location::TopLeft
+                } else if x >= f64::from(right_corner_x) {
+                    Location::TopRight
+                } else {
+                    Location::Top
+                }
+            }
+            Location::Bottom | Location::BottomLeft | Location::BottomRight => {
+                if x <= f64::from(left_corner_x) {
+                    Location::BottomLeft
+                } else if x >= f64::from(right_corner_x) {
+                    Location::BottomRight
+                } else {
+                    Location::Bottom
+                }
+            }
+            Location::Left => {
+                if y <= f64::from(top_corner_y) {
+                    Location::TopLeft
+                } else if y >= f64::from(bottom_corner_y) {
+                    Location::BottomLeft
+                } else {
+                    Location::Left
+                }
+            }
+            Location::Right => {
+                if y <= f64::from(top_corner_y) {
+                    Location::TopRight
+                } else if y >= f64::from(bottom_corner_y) {
+                    Location::BottomRight
+                } else {
+                    Location::Right
+                }
+            }
+            other => other,
+        }
+    }
+
+    /// The resize corner of the window under the point of the upright header, when it's in the
+    /// transparent area around the rounded corners, [`Location::Head`] elsewhere.
+    fn header_corner(&self, decoration: &DecorationParts, x: f64, y: f64) -> Location {
+        let widening = self.header_widening();
+        let (state, start, corners) = match self.header_position {
+            HeaderPosition::Top => (
+                self.state,
+                // The header is drawn over the visible borders, see `PartPainter::buffer_rect`.
+                -f64::from(widening / 2),
+                (Location::TopLeft, Location::TopRight),
+            ),
+            HeaderPosition::Left => (
+                upright_state(self.state),
+                0.,
+                (Location::BottomLeft, Location::TopLeft),
+            ),
+        };
+        let end = start + f64::from(decoration.header_length() + widening);
+        let corner_radius = self.corner_radius as f32 * self.floating.value(Instant::now());
+        let radii = top_corner_radii(&state, 1., corner_radius);
+
+        match rounded_corner_at(x, y, start, end, radii) {
+            Some(Side::Left) => corners.0,
+            Some(Side::Right) => corners.1,
+            None => Location::Head,
+        }
+    }
+
+    /// Send the job to the render thread, spawning it first if needed.
+    fn start_render(&mut self, job: RenderJob) {
+        if self.render_thread.is_none() {
+            self.render_thread = RenderThread::spawn();
+        }
+
+        let job = match self.render_thread.as_mut() {
+            Some(render_thread) => match render_thread.send(Box::new(job)) {
+                Ok(()) => return,
+                Err(job) => job,
+            },
+            None => Box::new(job),
+        };
+
+        self.stop_render_thread();
+        self.restore_render_state(job);
+    }
+
+    /// Draw the frame on its thread from now on, redrawing all the parts.
+    fn stop_render_thread(&mut self) {
+        log::warn!("the render thread is gone, drawing the frame on its thread");
+        self.render_thread = None;
+        self.threaded_rendering = false;
+        self.dirty = true;
+    }
+
+    /// Move the state back from the job taken from the render thread, keeping the drawn parts
+    /// to present, or falling back to drawing on the thread of the frame when it panicked.
+    fn finish_render(&mut self, job: Box<RenderJob>) {
+        if job.panicked {
+            self.stop_render_thread();
+            self.restore_render_state(job);
+        } else {
+            self.rendered_parts = self.restore_render_state(job);
+        }
+    }
+
+    /// Move the state the job was drawn from back into the frame, returning the drawn parts.
+    fn restore_render_state(&mut self, job: Box<RenderJob>) -> Vec<(RenderTarget, Option<Pixmap>)> {
+        self.shadow = job.shadow;
+        self.title_text = job.title_text;
+        self.title_pixmap = job.title_pixmap;
+        self.subtitle_text = job.subtitle_text;
+        self.icon = job.icon;
+        job.targets.into_iter().zip(job.rendered).collect()
+    }
+
+    /// Wait for the parts drawn on the render thread, to update the state they're drawn from.
+    ///
+    /// The parts are presented with the next draw.
+    fn join_render(&mut self) {
+        if let Some(job) = self.render_thread.as_mut().and_then(RenderThread::wait) {
+            self.finish_render(job);
+        }
+    }
+
+    fn redraw_inner(&mut self) -> Option<bool> {
+        // Nothing is drawn until the parts drawn on the render thread are presented, since the
+        // state they're drawn from is moved to it. The redraws in sync with the window wait.
+        if let Some(render_thread) = self.render_thread.as_mut() {
+            let job = if self.should_sync {
+                render_thread.wait()
+            } else {
+                render_thread.try_finish()
+            };
+            let in_flight = render_thread.in_flight();
+            if let Some(job) = job {
+                self.finish_render(job);
+            } else if in_flight {
+                return Some(false);
+            }
+        }
+        let rendered_parts = mem::take(&mut self.rendered_parts);
+
+        self.reload_button_layout();
+
+        if let Some(decorations) = self.decorations.as_mut() {
+            self.should_sync |= decorations.sync_scales();
+        }
+
+        let now = Instant::now();
+        let mut theme = self.faded_theme(now);
+        let fading = theme.is_some();
+        if !fading {
+            self.theme_fade = None;
+        }
+
+        let attention = self.attention.value(now);
+        if attention > 0. {
+            theme = Some(
+                theme
+                    .as_ref()
+                    .unwrap_or(&self.theme)
+                    .with_attention(attention),
+            );
+        }
+
+        let flashing = self.flash.update(now);
+        if flashing {
+            let flash = self.flash.value(now);
+            theme = Some(theme.as_ref().unwrap_or(&self.theme).with_flash(flash));
+        }
+
+        // Tag the parts once the image description of the color space is ready.
+        let color_management = color_management(&self.globals, self.color_space);
+        if color_management.is_some() != self.color_tagged {
+            self.color_tagged = color_management.is_some();
+            self.dirty = true;
+        }
+
+        let decorations = self.decorations.as_mut()?;
+        self.last_error = None;
+
+        // Reset the dirty bit.
+        let redraw_all = mem::take(&mut self.dirty);
+        let should_sync = mem::take(&mut self.should_sync);
+
+        let floating = self.floating.value(now);
+        let activation = self.activation.value(now);
+        // Keep redrawing on the following frames until the transitions are over.
+        self.dirty = self.floating.is_running(now)
+            || self.activation.is_running(now)
+            || self.attention.is_running(now)
+            || flashing
+            || fading;
+
+        // Don't draw borders if the frame explicitly hidden or fullscreened.
+        if self.state.contains(WindowState::FULLSCREEN) {
+            decorations.hide();
+            return Some(true);
+        }
+
+        if redraw_all {
+            decorations.set_color_management(color_management.as_deref());
+        }
+
+        let draw_borders = if self.shadow_policy.hides_edges(&self.state) {
+            // Don't draw the borders.
+            decorations.hide_borders();
+            false
+        } else {
+            true
+        };
+
+        let draw_header = !self.hide_titlebar && self.mode == DecorationMode::Full;
+        if !draw_header {
+            decorations.hide_header();
+        }
+
+        let mut painter = PartPainter {
+            state: self.state,
+            theme: theme.as_ref().unwrap_or(&self.theme),
+            buttons: &mut self.buttons,
+            shadow: &mut self.shadow,
+            title_text: self.title_text.as_mut(),
+            title_pixmap: self.title_pixmap.as_mut(),
+            subtitle_text: self.subtitle_text.as_mut(),
+            icon: self.icon.as_mut(),
+            hovered: self.pointers.hovered_buttons(),
+            pressed: self.pointers.pressed_buttons(),
+            resize_hint: if self.resize_hint && self.resizable {
+                self.pointers.hovered_edges()
+            } else {
+                Vec::new()
+            },
+            progress: self.progress,
+            snap_preview: self.snap_preview,
+            resizable: self.resizable,
+            rtl: self.rtl,
+            title_alignment: self.title_alignment,
+            header_position: self.header_position,
+            draw_header,
+            draw_borders,
+            draw_shadow: self.mode == DecorationMode::Full,
+            shadow_policy: self.shadow_policy,
+            floating,
+            activation,
+            corner_radius: self.corner_radius,
+            outline: None,
+            #[cfg(feature = "debug-overlay")]
+            debug: self
+                .debug_overlay
+                .then(|| debug::DebugOverlay::new(decorations)),
+        };
+        if let Some(outline) = self.outline {
+            painter.outline = Some((outline, painter.outline_bounds(&decorations.rects())));
+        }
+
+        if self.layout == DecorationLayout::SingleSurface {
+            if let Err(err) = draw_single_surface(
+                decorations,
+                &mut painter,
+                &mut self.pool,
+                &self.compositor,
+                #[cfg(feature = "blur")]
+                self.globals.blur_manager.as_deref(),
+                self.scale_factor,
+                redraw_all,
+                should_sync,
+            )? {
+                log::warn!("{err}");
+                self.last_error = Some(err);
+            }
+            return Some(should_sync);
+        }
+
+        if let Some(solid_buffers) = self.globals.solid_buffers.as_deref() {
+            solid_buffers.dispatch();
+        }
+
+        // Present the parts drawn on the render thread, unless they were moved since.
+        for (target, rendered) in rendered_parts {
+            let RenderTarget {
+                idx, rect, opaque, ..
+            } = target;
+            let Some((_, part)) = decorations.parts_mut().nth(idx) else {
+                continue;
+            };
+            let Some(part_surface) = part.surface.as_mut() else {
+                continue;
+            };
+            if !painter.is_visible(idx) {
+                continue;
+            }
+            if painter.buffer_rect(idx, part.surface_rect) != rect {
+                part_surface.needs_redraw = true;
+                continue;
+            }
+
+            let format = if opaque {
+                self.opaque_format
+            } else {
+                wl_shm::Format::Argb8888
+            };
+            part_surface.clear_segments();
+            let Some((canvas, scale)) =
+                part_surface.buffer(&mut self.pool, rect, target.scale, format)
+            else {
+                let err = FrameError::BufferCreation {
+                    width: rect.width * target.scale,
+                    height: rect.height * target.scale,
+                };
+                log::warn!("{err}");
+                self.last_error = Some(err);
+                continue;
+            };
+            let Some(mut pixmap) =
+                PixmapMut::from_bytes(canvas, rect.width * scale, rect.height * scale)
+            else {
+                let err = FrameError::PixmapCreation {
+                    width: rect.width * scale,
+                    height: rect.height * scale,
+                };
+                log::warn!("{err}");
+                self.last_error = Some(err);
+                continue;
+            };
+            match rendered {
+                Some(rendered) if scale == target.scale => {
+                    pixmap.data_mut().copy_from_slice(rendered.data());
+                }
+                // Drawn here at the lower scale the buffer could be created with.
+                _ => painter.draw(idx, rect, scale, &mut pixmap),
+            }
+            part_surface.presented = None;
+
+            let scale = match self.globals.solid_buffers.as_deref() {
+                Some(solid_buffers)
+                    if part_surface.set_solid(
+                        solid_buffers,
+                        uniform_color(pixmap.as_ref()),
+                        rect,
+                    ) =>
+                {
+                    1
+                }
+                _ => scale,
+            };
+
+            #[cfg(feature = "blur")]
+            if let Some(blur_manager) = self.globals.blur_manager.as_deref() {
+                if idx == DecorationParts::HEADER {
+                    let rects = painter.header_blur(rect);
+                    blur_manager.update(
+                        &mut part_surface.blur,
+                        &part_surface.surface,
+                        &self.compositor,
+                        rects,
+                    );
+                }
+            }
+
+            present_part(
+                part_surface,
+                part.input_rect,
+                &self.compositor,
+                rect,
+                opaque,
+                scale,
+                should_sync,
+                None,
+            )?;
+        }
+
+        // The redraws in sync with the window are drawn right away.
+        let threaded = self.threaded_rendering && !should_sync;
+        let mut targets = Vec::new();
+
+        // Draw the borders.
+        for (idx, part) in decorations.parts_mut() {
+            let Some(part_surface) = part.surface.as_mut() else {
+                continue;
+            };
+
+            // Only the parts needing it, e.g. with the changed scale, are redrawn when nothing
+            // else changed.
+            let redraw = mem::take(&mut part_surface.needs_redraw) || redraw_all;
+            let damage = part_surface.damage.take().filter(|_| !redraw_all);
+            if !redraw || !painter.is_visible(idx) {
+                continue;
+            }
+
+            let preferred_scale = part_surface.scale.unwrap_or(self.scale_factor);
+
+            let rect = painter.buffer_rect(idx, part.surface_rect);
+
+            let opaque = painter.is_opaque(idx);
+            let format = if opaque {
+                self.opaque_format
+            } else {
+                wl_shm::Format::Argb8888
+            };
+
+            // The long edges are drawn as their caps with the middle stretched by the
+            // compositor, instead of the buffers along the whole window.
+            let stretched = self
+                .globals
+                .solid_buffers
+                .as_deref()
+                .zip(painter.stretch_caps(idx, rect))
+                .map(|(solid_buffers, caps)| {
+                    draw_stretched(
+                        part_surface,
+                        &mut painter,
+                        StretchTarget {
+                            idx,
+                            rect,
+                            caps,
+                            scale: preferred_scale,
+                        },
+                        &mut self.pool,
+                        &self.compositor,
+                        &*self.surface_factory.0,
+                        solid_buffers,
+                        color_management.as_deref(),
+                    )
+                });
+
+            let (scale, damage) = if let Some(result) = stretched {
+                part_surface.presented = None;
+                if let Err(err) = result {
+                    log::warn!("{err}");
+                    self.last_error = Some(err);
+                    continue;
+                }
+                (1, None)
+            } else if threaded {
+                // Drawn on the render thread, and presented once it's done.
+                part_surface.presented = None;
+                targets.push(RenderTarget {
+                    idx,
+                    rect,
+                    scale: preferred_scale,
+                    opaque,
+                });
+                continue;
+            } else {
+                part_surface.clear_segments();
+
+                let Some((canvas, scale)) =
+                    part_surface.buffer(&mut self.pool, rect, preferred_scale, format)
+                else {
+                    let err = FrameError::BufferCreation {
+                        width: rect.width * preferred_scale,
+                        height: rect.height * preferred_scale,
+                    };
+                    log::warn!("{err}");
+                    self.last_error = Some(err);
+                    continue;
+                };
+                let (width, height) = (rect.width * scale, rect.height * scale);
+
+                let Some(mut pixmap) = PixmapMut::from_bytes(canvas, width, height) else {
+                    let err = FrameError::PixmapCreation { width, height };
+                    log::warn!("{err}");
+                    self.last_error = Some(err);
+                    continue;
+                };
+                painter.draw(idx, rect, scale, &mut pixmap);
+
+                // The parts of a single color, e.g. the transparent borders along the tiled
+                // edges, don't need the memory of the drawn buffer.
+                match self.globals.solid_buffers.as_deref() {
+                    Some(solid_buffers)
+                        if part_surface.set_solid(
+                            solid_buffers,
+                            uniform_color(pixmap.as_ref()),
+                            rect,
+                        ) =>
+                    {
+                        part_surface.presented = None;
+                        (1, None)
+                    }
+                    // Only what changed since the border was presented last is damaged.
+                    _ => {
+                        let drawing = painter.border_drawing(idx, rect, scale);
+                        let damage = damage.or_else(|| {
+                            drawing
+                                .as_ref()?
+                                .damage_since(part_surface.presented.as_ref())
+                        });
+                        part_surface.presented = drawing;
+                        (scale, damage)
+                    }
+                }
+            };
+
+            #[cfg(feature = "blur")]
+            if let Some(blur_manager) = self.globals.blur_manager.as_deref() {
+                if idx == DecorationParts::HEADER {
+                    let rects = painter.header_blur(rect);
+                    blur_manager.update(
+                        &mut part_surface.blur,
+                        &part_surface.surface,
+                        &self.compositor,
+                        rects,
+                    );
+                }
+            }
+
+            present_part(
+                part_surface,
+                part.input_rect,
+                &self.compositor,
+                rect,
+                opaque,
+                scale,
+                should_sync,
+                damage,
+            )?;
+        }
+
+        if !targets.is_empty() {
+            let theme = painter.theme.clone();
+            let PartPainter {
+                state,
+                hovered,
+                pressed,
+                resize_hint,
+                progress,
+                snap_preview,
+                resizable,
+                rtl,
+                title_alignment,
+                header_position,
+                draw_header,
+                draw_borders,
+                draw_shadow,
+                shadow_policy,
+                floating,
+                corner_radius,
+                activation,
+                outline,
+                ..
+            } = painter;
+            self.sent_title_metrics = self.title_metrics();
+            let job = RenderJob {
+                state,
+                theme,
+                buttons: self.buttons.clone(),
+                shadow: mem::take(&mut self.shadow),
+                title_text: self.title_text.take(),
+                title_pixmap: self.title_pixmap.take(),
+                subtitle_text: self.subtitle_text.take(),
+                icon: self.icon.take(),
+                hovered,
+                pressed,
+                resize_hint,
+                progress,
+                snap_preview,
+                resizable,
+                rtl,
+                title_alignment,
+                header_position,
+                draw_header,
+                draw_borders,
+                draw_shadow,
+                shadow_policy,
+                floating,
+                corner_radius,
+                activation,
+                outline,
+                targets,
+                rendered: Vec::new(),
+                panicked: false,
+                on_ready: self.render_ready_callback.clone(),
+            };
+            self.start_render(job);
+        }
+
+        Some(should_sync)
+    }
+}
+
+impl<State> DecorationsFrame for AdwaitaFrame<State>
+where
+    State: Dispatch<WlSurface, SurfaceData> + Dispatch<WlSubsurface, SubsurfaceData> + 'static,
+{
+    fn update_state(&mut self, state: WindowState) {
+        let difference = self.state.symmetric_difference(state);
+        self.state = state;
+
+        if difference.contains(WindowState::SUSPENDED) {
+            if self.is_suspended() {
+                self.suspend();
+            } else {
+                // Everything changed while suspended is drawn at once.
+                self.mark_dirty();
+            }
+        }
+
+        let now = Instant::now();
+        let floating = if state.contains(WindowState::MAXIMIZED) {
+            0.
+        } else {
+            1.
+        };
+        let activation = if state.contains(WindowState::ACTIVATED) {
+            1.
+        } else {
+            0.
+        };
+        let animate = self.animations && !self.is_suspended();
+        self.floating.set(floating, now, animate);
+        self.activation.set(activation, now, animate);
+        if difference.intersects(
+            WindowState::ACTIVATED
+                | WindowState::FULLSCREEN
+                | WindowState::MAXIMIZED
+                | WindowState::TILED,
+        ) {
+            self.mark_dirty();
+        }
+    }
+
+    fn update_wm_capabilities(&mut self, wm_capabilities: WindowManagerCapabilities) {
+        if self.wm_capabilities != wm_capabilities {
+            self.mark_dirty();
+        }
+        self.wm_capabilities = wm_capabilities;
+        self.buttons.update_wm_capabilities(wm_capabilities);
+    }
+
+    fn set_hidden(&mut self, hidden: bool) {
+        if hidden {
+            self.dirty = false;
+            let _ = self.pool.resize(1);
+            self.decorations = None;
+        } else if self.decorations.is_none() {
+            let mut decorations = DecorationParts::new(
+                &self.base_surface,
+                &*self.surface_factory.0,
+                self.header_height(),
+                self.layout,
+                self.mode,
+            );
+            decorations.set_handle_size(self.resize_handle_size);
+            decorations.set_header_position(self.header_position);
+            if let Some((width, height)) = self.last_size {
+                decorations.resize(width.get(), height.get());
+                self.buttons
+                    .arrange(decorations.header_length(), get_margin_h_lp(&self.state));
+            }
+            self.decorations = Some(decorations);
+            self.mark_dirty();
+            self.should_sync = true;
+        }
+    }
+
+    fn set_resizable(&mut self, resizable: bool) {
+        if self.resizable != resizable {
+            self.mark_dirty();
+        }
+        self.resizable = resizable;
+    }
+
+    fn resize(&mut self, width: NonZeroU32, height: NonZeroU32) {
+        // The size is applied once unhidden.
+        self.last_size = Some((width, height));
+        let Some(decorations) = self.decorations.as_mut() else {
+            return;
+        };
+
+        decorations.resize(width.get(), height.get());
+        self.buttons
+            .arrange(decorations.header_length(), get_margin_h_lp(&self.state));
+        self.mark_dirty();
+        self.should_sync = true;
+    }
+
+    fn draw(&mut self) -> bool {
+        // The updates are drawn together once thawed or shown again.
+        if self.is_frozen() || self.is_suspended() {
+            return false;
+        }
+
+        let synced = self.redraw_inner().unwrap_or(true);
+        // Keep drawing the running animations.
+        self.notify_dirty(false);
+        synced
+    }
+
+    fn subtract_borders(
+        &self,
+        width: NonZeroU32,
+        height: NonZeroU32,
+    ) -> (Option<NonZeroU32>, Option<NonZeroU32>) {
+        let insets = self.decoration_insets();
+        (
+            NonZeroU32::new(width.get().saturating_sub(insets.left + insets.right)),
+            NonZeroU32::new(height.get().saturating_sub(insets.top + insets.bottom)),
+        )
+    }
+
+    fn add_borders(&self, width: u32, height: u32) -> (u32, u32) {
+        let insets = self.decoration_insets();
+        (
+            width + insets.left + insets.right,
+            height + insets.top + insets.bottom,
+        )
+    }
+
+    fn location(&self) -> (i32, i32) {
+        let insets = self.decoration_insets();
+        (-(insets.left as i32), -(insets.top as i32))
+    }
+
+    fn set_title(&mut self, title: impl Into<String>) {
+        self.join_render();
+        let new_title = title.into();
+        if let Some(title_text) = self.title_text.as_mut() {
+            title_text.update_title(new_title.clone());
+        }
+
+        self.title = Some(new_title);
+        self.title_pixmap = None;
+        self.mark_dirty();
+    }
+
+    fn on_click(
+        &mut self,
+        timestamp: Duration,
+        click: FrameClick,
+        pressed: bool,
+    ) -> Option<FrameAction> {
+        self.on_click_for_seat(
+            DEFAULT_SEAT,
+            timestamp,
+            click,
+            pressed,
+            Modifiers::default(),
+        )
+    }
+
+    fn set_scaling_factor(&mut self, scale_factor: f64) {
+        // NOTE: Clamp it just in case to some ok-ish range.
+        self.scale_factor = scale_factor.clamp(0.1, 64.).ceil() as u32;
+        self.mark_dirty();
+        self.should_sync = true;
+    }
+
+    fn click_point_moved(
+        &mut self,
+        timestamp: Duration,
+        surface: &ObjectId,
+        x: f64,
+        y: f64,
+    ) -> Option<CursorIcon> {
+        self.click_point_moved_for_seat(DEFAULT_SEAT, timestamp, surface, x, y)
+    }
+
+    fn click_point_left(&mut self) {
+        self.click_point_left_for_seat(DEFAULT_SEAT)
+    }
+
+    fn is_dirty(&self) -> bool {
+        if self.is_frozen() || self.is_suspended() {
+            return false;
+        }
+
+        // The render thread wakes the app with `on_render_ready` instead.
+        if self
+            .render_thread
+            .as_ref()
+            .is_some_and(|render_thread| render_thread.in_flight() && !render_thread.is_ready())
+        {
+            return false;
+        }
+
+        self.dirty
+            || !self.rendered_parts.is_empty()
+            || self
+                .render_thread
+                .as_ref()
+                .is_some_and(RenderThread::is_ready)
+            || self
+                .button_layout_watcher
+                .as_ref()
+                .is_some_and(ButtonLayoutWatcher::has_changed)
+            || color_management(&self.globals, self.color_space).is_some() != self.color_tagged
+            || self
+                .decorations
+                .as_ref()
+                .is_some_and(|decorations| decorations.needs_redraw())
+    }
+
+    fn is_hidden(&self) -> bool {
+        self.decorations.is_none()
+    }
+}
+
+/// The error creating or drawing the frame, see [`AdwaitaFrame::new`] and
+/// [`AdwaitaFrame::last_error`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FrameError {
+    /// The buffer of the given size in pixels couldn't be created in the pool, even at the
+    /// lower scales.
+    BufferCreation { width: u32, height: u32 },
+    /// The drawn buffer of the given size in pixels couldn't be wrapped into the pixmap.
+    PixmapCreation { width: u32, height: u32 },
+    /// The memory pool of the buffers couldn't be created, with the reason.
+    PoolCreation(String),
+    /// The compositor doesn't advertise the global of the given interface the frame is drawn
+    /// with, e.g. `wl_subcompositor`, see [`AdwaitaFrame::new`].
+    MissingGlobal(&'static str),
+    /// The titlebar font couldn't be loaded, with the reason, so the title isn't drawn.
+    FontInit(String),
+}
+
+impl fmt::Display for FrameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FrameError::BufferCreation { width, height } => {
+                write!(
+                    f,
+                    "failed to create the {width}x{height} decorations buffer"
+                )
+            }
+            FrameError::PixmapCreation { width, height } => {
+                write!(
+                    f,
+                    "failed to draw into the {width}x{height} decorations buffer"
+                )
+            }
+            FrameError::PoolCreation(reason) => {
+                write!(f, "failed to create the decorations buffer pool: {reason}")
+            }
+            FrameError::MissingGlobal(interface) => {
+                write!(f, "the decorations need the {interface} global")
+            }
+            FrameError::FontInit(reason) => {
+                write!(f, "failed to load the titlebar font: {reason}")
+            }
+        }
+    }
+}
+
+impl Error for FrameError {}
+
+/// The sizes of the decorations in logical points, see [`AdwaitaFrame::metrics`].
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FrameMetrics {
+    /// The height of the headerbar above the window surface, or its width when it's placed
+    /// along the left edge with [`FrameConfig::header_position`].
+    pub header_height: u32,
+    /// The size of the border around the window, including the shadow and the visible border.
+    pub border_size: u32,
+    /// The width of the visible border line around the window.
+    pub visible_border: u32,
+    /// The radius of the top corners of the headerbar.
+    pub corner_radius: u32,
+    /// The size of the shadow around the window.
+    pub shadow_size: u32,
+}
+
+/// The configuration for the [`AdwaitaFrame`] frame.
+#[derive(Debug, Clone)]
+pub struct FrameConfig {
+    pub theme: ColorTheme,
+    hide_titlebar: bool,
+    /// `None` to follow the system configuration.
+    double_click_action: Option<DoubleClickAction>,
+    /// `None` to follow the system configuration.
+    middle_click_action: Option<MiddleClickAction>,
+    button_actions: Vec<(u32, MiddleClickAction)>,
+    scroll_action: ScrollAction,
+    buttons: ButtonSet,
+    layout: DecorationLayout,
+    decoration_mode: DecorationMode,
+    /// `None` to follow the system configuration.
+    button_layout: Option<(String, String)>,
+    corner_radius: u32,
+    title_alignment: TitleAlignment,
+    header_position: HeaderPosition,
+    resize_hint: bool,
+    outline: Option<OutlineStyle>,
+    resize_handle_size: u32,
+    resize_corner_size: u32,
+    themed_button_icons: bool,
+    button_icons: ButtonIcons,
+    /// `None` to follow the system configuration.
+    double_click_time: Option<Duration>,
+    drag_threshold: u32,
+    drag_modifier: DragModifier,
+    /// `None` to follow the system configuration.
+    hinting: Option<Hinting>,
+    /// `None` to follow the system configuration.
+    subpixel_order: Option<SubpixelOrder>,
+    color_space: ColorSpace,
+    globals: FrameGlobals,
+    release_suspended_buffers: bool,
+    cursor_map: CursorMap,
+    threaded_rendering: bool,
+    shadow_policy: ShadowPolicy,
+    /// `None` to load the font for the frame alone.
+    title_font_cache: Option<TitleFontCache>,
+    /// `None` to query the system configuration for the frame.
+    system_prefs: Option<SystemPrefs>,
+}
+
+impl FrameConfig {
+    /// Create the new configuration with the given `theme`.
+    pub fn new(theme: ColorTheme) -> Self {
+        Self {
+            theme,
+            hide_titlebar: false,
+            double_click_action: None,
+            middle_click_action: None,
+            button_actions: Vec::new(),
+            scroll_action: ScrollAction::None,
+            buttons: ButtonSet::ALL,
+            layout: DecorationLayout::MultiPart,
+            decoration_mode: DecorationMode::Full,
+            button_layout: None,
+            corner_radius: CORNER_RADIUS,
+            title_alignment: TitleAlignment::Center,
+            header_position: HeaderPosition::Top,
+            resize_hint: false,
+            outline: None,
+            resize_handle_size: RESIZE_HANDLE_SIZE,
+            resize_corner_size: RESIZE_HANDLE_CORNER_SIZE,
+            themed_button_icons: false,
+            button_icons: ButtonIcons::default(),
+            double_click_time: None,
+            drag_threshold: 0,
+            drag_modifier: DragModifier::Alt,
+            hinting: None,
+            subpixel_order: None,
+            color_space: ColorSpace::Untagged,
+            globals: FrameGlobals::default(),
+            release_suspended_buffers: false,
+            cursor_map: CursorMap::default(),
+            threaded_rendering: false,
+            shadow_policy: ShadowPolicy::Auto,
+            title_font_cache: None,
+            system_prefs: None,
+        }
+    }
+
+    /// Start chaining the options on the configuration with the [`ColorTheme::auto`] theme,
+    /// replaced with [`Self::theme`], up to the [`Self::build`].
+    pub fn builder() -> Self {
+        Self::auto()
+    }
+
+    /// Finish the chain of the options started with [`Self::builder`].
+    pub fn build(self) -> Self {
+        self
+    }
+
+    /// Replace the theme, along with the colors set with [`Self::state_colors`] before.
+    pub fn theme(mut self, theme: ColorTheme) -> Self {
+        self.theme = theme;
+        self
+    }
+
+    /// Draw the window in the `state` with the `active` and the `inactive` colors, replacing
+    /// the ones of the theme, see [`ColorTheme::with_state_colors`].
+    pub fn state_colors(mut self, state: ThemeState, active: ColorMap, inactive: ColorMap) -> Self {
+        self.theme = self.theme.with_state_colors(state, active, inactive);
+        self
+    }
+
+    /// Hide the headerbar, while keeping the borders and the shadows.
+    ///
+    /// Useful for applications drawing their own title area, like terminals with tabs.
+    pub fn hide_titlebar(mut self, hide_titlebar: bool) -> Self {
+        self.hide_titlebar = hide_titlebar;
+        self
+    }
+
+    /// Override the action on the double click on the headerbar.
+    ///
+    /// By default the `action-double-click-titlebar` system setting is used.
+    pub fn double_click_action(mut self, action: DoubleClickAction) -> Self {
+        self.double_click_action = Some(action);
+        self
+    }
+
+    /// Override the time to register the next click on the headerbar as a double click.
+    ///
+    /// By default the `double-click` system setting is used.
+    pub fn double_click_time(mut self, time: Duration) -> Self {
+        self.double_click_time = Some(time);
+        self
+    }
+
+    /// Move the window only after the pointer pressed on the headerbar travelled `threshold`
+    /// logical points, like the 8 points of GTK, to avoid the accidental moves.
+    ///
+    /// The move is then returned by [`AdwaitaFrame::drag_action`] instead of the press. Defaults
+    /// to `0`, moving the window right on the press.
+    pub fn drag_threshold(mut self, threshold: u32) -> Self {
+        self.drag_threshold = threshold;
+        self
+    }
+
+    /// Set the modifier key turning the presses anywhere on the decorations into the moves of
+    /// the window, and the presses of the right button into its resizes, see
+    /// [`AdwaitaFrame::on_button`].
+    ///
+    /// Defaults to [`DragModifier::Alt`].
+    pub fn drag_modifier(mut self, modifier: DragModifier) -> Self {
+        self.drag_modifier = modifier;
+        self
+    }
+
+    fn resolve_click_config(&self) -> ClickConfig {
+        ClickConfig {
+            double_click_action: self
+                .double_click_action
+                .or_else(|| {
+                    self.system_pref(
+                        |prefs| prefs.double_click_action,
+                        config::double_click_action,
+                    )
+                })
+                .unwrap_or_default(),
+            double_click_time: self
+                .double_click_time
+                .or_else(|| {
+                    self.system_pref(|prefs| prefs.double_click_time, config::double_click_time)
+                })
+                .unwrap_or(DOUBLE_CLICK_DURATION),
+            drag_threshold: self.drag_threshold,
+            drag_modifier: self.drag_modifier,
+        }
+    }
+
+    /// Override the action on the middle click on the headerbar.
+    ///
+    /// By default the `action-middle-click-titlebar` system setting is used.
+    pub fn middle_click_action(mut self, action: MiddleClickAction) -> Self {
+        self.middle_click_action = Some(action);
+        self
+    }
+
+    /// Perform the `action` on the click of the pointer `button` with the Linux input event
+    /// code on the headerbar, e.g. `0x113` and `0x114` for the side buttons often mapped to the
+    /// window actions, see [`AdwaitaFrame::on_button`].
+    ///
+    /// Replaces the previous action of the `button`. The left and the right buttons can't be
+    /// remapped.
+    pub fn button_action(mut self, button: u32, action: MiddleClickAction) -> Self {
+        self.button_actions.retain(|(code, _)| *code != button);
+        self.button_actions.push((button, action));
+        self
+    }
+
+    /// Set the action on the scroll over the headerbar, see [`AdwaitaFrame::on_scroll`].
+    ///
+    /// Defaults to [`ScrollAction::None`].
+    pub fn scroll_action(mut self, action: ScrollAction) -> Self {
+        self.scroll_action = action;
+        self
+    }
+
+    /// Override how the title glyphs are fitted to the pixel grid.
+    ///
+    /// By default the `font-hinting` system setting is used.
+    pub fn hinting(mut self, hinting: Hinting) -> Self {
+        self.hinting = Some(hinting);
+        self
+    }
+
+    /// Override the subpixel order of the display the title is rendered for, e.g.
+    /// [`SubpixelOrder::None`] for the grayscale title on the high density or rotated displays.
+    ///
+    /// By default the `rgba` fontconfig property is used. Only the `ab_glyph` renderer draws
+    /// the subpixel titles.
+    pub fn subpixel_order(mut self, order: SubpixelOrder) -> Self {
+        self.subpixel_order = Some(order);
+        self
+    }
+
+    fn resolve_text_rendering(&self) -> TextRendering {
+        TextRendering {
+            hinting: self
+                .hinting
+                .or_else(|| self.system_pref(|prefs| prefs.font_hinting, config::font_hinting))
+                .unwrap_or_default(),
+            subpixel_order: self
+                .subpixel_order
+                .or_else(|| self.system_pref(|prefs| prefs.subpixel_order, config::subpixel_order))
+                .unwrap_or_default(),
+        }
+    }
+
+    fn resolve_middle_click_action(&self) -> MiddleClickAction {
+        self.middle_click_action
+            .or_else(|| {
+                self.system_pref(
+                    |prefs| prefs.middle_click_action,
+                    config::middle_click_action,
+                )
+            })
+            .unwrap_or_default()
+    }
+
+    pub(crate) fn resolve_text_scale(&self) -> f32 {
+        self.system_pref(|prefs| prefs.text_scale, config::text_scale)
+    }
+
+    fn resolve_header_height(&self, text_scale: f32) -> u32 {
+        let titlebar_font =
+            self.system_pref(|prefs| prefs.titlebar_font.clone(), config::titlebar_font);
+        title::header_height(titlebar_font.as_deref(), text_scale)
+    }
+
+    fn resolve_animations(&self) -> bool {
+        self.system_pref(|prefs| prefs.enable_animations, config::enable_animations)
+    }
+
+    /// Follow the snapshot of the system configuration instead of querying it for the frame,
+    /// e.g. [`SystemPrefs::load`]ed once for all the windows of the application.
+    ///
+    /// The options set on the configuration take precedence over it.
+    pub fn system_prefs(mut self, prefs: SystemPrefs) -> Self {
+        self.system_prefs = Some(prefs);
+        self
+    }
+
+    /// The setting of the [`Self::system_prefs`], or queried from the system without them.
+    fn system_pref<T>(&self, pref: impl FnOnce(&SystemPrefs) -> T, query: impl FnOnce() -> T) -> T {
+        match self.system_prefs.as_ref() {
+            Some(prefs) => pref(prefs),
+            None => query(),
+        }
+    }
+
+    /// Show only the given titlebar buttons, e.g. [`ButtonSet::CLOSE`] for the dialogs.
+    ///
+    /// The buttons not supported by the window manager are hidden regardless.
+    pub fn buttons(mut self, buttons: ButtonSet) -> Self {
+        self.buttons = buttons;
+        self
+    }
+
+    /// Set how the decorations are split into the subsurfaces.
+    ///
+    /// Defaults to [`DecorationLayout::MultiPart`].
+    pub fn layout(mut self, layout: DecorationLayout) -> Self {
+        self.layout = layout;
+        self
+    }
+
+    /// Set which of the decorations are drawn, e.g. [`DecorationMode::BorderOnly`] for the
+    /// thin borders without the headerbar and the shadow on the tiling compositors.
+    ///
+    /// Changing it changes the size of the borders, see [`AdwaitaFrame::set_config`].
+    ///
+    /// Defaults to [`DecorationMode::Full`].
+    pub fn decoration_mode(mut self, mode: DecorationMode) -> Self {
+        self.decoration_mode = mode;
+        self
+    }
+
+    /// Set where the shadow is drawn, e.g. [`ShadowPolicy::NotWhenTiled`] to leave out the
+    /// border parts of the tiled windows like the ones of the maximized windows.
+    ///
+    /// Defaults to [`ShadowPolicy::Auto`].
+    pub fn shadow_policy(mut self, policy: ShadowPolicy) -> Self {
+        self.shadow_policy = policy;
+        self
+    }
+
+    /// Override the order of the titlebar buttons, in the format of the `button-layout` system
+    /// setting, e.g. `"close:"` or `"appmenu:minimize,maximize,close"`.
+    ///
+    /// By default the `button-layout` system setting is used, and the buttons are rearranged
+    /// on the next redraw after it changes.
+    pub fn button_layout(mut self, button_layout: &str) -> Self {
+        self.button_layout = button_layout
+            .split_once(':')
+            .map(|(left, right)| (left.to_owned(), right.to_owned()));
+        self
+    }
+
+    fn resolve_button_layout(&self) -> Option<(String, String)> {
+        self.button_layout.clone().or_else(|| {
+            self.system_pref(
+                |prefs| {
+                    let (left, right) = prefs.button_layout.as_deref()?.split_once(':')?;
+                    Some((left.to_owned(), right.to_owned()))
+                },
+                get_button_layout_config,
+            )
+        })
+    }
+
+    /// Set the radius of the top corners of the floating window in logical points.
+    ///
+    /// Defaults to the Adwaita radius.
+    pub fn corner_radius(mut self, corner_radius: u32) -> Self {
+        self.corner_radius = corner_radius;
+        self
+    }
+
+    /// Set the horizontal alignment of the title in the headerbar.
+    ///
+    /// Defaults to [`TitleAlignment::Center`].
+    pub fn title_alignment(mut self, title_alignment: TitleAlignment) -> Self {
+        self.title_alignment = title_alignment;
+        self
+    }
+
+    /// Place the headerbar along the given edge of the window.
+    ///
+    /// Defaults to [`HeaderPosition::Top`].
+    pub fn header_position(mut self, header_position: HeaderPosition) -> Self {
+        self.header_position = header_position;
+        self
+    }
+
+    /// Tell the compositor the color space the decorations are drawn in, so they don't look
+    /// washed out next to the HDR or the wide-gamut contents of the window.
+    ///
+    /// Defaults to [`ColorSpace::Untagged`].
+    pub fn color_space(mut self, color_space: ColorSpace) -> Self {
+        self.color_space = color_space;
+        self
+    }
+
+    /// Draw with the optional protocols of the `globals` bound once by the app with
+    /// [`FrameGlobals::bind`] and shared by its frames, e.g. to tag the decorations with the
+    /// [`Self::color_space`].
+    ///
+    /// Applies to the frames created with the configuration. Defaults to none of them.
+    pub fn globals(mut self, globals: FrameGlobals) -> Self {
+        self.globals = globals;
+        self
+    }
+
+    /// Release the memory of the buffers while the window is suspended, at the cost of
+    /// redrawing the whole frame once it's shown again.
+    ///
+    /// Disabled by default.
+    pub fn release_suspended_buffers(mut self, release: bool) -> Self {
+        self.release_suspended_buffers = release;
+        self
+    }
+
+    /// The cursors shown over the parts of the frame, for the cursor themes with the other
+    /// names, see [`AdwaitaFrame::cursor_name`].
+    ///
+    /// Defaults to [`CursorMap::default`].
+    pub fn cursor_map(mut self, cursor_map: CursorMap) -> Self {
+        self.cursor_map = cursor_map;
+        self
+    }
+
+    /// Draw the parts on another thread, so the redraws of the large windows at the high
+    /// scales, e.g. on the hover or during the transitions, don't block the event loop.
+    ///
+    /// The drawn parts are presented by the next [`DecorationsFrame::draw`], see
+    /// [`AdwaitaFrame::on_render_ready`] to schedule it. The redraws in sync with the window,
+    /// e.g. after the resize, and the [`DecorationLayout::SingleSurface`] layout are drawn on
+    /// the calling thread. While the parts are being drawn, [`AdwaitaFrame::title_is_truncated`]
+    /// and [`AdwaitaFrame::title_cache_stats`] answer for the title when the job was sent.
+    ///
+    /// Disabled by default.
+    pub fn threaded_rendering(mut self, threaded: bool) -> Self {
+        self.threaded_rendering = threaded;
+        self
+    }
+
+    /// Highlight the border segment under the pointer ready to resize the window.
+    ///
+    /// Disabled by default.
+    pub fn resize_hint(mut self, resize_hint: bool) -> Self {
+        self.resize_hint = resize_hint;
+        self
+    }
+
+    /// Draw the contrasting line outside the visible borders and around the rounded headerbar,
+    /// helping the low-vision users to find the window boundaries.
+    ///
+    /// Disabled by default.
+    pub fn outline(mut self, outline: OutlineStyle) -> Self {
+        self.outline = Some(outline);
+        self
+    }
+
+    /// Set the size of the resize handles along the edges of the window in logical points,
+    /// limited by the size of the borders.
+    ///
+    /// Defaults to `12`, like in GTK.
+    pub fn resize_handle_size(mut self, size: u32) -> Self {
+        self.resize_handle_size = size;
+        self
+    }
+
+    /// Set the length of the corner resize handles along the edges of the window in logical
+    /// points.
+    ///
+    /// Defaults to `24`, like in GTK.
+    pub fn resize_corner_size(mut self, size: u32) -> Self {
+        self.resize_corner_size = size;
+        self
+    }
+
+    /// Draw the symbolic icons of the buttons from the icon theme, like `window-close-symbolic`,
+    /// falling back to the built-in icons when they can't be loaded.
+    ///
+    /// Disabled by default.
+    pub fn themed_button_icons(mut self, themed_button_icons: bool) -> Self {
+        self.themed_button_icons = themed_button_icons;
+        self
+    }
+
+    /// Draw the glyphs of the buttons with the `icons` instead of the built-in
+    /// [`AdwaitaButtonIcons`], e.g. to follow another icon style.
+    pub fn button_icons(mut self, icons: impl ButtonIconProvider + Send + Sync + 'static) -> Self {
+        self.button_icons = ButtonIcons(Arc::new(icons));
+        self
+    }
+
+    /// Draw the titles with the font loaded once by the `cache` shared with the other frames,
+    /// instead of loading it for every frame, e.g. in the applications with many windows.
+    ///
+    /// Applies to the frames created with the configuration.
+    pub fn title_font_cache(mut self, cache: TitleFontCache) -> Self {
+        self.title_font_cache = Some(cache);
+        self
+    }
+
+    /// This is equivalent of calling `FrameConfig::new(ColorTheme::auto())`.
+    ///
+    /// For details see [`ColorTheme::auto`].
+    pub fn auto() -> Self {
+        Self::new(ColorTheme::auto())
+    }
+
+    /// This is equivalent of calling `FrameConfig::new(ColorTheme::light())`.
+    ///
+    /// For details see [`ColorTheme::light`].
+    pub fn light() -> Self {
+        Self::new(ColorTheme::light())
+    }
+
+    /// This is equivalent of calling `FrameConfig::new(ColorTheme::dark())`.
+    ///
+    /// For details see [`ColorTheme::dark`].
+    pub fn dark() -> Self {
+        Self::new(ColorTheme::dark())
+    }
+}
+
+/// The color management to tag the parts with the `color_space`, `None` when it's untagged, or
+/// the compositor doesn't support it or its image description isn't ready yet.
+fn color_management(
+    globals: &FrameGlobals,
+    color_space: ColorSpace,
+) -> Option<Arc<ColorManagement>> {
+    match color_space {
+        ColorSpace::Untagged => None,
+        ColorSpace::Srgb => globals
+            .color_management
+            .clone()
+            .filter(|color_management| color_management.is_ready()),
+    }
+}
+
+/// Draw all the visible parts into the surface of the [`DecorationLayout::SingleSurface`]
+/// layout, with only the parts' input rectangles in its input region.
+///
+/// Returns the error when the buffer can't be created even at the lower scales.
+#[cfg_attr(feature = "blur", allow(clippy::too_many_arguments))]
+fn draw_single_surface(
+    decorations: &mut DecorationParts,
+    painter: &mut PartPainter,
+    pool: &mut SlotPool,
+    compositor: &CompositorState,
+    #[cfg(feature = "blur")] blur_manager: Option<&blur::BlurManager>,
+    scale_factor: u32,
+    redraw_all: bool,
+    should_sync: bool,
+) -> Option<Result<(), FrameError>> {
+    let rects = decorations.rects();
+    let Some(bounds) = painter.bounds(&rects) else {
+        decorations.hide();
+        return Some(Ok(()));
+    };
+
+    let input_region = Region::new(compositor).ok()?;
+    for idx in (0..rects.len()).filter(|idx| painter.is_visible(*idx)) {
+        let rect = decorations.input_rect(idx);
+        input_region.add(
+            rect.x - bounds.x,
+            rect.y - bounds.y,
+            rect.width as i32,
+            rect.height as i32,
+        );
+    }
+
+    decorations.set_single_origin((bounds.x, bounds.y));
+    let single = decorations.single_mut()?;
+
+    // Only redrawn when needed, e.g. with the changed scale, when nothing else changed.
+    let redraw = mem::take(&mut single.needs_redraw) || redraw_all;
+    let damage = single.damage.take().filter(|_| !redraw_all);
+    if !redraw {
+        return Some(Ok(()));
+    }
+
+    let preferred_scale = single.scale.unwrap_or(scale_factor);
+    let Some((canvas, scale)) =
+        single.buffer(pool, bounds, preferred_scale, wl_shm::Format::Argb8888)
+    else {
+        return Some(Err(FrameError::BufferCreation {
+            width: bounds.width * preferred_scale,
+            height: bounds.height * preferred_scale,
+        }));
+    };
+    let (width, height) = (bounds.width * scale, bounds.height * scale);
+
+    let Some(mut pixmap) = PixmapMut::from_bytes(canvas, width, height) else {
+        return Some(Err(FrameError::PixmapCreation { width, height }));
+    };
+    painter.draw_composed(&rects, bounds, scale, &mut pixmap);
+
+    // The rest of the surface is transparent, but the compositor could still skip blending the
+    // opaque header.
+    let header = DecorationParts::HEADER;
+    let opaque = painter.is_opaque(header).then(|| {
+        let rect = painter.buffer_rect(header, rects[header]);
+        Rect {
+            x: rect.x - bounds.x,
+            y: rect.y - bounds.y,
+            ..rect
+        }
+    });
+    single.set_opaque_rect(compositor, opaque);
+
+    #[cfg(feature = "blur")]
+    if let Some(blur_manager) = blur_manager {
+        let rect = painter.buffer_rect(header, rects[header]);
+        let rects = painter.header_blur(rect).map(|rects| {
+            rects
+                .into_iter()
+                .map(|blurred| Rect {
+                    x: blurred.x + rect.x - bounds.x,
+                    y: blurred.y + rect.y - bounds.y,
+                    ..blurred
+                })
+                .collect()
+        });
+        blur_manager.update(&mut single.blur, &single.surface, compositor, rects);
+    }
+
+    single
+        .surface
+        .set_input_region(Some(input_region.wl_region()));
+    present(single, bounds, scale, should_sync, damage)?;
+    Some(Ok(()))
+}
+
+/// The edge part to draw stretched, with the `caps` in logical points at its both ends.
+#[derive(Debug, Clone, Copy)]
+struct StretchTarget {
+    idx: usize,
+    rect: Rect,
+    caps: (u32, u32),
+    scale: u32,
+}
+
+/// The source rectangles in the part drawn with only one point between its caps, and the
+/// destination rectangles in the whole part, of its start cap, middle and end cap.
+///
+/// The middle source is one point long, so it's stretched along the part.
+fn stretch_segments(idx: usize, rect: Rect, (start, end): (u32, u32)) -> [(Rect, Rect); 3] {
+    let vertical = matches!(idx, DecorationParts::LEFT | DecorationParts::RIGHT);
+    let length = if vertical { rect.height } else { rect.width };
+    let segment = |offset: u32, length: u32| {
+        if vertical {
+            Rect {
+                x: 0,
+                y: offset as i32,
+                width: rect.width,
+                height: length,
+            }
+        } else {
+            Rect {
+                x: offset as i32,
+                y: 0,
+                width: length,
+                height: rect.height,
+            }
+        }
+    };
+
+    [
+        (segment(0, start), segment(0, start)),
+        (segment(start, 1), segment(start, length - start - end)),
+        (segment(start + 1, end), segment(length - end, end)),
+    ]
+}
+
+/// Draw the edge part with only one point between its caps, and present the caps and the
+/// middle stretched by the viewport in the segments of the transparent part surface.
+#[allow(clippy::too_many_arguments)]
+fn draw_stretched(
+    part_surface: &mut PartSurface,
+    painter: &mut PartPainter,
+    target: StretchTarget,
+    pool: &mut SlotPool,
+    compositor: &CompositorState,
+    surface_factory: &dyn SurfaceFactory,
+    solid_buffers: &SolidBuffers,
+    color_management: Option<&ColorManagement>,
+) -> Result<(), FrameError> {
+    let StretchTarget {
+        idx,
+        rect,
+        caps,
+        scale,
+    } = target;
+    let segments = stretch_segments(idx, rect, caps);
+    let buffer_error = |rect: Rect| FrameError::BufferCreation {
+        width: rect.width * scale,
+        height: rect.height * scale,
+    };
+
+    let compressed = Rect {
+        width: segments[2].0.x as u32 + segments[2].0.width,
+        height: segments[2].0.y as u32 + segments[2].0.height,
+        ..rect
+    };
+    let mut compressed_pixmap = Pixmap::new(compressed.width * scale, compressed.height * scale)
+        .ok_or_else(|| buffer_error(compressed))?;
+    painter.draw(idx, compressed, scale, &mut compressed_pixmap.as_mut());
+
+    let parts = part_surface
+        .segments_mut(surface_factory, compositor)
+        .ok_or_else(|| buffer_error(rect))?;
+    for part in parts.iter_mut() {
+        part.set_color_management(color_management);
+    }
+    for (part, (source, destination)) in parts.iter_mut().zip(segments) {
+        let (canvas, buffer_scale) = part
+            .buffer(pool, source, scale, wl_shm::Format::Argb8888)
+            .filter(|(_, buffer_scale)| *buffer_scale == scale)
+            .ok_or_else(|| buffer_error(source))?;
+        let mut pixmap = PixmapMut::from_bytes(canvas, source.width * scale, source.height * scale)
+            .ok_or_else(|| buffer_error(source))?;
+        pixmap.draw_pixmap(
+            -source.x * scale as i32,
+            -source.y * scale as i32,
+            compressed_pixmap.as_ref(),
+            &PixmapPaint {
+                blend_mode: BlendMode::Source,
+                ..PixmapPaint::default()
+            },
+            Transform::identity(),
+            None,
+        );
+
+        let stretched = (destination.width, destination.height) != (source.width, source.height);
+        part.set_destination(
+            solid_buffers,
+            stretched.then_some((destination.width, destination.height)),
+        );
+        present(part, destination, buffer_scale, true, None).ok_or_else(|| buffer_error(source))?;
+    }
+
+    // The part surface is left transparent for the input.
+    part_surface.set_solid(solid_buffers, Some([0; 4]), rect);
+    Ok(())
+}
+
+/// Set the opaque and the input regions of the drawn part, and present it.
+#[allow(clippy::too_many_arguments)]
+fn present_part(
+    part_surface: &mut PartSurface,
+    input_rect: Option<Rect>,
+    compositor: &CompositorState,
+    rect: Rect,
+    opaque: bool,
+    scale: u32,
+    should_sync: bool,
+    damage: Option<Rect>,
+) -> Option<()> {
+    part_surface.set_opaque_rect(
+        compositor,
+        opaque.then_some(Rect {
+            x: 0,
+            y: 0,
+            width: rect.width,
+            height: rect.height,
+        }),
+    );
+
+    if let Some(input_rect) = input_rect {
+        let input_region = Region::new(compositor).ok()?;
+        input_region.add(
+            input_rect.x,
+            input_rect.y,
+            input_rect.width as i32,
+            input_rect.height as i32,
+        );
+
+        part_surface
+            .surface
+            .set_input_region(Some(input_region.wl_region()));
+    }
+
+    present(part_surface, rect, scale, should_sync, damage)
+}
+
+/// Attach the drawn buffer of the `part_surface` at the `rect` and commit it, with only the
+/// `damage` in the coordinates of the main surface changed, `None` when all of it changed.
+fn present(
+    part_surface: &mut PartSurface,
+    rect: Rect,
+    scale: u32,
+    should_sync: bool,
+    damage: Option<Rect>,
+) -> Option<()> {
+    if should_sync {
+        part_surface.subsurface.set_sync();
+    } else {
+        part_surface.subsurface.set_desync();
+    }
+
+    part_surface.surface.set_buffer_scale(scale as i32);
+
+    part_surface.subsurface.set_position(rect.x, rect.y);
+    part_surface.attach_buffer()?;
+
+    let (x, y, width, height) = match damage {
+        Some(damage) => (
+            damage.x - rect.x,
+            damage.y - rect.y,
+            damage.width as i32,
+            damage.height as i32,
+        ),
+        None => (0, 0, i32::MAX, i32::MAX),
+    };
+    if part_surface.surface.version() >= 4 {
+        let scale = scale as i32;
+        part_surface.surface.damage_buffer(
+            x * scale,
+            y * scale,
+            width.saturating_mul(scale),
+            height.saturating_mul(scale),
+        );
+    } else {
+        part_surface.surface.damage(x, y, width, height);
+    }
+
+    part_surface.surface.commit();
+    Some(())
+}
+
+/// The length in logical points of the middle of the edge part, from which it's stretched by the
+/// compositor instead of drawn along the whole edge.
+const MIN_STRETCHED_LENGTH: u32 = 32;
+
+/// The Linux input event codes of the pointer buttons, see [`AdwaitaFrame::on_button`].
+const BTN_LEFT: u32 = 0x110;
+const BTN_RIGHT: u32 = 0x111;
+const BTN_MIDDLE: u32 = 0x112;
+
+/// The default order of the buttons in the format of the `button-layout` setting.
+const DEFAULT_BUTTON_LAYOUT: &str = ":minimize,maximize,close";
+
+/// The height of the progress strip along the bottom of the headerbar in logical points.
+const PROGRESS_HEIGHT: u32 = 2;
+
+/// The horizontal gap between the title and the buttons in logical points.
+const TEXT_OFFSET: f32 = 10.;
+
+/// The narrowest room between the buttons the title is drawn into in logical points, so it's
+/// dropped before the buttons when the window shrinks.
+const MIN_TITLE_WIDTH: f32 = 24.;
+
+/// The opacity of the subtitle relative to the title, like the `dim-label` of libadwaita.
+const SUBTITLE_OPACITY: f32 = 0.55;
+
+/// The horizontal gap between the icon and the title in logical points.
+const ICON_SPACING: f32 = 6.;
+
+/// The state needed to draw the decoration parts, borrowed from the frame.
+struct PartPainter<'a> {
+    state: WindowState,
+    theme: &'a ColorTheme,
+    buttons: &'a mut Buttons,
+    shadow: &'a mut Shadow,
+    title_text: Option<&'a mut TitleText>,
+    title_pixmap: Option<&'a mut TitlePixmap>,
+    subtitle_text: Option<&'a mut TitleText>,
+    icon: Option<&'a mut Icon>,
+    /// The buttons under any of the pointers.
+    hovered: Vec<ButtonKind>,
+    /// The buttons pressed by any of the pointers.
+    pressed: Vec<ButtonKind>,
+    /// The resize edges under any of the pointers to hint.
+    resize_hint: Vec<Location>,
+    /// The progress shown along the bottom of the headerbar.
+    progress: Option<f32>,
+    /// The snap preview drawn over the borders.
+    snap_preview: Option<SnapRegion>,
+    resizable: bool,
+    rtl: bool,
+    title_alignment: TitleAlignment,
+    header_position: HeaderPosition,
+    draw_header: bool,
+    draw_borders: bool,
+    /// Whether the borders cast the shadow, unless only the borders are drawn.
+    draw_shadow: bool,
+    /// Which of the borders cast the shadow, see [`ShadowPolicy`].
+    shadow_policy: ShadowPolicy,
+    /// The value of the maximize transition, see `AdwaitaFrame::floating`.
+    floating: f32,
+    /// The radius of the top corners in logical points when floating.
+    corner_radius: u32,
+    /// The value of the activation transition, see `AdwaitaFrame::activation`.
+    activation: f32,
+    /// The outline with the bounds in logical points it's drawn around, see
+    /// [`PartPainter::outline_bounds`].
+    outline: Option<(OutlineStyle, Rect)>,
+    /// The overlay outlining the layout, `None` unless it's requested.
+    #[cfg(feature = "debug-overlay")]
+    debug: Option<debug::DebugOverlay>,
+}
+
+impl PartPainter<'_> {
+    fn is_visible(&self, idx: usize) -> bool {
+        if idx == DecorationParts::HEADER {
+            self.draw_header
+        } else {
+            self.draw_borders
+        }
+    }
+
+    /// The rectangle of the part's buffer in the logical points, positioned relative to the
+    /// main surface.
+    fn buffer_rect(&self, idx: usize, surface_rect: Rect) -> Rect {
+        let mut rect = surface_rect;
+        // XXX to perfectly align the visible borders we draw them with
+        // the header, otherwise rounded corners won't look 'smooth' at the
+        // start. To achieve that, we enlargen the width of the header by
+        // 2 * `VISIBLE_BORDER_SIZE`, and move `x` by `VISIBLE_BORDER_SIZE`
+        // to the left.
+        if idx == DecorationParts::HEADER && self.draw_borders {
+            match self.header_position {
+                HeaderPosition::Top => {
+                    rect.width += 2 * VISIBLE_BORDER_SIZE;
+                    rect.x -= VISIBLE_BORDER_SIZE as i32;
+                }
+                HeaderPosition::Left => {
+                    rect.height += 2 * VISIBLE_BORDER_SIZE;
+                    rect.y -= VISIBLE_BORDER_SIZE as i32;
+                }
+            }
+        }
+        rect
+    }
+
+    /// The bounds of the visible borders and the headerbar in logical points, for the given
+    /// surface rectangles of the parts.
+    fn outline_bounds(&self, rects: &[Rect; 5]) -> Rect {
+        let border = self.theme.visible_border_size();
+        let window = Rect {
+            x: -(border as i32),
+            y: -(border as i32),
+            width: rects[DecorationParts::RIGHT].x as u32 + 2 * border,
+            height: rects[DecorationParts::BOTTOM].y as u32 + 2 * border,
+        };
+
+        if self.draw_header {
+            let header = DecorationParts::HEADER;
+            window.union(self.buffer_rect(header, rects[header]))
+        } else {
+            window
+        }
+    }
+
+    /// The radii of the top left, top right, bottom right and bottom left corners of the
+    /// outline bounds in logical points.
+    fn outline_radii(&self) -> [f32; 4] {
+        if !self.draw_header {
+            return [0.; 4];
+        }
+
+        match self.header_position {
+            HeaderPosition::Top => {
+                let (left, right) = top_corner_radii(&self.state, 1., self.corner_radius());
+                [left, right, 0., 0.]
+            }
+            // The upright header is turned counterclockwise.
+            HeaderPosition::Left => {
+                let (left, right) =
+                    top_corner_radii(&upright_state(self.state), 1., self.corner_radius());
+                [right, 0., 0., left]
+            }
+        }
+    }
+
+    /// The radius of the top corners in logical points at the current maximize transition.
+    fn corner_radius(&self) -> f32 {
+        self.corner_radius as f32 * self.floating
+    }
+
+    /// The lengths in logical points of the caps at the both ends of the edge part between which
+    /// it's the same along the edge, `None` when the part is too short to be worth stretching.
+    fn stretch_caps(&self, idx: usize, rect: Rect) -> Option<(u32, u32)> {
+        // The overlay and the outline are drawn in the coordinates of the whole part.
+        #[cfg(feature = "debug-overlay")]
+        if self.debug.is_some() {
+            return None;
+        }
+        if self.outline.is_some() {
+            return None;
+        }
+
+        let length = match idx {
+            DecorationParts::TOP | DecorationParts::BOTTOM => rect.width,
+            DecorationParts::LEFT | DecorationParts::RIGHT => rect.height,
+            _ => return None,
+        };
+        let (start, end) = self.edge_caps(idx, rect);
+
+        (length >= start + end + MIN_STRETCHED_LENGTH).then_some((start, end))
+    }
+
+    /// The lengths in logical points of the caps at the both ends of the edge part, between
+    /// which it's the same along the edge.
+    fn edge_caps(&self, idx: usize, rect: Rect) -> (u32, u32) {
+        let corner_radius = self.shadow.corner_radius();
+        match idx {
+            DecorationParts::TOP | DecorationParts::BOTTOM => {
+                let cap = corner_radius + SHADOW_SIZE;
+                (cap, cap)
+            }
+            // The visible border starts below the header beside the part.
+            _ => (corner_radius.max(rect.y.unsigned_abs()), corner_radius),
+        }
+    }
+
+    /// Whether the part casts the shadow, by default only the edges not touching the other
+    /// windows or the screen edges do.
+    fn casts_shadow(&self, idx: usize) -> bool {
+        let tiled_edge = match idx {
+            DecorationParts::TOP | DecorationParts::HEADER => WindowState::TILED_TOP,
+            DecorationParts::LEFT => WindowState::TILED_LEFT,
+            DecorationParts::RIGHT => WindowState::TILED_RIGHT,
+            _ => WindowState::TILED_BOTTOM,
+        };
+        self.draw_shadow && self.shadow_policy.casts_shadow(&self.state, tiled_edge)
+    }
+
+    /// Whether the border part draws its visible border, which the header draws next to it
+    /// unless it's hidden.
+    fn draws_visible_border(&self, border: usize) -> bool {
+        let header_border = match self.header_position {
+            HeaderPosition::Top => DecorationParts::TOP,
+            HeaderPosition::Left => DecorationParts::LEFT,
+        };
+        border != header_border || !self.draw_header
+    }
+
+    /// What the border part in the buffer `rect` is drawn from at the `scale`, to damage only
+    /// the areas changed since it was presented, see [`BorderDrawing::damage_since`].
+    ///
+    /// `None` for the header and for the parts drawn in the coordinates of the whole frame,
+    /// which are damaged as a whole.
+    fn border_drawing(&self, idx: usize, rect: Rect, scale: u32) -> Option<BorderDrawing> {
+        #[cfg(feature = "debug-overlay")]
+        if self.debug.is_some() {
+            return None;
+        }
+        if idx == DecorationParts::HEADER || self.outline.is_some() {
+            return None;
+        }
+
+        let colors = self.theme.for_window_state(&self.state);
+        let strip = self
+            .draws_visible_border(idx)
+            .then(|| {
+                let size = self.theme.visible_border_size();
+                edge_rect(idx, rect.width, rect.height, rect.y.unsigned_abs(), size)
+            })
+            .flatten()
+            .map(|strip| (strip, colors.border_color));
+        let hinted = self
+            .resize_hint
+            .iter()
+            .any(|location| location.edge_parts().contains(&idx));
+        let snapping = self
+            .snap_preview
+            .is_some_and(|region| region.edge_parts().contains(&idx));
+
+        Some(BorderDrawing {
+            idx,
+            rect,
+            scale,
+            strip,
+            rest: BorderRest {
+                state: self.state - WindowState::ACTIVATED,
+                shadow: self
+                    .casts_shadow(idx)
+                    .then_some((self.activation, self.floating)),
+                caps: self.edge_caps(idx, rect),
+                hint: hinted.then_some(colors.resize_hint),
+                snap: snapping.then_some(colors.snap_preview),
+            },
+        })
+    }
+
+    /// Whether the part's buffer is fully opaque, which is the case for the header without the
+    /// rounded corners in the theme without the transparency.
+    fn is_opaque(&self, idx: usize) -> bool {
+        let colors = self.theme.for_window_state(&self.state);
+        idx == DecorationParts::HEADER
+            && self.draw_header
+            && top_corner_radii(&self.state, 1., self.corner_radius()) == (0., 0.)
+            && colors.headerbar.is_opaque()
+    }
+
+    /// The rectangles to blur behind the header's buffer of the `rect` in its coordinates,
+    /// `None` when the headerbar is opaque or hidden.
+    #[cfg(feature = "blur")]
+    fn header_blur(&self, rect: Rect) -> Option<Vec<Rect>> {
+        let colors = self.theme.for_window_state(&self.state);
+        if !self.draw_header || colors.headerbar.is_opaque() {
+            return None;
+        }
+
+        let (state, upright) = match self.header_position {
+            HeaderPosition::Top => (self.state, false),
+            HeaderPosition::Left => (upright_state(self.state), true),
+        };
+        let (left, right) = top_corner_radii(&state, 1., self.corner_radius());
+        Some(blur::header_rects(
+            rect.width,
+            rect.height,
+            (left.ceil() as u32, right.ceil() as u32),
+            upright,
+        ))
+    }
+
+    /// The union of the buffer rectangles of the visible parts, `None` when none is visible.
+    fn bounds(&self, rects: &[Rect; 5]) -> Option<Rect> {
+        (0..rects.len())
+            .filter(|idx| self.is_visible(*idx))
+            .map(|idx| self.buffer_rect(idx, rects[idx]))
+            .reduce(Rect::union)
+    }
+
+    /// Draw all the visible parts with the given surface rectangles into the `pixmap` covering
+    /// the `bounds`, in the order they're stacked.
+    fn draw_composed(
+        &mut self,
+        rects: &[Rect; 5],
+        bounds: Rect,
+        scale: u32,
+        pixmap: &mut PixmapMut,
+    ) {
+        pixmap.fill(Color::TRANSPARENT);
+
+        for (idx, surface_rect) in rects.iter().enumerate() {
+            if !self.is_visible(idx) {
+                continue;
+            }
+
+            let rect = self.buffer_rect(idx, *surface_rect);
+            let Some(mut part) = Pixmap::new(rect.width * scale, rect.height * scale) else {
+                continue;
+            };
+            self.draw(idx, rect, scale, &mut part.as_mut());
+
+            pixmap.draw_pixmap(
+                (rect.x - bounds.x) * scale as i32,
+                (rect.y - bounds.y) * scale as i32,
+                part.as_ref(),
+                &PixmapPaint::default(),
+                Transform::identity(),
+                None,
+            );
+        }
+    }
+
+    /// Draw the part with the given buffer rectangle into the `pixmap`.
+    fn draw(&mut self, idx: usize, rect: Rect, scale: u32, pixmap: &mut PixmapMut) {
+        if idx == DecorationParts::HEADER && self.header_position == HeaderPosition::Left {
+            self.draw_turned_header(rect, scale, pixmap);
+        } else {
+            self.draw_part(idx, rect, scale, pixmap);
+        }
+
+        // Each part draws its piece of the outline around the whole frame.
+        if let Some((outline, bounds)) = self.outline.filter(|_| self.draw_borders) {
+            let colors = self.theme.for_window_state(&self.state);
+            let radii = self.outline_radii();
+            let _ = draw_outline(pixmap, rect, scale, &outline, bounds, radii, colors);
+        }
+
+        #[cfg(feature = "debug-overlay")]
+        if let Some(debug) = self.debug.as_ref() {
+            debug.draw_part(idx, rect, scale, pixmap);
+        }
+    }
+
+    /// Draw the header along the left edge upright, as if it was above the window, and turn it
+    /// into the `pixmap` to read from the bottom up.
+    fn draw_turned_header(&mut self, rect: Rect, scale: u32, pixmap: &mut PixmapMut) {
+        pixmap.fill(Color::TRANSPARENT);
+
+        let upright_rect = Rect {
+            x: rect.y,
+            y: rect.x,
+            width: rect.height,
+            height: rect.width,
+        };
+        let Some(mut upright) =
+            Pixmap::new(upright_rect.width * scale, upright_rect.height * scale)
+        else {
+            return;
+        };
+
+        let state = self.state;
+        self.state = upright_state(state);
+        self.draw_part(
+            DecorationParts::HEADER,
+            upright_rect,
+            scale,
+            &mut upright.as_mut(),
+        );
+        self.state = state;
+
+        // Turn it counterclockwise, so the start of the headerbar ends up at the bottom.
+        let transform = Transform::from_row(0., -1., 1., 0., 0., pixmap.height() as f32);
+        pixmap.draw_pixmap(
+            0,
+            0,
+            upright.as_ref(),
+            &PixmapPaint::default(),
+            transform,
+            None,
+        );
+    }
+
+    /// Draw the part the way it's placed above the window into the `pixmap`.
+    fn draw_part(&mut self, idx: usize, rect: Rect, scale: u32, pixmap: &mut PixmapMut) {
+        let mut rect = rect;
+        rect.width *= scale;
+        rect.height *= scale;
+
+        let colors = self.theme.for_window_state(&self.state);
+
+        // Fill everything with transparent background, since we draw rounded corners and
+        // do invisible borders to enlarge the input zone.
+        pixmap.fill(Color::TRANSPARENT);
+
+        if self.casts_shadow(idx) {
+            self.shadow
+                .draw_animated(pixmap, scale as f32, self.activation, self.floating, idx);
+        }
+
+        match idx {
+            DecorationParts::HEADER => {
+                let corner_radius = self.corner_radius();
+                let icon = self.icon.as_mut().and_then(|icon| icon.pixmap(scale));
+
+                let (start_x, end_x) =
+                    title_bounds(rect.width as f32, scale as f32, &self.state, self.buttons);
+                let icon_w = icon_width(icon.is_some(), scale as f32);
+                let max_width = Some((end_x - start_x - icon_w).max(0.) as u32);
+
+                // The text is rendered in the active color only, and dimmed when it's drawn
+                // into the inactive headerbar.
+                let font_color = self
+                    .theme
+                    .for_window_state(&(self.state | WindowState::ACTIVATED))
+                    .font_color;
+                if let Some(title_text) = self.title_text.as_mut() {
+                    title_text.update_scale(scale.into());
+                    title_text.update_color(font_color);
+                    title_text.update_max_width(max_width);
+                }
+
+                if let Some(title_pixmap) = self.title_pixmap.as_mut() {
+                    title_pixmap.update_scale(scale.into());
+                    title_pixmap.update_max_width(max_width);
+                }
 
-        let location = self.precise_location(location, decorations, x, y);
-        let new_cursor = self.mouse.moved(location, x, y, self.resizable);
+                if let Some(subtitle_text) = self.subtitle_text.as_mut() {
+                    let mut color = font_color;
+                    color.apply_opacity(SUBTITLE_OPACITY);
 
-        // Set dirty if we moved the cursor between the buttons.
-        self.dirty |= (matches!(old_location, Location::Button(_))
-            || matches!(self.mouse.location, Location::Button(_)))
-            && old_location != self.mouse.location;
+                    subtitle_text.update_scale(scale.into());
+                    subtitle_text.update_color(color);
+                    subtitle_text.update_max_width(max_width);
+                }
 
-        Some(new_cursor)
-    }
+                let title_clip = draw_headerbar(
+                    pixmap,
+                    match self.title_pixmap.as_ref() {
+                        Some(title_pixmap) => title_pixmap.line(),
+                        None => self.title_text.as_ref().and_then(|t| t.line()),
+                    },
+                    self.subtitle_text.as_ref().and_then(|t| t.line()),
+                    icon,
+                    scale as f32,
+                    self.resizable,
+                    &self.state,
+                    self.theme,
+                    self.buttons,
+                    &self.hovered,
+                    &self.pressed,
+                    self.rtl,
+                    self.title_alignment,
+                    corner_radius,
+                );
 
-    fn click_point_left(&mut self) {
-        self.mouse.left()
-    }
+                #[cfg(feature = "debug-overlay")]
+                if let Some(debug) = self.debug.as_ref() {
+                    debug.draw_header(self.buttons.rects(), title_clip, rect.x, scale, pixmap);
+                }
+                #[cfg(not(feature = "debug-overlay"))]
+                let _ = title_clip;
 
-    fn is_dirty(&self) -> bool {
-        self.dirty
-    }
+                if let Some(progress) = self.progress {
+                    // Skip the visible borders the header is widened by.
+                    let inset = if self.draw_borders {
+                        VISIBLE_BORDER_SIZE * scale
+                    } else {
+                        0
+                    };
+                    let height = PROGRESS_HEIGHT * scale;
+                    let width = (rect.width - 2 * inset) as f32 * progress;
+                    if let Some(strip) = tiny_skia::Rect::from_xywh(
+                        inset as f32,
+                        (rect.height - height) as f32,
+                        width,
+                        height as f32,
+                    ) {
+                        pixmap.fill_rect(
+                            strip,
+                            &colors.progress_paint(),
+                            Transform::identity(),
+                            None,
+                        );
+                    }
+                }
+            }
+            border => {
+                // The visible border is one pt.
+                let visible_border_size = self.theme.visible_border_size() * scale;
 
-    fn is_hidden(&self) -> bool {
-        self.decorations.is_none()
+                // XXX we do all the match using integral types and then convert to f32 in the
+                // end to ensure that result is finite.
+                let skip = rect.y.unsigned_abs() * scale;
+                let edge_rect = |size: u32| {
+                    let edge = edge_rect(border, rect.width, rect.height, skip, size)?;
+                    tiny_skia::Rect::from_xywh(
+                        edge.x as f32,
+                        edge.y as f32,
+                        edge.width as f32,
+                        edge.height as f32,
+                    )
+                };
+
+                let border_rect = self
+                    .draws_visible_border(border)
+                    .then(|| edge_rect(visible_border_size))
+                    .flatten();
+
+                // Fill the visible border, if present.
+                if let Some(border_rect) = border_rect {
+                    pixmap.fill_rect(
+                        border_rect,
+                        &colors.border_paint(),
+                        Transform::identity(),
+                        None,
+                    );
+                }
+
+                // Hint the resize edge under the pointer.
+                let hinted = self
+                    .resize_hint
+                    .iter()
+                    .any(|location| location.edge_parts().contains(&border));
+                if let Some(hint_rect) = edge_rect(RESIZE_HINT_SIZE * scale).filter(|_| hinted) {
+                    pixmap.fill_rect(
+                        hint_rect,
+                        &colors.resize_hint_paint(),
+                        Transform::identity(),
+                        None,
+                    );
+                }
+
+                // Cover the whole border facing the snap region.
+                let snapping = self
+                    .snap_preview
+                    .is_some_and(|region| region.edge_parts().contains(&border));
+                let snap_rect = tiny_skia::Rect::from_xywh(
+                    0.,
+                    0.,
+                    pixmap.width() as f32,
+                    pixmap.height() as f32,
+                );
+                if let Some(snap_rect) = snap_rect.filter(|_| snapping) {
+                    pixmap.fill_rect(
+                        snap_rect,
+                        &colors.snap_preview_paint(),
+                        Transform::identity(),
+                        None,
+                    );
+                }
+            }
+        }
     }
 }
 
-/// The configuration for the [`AdwaitaFrame`] frame.
-#[derive(Debug, Clone)]
-pub struct FrameConfig {
-    pub theme: ColorTheme,
+/// What the border part was drawn from, see [`PartPainter::border_drawing`].
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct BorderDrawing {
+    idx: usize,
+    /// The buffer rectangle in logical points, positioned relative to the main surface.
+    rect: Rect,
+    scale: u32,
+    /// The visible border in logical points in the coordinates of the part, with its color.
+    strip: Option<(Rect, Color)>,
+    rest: BorderRest,
 }
 
-impl FrameConfig {
-    /// Create the new configuration with the given `theme`.
-    pub fn new(theme: ColorTheme) -> Self {
-        Self { theme }
-    }
+/// Everything drawn into the border part besides the visible border.
+#[derive(Debug, Clone, PartialEq)]
+struct BorderRest {
+    /// The window state without the activation, which only changes the colors.
+    state: WindowState,
+    /// The activation and the maximize transitions the shadow is drawn at, `None` without it.
+    shadow: Option<(f32, f32)>,
+    /// See [`PartPainter::edge_caps`].
+    caps: (u32, u32),
+    hint: Option<Color>,
+    snap: Option<Color>,
+}
 
-    /// This is equivalent of calling `FrameConfig::new(ColorTheme::auto())`.
+impl BorderDrawing {
+    /// The area in the coordinates of the main surface changed since the `last` drawing was
+    /// presented, `None` when the whole part changed.
     ///
-    /// For details see [`ColorTheme::auto`].
-    pub fn auto() -> Self {
-        Self {
-            theme: ColorTheme::auto(),
+    /// Only the visible border changes with the activation of the window without the shadow,
+    /// and only the end cap moves over the new area when the window is resized along the edge.
+    pub(crate) fn damage_since(&self, last: Option<&BorderDrawing>) -> Option<Rect> {
+        let last = last?;
+        if last.idx != self.idx || last.scale != self.scale || last.rest != self.rest {
+            return None;
         }
-    }
 
-    /// This is equivalent of calling `FrameConfig::new(ColorTheme::light())`.
-    ///
-    /// For details see [`ColorTheme::light`].
-    pub fn light() -> Self {
-        Self {
-            theme: ColorTheme::light(),
+        let rect = self.rect;
+        if last.rect == rect {
+            let area = [last.strip, self.strip]
+                .into_iter()
+                .flatten()
+                .filter(|_| last.strip != self.strip)
+                .map(|(strip, _)| strip)
+                .reduce(Rect::union)
+                .unwrap_or(Rect {
+                    x: 0,
+                    y: 0,
+                    width: 0,
+                    height: 0,
+                });
+            return Some(Rect {
+                x: rect.x + area.x,
+                y: rect.y + area.y,
+                ..area
+            });
+        }
+
+        if last.strip.map(|(_, color)| color) != self.strip.map(|(_, color)| color) {
+            return None;
+        }
+
+        let (_, end_cap) = self.rest.caps;
+        match self.idx {
+            DecorationParts::TOP | DecorationParts::BOTTOM
+                if (last.rect.x, last.rect.height) == (rect.x, rect.height) =>
+            {
+                let start = last.rect.width.min(rect.width).saturating_sub(end_cap);
+                Some(Rect {
+                    x: rect.x + start as i32,
+                    width: rect.width - start,
+                    ..rect
+                })
+            }
+            DecorationParts::LEFT | DecorationParts::RIGHT
+                if (last.rect.y, last.rect.width) == (rect.y, rect.width) =>
+            {
+                let start = last.rect.height.min(rect.height).saturating_sub(end_cap);
+                Some(Rect {
+                    y: rect.y + start as i32,
+                    height: rect.height - start,
+                    ..rect
+                })
+            }
+            _ => None,
         }
     }
+}
 
-    /// This is equivalent of calling `FrameConfig::new(ColorTheme::dark())`.
-    ///
-    /// For details see [`ColorTheme::dark`].
-    pub fn dark() -> Self {
-        Self {
-            theme: ColorTheme::dark(),
+/// The strip of the `size` along the window side of the `border` part of the `width` and the
+/// `height`, in its coordinates, with the side parts starting `skip` below the header beside
+/// them.
+///
+/// The strip is inset from the ends of the top and the bottom parts by the thickness of the
+/// side parts, which is the one of the part itself.
+fn edge_rect(border: usize, width: u32, height: u32, skip: u32, size: u32) -> Option<Rect> {
+    let rect = match border {
+        DecorationParts::LEFT | DecorationParts::RIGHT => Rect {
+            x: if border == DecorationParts::LEFT {
+                width.checked_sub(size)? as i32
+            } else {
+                0
+            },
+            y: skip as i32,
+            width: size,
+            height: height.checked_sub(skip)?,
+        },
+        DecorationParts::TOP | DecorationParts::BOTTOM => {
+            let x = height.checked_sub(size)?;
+            Rect {
+                x: x as i32,
+                y: if border == DecorationParts::TOP {
+                    x as i32
+                } else {
+                    0
+                },
+                width: width.checked_sub(2 * x)?,
+                height: size,
+            }
         }
+        _ => return None,
+    };
+    Some(rect)
+}
+
+/// The width taken by the icon next to the title in pixels, including the spacing.
+fn icon_width(has_icon: bool, scale: f32) -> f32 {
+    if has_icon {
+        (ICON_SIZE as f32 + ICON_SPACING) * scale
+    } else {
+        0.
     }
 }
 
+/// The horizontal bounds of the area available for the title and the icon, in pixels.
+///
+/// Keep in sync with the title positioning in `draw_headerbar`.
+fn title_bounds(canvas_w: f32, scale: f32, state: &WindowState, buttons: &Buttons) -> (f32, f32) {
+    let margin_h = get_margin_h_lp(state) * 2.0;
+    let header_w = canvas_w - margin_h * 2.0;
+    let offset_x = TEXT_OFFSET * scale;
+
+    let left_buttons_end_x = buttons.left_buttons_end_x().unwrap_or(0.0) * scale;
+    let right_buttons_start_x = buttons.right_buttons_start_x().unwrap_or(header_w / scale) * scale;
+
+    let start_x = (left_buttons_end_x + offset_x).max(margin_h + offset_x);
+    let end_x = right_buttons_start_x - offset_x;
+    (start_x, end_x)
+}
+
 #[allow(clippy::too_many_arguments)]
 fn draw_headerbar(
     pixmap: &mut PixmapMut,
-    text_pixmap: Option<&Pixmap>,
+    text_pixmap: Option<TitleLine<'_>>,
+    subtitle_pixmap: Option<TitleLine<'_>>,
+    icon: Option<&Pixmap>,
     scale: f32,
     resizable: bool,
     state: &WindowState,
     theme: &ColorTheme,
-    buttons: &Buttons,
-    mouse: Location,
-) {
-    let colors = theme.for_state(state.contains(WindowState::ACTIVATED));
+    buttons: &mut Buttons,
+    hovered: &[ButtonKind],
+    pressed: &[ButtonKind],
+    rtl: bool,
+    title_alignment: TitleAlignment,
+    corner_radius: f32,
+) -> Option<tiny_skia::Rect> {
+    let active = state.contains(WindowState::ACTIVATED);
+    let mut title_clip = None;
+    let colors = theme.for_window_state(state);
 
-    let _ = draw_headerbar_bg(pixmap, scale, colors, state);
+    let _ = draw_headerbar_bg(pixmap, scale, colors, state, corner_radius);
 
     // Horizontal margin.
     let margin_h = get_margin_h_lp(state) * 2.0;
@@ -605,63 +4111,101 @@ fn draw_headerbar(
     let header_w = canvas_w - margin_h * 2.0;
     let header_h = canvas_h;
 
-    if let Some(text_pixmap) = text_pixmap {
-        const TEXT_OFFSET: f32 = 10.;
+    if text_pixmap.is_some() || subtitle_pixmap.is_some() || icon.is_some() {
         let offset_x = TEXT_OFFSET * scale;
 
-        let text_w = text_pixmap.width() as f32;
-        let text_h = text_pixmap.height() as f32;
+        // The subtitle is stacked under the title.
+        let lines = [text_pixmap, subtitle_pixmap];
+        let text_w = lines
+            .iter()
+            .flatten()
+            .map(|line| line.pixmap.width() as f32)
+            .fold(0., f32::max);
+        let text_h: f32 = lines
+            .iter()
+            .flatten()
+            .map(|line| line.pixmap.height() as f32)
+            .sum();
+        let icon_w = icon_width(icon.is_some(), scale);
+        // The icon and the title are positioned together.
+        let content_w = text_w + icon_w;
 
-        let x = margin_h + header_w / 2. - text_w / 2.;
-        let y = header_h / 2. - text_h / 2.;
+        let centered_x = margin_h + header_w / 2. - content_w / 2.;
 
         let left_buttons_end_x = buttons.left_buttons_end_x().unwrap_or(0.0) * scale;
         let right_buttons_start_x =
             buttons.right_buttons_start_x().unwrap_or(header_w / scale) * scale;
 
-        {
-            // We have enough space to center text
-            let (x, y, text_canvas_start_x) = if (x + text_w < right_buttons_start_x - offset_x)
-                && (x > left_buttons_end_x + offset_x)
-            {
-                let text_canvas_start_x = x;
-
-                (x, y, text_canvas_start_x)
-            } else {
-                let x = left_buttons_end_x + offset_x;
-                let text_canvas_start_x = left_buttons_end_x + offset_x;
-
-                (x, y, text_canvas_start_x)
+        let room = right_buttons_start_x - left_buttons_end_x - 2. * offset_x;
+        if room >= MIN_TITLE_WIDTH * scale {
+            let alignment = resolve_title_alignment(
+                title_alignment,
+                rtl,
+                centered_x,
+                content_w,
+                left_buttons_end_x + offset_x,
+                right_buttons_start_x - offset_x,
+            );
+            let start_x = left_buttons_end_x + offset_x;
+            let (x, text_canvas_start_x) = match alignment {
+                TitleAlignment::Center => (centered_x, centered_x),
+                TitleAlignment::Left => (start_x, start_x),
+                TitleAlignment::Right => {
+                    let x = (right_buttons_start_x - offset_x - content_w).max(start_x);
+                    (x, start_x)
+                }
             };
 
-            let text_canvas_end_x = right_buttons_start_x - x - offset_x;
+            let text_canvas_end_x = right_buttons_start_x - text_canvas_start_x - offset_x;
             // Ensure that text start within the bounds.
             let x = x.max(margin_h + offset_x);
 
+            // The icon is placed before the title, on the right for the right-to-left languages.
+            let (text_x, icon_x) = if rtl {
+                (x, x + text_w + ICON_SPACING * scale)
+            } else {
+                (x + icon_w, x)
+            };
+
             if let Some(clip) =
-                Rect::from_xywh(text_canvas_start_x, 0., text_canvas_end_x, canvas_h)
+                tiny_skia::Rect::from_xywh(text_canvas_start_x, 0., text_canvas_end_x, canvas_h)
             {
-                if let Some(mut mask) = Mask::new(canvas_w as u32, canvas_h as u32) {
-                    mask.fill_path(
-                        &PathBuilder::from_rect(clip),
-                        FillRule::Winding,
-                        false,
-                        Transform::identity(),
-                    );
-                    pixmap.draw_pixmap(
-                        x.round() as i32,
-                        y as i32,
-                        text_pixmap.as_ref(),
-                        &PixmapPaint::default(),
-                        Transform::identity(),
-                        Some(&mask),
-                    );
-                } else {
-                    log::error!(
-                        "Invalid mask width and height: w: {}, h: {}",
-                        canvas_w as u32,
-                        canvas_h as u32
-                    );
+                title_clip = Some(clip);
+
+                let text_paint = PixmapPaint {
+                    opacity: if active {
+                        1.
+                    } else {
+                        theme.inactive_title_opacity(state)
+                    },
+                    ..Default::default()
+                };
+                let mut y = header_h / 2. - text_h / 2.;
+                for line in lines.into_iter().flatten() {
+                    let line_w = line.pixmap.width() as f32;
+                    let x = match alignment {
+                        TitleAlignment::Center => text_x + (text_w - line_w) / 2.,
+                        TitleAlignment::Left => text_x,
+                        TitleAlignment::Right => text_x + text_w - line_w,
+                    };
+
+                    draw_title_line(pixmap, line, x.round() as i32, y as i32, &text_paint, clip);
+                    y += line.pixmap.height() as f32;
+                }
+
+                if let Some(icon) = icon {
+                    let size = ICON_SIZE as f32 * scale;
+                    let y = ((header_h - size) / 2.).round();
+                    let transform = Transform::from_scale(
+                        size / icon.width() as f32,
+                        size / icon.height() as f32,
+                    )
+                    .post_translate(icon_x.round(), y);
+                    let paint = PixmapPaint {
+                        quality: FilterQuality::Bilinear,
+                        ..Default::default()
+                    };
+                    draw_pixmap_clipped(pixmap, icon.as_ref(), &paint, transform, clip);
                 }
             }
         }
@@ -669,38 +4213,228 @@ fn draw_headerbar(
 
     // Draw the buttons.
     buttons.draw(
-        margin_h, header_w, scale, colors, mouse, pixmap, resizable, state,
+        margin_h,
+        header_w,
+        scale,
+        colors,
+        hovered,
+        pressed,
+        pixmap,
+        resizable,
+        state,
+        theme.high_contrast,
     );
+
+    title_clip
+}
+
+/// Draw the `line` of the title at `x` and `y` with the `paint` opacity, clipped to the `clip`.
+///
+/// The subpixel text darkens each channel of the background by its coverage with the
+/// multiplied filter, and then its color is added over it.
+fn draw_title_line(
+    pixmap: &mut PixmapMut,
+    line: TitleLine<'_>,
+    x: i32,
+    y: i32,
+    paint: &PixmapPaint,
+    clip: tiny_skia::Rect,
+) {
+    let transform = Transform::from_translate(x as f32, y as f32);
+    let Some(filter) = line.filter else {
+        draw_pixmap_clipped(pixmap, line.pixmap.as_ref(), paint, transform, clip);
+        return;
+    };
+
+    for (source, blend_mode) in [
+        (filter, BlendMode::Multiply),
+        (line.pixmap, BlendMode::Plus),
+    ] {
+        let paint = PixmapPaint {
+            blend_mode,
+            ..*paint
+        };
+        draw_pixmap_clipped(pixmap, source.as_ref(), &paint, transform, clip);
+    }
+}
+
+/// Draw the `source` placed with the `transform` into the `pixmap`, clipped to the `clip`.
+///
+/// Fills only the clipped area with the pattern of the `source`, the way `draw_pixmap` fills
+/// the whole of it, instead of clipping it with the mask of the whole canvas.
+fn draw_pixmap_clipped(
+    pixmap: &mut PixmapMut,
+    source: PixmapRef,
+    paint: &PixmapPaint,
+    transform: Transform,
+    clip: tiny_skia::Rect,
+) {
+    let rect = tiny_skia::Rect::from_xywh(0., 0., source.width() as f32, source.height() as f32)
+        .and_then(|rect| rect.transform(transform))
+        .and_then(|rect| rect.intersect(&clip));
+    let Some(rect) = rect else {
+        return;
+    };
+
+    let paint = Paint {
+        shader: Pattern::new(
+            source,
+            SpreadMode::Pad,
+            paint.quality,
+            paint.opacity,
+            transform,
+        ),
+        blend_mode: paint.blend_mode,
+        anti_alias: false,
+        force_hq_pipeline: false,
+    };
+    pixmap.fill_rect(rect, &paint, Transform::identity(), None);
+}
+
+/// The alignment the title of the `content_w` width is drawn with, given the horizontal
+/// space between the buttons from `start_x` to `end_x`.
+///
+/// The centered title colliding with the buttons is aligned to the start instead, which is on
+/// the right for the right-to-left languages.
+fn resolve_title_alignment(
+    alignment: TitleAlignment,
+    rtl: bool,
+    centered_x: f32,
+    content_w: f32,
+    start_x: f32,
+    end_x: f32,
+) -> TitleAlignment {
+    let fits_centered = centered_x > start_x && centered_x + content_w < end_x;
+    match alignment {
+        TitleAlignment::Center if !fits_centered && rtl => TitleAlignment::Right,
+        TitleAlignment::Center if !fits_centered => TitleAlignment::Left,
+        alignment => alignment,
+    }
 }
 
 #[must_use]
+/// Draw the `outline` around the `bounds` with the corner `radii` in logical points, into the
+/// part with the buffer `rect`.
+fn draw_outline(
+    pixmap: &mut PixmapMut,
+    rect: Rect,
+    scale: u32,
+    outline: &OutlineStyle,
+    bounds: Rect,
+    radii: [f32; 4],
+    colors: &ColorMap,
+) -> SkiaResult {
+    let scale = scale as f32;
+    let inner = tiny_skia::Rect::from_xywh(
+        (bounds.x - rect.x) as f32 * scale,
+        (bounds.y - rect.y) as f32 * scale,
+        bounds.width as f32 * scale,
+        bounds.height as f32 * scale,
+    )?;
+    // The outline stays within the shadow.
+    let width = outline.width.min(SHADOW_SIZE) as f32 * scale;
+    let outer = tiny_skia::Rect::from_ltrb(
+        inner.left() - width,
+        inner.top() - width,
+        inner.right() + width,
+        inner.bottom() + width,
+    )?;
+
+    let mut pb = PathBuilder::new();
+    push_rounded_rect(
+        &mut pb,
+        outer,
+        radii.map(|radius| {
+            if radius > 0. {
+                radius * scale + width
+            } else {
+                0.
+            }
+        }),
+    );
+    push_rounded_rect(&mut pb, inner, radii.map(|radius| radius * scale));
+    let path = pb.finish()?;
+
+    pixmap.fill_path(
+        &path,
+        &colors.outline_paint(outline),
+        FillRule::EvenOdd,
+        Transform::identity(),
+        None,
+    );
+    Some(())
+}
+
+/// Add the `rect` with the top left, top right, bottom right and bottom left corners rounded by
+/// the `radii` to the path.
+fn push_rounded_rect(pb: &mut PathBuilder, rect: tiny_skia::Rect, radii: [f32; 4]) {
+    // https://stackoverflow.com/a/27863181
+    let k = 1. - 0.552_284_8;
+    let [top_left, top_right, bottom_right, bottom_left] = radii;
+    let (left, top, right, bottom) = (rect.left(), rect.top(), rect.right(), rect.bottom());
+
+    pb.move_to(left, top + top_left);
+    pb.cubic_to(
+        left,
+        top + k * top_left,
+        left + k * top_left,
+        top,
+        left + top_left,
+        top,
+    );
+    pb.line_to(right - top_right, top);
+    pb.cubic_to(
+        right - k * top_right,
+        top,
+        right,
+        top + k * top_right,
+        right,
+        top + top_right,
+    );
+    pb.line_to(right, bottom - bottom_right);
+    pb.cubic_to(
+        right,
+        bottom - k * bottom_right,
+        right - k * bottom_right,
+        bottom,
+        right - bottom_right,
+        bottom,
+    );
+    pb.line_to(left + bottom_left, bottom);
+    pb.cubic_to(
+        left + k * bottom_left,
+        bottom,
+        left,
+        bottom - k * bottom_left,
+        left,
+        bottom - bottom_left,
+    );
+    pb.close();
+}
+
 fn draw_headerbar_bg(
     pixmap: &mut PixmapMut,
     scale: f32,
     colors: &ColorMap,
     state: &WindowState,
+    corner_radius: f32,
 ) -> SkiaResult {
     let w = pixmap.width() as f32;
     let h = pixmap.height() as f32;
 
-    let radius = if state.intersects(WindowState::MAXIMIZED | WindowState::TILED) {
-        0.
-    } else {
-        CORNER_RADIUS as f32 * scale
-    };
-
-    let bg = rounded_headerbar_shape(0., 0., w, h, radius)?;
+    let (left_radius, right_radius) = top_corner_radii(state, scale, corner_radius);
+    let bg = rounded_headerbar_shape(0., 0., w, h, left_radius, right_radius)?;
 
     pixmap.fill_path(
         &bg,
-        &colors.headerbar_paint(),
+        &colors.headerbar_paint(h, scale),
         FillRule::Winding,
         Transform::identity(),
         None,
     );
 
     pixmap.fill_rect(
-        Rect::from_xywh(0., h - 1., w, h)?,
+        tiny_skia::Rect::from_xywh(0., h - 1., w, h)?,
         &colors.border_paint(),
         Transform::identity(),
         None,
@@ -709,9 +4443,74 @@ fn draw_headerbar_bg(
     Some(())
 }
 
-fn rounded_headerbar_shape(x: f32, y: f32, width: f32, height: f32, radius: f32) -> Option<Path> {
+/// The `state` of the window as seen by the header along the left edge drawn upright, with the
+/// tiled edges turned clockwise.
+fn upright_state(state: WindowState) -> WindowState {
+    let turned = [
+        (WindowState::TILED_LEFT, WindowState::TILED_TOP),
+        (WindowState::TILED_TOP, WindowState::TILED_RIGHT),
+        (WindowState::TILED_RIGHT, WindowState::TILED_BOTTOM),
+        (WindowState::TILED_BOTTOM, WindowState::TILED_LEFT),
+    ];
+
+    let mut upright = state.difference(WindowState::TILED);
+    for (edge, upright_edge) in turned {
+        upright.set(upright_edge, state.contains(edge));
+    }
+    upright
+}
+
+/// The radii of the top left and the top right corners of the frame in pixels.
+///
+/// The corner is square when the window is tiled on either of its edges, otherwise it's the
+/// `corner_radius` in logical points animated towards `0` when the window gets maximized.
+fn top_corner_radii(state: &WindowState, scale: f32, corner_radius: f32) -> (f32, f32) {
+    let radius = |tiled_side: WindowState| {
+        if state.intersects(WindowState::TILED_TOP | tiled_side) {
+            0.
+        } else {
+            corner_radius * scale
+        }
+    };
+
+    (
+        radius(WindowState::TILED_LEFT),
+        radius(WindowState::TILED_RIGHT),
+    )
+}
+
+/// The side of the top corner cut off by the rounding, when the point of the header spanning
+/// from `start` to `end` is outside of it.
+fn rounded_corner_at(
+    x: f64,
+    y: f64,
+    start: f64,
+    end: f64,
+    (left_radius, right_radius): (f32, f32),
+) -> Option<Side> {
+    let outside =
+        |center_x: f64, radius: f64| y < radius && (x - center_x).hypot(y - radius) > radius;
+
+    let (left_radius, right_radius) = (f64::from(left_radius), f64::from(right_radius));
+    if x < start + left_radius && outside(start + left_radius, left_radius) {
+        Some(Side::Left)
+    } else if x > end - right_radius && outside(end - right_radius, right_radius) {
+        Some(Side::Right)
+    } else {
+        None
+    }
+}
+
+fn rounded_headerbar_shape(
+    x: f32,
+    y: f32,
+    width: f32,
+    height: f32,
+    left_radius: f32,
+    right_radius: f32,
+) -> Option<Path> {
     // https://stackoverflow.com/a/27863181
-    let cubic_bezier_circle = 0.552_284_8 * radius;
+    let cubic_bezier_circle = 0.552_284_8;
 
     let mut pb = PathBuilder::new();
     let mut cursor = Point::from_xy(x, y);
@@ -722,15 +4521,15 @@ fn rounded_headerbar_shape(x: f32, y: f32, width: f32, height: f32, radius: f32)
     // !!!
 
     // Positioning the cursor
-    cursor.y += radius;
+    cursor.y += left_radius;
     pb.move_to(cursor.x, cursor.y);
 
     // Drawing the outline
-    let next = Point::from_xy(cursor.x + radius, cursor.y - radius);
+    let next = Point::from_xy(cursor.x + left_radius, cursor.y - left_radius);
     pb.cubic_to(
         cursor.x,
-        cursor.y - cubic_bezier_circle,
-        next.x - cubic_bezier_circle,
+        cursor.y - cubic_bezier_circle * left_radius,
+        next.x - cubic_bezier_circle * left_radius,
         next.y,
         next.x,
         next.y,
@@ -738,17 +4537,17 @@ fn rounded_headerbar_shape(x: f32, y: f32, width: f32, height: f32, radius: f32)
     cursor = next;
     pb.line_to(
         {
-            cursor.x = x + width - radius;
+            cursor.x = x + width - right_radius;
             cursor.x
         },
         cursor.y,
     );
-    let next = Point::from_xy(cursor.x + radius, cursor.y + radius);
+    let next = Point::from_xy(cursor.x + right_radius, cursor.y + right_radius);
     pb.cubic_to(
-        cursor.x + cubic_bezier_circle,
+        cursor.x + cubic_bezier_circle * right_radius,
         cursor.y,
         next.x,
-        next.y - cubic_bezier_circle,
+        next.y - cubic_bezier_circle * right_radius,
         next.x,
         next.y,
     );
@@ -778,3 +4577,389 @@ fn get_margin_h_lp(state: &WindowState) -> f32 {
         VISIBLE_BORDER_SIZE as f32
     }
 }
+
+#[test]
+fn rounded_corners_are_outside_header() {
+    let radii = (10., 10.);
+    assert_eq!(rounded_corner_at(1., 1., 0., 100., radii), Some(Side::Left));
+    assert_eq!(
+        rounded_corner_at(99., 1., 0., 100., radii),
+        Some(Side::Right)
+    );
+    assert_eq!(rounded_corner_at(5., 5., 0., 100., radii), None);
+    assert_eq!(rounded_corner_at(1., 12., 0., 100., radii), None);
+    assert_eq!(rounded_corner_at(50., 0., 0., 100., radii), None);
+    // The square corner of the tiled side.
+    assert_eq!(rounded_corner_at(1., 1., 0., 100., (0., 10.)), None);
+}
+
+#[test]
+fn top_corner_radii_follow_tiled_edges() {
+    let radius = CORNER_RADIUS as f32;
+    assert_eq!(
+        top_corner_radii(&WindowState::empty(), 1., radius),
+        (radius, radius)
+    );
+    assert_eq!(
+        top_corner_radii(&WindowState::TILED_LEFT, 1., radius),
+        (0., radius)
+    );
+    assert_eq!(
+        top_corner_radii(&WindowState::TILED_RIGHT, 1., radius),
+        (radius, 0.)
+    );
+    assert_eq!(
+        top_corner_radii(&WindowState::TILED_BOTTOM, 1., radius),
+        (radius, radius)
+    );
+    assert_eq!(
+        top_corner_radii(&WindowState::TILED_TOP, 1., radius),
+        (0., 0.)
+    );
+    assert_eq!(top_corner_radii(&WindowState::MAXIMIZED, 2., 0.), (0., 0.));
+    assert_eq!(
+        top_corner_radii(&WindowState::TILED_LEFT, 2., radius / 2.),
+        (0., radius)
+    );
+}
+
+#[test]
+fn frame_config_builder_sets_options() {
+    let config = FrameConfig::builder()
+        .theme(ColorTheme::light())
+        .hide_titlebar(true)
+        .button_layout("close:minimize")
+        .corner_radius(4)
+        .resize_corner_size(16)
+        .button_action(0x113, MiddleClickAction::Minimize)
+        .button_action(0x113, MiddleClickAction::Menu)
+        .color_space(ColorSpace::Srgb)
+        .release_suspended_buffers(true)
+        .cursor_map(CursorMap::default().name_for(
+            Location::TopRight,
+            CursorIcon::NeResize,
+            "size_bdiag",
+        ))
+        .threaded_rendering(true)
+        .decoration_mode(DecorationMode::BorderOnly)
+        .shadow_policy(ShadowPolicy::NotWhenTiled)
+        .drag_modifier(DragModifier::Logo)
+        .state_colors(
+            ThemeState::Tiled,
+            ColorTheme::dark().active,
+            ColorTheme::dark().inactive,
+        )
+        .build();
+
+    assert!(config.hide_titlebar);
+    assert_eq!(config.corner_radius, 4);
+    assert_eq!(config.resize_handle_size, RESIZE_HANDLE_SIZE);
+    assert_eq!(config.resize_corner_size, 16);
+    assert_eq!(
+        config.button_layout,
+        Some(("close".to_owned(), "minimize".to_owned()))
+    );
+    assert_eq!(config.layout, DecorationLayout::MultiPart);
+    assert_eq!(config.button_actions, [(0x113, MiddleClickAction::Menu)]);
+    assert_eq!(config.color_space, ColorSpace::Srgb);
+    assert!(config.release_suspended_buffers);
+    assert_eq!(
+        config.cursor_map.name(Location::TopRight, true),
+        "size_bdiag"
+    );
+    assert_eq!(config.cursor_map.name(Location::TopRight, false), "default");
+    assert_eq!(config.cursor_map.name(Location::Top, true), "n-resize");
+    assert!(config.threaded_rendering);
+    assert_eq!(config.decoration_mode, DecorationMode::BorderOnly);
+    assert_eq!(config.shadow_policy, ShadowPolicy::NotWhenTiled);
+    assert_eq!(config.drag_modifier, DragModifier::Logo);
+    assert_eq!(
+        config.theme.for_window_state(&WindowState::TILED_LEFT),
+        &ColorTheme::dark().inactive
+    );
+    assert_eq!(
+        config.theme.for_window_state(&WindowState::empty()),
+        &ColorTheme::light().inactive
+    );
+}
+
+#[test]
+fn system_prefs_replace_the_queries() {
+    let prefs = SystemPrefs {
+        prefer_dark: false,
+        titlebar_font: Some("Cantarell Bold 11".to_owned()),
+        wm_titlebar_font: None,
+        system_font: None,
+        titlebar_uses_system_font: false,
+        button_layout: Some("close:minimize".to_owned()),
+        double_click_action: Some(DoubleClickAction::Minimize),
+        middle_click_action: Some(MiddleClickAction::Menu),
+        double_click_time: Some(Duration::from_millis(250)),
+        text_scale: 1.,
+        enable_animations: false,
+        font_hinting: Some(Hinting::Slight),
+        subpixel_order: Some(SubpixelOrder::Rgb),
+    };
+    let config = FrameConfig::light()
+        .system_prefs(prefs)
+        .middle_click_action(MiddleClickAction::Lower);
+
+    let click_config = config.resolve_click_config();
+    assert_eq!(
+        click_config.double_click_action,
+        DoubleClickAction::Minimize
+    );
+    assert_eq!(click_config.double_click_time, Duration::from_millis(250));
+    // The options of the configuration take precedence.
+    assert_eq!(
+        config.resolve_middle_click_action(),
+        MiddleClickAction::Lower
+    );
+    assert_eq!(
+        config.resolve_button_layout(),
+        Some(("close".to_owned(), "minimize".to_owned()))
+    );
+    assert_eq!(config.resolve_text_rendering().hinting, Hinting::Slight);
+    assert_eq!(config.resolve_header_height(1.), theme::HEADER_SIZE);
+    assert!(!config.resolve_animations());
+}
+
+#[test]
+fn dirty_callback_fires_once_dirty() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    let calls = Arc::new(AtomicUsize::new(0));
+    let callback = DirtyCallback(Arc::new({
+        let calls = calls.clone();
+        move || {
+            calls.fetch_add(1, Ordering::Relaxed);
+        }
+    }));
+
+    // Only the part redrawn on the clean frame, e.g. the progress strip, wakes the app.
+    callback.notify(false, false);
+    callback.notify(true, true);
+    assert_eq!(calls.load(Ordering::Relaxed), 0);
+    callback.notify(false, true);
+    assert_eq!(calls.load(Ordering::Relaxed), 1);
+}
+
+#[test]
+fn freeze_nests() {
+    let mut frozen = Freeze::default();
+    assert!(!frozen.is_frozen());
+
+    frozen.freeze();
+    frozen.freeze();
+    frozen.thaw();
+    assert!(frozen.is_frozen());
+    frozen.thaw();
+    assert!(!frozen.is_frozen());
+}
+
+#[test]
+fn unmatched_thaw_is_ignored() {
+    let mut frozen = Freeze::default();
+    frozen.thaw();
+    assert!(!frozen.is_frozen());
+
+    // The next batch still freezes the frame.
+    frozen.freeze();
+    assert!(frozen.is_frozen());
+    frozen.thaw();
+    assert!(!frozen.is_frozen());
+}
+
+#[test]
+fn border_damage_follows_changes() {
+    let rect = Rect {
+        x: -44,
+        y: -90,
+        width: 888,
+        height: 44,
+    };
+    #[allow(clippy::unwrap_used)]
+    let strip = edge_rect(DecorationParts::TOP, rect.width, rect.height, 0, 1).unwrap();
+    assert_eq!(
+        strip,
+        Rect {
+            x: 43,
+            y: 43,
+            width: 802,
+            height: 1
+        }
+    );
+    let drawing = BorderDrawing {
+        idx: DecorationParts::TOP,
+        rect,
+        scale: 2,
+        strip: Some((strip, Color::BLACK)),
+        rest: BorderRest {
+            state: WindowState::TILED,
+            shadow: None,
+            caps: (51, 51),
+            hint: None,
+            snap: None,
+        },
+    };
+    assert_eq!(drawing.damage_since(None), None);
+    let unchanged = drawing.damage_since(Some(&drawing));
+    assert_eq!(
+        unchanged.map(|damage| damage.width * damage.height),
+        Some(0)
+    );
+
+    // Only the visible border changes with the activation.
+    let mut activated = drawing.clone();
+    activated.strip = Some((strip, Color::WHITE));
+    assert_eq!(
+        activated.damage_since(Some(&drawing)),
+        Some(Rect {
+            x: -1,
+            y: -47,
+            width: 802,
+            height: 1
+        })
+    );
+
+    // The end cap moves over the new area.
+    let mut resized = drawing.clone();
+    resized.rect.width += 100;
+    resized.strip = Some((
+        Rect {
+            width: 902,
+            ..strip
+        },
+        Color::BLACK,
+    ));
+    assert_eq!(
+        resized.damage_since(Some(&drawing)),
+        Some(Rect {
+            x: -44 + 837,
+            width: 151,
+            ..rect
+        })
+    );
+
+    // The shadow and the scale change the whole part.
+    let mut shadowed = drawing.clone();
+    shadowed.rest.shadow = Some((1., 1.));
+    assert_eq!(shadowed.damage_since(Some(&drawing)), None);
+    let mut scaled = drawing.clone();
+    scaled.scale = 1;
+    assert_eq!(scaled.damage_since(Some(&drawing)), None);
+}
+
+#[test]
+fn clipped_pixmap_matches_unclipped_inside() {
+    #[allow(clippy::unwrap_used)]
+    let (mut source, mut clipped, mut expected) = (
+        Pixmap::new(20, 10).unwrap(),
+        Pixmap::new(40, 20).unwrap(),
+        Pixmap::new(40, 20).unwrap(),
+    );
+    source.fill(Color::from_rgba8(200, 20, 20, 180));
+    #[allow(clippy::unwrap_used)]
+    let clip = tiny_skia::Rect::from_xywh(10., 0., 15., 20.).unwrap();
+
+    let paint = PixmapPaint::default();
+    let transform = Transform::from_translate(5., 5.);
+    draw_pixmap_clipped(
+        &mut clipped.as_mut(),
+        source.as_ref(),
+        &paint,
+        transform,
+        clip,
+    );
+    expected.draw_pixmap(5, 5, source.as_ref(), &paint, Transform::identity(), None);
+
+    for x in 0..40 {
+        #[allow(clippy::unwrap_used)]
+        let (pixel, unclipped) = (clipped.pixel(x, 8).unwrap(), expected.pixel(x, 8).unwrap());
+        if (10..25).contains(&x) {
+            assert_eq!(pixel, unclipped, "{x}");
+        } else {
+            assert_eq!(pixel.alpha(), 0, "{x}");
+        }
+    }
+}
+
+#[test]
+fn narrow_header_drops_title() {
+    let state = WindowState::ACTIVATED;
+    let mut buttons = Buttons::default();
+    #[allow(clippy::unwrap_used)]
+    let title = Pixmap::new(50, 10).unwrap();
+
+    let mut draw = |width: u32| {
+        buttons.arrange(width, get_margin_h_lp(&state));
+        #[allow(clippy::unwrap_used)]
+        let mut pixmap = Pixmap::new(width, theme::HEADER_SIZE).unwrap();
+        let clip = draw_headerbar(
+            &mut pixmap.as_mut(),
+            Some((&title).into()),
+            None,
+            None,
+            1.,
+            true,
+            &state,
+            &ColorTheme::default(),
+            &mut buttons,
+            &[],
+            &[],
+            false,
+            TitleAlignment::Center,
+            0.,
+        );
+        if let Some(clip) = clip {
+            assert!(clip.width() >= MIN_TITLE_WIDTH, "title clipped at {width}");
+            assert!(
+                clip.right() <= width as f32,
+                "title over the edge at {width}"
+            );
+        }
+        clip
+    };
+
+    for width in 1..=200 {
+        draw(width);
+    }
+    assert!(draw(200).is_some());
+    assert!(draw(40).is_none());
+}
+
+#[test]
+fn centered_title_falls_back_to_start() {
+    let resolve = |alignment, rtl, centered_x| {
+        resolve_title_alignment(alignment, rtl, centered_x, 100., 50., 300.)
+    };
+
+    assert_eq!(
+        resolve(TitleAlignment::Center, false, 120.),
+        TitleAlignment::Center
+    );
+    assert_eq!(
+        resolve(TitleAlignment::Center, false, 40.),
+        TitleAlignment::Left
+    );
+    assert_eq!(
+        resolve(TitleAlignment::Center, true, 250.),
+        TitleAlignment::Right
+    );
+    assert_eq!(
+        resolve(TitleAlignment::Right, false, 120.),
+        TitleAlignment::Right
+    );
+}
+
+#[test]
+fn frame_errors_name_the_cause() {
+    assert_eq!(
+        FrameError::MissingGlobal("wl_subcompositor").to_string(),
+        "the decorations need the wl_subcompositor global"
+    );
+    assert_eq!(
+        FrameError::PoolCreation("out of memory".into()).to_string(),
+        "failed to create the decorations buffer pool: out of memory"
+    );
+}
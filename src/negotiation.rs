@@ -0,0 +1,100 @@
+//! Negotiation of the server-side decorations with the compositor.
+
+use std::fmt;
+
+use smithay_client_toolkit::reexports::csd_frame::DecorationsFrame;
+use smithay_client_toolkit::reexports::protocols::xdg::decoration::zv1::client::zxdg_toplevel_decoration_v1::{
+    Mode, ZxdgToplevelDecorationV1,
+};
+use smithay_client_toolkit::shell::xdg::window::{DecorationMode, WindowConfigure};
+
+/// The helper showing the frame only when the compositor asks for the client-side decorations.
+///
+/// Feed the decoration mode from every `zxdg_toplevel_decoration_v1.configure` into
+/// [`Self::configure`], or the whole [`WindowConfigure`] into [`Self::configure_window`] when
+/// using the SCTK window, and the frame is hidden or shown accordingly.
+pub struct DecorationNegotiator {
+    /// `None` when the compositor doesn't support `zxdg_decoration_manager_v1`.
+    toplevel_decoration: Option<ZxdgToplevelDecorationV1>,
+    mode: Mode,
+    on_mode_changed: Option<Box<dyn FnMut(Mode)>>,
+}
+
+impl DecorationNegotiator {
+    /// Create the negotiator for the given toplevel decoration.
+    ///
+    /// Without the toplevel decoration the client-side decorations are always used.
+    pub fn new(toplevel_decoration: Option<ZxdgToplevelDecorationV1>) -> Self {
+        Self {
+            toplevel_decoration,
+            mode: Mode::ClientSide,
+            on_mode_changed: None,
+        }
+    }
+
+    /// Set the callback invoked when the compositor changes the decoration mode.
+    pub fn on_mode_changed(mut self, callback: impl FnMut(Mode) + 'static) -> Self {
+        self.on_mode_changed = Some(Box::new(callback));
+        self
+    }
+
+    /// The decoration mode currently used by the window.
+    pub fn mode(&self) -> Mode {
+        self.mode
+    }
+
+    /// Ask the compositor for the decoration mode, `None` lets the compositor decide.
+    pub fn request_mode(&self, mode: Option<Mode>) {
+        let Some(toplevel_decoration) = self.toplevel_decoration.as_ref() else {
+            return;
+        };
+
+        match mode {
+            Some(mode) => toplevel_decoration.set_mode(mode),
+            None => toplevel_decoration.unset_mode(),
+        }
+    }
+
+    /// Apply the decoration mode sent by the compositor to the frame.
+    ///
+    /// Returns `true` when the mode changed.
+    pub fn configure(&mut self, mode: Mode, frame: &mut impl DecorationsFrame) -> bool {
+        frame.set_hidden(mode == Mode::ServerSide);
+
+        if mode == self.mode {
+            return false;
+        }
+
+        self.mode = mode;
+        if let Some(on_mode_changed) = self.on_mode_changed.as_mut() {
+            on_mode_changed(mode);
+        }
+
+        true
+    }
+
+    /// Apply the decoration mode of the SCTK window configure to the frame.
+    ///
+    /// Returns `true` when the mode changed.
+    pub fn configure_window(
+        &mut self,
+        configure: &WindowConfigure,
+        frame: &mut impl DecorationsFrame,
+    ) -> bool {
+        let mode = match configure.decoration_mode {
+            DecorationMode::Client => Mode::ClientSide,
+            DecorationMode::Server => Mode::ServerSide,
+        };
+
+        self.configure(mode, frame)
+    }
+}
+
+impl fmt::Debug for DecorationNegotiator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DecorationNegotiator")
+            .field("toplevel_decoration", &self.toplevel_decoration)
+            .field("mode", &self.mode)
+            .finish_non_exhaustive()
+    }
+}
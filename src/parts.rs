@@ -1,21 +1,160 @@
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
 use smithay_client_toolkit::reexports::client::{
     backend::ObjectId,
     protocol::{wl_subsurface::WlSubsurface, wl_surface::WlSurface},
     Dispatch, Proxy, QueueHandle,
 };
 
+use smithay_client_toolkit::reexports::client::globals::GlobalList;
+use smithay_client_toolkit::reexports::client::protocol::{wl_buffer, wl_shm};
+use smithay_client_toolkit::reexports::client::{delegate_noop, Connection, EventQueue};
+use smithay_client_toolkit::reexports::csd_frame::WindowState;
+use smithay_client_toolkit::reexports::protocols::wp::color_management::v1::client::wp_color_management_surface_v1::WpColorManagementSurfaceV1;
+use smithay_client_toolkit::reexports::protocols::wp::single_pixel_buffer::v1::client::wp_single_pixel_buffer_manager_v1::WpSinglePixelBufferManagerV1;
+use smithay_client_toolkit::reexports::protocols::wp::viewporter::client::{
+    wp_viewport::WpViewport, wp_viewporter::WpViewporter,
+};
 use smithay_client_toolkit::{
-    compositor::SurfaceData,
+    compositor::{CompositorState, Region, SurfaceData},
+    shm::slot::{Buffer, SlotPool},
     subcompositor::{SubcompositorState, SubsurfaceData},
 };
 
-use crate::theme::{BORDER_SIZE, HEADER_SIZE, RESIZE_HANDLE_SIZE};
-use crate::{pointer::Location, wl_typed::WlTyped};
+use tiny_skia::PixmapRef;
+
+use crate::color::ColorManagement;
+use crate::pointer::Location;
+use crate::theme::{BORDER_SIZE, HIGH_CONTRAST_BORDER_SIZE, RESIZE_HANDLE_SIZE};
+use crate::BorderDrawing;
+
+/// How the decorations are split into the subsurfaces.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum DecorationLayout {
+    /// Each border and the header get their own subsurface.
+    #[default]
+    MultiPart,
+    /// The borders, the shadow and the header are drawn into one subsurface around the window,
+    /// with the window area carved out of its input region.
+    ///
+    /// The subsurface is placed below the window, so the compositor doesn't blend its
+    /// transparent window area over the window declaring its opaque region.
+    ///
+    /// Lighter on the compositors struggling with many subsurfaces, at the cost of the larger
+    /// buffer redrawn as a whole.
+    SingleSurface,
+}
+
+/// Which of the decorations are drawn around the window.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum DecorationMode {
+    /// The headerbar, the borders and the shadow.
+    #[default]
+    Full,
+    /// Only the thin visible border, e.g. for the tiling compositors like Sway.
+    ///
+    /// The header part isn't created at all, and the borders are only as thick as the resize
+    /// handles, without the shadow.
+    BorderOnly,
+}
+
+/// Where the shadow is drawn around the window, and whether the border parts are created for
+/// the tiled windows at all.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum ShadowPolicy {
+    /// Along the edges not touching the other windows or the screen edges, like libadwaita.
+    #[default]
+    Auto,
+    /// Along all the edges, also the tiled ones.
+    Always,
+    /// Nowhere, leaving only the visible border and the resize handles.
+    Never,
+    /// Only around the windows not tiled along any edge, the border parts of the tiled windows
+    /// are left out like the ones of the maximized windows.
+    ///
+    /// Keeps the compositors showing the remnants of the transparent border parts from drawing
+    /// them between the tiled windows.
+    NotWhenTiled,
+}
+
+impl ShadowPolicy {
+    /// Whether the edge, which is tiled when the `state` contains the `tiled_edge`, casts the
+    /// shadow.
+    pub(crate) fn casts_shadow(self, state: &WindowState, tiled_edge: WindowState) -> bool {
+        match self {
+            ShadowPolicy::Auto => !state.contains(tiled_edge),
+            ShadowPolicy::Always => true,
+            ShadowPolicy::Never => false,
+            ShadowPolicy::NotWhenTiled => !state.intersects(WindowState::TILED),
+        }
+    }
+
+    /// Whether the border parts are left out in the `state`, always when maximized.
+    pub(crate) fn hides_edges(self, state: &WindowState) -> bool {
+        state.contains(WindowState::MAXIMIZED)
+            || (self == ShadowPolicy::NotWhenTiled && state.intersects(WindowState::TILED))
+    }
+}
+
+/// The edge of the window the headerbar is placed along.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum HeaderPosition {
+    /// Above the window, like in Adwaita.
+    #[default]
+    Top,
+    /// Along the left edge of the window, with the title turned to read from the bottom up and
+    /// the buttons stacked from the top.
+    ///
+    /// Saves the vertical space on the wide screens, e.g. in the tiling shells.
+    Left,
+}
+
+/// The part of the decorations.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum PartId {
+    Top,
+    Left,
+    Right,
+    Bottom,
+    Header,
+}
+
+impl PartId {
+    // XXX the order must be in sync with the `DecorationParts` constants.
+    #[cfg_attr(
+        not(any(feature = "headless", feature = "screenshot")),
+        allow(dead_code)
+    )]
+    pub(crate) const ALL: [PartId; 5] = [
+        PartId::Top,
+        PartId::Left,
+        PartId::Right,
+        PartId::Bottom,
+        PartId::Header,
+    ];
+}
 
 /// The decoration's 'parts'.
 #[derive(Debug)]
 pub struct DecorationParts {
     parts: [Part; 5],
+    /// The surface with all the parts in the [`DecorationLayout::SingleSurface`] layout.
+    single: Option<PartSurface>,
+    /// The position of the single surface relative to the main surface.
+    single_origin: (i32, i32),
+    /// The height of the header, `0` when the titlebar is hidden.
+    header_height: u32,
+    /// The edge of the main surface the header is placed along.
+    header_position: HeaderPosition,
+    /// The size of the resize handles along the edges.
+    handle_size: u32,
+    mode: DecorationMode,
+    /// The thickness of the border parts, see `Self::border_size_for`.
+    border_size: u32,
+    /// The size of the main surface set with `Self::resize`.
+    size: (u32, u32),
 }
 
 impl DecorationParts {
@@ -28,24 +167,26 @@ impl DecorationParts {
     pub const BOTTOM: usize = 3;
     pub const HEADER: usize = 4;
 
-    pub fn new<State>(
-        base_surface: &WlTyped<WlSurface, SurfaceData>,
-        subcompositor: &SubcompositorState,
-        queue_handle: &QueueHandle<State>,
-    ) -> Self
-    where
-        State: Dispatch<WlSurface, SurfaceData> + Dispatch<WlSubsurface, SubsurfaceData> + 'static,
-    {
+    pub fn new(
+        base_surface: &WlSurface,
+        surface_factory: &dyn SurfaceFactory,
+        header_height: u32,
+        layout: DecorationLayout,
+        mode: DecorationMode,
+    ) -> Self {
+        let new_surface = |layout_matches: bool| {
+            layout_matches.then(|| PartSurface::new(base_surface, surface_factory))
+        };
+        let part_surface = || new_surface(layout == DecorationLayout::MultiPart);
+
         // XXX the order must be in sync with associated constants.
         let parts = [
             // Top.
             Part::new(
-                base_surface,
-                subcompositor,
-                queue_handle,
+                part_surface(),
                 Rect {
                     x: -(BORDER_SIZE as i32),
-                    y: -(HEADER_SIZE as i32 + BORDER_SIZE as i32),
+                    y: -(header_height as i32 + BORDER_SIZE as i32),
                     width: 0, // Defined by `Self::resize`.
                     height: BORDER_SIZE,
                 },
@@ -58,12 +199,10 @@ impl DecorationParts {
             ),
             // Left.
             Part::new(
-                base_surface,
-                subcompositor,
-                queue_handle,
+                part_surface(),
                 Rect {
                     x: -(BORDER_SIZE as i32),
-                    y: -(HEADER_SIZE as i32),
+                    y: -(header_height as i32),
                     width: BORDER_SIZE,
                     height: 0, // Defined by `Self::resize`.
                 },
@@ -76,12 +215,10 @@ impl DecorationParts {
             ),
             // Right.
             Part::new(
-                base_surface,
-                subcompositor,
-                queue_handle,
+                part_surface(),
                 Rect {
                     x: 0, // Defined by `Self::resize`.
-                    y: -(HEADER_SIZE as i32),
+                    y: -(header_height as i32),
                     width: BORDER_SIZE,
                     height: 0, // Defined by `Self::resize`.
                 },
@@ -94,9 +231,7 @@ impl DecorationParts {
             ),
             // Bottom.
             Part::new(
-                base_surface,
-                subcompositor,
-                queue_handle,
+                part_surface(),
                 Rect {
                     x: -(BORDER_SIZE as i32),
                     y: 0,     // Defined by `Self::resize`.
@@ -110,99 +245,464 @@ impl DecorationParts {
                     height: RESIZE_HANDLE_SIZE,
                 }),
             ),
-            // Header.
+            // Header, left out along with its surface when only the borders are drawn.
             Part::new(
-                base_surface,
-                subcompositor,
-                queue_handle,
+                part_surface().filter(|_| mode == DecorationMode::Full),
                 Rect {
                     x: 0,
-                    y: -(HEADER_SIZE as i32),
+                    y: -(header_height as i32),
                     width: 0, // Defined by `Self::resize`.
-                    height: HEADER_SIZE,
+                    height: header_height,
                 },
                 None,
             ),
         ];
 
-        Self { parts }
+        // The single surface overlaps the window, so keep it behind.
+        let single = new_surface(layout == DecorationLayout::SingleSurface);
+        if let Some(single) = single.as_ref() {
+            single.subsurface.place_below(base_surface);
+        }
+
+        Self {
+            parts,
+            single,
+            single_origin: (0, 0),
+            header_height,
+            header_position: HeaderPosition::Top,
+            handle_size: RESIZE_HANDLE_SIZE,
+            mode,
+            border_size: Self::border_size_for(mode, RESIZE_HANDLE_SIZE),
+            size: (0, 0),
+        }
+    }
+
+    /// The thickness of the border parts in the `mode` with the resize handles of the
+    /// `handle_size`: room for the shadow, or just the handles and the thickest visible border.
+    pub fn border_size_for(mode: DecorationMode, handle_size: u32) -> u32 {
+        match mode {
+            DecorationMode::Full => BORDER_SIZE,
+            DecorationMode::BorderOnly => handle_size.clamp(HIGH_CONTRAST_BORDER_SIZE, BORDER_SIZE),
+        }
+    }
+
+    /// The thickness of the border parts in logical points.
+    pub fn border_size(&self) -> u32 {
+        self.border_size
     }
 
-    pub fn parts(&self) -> std::iter::Enumerate<std::slice::Iter<Part>> {
+    pub fn parts(&self) -> std::iter::Enumerate<std::slice::Iter<'_, Part>> {
         self.parts.iter().enumerate()
     }
 
-    pub fn hide(&self) {
-        for part in self.parts.iter() {
-            part.subsurface.set_sync();
-            part.surface.attach(None, 0, 0);
-            part.surface.commit();
+    pub fn parts_mut(&mut self) -> std::iter::Enumerate<std::slice::IterMut<'_, Part>> {
+        self.parts.iter_mut().enumerate()
+    }
+
+    /// The surface with all the parts, `None` in the [`DecorationLayout::MultiPart`] layout.
+    pub fn single_mut(&mut self) -> Option<&mut PartSurface> {
+        self.single.as_mut()
+    }
+
+    /// Move the single surface to the `origin` relative to the main surface.
+    pub fn set_single_origin(&mut self, origin: (i32, i32)) {
+        self.single_origin = origin;
+    }
+
+    /// Release the memory of the buffers of all the surfaces, they're redrawn from scratch.
+    pub fn release_buffers(&mut self) {
+        for part_surface in self.surfaces_mut() {
+            part_surface.release_buffers();
+        }
+    }
+
+    /// Tag all the surfaces with the color space of the `color_management`, or untag them with
+    /// `None`, from their next commit.
+    pub fn set_color_management(&mut self, color_management: Option<&ColorManagement>) {
+        for part_surface in self.surfaces_mut() {
+            part_surface.set_color_management(color_management);
+        }
+    }
+
+    fn surfaces(&self) -> impl Iterator<Item = &PartSurface> {
+        self.parts
+            .iter()
+            .filter_map(|part| part.surface.as_ref())
+            .chain(self.single.as_ref())
+    }
+
+    fn surfaces_mut(&mut self) -> impl Iterator<Item = &mut PartSurface> {
+        self.parts
+            .iter_mut()
+            .filter_map(|part| part.surface.as_mut())
+            .chain(self.single.as_mut())
+    }
+
+    /// Set the preferred buffer scale of the given surface.
+    ///
+    /// Returns `false` when the surface isn't a part of the decorations.
+    pub fn set_scale(&mut self, surface: &ObjectId, scale: u32) -> bool {
+        let Some(part_surface) = self
+            .surfaces_mut()
+            .find(|part_surface| &part_surface.surface.id() == surface)
+        else {
+            return false;
+        };
+
+        part_surface.set_scale(scale);
+        true
+    }
+
+    /// Follow the preferred buffer scales of the surfaces changed since the last draw, e.g. when
+    /// the scale events arrive between the resize and the draw and the app didn't pass them to
+    /// [`Self::set_scale`] yet.
+    ///
+    /// Returns `true` when any surface should be redrawn at the new scale.
+    pub fn sync_scales(&mut self) -> bool {
+        let mut changed = false;
+        for part_surface in self.surfaces_mut() {
+            let tracked_scale = part_surface.tracked_scale();
+            if tracked_scale.is_some() && tracked_scale != part_surface.tracked_scale_seen {
+                part_surface.tracked_scale_seen = tracked_scale;
+                changed |= tracked_scale.is_some_and(|scale| part_surface.set_scale(scale));
+            }
+        }
+        changed
+    }
+
+    /// Whether any surface should be redrawn even when the frame isn't dirty.
+    pub fn needs_redraw(&self) -> bool {
+        self.surfaces().any(|part_surface| {
+            part_surface.needs_redraw || {
+                let tracked_scale = part_surface.tracked_scale();
+                tracked_scale.is_some() && tracked_scale != part_surface.tracked_scale_seen
+            }
+        })
+    }
+
+    /// Redraw only the surface of the part on the next draw.
+    pub fn redraw_part(&mut self, idx: usize) {
+        let part_surface = match self.parts[idx].surface.as_mut() {
+            Some(part_surface) => Some(part_surface),
+            None => self.single.as_mut(),
+        };
+        if let Some(part_surface) = part_surface {
+            part_surface.needs_redraw = true;
+            part_surface.damage = None;
+        }
+    }
+
+    /// Redraw the surface of the part on the next draw, with only the `area` in the coordinates
+    /// of the main surface changed.
+    pub fn redraw_area(&mut self, idx: usize, area: Rect) {
+        let part_surface = match self.parts[idx].surface.as_mut() {
+            Some(part_surface) => Some(part_surface),
+            None => self.single.as_mut(),
+        };
+        let Some(part_surface) = part_surface else {
+            return;
+        };
+
+        if !part_surface.needs_redraw {
+            part_surface.needs_redraw = true;
+            part_surface.damage = Some(area);
+        } else if let Some(damage) = part_surface.damage {
+            part_surface.damage = Some(damage.union(area));
+        }
+    }
+
+    pub fn hide(&mut self) {
+        for part_surface in self.surfaces_mut() {
+            part_surface.needs_redraw = false;
+            part_surface.presented = None;
+            part_surface.subsurface.set_sync();
+            part_surface.hide();
+        }
+    }
+
+    pub fn hide_header(&self) {
+        if let Some(part_surface) = self.parts[Self::HEADER].surface.as_ref() {
+            part_surface.hide();
         }
     }
 
     pub fn hide_borders(&self) {
         for (_, part) in self.parts().filter(|(idx, _)| *idx != Self::HEADER) {
-            part.surface.attach(None, 0, 0);
-            part.surface.commit();
+            if let Some(part_surface) = part.surface.as_ref() {
+                part_surface.hide();
+            }
         }
     }
 
+    /// The surface rectangles of the parts for the main surface of the given size, with the
+    /// border parts `border_size` thick, in the order of the associated constants.
+    pub fn surface_rects(
+        width: u32,
+        height: u32,
+        header_height: u32,
+        header_position: HeaderPosition,
+        border_size: u32,
+    ) -> [Rect; 5] {
+        // The space taken by the header on the left and above the main surface.
+        let (header_left, header_top) = match header_position {
+            HeaderPosition::Top => (0, header_height),
+            HeaderPosition::Left => (header_height, 0),
+        };
+        let top_width = width + header_left + 2 * border_size;
+        let side_height = height + header_top;
+        [
+            // Top.
+            Rect {
+                x: -(header_left as i32 + border_size as i32),
+                y: -(header_top as i32 + border_size as i32),
+                width: top_width,
+                height: border_size,
+            },
+            // Left.
+            Rect {
+                x: -(header_left as i32 + border_size as i32),
+                y: -(header_top as i32),
+                width: border_size,
+                height: side_height,
+            },
+            // Right.
+            Rect {
+                x: width as i32,
+                y: -(header_top as i32),
+                width: border_size,
+                height: side_height,
+            },
+            // Bottom.
+            Rect {
+                x: -(header_left as i32 + border_size as i32),
+                y: height as i32,
+                width: top_width,
+                height: border_size,
+            },
+            // Header.
+            match header_position {
+                HeaderPosition::Top => Rect {
+                    x: 0,
+                    y: -(header_height as i32),
+                    width,
+                    height: header_height,
+                },
+                HeaderPosition::Left => Rect {
+                    x: -(header_height as i32),
+                    y: 0,
+                    width: header_height,
+                    height,
+                },
+            },
+        ]
+    }
+
     // These unwraps are guaranteed to succeed because the affected options are filled above
     // and then never emptied afterwards.
     #[allow(clippy::unwrap_used)]
     pub fn resize(&mut self, width: u32, height: u32) {
-        self.parts[Self::HEADER].surface_rect.width = width;
+        self.size = (width, height);
+
+        let rects = Self::surface_rects(
+            width,
+            height,
+            self.header_height,
+            self.header_position,
+            self.border_size,
+        );
+        for (part, rect) in self.parts.iter_mut().zip(rects) {
+            part.surface_rect = rect;
+        }
+
+        // The handles extend from the window into the borders.
+        let handle_size = self.handle_size;
+        let border_size = self.border_size;
+        let handle_offset = border_size as i32 - handle_size as i32;
+        let handle_width =
+            self.parts[Self::TOP].surface_rect.width - (border_size * 2) + (handle_size * 2);
+        let handle_height = self.parts[Self::LEFT].surface_rect.height;
+
+        *self.parts[Self::TOP].input_rect.as_mut().unwrap() = Rect {
+            x: handle_offset,
+            y: handle_offset,
+            width: handle_width,
+            height: handle_size,
+        };
+
+        *self.parts[Self::LEFT].input_rect.as_mut().unwrap() = Rect {
+            x: handle_offset,
+            y: 0,
+            width: handle_size,
+            height: handle_height,
+        };
+
+        *self.parts[Self::RIGHT].input_rect.as_mut().unwrap() = Rect {
+            x: 0,
+            y: 0,
+            width: handle_size,
+            height: handle_height,
+        };
+
+        *self.parts[Self::BOTTOM].input_rect.as_mut().unwrap() = Rect {
+            x: handle_offset,
+            y: 0,
+            width: handle_width,
+            height: handle_size,
+        };
+    }
+
+    /// Update the size of the resize handles along the edges in logical points, limited by
+    /// the size of the borders with the shadow.
+    ///
+    /// The borders without the shadow are as thick as the handles.
+    pub fn set_handle_size(&mut self, handle_size: u32) {
+        let handle_size = handle_size.clamp(1, BORDER_SIZE);
+        if self.handle_size == handle_size {
+            return;
+        }
+
+        self.handle_size = handle_size;
+        self.border_size = Self::border_size_for(self.mode, handle_size);
+
+        let (width, height) = self.size;
+        self.resize(width, height);
+    }
+
+    /// The input rectangle of the part, positioned relative to the main surface.
+    pub fn input_rect(&self, idx: usize) -> Rect {
+        let part = &self.parts[idx];
+        match part.input_rect {
+            Some(input_rect) => Rect {
+                x: part.surface_rect.x + input_rect.x,
+                y: part.surface_rect.y + input_rect.y,
+                ..input_rect
+            },
+            None => part.surface_rect,
+        }
+    }
 
-        self.parts[Self::BOTTOM].surface_rect.width = width + 2 * BORDER_SIZE;
-        self.parts[Self::BOTTOM].surface_rect.y = height as i32;
-        self.parts[Self::BOTTOM].input_rect.as_mut().unwrap().width =
-            self.parts[Self::BOTTOM].surface_rect.width - (BORDER_SIZE * 2)
-                + (RESIZE_HANDLE_SIZE * 2);
+    /// Update the height of the header, `0` hides the titlebar.
+    pub fn set_header_height(&mut self, header_height: u32) {
+        if self.header_height == header_height {
+            return;
+        }
 
-        self.parts[Self::TOP].surface_rect.width = self.parts[Self::BOTTOM].surface_rect.width;
-        self.parts[Self::TOP].input_rect.as_mut().unwrap().width =
-            self.parts[Self::TOP].surface_rect.width - (BORDER_SIZE * 2) + (RESIZE_HANDLE_SIZE * 2);
+        self.header_height = header_height;
+
+        let (width, height) = self.size;
+        self.resize(width, height);
+    }
 
-        self.parts[Self::LEFT].surface_rect.height = height + HEADER_SIZE;
-        self.parts[Self::LEFT].input_rect.as_mut().unwrap().height =
-            self.parts[Self::LEFT].surface_rect.height;
+    /// Move the header to the `header_position` edge of the main surface.
+    pub fn set_header_position(&mut self, header_position: HeaderPosition) {
+        if self.header_position == header_position {
+            return;
+        }
+
+        self.header_position = header_position;
+
+        let (width, height) = self.size;
+        self.resize(width, height);
+    }
 
-        self.parts[Self::RIGHT].surface_rect.height = self.parts[Self::LEFT].surface_rect.height;
-        self.parts[Self::RIGHT].surface_rect.x = width as i32;
-        self.parts[Self::RIGHT].input_rect.as_mut().unwrap().height =
-            self.parts[Self::RIGHT].surface_rect.height;
+    /// The size of the main surface the decorations are placed around.
+    pub fn size(&self) -> (u32, u32) {
+        self.size
     }
 
     pub fn header(&self) -> &Part {
         &self.parts[Self::HEADER]
     }
 
+    /// The length of the header along the edge of the main surface it's placed on.
+    pub fn header_length(&self) -> u32 {
+        let rect = self.parts[Self::HEADER].surface_rect;
+        match self.header_position {
+            HeaderPosition::Top => rect.width,
+            HeaderPosition::Left => rect.height,
+        }
+    }
+
     pub fn side_height(&self) -> u32 {
         self.parts[Self::LEFT].surface_rect.height
     }
 
-    pub fn find_surface(&self, surface: &ObjectId) -> Location {
-        let pos = match self
-            .parts
-            .iter()
-            .position(|part| &part.surface.id() == surface)
-        {
-            Some(pos) => pos,
-            None => return Location::None,
-        };
+    pub fn top_width(&self) -> u32 {
+        self.parts[Self::TOP].surface_rect.width
+    }
+
+    /// The current surface rectangles of the parts, positioned relative to the main surface.
+    pub fn rects(&self) -> [Rect; 5] {
+        self.parts.each_ref().map(|part| part.surface_rect)
+    }
 
-        match pos {
+    /// Find the part under the point of the given surface.
+    ///
+    /// Returns the location of the part with the point in its local coordinates, `None` when
+    /// the surface isn't a part of the decorations.
+    pub fn locate(&self, surface: &ObjectId, x: f64, y: f64) -> Option<(Location, f64, f64)> {
+        if let Some(idx) = self.parts.iter().position(|part| {
+            part.surface
+                .as_ref()
+                .is_some_and(|part_surface| &part_surface.surface.id() == surface)
+        }) {
+            return Some((Self::location(idx), x, y));
+        }
+
+        let single = self.single.as_ref()?;
+        if &single.surface.id() != surface {
+            return None;
+        }
+
+        // Relative to the main surface.
+        let x = x + f64::from(self.single_origin.0);
+        let y = y + f64::from(self.single_origin.1);
+
+        // The header is on top of the other parts.
+        let idx = [
+            Self::HEADER,
+            Self::TOP,
+            Self::LEFT,
+            Self::RIGHT,
+            Self::BOTTOM,
+        ]
+        .into_iter()
+        .find(|idx| self.input_rect(*idx).contains(x, y))?;
+        let origin = self.parts[idx].surface_rect;
+        Some((
+            Self::location(idx),
+            x - f64::from(origin.x),
+            y - f64::from(origin.y),
+        ))
+    }
+
+    /// The location of the part.
+    pub fn location(idx: usize) -> Location {
+        match idx {
             Self::HEADER => Location::Head,
             Self::TOP => Location::Top,
             Self::BOTTOM => Location::Bottom,
             Self::LEFT => Location::Left,
             Self::RIGHT => Location::Right,
-            _ => unreachable!(),
+            _ => Location::None,
         }
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+/// The space the decorations take on each side of the main surface in logical points, see
+/// [`AdwaitaFrame::decoration_insets`].
+///
+/// [`AdwaitaFrame::decoration_insets`]: crate::AdwaitaFrame::decoration_insets
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Insets {
+    pub top: u32,
+    pub left: u32,
+    pub right: u32,
+    pub bottom: u32,
+}
+
+/// The rectangle in logical coordinates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Rect {
     pub x: i32,
     pub y: i32,
@@ -210,51 +710,663 @@ pub struct Rect {
     pub height: u32,
 }
 
+impl Rect {
+    pub(crate) fn contains(&self, x: f64, y: f64) -> bool {
+        x >= f64::from(self.x)
+            && x < f64::from(self.x) + f64::from(self.width)
+            && y >= f64::from(self.y)
+            && y < f64::from(self.y) + f64::from(self.height)
+    }
+
+    /// The smallest rectangle containing both of the rectangles.
+    pub(crate) fn union(self, other: Rect) -> Rect {
+        let x = self.x.min(other.x);
+        let y = self.y.min(other.y);
+        let right = (self.x + self.width as i32).max(other.x + other.width as i32);
+        let bottom = (self.y + self.height as i32).max(other.y + other.height as i32);
+        Rect {
+            x,
+            y,
+            width: (right - x) as u32,
+            height: (bottom - y) as u32,
+        }
+    }
+
+    /// Split the rect into three horizontal pieces at the given `x` coordinates.
+    ///
+    /// The `end` before the `start` is moved to it, so the pieces never overlap.
+    pub(crate) fn split_x<T: Copy>(
+        self,
+        start: i32,
+        end: i32,
+        labels: [T; 3],
+    ) -> impl Iterator<Item = (Rect, T)> {
+        let right = self.x + self.width as i32;
+        let start = start.clamp(self.x, right);
+        let bounds = [self.x, start, end.clamp(start, right), right];
+        (0..3).filter_map(move |i| {
+            let width = bounds[i + 1].saturating_sub(bounds[i]);
+            (width > 0).then(|| {
+                let rect = Rect {
+                    x: bounds[i],
+                    width: width as u32,
+                    ..self
+                };
+                (rect, labels[i])
+            })
+        })
+    }
+
+    /// Split the rect into three vertical pieces at the given `y` coordinates, like
+    /// [`Self::split_x`].
+    pub(crate) fn split_y<T: Copy>(
+        self,
+        start: i32,
+        end: i32,
+        labels: [T; 3],
+    ) -> impl Iterator<Item = (Rect, T)> {
+        let bottom = self.y + self.height as i32;
+        let start = start.clamp(self.y, bottom);
+        let bounds = [self.y, start, end.clamp(start, bottom), bottom];
+        (0..3).filter_map(move |i| {
+            let height = bounds[i + 1].saturating_sub(bounds[i]);
+            (height > 0).then(|| {
+                let rect = Rect {
+                    y: bounds[i],
+                    height: height as u32,
+                    ..self
+                };
+                (rect, labels[i])
+            })
+        })
+    }
+}
+
 #[derive(Debug)]
 pub struct Part {
-    pub surface: WlTyped<WlSurface, SurfaceData>,
-    pub subsurface: WlTyped<WlSubsurface, SubsurfaceData>,
-
     /// Positioned relative to the main surface.
     pub surface_rect: Rect,
     /// Positioned relative to the local surface, aka. `surface_rect`.
     ///
     /// `None` if it fully covers `surface_rect`.
     pub input_rect: Option<Rect>,
+
+    /// The own surface of the part, `None` in the [`DecorationLayout::SingleSurface`] layout.
+    pub surface: Option<PartSurface>,
 }
 
 impl Part {
-    fn new<State>(
-        parent: &WlTyped<WlSurface, SurfaceData>,
-        subcompositor: &SubcompositorState,
-        queue_handle: &QueueHandle<State>,
-        surface_rect: Rect,
-        input_rect: Option<Rect>,
-    ) -> Part
-    where
-        State: Dispatch<WlSurface, SurfaceData> + Dispatch<WlSubsurface, SubsurfaceData> + 'static,
-    {
-        let (subsurface, surface) =
-            subcompositor.create_subsurface(parent.inner().clone(), queue_handle);
-
-        let subsurface = WlTyped::wrap::<State>(subsurface);
-        let surface = WlTyped::wrap::<State>(surface);
+    fn new(surface: Option<PartSurface>, surface_rect: Rect, input_rect: Option<Rect>) -> Part {
+        Part {
+            surface_rect,
+            input_rect,
+            surface,
+        }
+    }
+}
+
+/// Creates the subsurfaces the decorations are drawn into, e.g. to drive the frame without the
+/// event queue of the SCTK state, with a mock compositor in the tests or another client stack.
+///
+/// The frame created with [`AdwaitaFrame::new`] uses the `wl_subcompositor` of SCTK, see
+/// [`AdwaitaFrame::with_surface_factory`] for the other ones.
+///
+/// [`AdwaitaFrame::new`]: crate::AdwaitaFrame::new
+/// [`AdwaitaFrame::with_surface_factory`]: crate::AdwaitaFrame::with_surface_factory
+pub trait SurfaceFactory {
+    /// Create a new surface with the subsurface role over the `parent` surface.
+    ///
+    /// The frame only sends the requests on the objects, so their events may be ignored. The
+    /// subsurface is destroyed before its surface once the part is no longer needed.
+    fn create_subsurface(&self, parent: &WlSurface) -> (WlSubsurface, WlSurface);
+}
+
+/// The [`SurfaceFactory`] of the SCTK `wl_subcompositor`, with the objects dispatched by the
+/// `State` of the application.
+#[derive(Debug)]
+pub(crate) struct SctkSurfaceFactory<State> {
+    pub subcompositor: Arc<SubcompositorState>,
+    pub queue_handle: QueueHandle<State>,
+}
+
+impl<State> SurfaceFactory for SctkSurfaceFactory<State>
+where
+    State: Dispatch<WlSurface, SurfaceData> + Dispatch<WlSubsurface, SubsurfaceData> + 'static,
+{
+    fn create_subsurface(&self, parent: &WlSurface) -> (WlSubsurface, WlSurface) {
+        self.subcompositor
+            .create_subsurface(parent.clone(), &self.queue_handle)
+    }
+}
+
+/// The [`SurfaceFactory`] of the frame.
+pub(crate) struct SurfaceFactoryBox(pub Box<dyn SurfaceFactory>);
+
+impl fmt::Debug for SurfaceFactoryBox {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SurfaceFactoryBox").finish_non_exhaustive()
+    }
+}
+
+/// The subsurface the decorations are drawn into.
+#[derive(Debug)]
+pub struct PartSurface {
+    pub surface: WlSurface,
+    pub subsurface: WlSubsurface,
+
+    /// The preferred buffer scale of the surface, `None` to use the scale of the frame.
+    pub scale: Option<u32>,
+    /// The last scale tracked for the surface by the toolkit, see
+    /// [`DecorationParts::sync_scales`].
+    tracked_scale_seen: Option<u32>,
+    /// Whether the surface should be redrawn even when the frame isn't dirty, e.g. because its
+    /// scale changed.
+    pub needs_redraw: bool,
+    /// The only area changed for the redraw in the coordinates of the main surface, `None` when
+    /// the whole surface changed.
+    pub damage: Option<Rect>,
+    /// What the border part presented last was drawn from, to damage only what changed since.
+    pub presented: Option<BorderDrawing>,
+
+    /// The buffers of the last size and format, reused while the compositor doesn't hold them.
+    buffers: Vec<Buffer>,
+    /// The format of the `buffers`.
+    format: wl_shm::Format,
+
+    /// The viewport stretching the single pixel buffers over the surface.
+    viewport: Option<WpViewport>,
+    /// The single pixel buffer to attach instead of the drawn buffer.
+    solid: Option<wl_buffer::WlBuffer>,
+    /// The subsurfaces with the caps at the both ends and the stretched middle of the edge
+    /// part, see [`Self::segments_mut`].
+    segments: Option<Box<[PartSurface; 3]>>,
+    /// The color space the surface is tagged with, see [`Self::set_color_management`].
+    color_surface: Option<WpColorManagementSurfaceV1>,
+    /// The blur behind the translucent headerbar drawn into the surface.
+    #[cfg(feature = "blur")]
+    pub blur: Option<crate::blur::SurfaceBlur>,
+}
+
+impl PartSurface {
+    /// Set the preferred buffer scale, returning whether it changed and the surface should be
+    /// redrawn.
+    fn set_scale(&mut self, scale: u32) -> bool {
+        let changed = self.scale != Some(scale);
+        if changed {
+            self.needs_redraw = true;
+            self.damage = None;
+        }
+        self.scale = Some(scale);
+        changed
+    }
+
+    /// The buffer scale the toolkit tracks for the surface from `wl_surface.preferred_buffer_scale`
+    /// or the scales of the entered outputs, `None` before it entered any output or when the
+    /// surface was created without the [`SurfaceData`] of the toolkit.
+    fn tracked_scale(&self) -> Option<u32> {
+        let data = self.surface.data::<SurfaceData>()?;
+        data.outputs().next()?;
+        u32::try_from(data.scale_factor())
+            .ok()
+            .filter(|scale| *scale > 0)
+    }
+
+    fn new(parent: &WlSurface, surface_factory: &dyn SurfaceFactory) -> PartSurface {
+        let (subsurface, surface) = surface_factory.create_subsurface(parent);
 
         // Sync with the parent surface.
         subsurface.set_sync();
 
-        Part {
+        PartSurface {
             surface,
             subsurface,
-            surface_rect,
-            input_rect,
+            scale: None,
+            tracked_scale_seen: None,
+            needs_redraw: false,
+            damage: None,
+            presented: None,
+            buffers: Vec::new(),
+            format: wl_shm::Format::Argb8888,
+            viewport: None,
+            solid: None,
+            segments: None,
+            color_surface: None,
+            #[cfg(feature = "blur")]
+            blur: None,
+        }
+    }
+
+    /// The canvas of the buffer for the `rect` in logical points at the `scale` and the `format`
+    /// released by the compositor, the buffer is attached with [`Self::attach_buffer`] once
+    /// drawn.
+    ///
+    /// The new buffer is created only when the size or the format changed or all the buffers
+    /// are still held by the compositor, so the steady-state redraws don't hit the slot
+    /// allocator. When it can't be created, it's retried with the buffers of the surface
+    /// released and then at the lower scales.
+    ///
+    /// Returns the canvas with the scale it was created at.
+    pub fn buffer<'pool>(
+        &mut self,
+        pool: &'pool mut SlotPool,
+        rect: Rect,
+        scale: u32,
+        format: wl_shm::Format,
+    ) -> Option<(&'pool mut [u8], u32)> {
+        let scale = (1..=scale.max(1)).rev().find(|scale| {
+            let (width, height) = (rect.width * scale, rect.height * scale);
+            if self.prepare_buffer(pool, width, height, format).is_some() {
+                return true;
+            }
+
+            // Give the memory of the buffers released by the compositor back to the pool.
+            self.buffers.clear();
+            self.prepare_buffer(pool, width, height, format).is_some()
+        })?;
+
+        Some((self.buffers.last()?.canvas(pool)?, scale))
+    }
+
+    /// Make the last buffer free to draw into, with the given size in pixels and format.
+    fn prepare_buffer(
+        &mut self,
+        pool: &mut SlotPool,
+        width: u32,
+        height: u32,
+        format: wl_shm::Format,
+    ) -> Option<()> {
+        if self.format != format {
+            self.format = format;
+            self.buffers.clear();
+        }
+
+        let (width, height) = (width as i32, height as i32);
+        let stride = width * 4;
+        self.buffers
+            .retain(|buffer| buffer.stride() == stride && buffer.height() == height);
+
+        match self
+            .buffers
+            .iter()
+            .position(|buffer| buffer.canvas(pool).is_some())
+        {
+            // Keep the buffer to draw into last.
+            Some(idx) => {
+                let buffer = self.buffers.remove(idx);
+                self.buffers.push(buffer);
+            }
+            None => {
+                let (buffer, _) = pool.create_buffer(width, height, stride, format).ok()?;
+                // The oldest buffer gets destroyed once the compositor releases it.
+                if self.buffers.len() >= BUFFERS_PER_SURFACE {
+                    self.buffers.remove(0);
+                }
+                self.buffers.push(buffer);
+            }
+        }
+
+        Some(())
+    }
+
+    /// Attach the buffer drawn after [`Self::buffer`] to the surface.
+    pub fn attach_buffer(&mut self) -> Option<()> {
+        if let Some(solid) = self.solid.take() {
+            self.surface.attach(Some(&solid), 0, 0);
+            return Some(());
+        }
+
+        self.buffers.last()?.attach_to(&self.surface).ok()
+    }
+
+    /// Replace the buffer drawn after [`Self::buffer`] with the compositor-side buffer of the
+    /// `color` stretched over the `rect` in logical points, or go back to the drawn buffer when
+    /// the `color` is `None`.
+    ///
+    /// The drawn buffers are given back to the pool while the single pixel buffer is used, so
+    /// the buffer must be attached at the scale of `1`.
+    ///
+    /// Returns whether the single pixel buffer is used.
+    pub fn set_solid(
+        &mut self,
+        solid_buffers: &SolidBuffers,
+        color: Option<[u8; 4]>,
+        rect: Rect,
+    ) -> bool {
+        let Some(color) = color else {
+            self.solid = None;
+            if let Some(viewport) = self.viewport.as_ref() {
+                viewport.set_destination(-1, -1);
+            }
+            return false;
+        };
+
+        self.set_destination(solid_buffers, Some((rect.width, rect.height)));
+        self.solid = Some(solid_buffers.buffer(color));
+        self.buffers.clear();
+        true
+    }
+
+    /// Stretch the attached buffer to the `size` in logical points, or unset the stretching when
+    /// it's `None`.
+    ///
+    /// Applied with the next commit.
+    pub fn set_destination(&mut self, solid_buffers: &SolidBuffers, size: Option<(u32, u32)>) {
+        match size {
+            Some((width, height)) => self
+                .viewport
+                .get_or_insert_with(|| solid_buffers.viewport(&self.surface))
+                .set_destination(width as i32, height as i32),
+            None => {
+                if let Some(viewport) = self.viewport.as_ref() {
+                    viewport.set_destination(-1, -1);
+                }
+            }
+        }
+    }
+
+    /// The subsurfaces with the start cap, the middle and the end cap of the edge part, created
+    /// on the first use.
+    ///
+    /// They don't take the input, so the pointer still goes to the part surface underneath.
+    pub fn segments_mut(
+        &mut self,
+        surface_factory: &dyn SurfaceFactory,
+        compositor: &CompositorState,
+    ) -> Option<&mut [PartSurface; 3]> {
+        if self.segments.is_none() {
+            let input_region = Region::new(compositor).ok()?;
+            let segments = [(); 3].map(|_| {
+                let segment = PartSurface::new(&self.surface, surface_factory);
+                segment
+                    .surface
+                    .set_input_region(Some(input_region.wl_region()));
+                segment
+            });
+            self.segments = Some(Box::new(segments));
+        }
+
+        self.segments.as_deref_mut()
+    }
+
+    /// Release the buffers of the surface and its segments, the ones still held by the
+    /// compositor are destroyed once released.
+    pub fn release_buffers(&mut self) {
+        self.buffers.clear();
+        self.presented = None;
+        for segment in self
+            .segments
+            .iter_mut()
+            .flat_map(|segments| segments.iter_mut())
+        {
+            segment.release_buffers();
+        }
+    }
+
+    /// Tag the surface and its segments with the color space of the `color_management`, or
+    /// untag them with `None`, from their next commit.
+    pub fn set_color_management(&mut self, color_management: Option<&ColorManagement>) {
+        match (color_management, self.color_surface.as_ref()) {
+            (Some(color_management), None) => {
+                self.color_surface = color_management.tag(&self.surface);
+            }
+            (None, Some(_)) => {
+                if let Some(color_surface) = self.color_surface.take() {
+                    color_surface.destroy();
+                }
+            }
+            _ => (),
+        }
+
+        for segment in self
+            .segments
+            .iter_mut()
+            .flat_map(|segments| segments.iter_mut())
+        {
+            segment.set_color_management(color_management);
+        }
+    }
+
+    /// Destroy the segments of the part, when it's drawn as a whole again.
+    pub fn clear_segments(&mut self) {
+        self.segments = None;
+    }
+
+    /// Mark the `opaque` rectangle in the surface local coordinates as opaque, so the compositor
+    /// could skip blending it, `None` when nothing is opaque.
+    ///
+    /// Applied with the next commit.
+    pub fn set_opaque_rect(&self, compositor: &CompositorState, opaque: Option<Rect>) {
+        let region = opaque.and_then(|rect| {
+            let region = Region::new(compositor).ok()?;
+            region.add(rect.x, rect.y, rect.width as i32, rect.height as i32);
+            Some(region)
+        });
+        self.surface
+            .set_opaque_region(region.as_ref().map(Region::wl_region));
+    }
+
+    fn hide(&self) {
+        self.surface.attach(None, 0, 0);
+        self.surface.commit();
+    }
+}
+
+/// The format for the fully opaque buffers out of the `formats` advertised by the compositor.
+///
+/// Without the alpha channel the compositor doesn't need to blend the buffer with the window
+/// underneath.
+pub fn opaque_format(formats: &[wl_shm::Format]) -> wl_shm::Format {
+    if formats.contains(&wl_shm::Format::Xrgb8888) {
+        wl_shm::Format::Xrgb8888
+    } else {
+        wl_shm::Format::Argb8888
+    }
+}
+
+/// The number of buffers kept per surface, so the surface could be redrawn while the
+/// compositor still reads the previous one.
+const BUFFERS_PER_SURFACE: usize = 2;
+
+/// The compositor-side buffers of a single color, from the `wp_single_pixel_buffer_v1` stretched
+/// with the `wp_viewporter`.
+///
+/// The objects live on their own queue, so the state of the application doesn't need to handle
+/// them.
+#[derive(Debug)]
+pub struct SolidBuffers {
+    queue: Mutex<EventQueue<SolidBuffersState>>,
+    qh: QueueHandle<SolidBuffersState>,
+    manager: WpSinglePixelBufferManagerV1,
+    viewporter: WpViewporter,
+}
+
+impl SolidBuffers {
+    /// Bind the advertised `globals` on the `connection`, `None` when the compositor doesn't
+    /// support them.
+    pub fn bind(globals: &GlobalList, connection: &Connection) -> Option<Self> {
+        let queue = connection.new_event_queue();
+        let qh = queue.handle();
+        let manager = globals.bind(&qh, 1..=1, ()).ok()?;
+        let viewporter = globals.bind(&qh, 1..=1, ()).ok()?;
+
+        Some(Self {
+            queue: Mutex::new(queue),
+            qh,
+            manager,
+            viewporter,
+        })
+    }
+
+    /// Destroy the buffers released by the compositor, without blocking.
+    pub fn dispatch(&self) {
+        let Ok(mut queue) = self.queue.lock() else {
+            return;
+        };
+        if let Err(err) = queue.dispatch_pending(&mut SolidBuffersState) {
+            log::warn!("Failed to dispatch the single pixel buffers: {err}");
+        }
+    }
+
+    /// The buffer of the premultiplied color in the byte order of `wl_shm::Format::Argb8888`.
+    ///
+    /// Every attach gets its own buffer, destroyed once released by the compositor.
+    fn buffer(&self, [blue, green, red, alpha]: [u8; 4]) -> wl_buffer::WlBuffer {
+        let channel = |value: u8| u32::from(value) * (u32::MAX / 255);
+        self.manager.create_u32_rgba_buffer(
+            channel(red),
+            channel(green),
+            channel(blue),
+            channel(alpha),
+            &self.qh,
+            (),
+        )
+    }
+
+    fn viewport(&self, surface: &WlSurface) -> WpViewport {
+        self.viewporter.get_viewport(surface, &self.qh, ())
+    }
+}
+
+impl Drop for SolidBuffers {
+    fn drop(&mut self) {
+        self.manager.destroy();
+        self.viewporter.destroy();
+    }
+}
+
+/// The color of the `pixmap` when all of its pixels are the same.
+pub fn uniform_color(pixmap: PixmapRef) -> Option<[u8; 4]> {
+    let (first, rest) = pixmap.data().split_first_chunk::<4>()?;
+    rest.chunks_exact(4)
+        .all(|pixel| pixel == first)
+        .then_some(*first)
+}
+
+#[derive(Debug)]
+struct SolidBuffersState;
+
+impl Dispatch<wl_buffer::WlBuffer, ()> for SolidBuffersState {
+    fn event(
+        _: &mut Self,
+        buffer: &wl_buffer::WlBuffer,
+        event: wl_buffer::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        if let wl_buffer::Event::Release = event {
+            buffer.destroy();
         }
     }
 }
 
-impl Drop for Part {
+delegate_noop!(SolidBuffersState: ignore WpSinglePixelBufferManagerV1);
+delegate_noop!(SolidBuffersState: ignore WpViewporter);
+delegate_noop!(SolidBuffersState: ignore WpViewport);
+
+impl Drop for PartSurface {
     fn drop(&mut self) {
+        self.segments = None;
+        if let Some(viewport) = self.viewport.take() {
+            viewport.destroy();
+        }
+        if let Some(color_surface) = self.color_surface.take() {
+            color_surface.destroy();
+        }
+        #[cfg(feature = "blur")]
+        {
+            self.blur = None;
+        }
         self.subsurface.destroy();
         self.surface.destroy();
     }
 }
+
+#[test]
+fn rect_split_x_clamps_to_bounds() {
+    let rect = Rect {
+        x: -10,
+        y: 0,
+        width: 100,
+        height: 5,
+    };
+
+    let pieces: Vec<_> = rect.split_x(0, 200, [0, 1, 2]).collect();
+    assert_eq!(pieces.len(), 2);
+    assert_eq!(
+        (pieces[0].0.x, pieces[0].0.width, pieces[0].1),
+        (-10, 10, 0)
+    );
+    assert_eq!((pieces[1].0.x, pieces[1].0.width, pieces[1].1), (0, 90, 1));
+}
+
+#[test]
+fn narrow_rect_split_doesnt_overlap() {
+    let rect = Rect {
+        x: 0,
+        y: 0,
+        width: 30,
+        height: 5,
+    };
+
+    // The corners of the narrow window meet at the start of the right one.
+    let pieces: Vec<_> = rect.split_x(20, 10, [0, 1, 2]).collect();
+    assert_eq!(pieces.len(), 2);
+    assert_eq!((pieces[0].0.x, pieces[0].0.width, pieces[0].1), (0, 20, 0));
+    assert_eq!((pieces[1].0.x, pieces[1].0.width, pieces[1].1), (20, 10, 2));
+
+    let rect = Rect {
+        width: 5,
+        height: 30,
+        ..rect
+    };
+    let pieces: Vec<_> = rect.split_y(40, -10, [0, 1, 2]).collect();
+    assert_eq!(pieces.len(), 1);
+    assert_eq!((pieces[0].0.y, pieces[0].0.height, pieces[0].1), (0, 30, 0));
+}
+
+#[test]
+fn rect_union_covers_both() {
+    let a = Rect {
+        x: -10,
+        y: -10,
+        width: 20,
+        height: 5,
+    };
+    let b = Rect {
+        x: 0,
+        y: 0,
+        width: 30,
+        height: 10,
+    };
+
+    let union = a.union(b);
+    assert_eq!(
+        (union.x, union.y, union.width, union.height),
+        (-10, -10, 40, 20)
+    );
+    assert!(union.contains(-10., -10.) && union.contains(29.5, 9.5));
+    assert!(!union.contains(30., 0.));
+}
+
+#[test]
+fn uniform_color_needs_every_pixel() {
+    #[allow(clippy::unwrap_used)]
+    let mut pixmap = tiny_skia::Pixmap::new(4, 3).unwrap();
+    assert_eq!(uniform_color(pixmap.as_ref()), Some([0, 0, 0, 0]));
+
+    pixmap.fill(tiny_skia::Color::from_rgba8(10, 20, 30, 255));
+    assert_eq!(uniform_color(pixmap.as_ref()), Some([10, 20, 30, 255]));
+
+    pixmap.pixels_mut()[5] = tiny_skia::PremultipliedColorU8::TRANSPARENT;
+    assert_eq!(uniform_color(pixmap.as_ref()), None);
+}
+
+#[test]
+fn opaque_format_falls_back_to_argb() {
+    assert_eq!(
+        opaque_format(&[wl_shm::Format::Argb8888, wl_shm::Format::Xrgb8888]),
+        wl_shm::Format::Xrgb8888
+    );
+    assert_eq!(opaque_format(&[]), wl_shm::Format::Argb8888);
+}
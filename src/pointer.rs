@@ -1,32 +1,197 @@
+use std::collections::HashMap;
+use std::mem;
 use std::time::Duration;
 
 use smithay_client_toolkit::reexports::csd_frame::{
     CursorIcon, FrameAction, ResizeEdge, WindowManagerCapabilities, WindowState,
 };
+use smithay_client_toolkit::reexports::protocols::wp::cursor_shape::v1::client::wp_cursor_shape_device_v1::Shape;
 
 use crate::{
     buttons::ButtonKind,
-    theme::{BORDER_SIZE, HEADER_SIZE},
+    parts::{DecorationParts, HeaderPosition},
 };
 
 /// Time to register the next click as a double click.
 ///
 /// The value is the same as the default in gtk4.
-const DOUBLE_CLICK_DURATION: Duration = Duration::from_millis(400);
+pub(crate) const DOUBLE_CLICK_DURATION: Duration = Duration::from_millis(400);
+
+/// Time the maximize button is held down to offer the tiling instead of maximizing.
+///
+/// The value is the same as the default long press time in gtk4.
+pub(crate) const LONG_PRESS_DURATION: Duration = Duration::from_millis(500);
+
+/// The handling of the clicks on the headerbar.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct ClickConfig {
+    pub double_click_action: DoubleClickAction,
+    /// Time to register the next click as a double click.
+    pub double_click_time: Duration,
+    /// The distance in logical points the pointer pressed on the headerbar travels before
+    /// the window is moved, `0` to move it right on the press.
+    pub drag_threshold: u32,
+    /// The modifier turning the presses anywhere on the decorations into the moves and the
+    /// resizes.
+    pub drag_modifier: DragModifier,
+}
+
+/// The identifier of the seat, e.g. the protocol id of its `wl_seat`.
+pub type SeatId = u32;
+
+/// The seat of the pointer handled by the [`DecorationsFrame`] methods, never used by the
+/// `wl_seat` protocol ids.
+///
+/// [`DecorationsFrame`]: smithay_client_toolkit::reexports::csd_frame::DecorationsFrame
+pub(crate) const DEFAULT_SEAT: SeatId = 0;
+
+/// The states of the pointers of all the seats, so the pointers hovering the different buttons
+/// don't reset each other.
+#[derive(Debug, Default)]
+pub(crate) struct Pointers {
+    seats: HashMap<SeatId, MouseState>,
+}
+
+impl Pointers {
+    /// The state of the pointer of the `seat`.
+    pub fn seat(&mut self, seat: SeatId) -> &mut MouseState {
+        self.seats.entry(seat).or_default()
+    }
+
+    /// The pointer of the `seat` left the decorations frame.
+    ///
+    /// Returns the location it left from, so its hover highlight could be cleared.
+    pub fn left(&mut self, seat: SeatId) -> Location {
+        self.seats
+            .get_mut(&seat)
+            .map(|mouse| {
+                let location = mouse.location;
+                mouse.left();
+                location
+            })
+            .unwrap_or_default()
+    }
+
+    /// The location of the pointer of the `seat`.
+    #[cfg(feature = "automation")]
+    pub fn location(&self, seat: SeatId) -> Location {
+        self.seats
+            .get(&seat)
+            .map_or(Location::None, |mouse| mouse.location)
+    }
+
+    /// The resize edges and corners under any of the pointers.
+    pub fn hovered_edges(&self) -> Vec<Location> {
+        self.seats
+            .values()
+            .map(|mouse| mouse.location)
+            .filter(|location| !location.edge_parts().is_empty())
+            .collect()
+    }
+
+    /// The buttons under any of the pointers.
+    pub fn hovered_buttons(&self) -> Vec<ButtonKind> {
+        self.seats
+            .values()
+            .filter_map(|mouse| match mouse.location {
+                Location::Button(kind) => Some(kind),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// The buttons pressed by any of the pointers.
+    pub fn pressed_buttons(&self) -> Vec<ButtonKind> {
+        self.seats
+            .values()
+            .filter_map(MouseState::pressed_button)
+            .collect()
+    }
+}
 
 /// The state of the mouse input inside the decorations frame.
 #[derive(Debug, Default)]
 pub(crate) struct MouseState {
     pub location: Location,
 
+    /// The primary button is held down.
+    pub held: bool,
+
     /// The surface local location inside the surface.
     position: (f64, f64),
 
     /// The instant of the last click.
     last_normal_click: Option<Duration>,
+
+    /// The position of the press on the headerbar waiting for the drag threshold to move the
+    /// window.
+    press: Option<(f64, f64)>,
+
+    /// The scroll over the headerbar accumulated towards the [`SCROLL_THRESHOLD`].
+    scroll: f64,
+
+    /// The instant the maximize button was pressed, waiting for the [`LONG_PRESS_DURATION`].
+    maximize_press: Option<Duration>,
+
+    /// The maximize button was held down long enough, its release doesn't maximize.
+    long_pressed: bool,
+
+    /// The press with the [`DragModifier`] moved or resized the window, its release is ignored.
+    modifier_press: bool,
 }
 
+/// The scroll in the `wl_pointer` axis units, one notch of the wheel, performing the
+/// [`ScrollAction::Maximize`].
+const SCROLL_THRESHOLD: f64 = 10.;
+
 impl MouseState {
+    /// The click with the `modifiers` held, moving the window with the [`FrameClick::Normal`]
+    /// one and resizing it from the edge nearest to the pointer with the
+    /// [`FrameClick::Alternate`] one when the [`DragModifier`] is held.
+    ///
+    /// `None` when the click isn't taken by the modifier, and should be handled as usual.
+    /// The headerbar is `header_length` long along the `header_position`.
+    ///
+    /// [`FrameClick::Normal`]: smithay_client_toolkit::reexports::csd_frame::FrameClick::Normal
+    /// [`FrameClick::Alternate`]: smithay_client_toolkit::reexports::csd_frame::FrameClick::Alternate
+    pub fn modifier_click(
+        &mut self,
+        alternate: bool,
+        pressed: bool,
+        modifiers: Modifiers,
+        resizable: bool,
+        config: &ClickConfig,
+        (header_length, header_position): (u32, HeaderPosition),
+    ) -> Option<Option<FrameAction>> {
+        if !pressed {
+            // The release of the modifier press doesn't activate the button under it.
+            return mem::take(&mut self.modifier_press).then_some(None);
+        }
+        if !config.drag_modifier.is_held(modifiers) {
+            return None;
+        }
+
+        self.modifier_press = true;
+        self.last_normal_click = None;
+        self.press = None;
+        self.maximize_press = None;
+        if !alternate {
+            return Some(Some(FrameAction::Move));
+        }
+
+        let edge = resizable
+            .then(|| {
+                nearest_edge(
+                    self.location,
+                    self.position.0,
+                    header_length,
+                    header_position,
+                )
+            })
+            .flatten();
+        Some(edge.map(FrameAction::Resize))
+    }
+
     /// The normal click on decorations frame was made.
     pub fn click(
         &mut self,
@@ -35,9 +200,20 @@ impl MouseState {
         resizable: bool,
         state: &WindowState,
         wm_capabilities: &WindowManagerCapabilities,
+        config: &ClickConfig,
     ) -> Option<FrameAction> {
-        let maximized = state.contains(WindowState::MAXIMIZED);
+        if !pressed {
+            self.press = None;
+            self.maximize_press = None;
+        }
+
         let action = match self.location {
+            Location::Button(ButtonKind::Maximize) if pressed => {
+                self.maximize_press = Some(timestamp);
+                self.long_pressed = false;
+                return None;
+            }
+            Location::Button(_) if !pressed && mem::take(&mut self.long_pressed) => return None,
             Location::Top if resizable => FrameAction::Resize(ResizeEdge::Top),
             Location::TopLeft if resizable => FrameAction::Resize(ResizeEdge::TopLeft),
             Location::Left if resizable => FrameAction::Resize(ResizeEdge::Left),
@@ -46,35 +222,62 @@ impl MouseState {
             Location::BottomRight if resizable => FrameAction::Resize(ResizeEdge::BottomRight),
             Location::Right if resizable => FrameAction::Resize(ResizeEdge::Right),
             Location::TopRight if resizable => FrameAction::Resize(ResizeEdge::TopRight),
-            Location::Button(ButtonKind::Close) if !pressed => FrameAction::Close,
-            Location::Button(ButtonKind::Maximize) if !pressed && !maximized => {
-                FrameAction::Maximize
-            }
-            Location::Button(ButtonKind::Maximize) if !pressed && maximized => {
-                FrameAction::UnMaximize
-            }
-            Location::Button(ButtonKind::Minimize) if !pressed => FrameAction::Minimize,
-            Location::Head
-                if pressed && wm_capabilities.contains(WindowManagerCapabilities::MAXIMIZE) =>
-            {
-                match self.last_normal_click.replace(timestamp) {
-                    Some(last) if timestamp.saturating_sub(last) < DOUBLE_CLICK_DURATION => {
-                        if maximized {
-                            FrameAction::UnMaximize
-                        } else {
-                            FrameAction::Maximize
-                        }
-                    }
-                    _ => FrameAction::Move,
+            Location::Button(kind) if !pressed => return kind.action(state),
+            Location::Head if pressed => match self.last_normal_click.replace(timestamp) {
+                Some(last) if timestamp.saturating_sub(last) < config.double_click_time => self
+                    .header_action(config.double_click_action, state, wm_capabilities)
+                    .unwrap_or(FrameAction::Move),
+                // Wait for the pointer to travel past the threshold.
+                _ if config.drag_threshold > 0 => {
+                    self.press = Some(self.position);
+                    return None;
                 }
-            }
-            Location::Head if pressed => FrameAction::Move,
+                _ => FrameAction::Move,
+            },
             _ => return None,
         };
 
         Some(action)
     }
 
+    /// The frame action for the click on the headerbar, `None` when it's not supported.
+    fn header_action(
+        &self,
+        action: DoubleClickAction,
+        state: &WindowState,
+        wm_capabilities: &WindowManagerCapabilities,
+    ) -> Option<FrameAction> {
+        match action {
+            DoubleClickAction::ToggleMaximize
+                if wm_capabilities.contains(WindowManagerCapabilities::MAXIMIZE) =>
+            {
+                if state.contains(WindowState::MAXIMIZED) {
+                    Some(FrameAction::UnMaximize)
+                } else {
+                    Some(FrameAction::Maximize)
+                }
+            }
+            DoubleClickAction::Minimize
+                if wm_capabilities.contains(WindowManagerCapabilities::MINIMIZE) =>
+            {
+                Some(FrameAction::Minimize)
+            }
+            DoubleClickAction::Menu
+                if wm_capabilities.contains(WindowManagerCapabilities::WINDOW_MENU) =>
+            {
+                let (x, y) = self.menu_position();
+                Some(FrameAction::ShowMenu(x, y))
+            }
+            _ => None,
+        }
+    }
+
+    /// The position of the window menu relative to the upright headerbar, moved to the base
+    /// surface by the frame.
+    fn menu_position(&self) -> (i32, i32) {
+        (self.position.0 as i32, self.position.1 as i32)
+    }
+
     /// Alternative click on decorations frame was made.
     pub fn alternate_click(
         &mut self,
@@ -88,47 +291,320 @@ impl MouseState {
             Location::Head | Location::Button(_)
                 if pressed && wm_capabilities.contains(WindowManagerCapabilities::WINDOW_MENU) =>
             {
-                Some(FrameAction::ShowMenu(
-                    // XXX this could be one 1pt off when the frame is not maximized, but it's not
-                    // like it really matters in the end.
-                    self.position.0 as i32 - BORDER_SIZE as i32,
-                    // We must offset it by header size for precise position.
-                    self.position.1 as i32 - HEADER_SIZE as i32,
-                ))
+                let (x, y) = self.menu_position();
+                Some(FrameAction::ShowMenu(x, y))
             }
             _ => None,
         }
     }
 
+    /// Middle click, or the click of the other button performing the `button_action`, on
+    /// decorations frame was made.
+    pub fn button_click(
+        &mut self,
+        pressed: bool,
+        state: &WindowState,
+        wm_capabilities: &WindowManagerCapabilities,
+        button_action: MiddleClickAction,
+    ) -> Option<HeaderbarAction> {
+        // Invalidate the normal click.
+        self.last_normal_click = None;
+
+        if !pressed || self.location != Location::Head {
+            return None;
+        }
+
+        let action = match button_action {
+            MiddleClickAction::Lower => return Some(HeaderbarAction::Lower),
+            MiddleClickAction::ToggleMaximize => DoubleClickAction::ToggleMaximize,
+            MiddleClickAction::Minimize => DoubleClickAction::Minimize,
+            MiddleClickAction::Menu => DoubleClickAction::Menu,
+            MiddleClickAction::None => return None,
+        };
+
+        self.header_action(action, state, wm_capabilities)
+            .map(HeaderbarAction::Frame)
+    }
+
+    /// The vertical scroll by `delta` in the `wl_pointer` axis units, growing downwards, over
+    /// the decorations frame.
+    pub fn scroll(
+        &mut self,
+        delta: f64,
+        state: &WindowState,
+        wm_capabilities: &WindowManagerCapabilities,
+        scroll_action: ScrollAction,
+    ) -> Option<HeaderbarAction> {
+        if self.location != Location::Head {
+            self.scroll = 0.;
+            return None;
+        }
+
+        match scroll_action {
+            ScrollAction::None => None,
+            ScrollAction::Custom => Some(HeaderbarAction::Scroll(delta)),
+            ScrollAction::Maximize => {
+                // The kinetic scroll is accumulated, and starts over when it turns around.
+                if self.scroll * delta < 0. {
+                    self.scroll = 0.;
+                }
+                self.scroll += delta;
+                if self.scroll.abs() < SCROLL_THRESHOLD {
+                    return None;
+                }
+
+                let up = mem::take(&mut self.scroll) < 0.;
+                if !wm_capabilities.contains(WindowManagerCapabilities::MAXIMIZE) {
+                    return None;
+                }
+
+                match (up, state.contains(WindowState::MAXIMIZED)) {
+                    (true, false) => Some(HeaderbarAction::Frame(FrameAction::Maximize)),
+                    (false, true) => Some(HeaderbarAction::Frame(FrameAction::UnMaximize)),
+                    _ => None,
+                }
+            }
+        }
+    }
+
     /// The mouse moved inside the decorations frame.
-    pub fn moved(&mut self, location: Location, x: f64, y: f64, resizable: bool) -> CursorIcon {
+    pub fn moved(
+        &mut self,
+        location: Location,
+        x: f64,
+        y: f64,
+        resizable: bool,
+        cursors: &CursorMap,
+    ) -> CursorIcon {
         self.location = location;
         self.position = (x, y);
-        match self.location {
-            _ if !resizable => CursorIcon::Default,
-            Location::Top => CursorIcon::NResize,
-            Location::TopRight => CursorIcon::NeResize,
-            Location::Right => CursorIcon::EResize,
-            Location::BottomRight => CursorIcon::SeResize,
-            Location::Bottom => CursorIcon::SResize,
-            Location::BottomLeft => CursorIcon::SwResize,
-            Location::Left => CursorIcon::WResize,
-            Location::TopLeft => CursorIcon::NwResize,
-            _ => CursorIcon::Default,
+        cursors.icon(location, resizable)
+    }
+
+    /// The move of the window once the pointer pressed on the headerbar travelled past the
+    /// `drag_threshold`.
+    pub fn drag(&mut self, drag_threshold: u32) -> Option<FrameAction> {
+        let (x, y) = self.press?;
+        let distance = (self.position.0 - x).hypot(self.position.1 - y);
+        if self.location == Location::Head && distance < drag_threshold as f64 {
+            return None;
+        }
+
+        self.press = None;
+        Some(FrameAction::Move)
+    }
+
+    /// Whether the maximize button is held down for the [`LONG_PRESS_DURATION`] at the
+    /// `timestamp`, true only once per press.
+    pub fn long_press(&mut self, timestamp: Duration) -> bool {
+        let on_maximize = self.held && self.location == Location::Button(ButtonKind::Maximize);
+        match self.maximize_press {
+            Some(press)
+                if on_maximize && timestamp.saturating_sub(press) >= LONG_PRESS_DURATION =>
+            {
+                self.maximize_press = None;
+                self.long_pressed = true;
+                true
+            }
+            _ => false,
         }
     }
 
     /// The mouse left the decorations frame.
+    /// The button under the pointer held down, drawn pressed.
+    pub fn pressed_button(&self) -> Option<ButtonKind> {
+        match self.location {
+            Location::Button(kind) if self.held => Some(kind),
+            _ => None,
+        }
+    }
+
     pub fn left(&mut self) {
-        // Reset only the location.
+        // Reset only the location and the pending drag.
         self.location = Location::None;
+        self.press = None;
+        self.maximize_press = None;
     }
 }
 
+/// The resize edge nearest to the pointer at the `location`, the one of the border under it or
+/// the one picked by the third of the headerbar `header_length` long it's at `x` along, upright.
+fn nearest_edge(
+    location: Location,
+    x: f64,
+    header_length: u32,
+    header_position: HeaderPosition,
+) -> Option<ResizeEdge> {
+    let edge = match location {
+        Location::Top => ResizeEdge::Top,
+        Location::TopRight => ResizeEdge::TopRight,
+        Location::Right => ResizeEdge::Right,
+        Location::BottomRight => ResizeEdge::BottomRight,
+        Location::Bottom => ResizeEdge::Bottom,
+        Location::BottomLeft => ResizeEdge::BottomLeft,
+        Location::Left => ResizeEdge::Left,
+        Location::TopLeft => ResizeEdge::TopLeft,
+        Location::Head | Location::Button(_) => {
+            let third = f64::from(header_length) / 3.;
+            // The upright header along the left edge starts at the bottom.
+            let edges = match header_position {
+                HeaderPosition::Top => [ResizeEdge::TopLeft, ResizeEdge::Top, ResizeEdge::TopRight],
+                HeaderPosition::Left => [
+                    ResizeEdge::BottomLeft,
+                    ResizeEdge::Left,
+                    ResizeEdge::TopLeft,
+                ],
+            };
+            match x {
+                x if x < third => edges[0],
+                x if x < 2. * third => edges[1],
+                _ => edges[2],
+            }
+        }
+        Location::None => return None,
+    };
+
+    Some(edge)
+}
+
+/// The keyboard modifiers held during the click, see [`AdwaitaFrame::on_button`].
+///
+/// [`AdwaitaFrame::on_button`]: crate::AdwaitaFrame::on_button
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub struct Modifiers {
+    pub ctrl: bool,
+    pub alt: bool,
+    pub shift: bool,
+    /// The "Windows" key.
+    pub logo: bool,
+}
+
+/// The modifier key turning the presses anywhere on the decorations into the moves of the
+/// window, and the presses of the right button into its resizes from the nearest edge.
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum DragModifier {
+    /// The Alt key, like most of the desktops.
+    #[default]
+    Alt,
+    /// The "Windows" key.
+    Logo,
+    /// The presses are never taken by the modifiers.
+    None,
+}
+
+impl DragModifier {
+    /// Whether the key is held in the `modifiers`.
+    fn is_held(self, modifiers: Modifiers) -> bool {
+        match self {
+            DragModifier::Alt => modifiers.alt,
+            DragModifier::Logo => modifiers.logo,
+            DragModifier::None => false,
+        }
+    }
+}
+
+/// The `wp_cursor_shape_v1` shape of the cursor returned by the frame, for the embedders
+/// setting the cursor with the cursor shape protocol themselves.
+///
+/// The cursors the frame never returns map to [`Shape::Default`], while [`CursorIcon::name`]
+/// gives the cursor theme name instead.
+pub fn cursor_shape(cursor: CursorIcon) -> Shape {
+    match cursor {
+        CursorIcon::NResize => Shape::NResize,
+        CursorIcon::NeResize => Shape::NeResize,
+        CursorIcon::EResize => Shape::EResize,
+        CursorIcon::SeResize => Shape::SeResize,
+        CursorIcon::SResize => Shape::SResize,
+        CursorIcon::SwResize => Shape::SwResize,
+        CursorIcon::WResize => Shape::WResize,
+        CursorIcon::NwResize => Shape::NwResize,
+        _ => Shape::Default,
+    }
+}
+
+/// The cursors shown over the parts of the decorations frame, see
+/// [`FrameConfig::cursor_map`].
+///
+/// Each [`Location`] has the typed [`CursorIcon`] returned by the frame, and optionally the
+/// name of the cursor in the cursor theme used in place of [`CursorIcon::name`], like
+/// `size_bdiag` for the themes following the X11 names.
+///
+/// [`FrameConfig::cursor_map`]: crate::FrameConfig::cursor_map
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CursorMap {
+    cursors: HashMap<Location, (CursorIcon, Option<String>)>,
+}
+
+impl Default for CursorMap {
+    /// The resize cursors along the edges, the default cursor elsewhere.
+    fn default() -> Self {
+        let cursors = [
+            (Location::Top, CursorIcon::NResize),
+            (Location::TopRight, CursorIcon::NeResize),
+            (Location::Right, CursorIcon::EResize),
+            (Location::BottomRight, CursorIcon::SeResize),
+            (Location::Bottom, CursorIcon::SResize),
+            (Location::BottomLeft, CursorIcon::SwResize),
+            (Location::Left, CursorIcon::WResize),
+            (Location::TopLeft, CursorIcon::NwResize),
+        ];
+
+        Self {
+            cursors: cursors
+                .into_iter()
+                .map(|(location, icon)| (location, (icon, None)))
+                .collect(),
+        }
+    }
+}
+
+impl CursorMap {
+    /// Show the `icon` over the `location`, dropping its cursor name.
+    pub fn icon_for(mut self, location: Location, icon: CursorIcon) -> Self {
+        self.cursors.insert(location, (icon, None));
+        self
+    }
+
+    /// Show the cursor of the theme called `name` over the `location`, with the `icon` for the
+    /// embedders setting the cursors by the [`CursorIcon`].
+    pub fn name_for(
+        mut self,
+        location: Location,
+        icon: CursorIcon,
+        name: impl Into<String>,
+    ) -> Self {
+        self.cursors.insert(location, (icon, Some(name.into())));
+        self
+    }
+
+    /// The cursor over the `location`, the resize cursors are shown only on the `resizable`
+    /// windows.
+    pub fn icon(&self, location: Location, resizable: bool) -> CursorIcon {
+        if !resizable && !location.edge_parts().is_empty() {
+            return CursorIcon::Default;
+        }
+
+        self.cursors
+            .get(&location)
+            .map_or(CursorIcon::Default, |(icon, _)| *icon)
+    }
+
+    /// The name of the cursor over the `location` in the cursor theme, see [`Self::icon`].
+    pub fn name(&self, location: Location, resizable: bool) -> &str {
+        match self.cursors.get(&location) {
+            Some((_, Some(name))) if resizable || location.edge_parts().is_empty() => name,
+            _ => self.icon(location, resizable).name(),
+        }
+    }
+}
+
+/// The part of the decorations frame under the given point.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
 pub enum Location {
+    /// Not on the decorations.
     #[default]
     None,
+    /// The headerbar, excluding the buttons.
     Head,
     Top,
     TopRight,
@@ -138,5 +614,441 @@ pub enum Location {
     BottomLeft,
     Left,
     TopLeft,
+    /// The titlebar button.
     Button(ButtonKind),
 }
+
+impl Location {
+    /// The border parts along the resize edge or the corner, empty for the other locations.
+    pub(crate) fn edge_parts(self) -> &'static [usize] {
+        match self {
+            Location::Top => &[DecorationParts::TOP],
+            Location::TopRight => &[DecorationParts::TOP, DecorationParts::RIGHT],
+            Location::Right => &[DecorationParts::RIGHT],
+            Location::BottomRight => &[DecorationParts::BOTTOM, DecorationParts::RIGHT],
+            Location::Bottom => &[DecorationParts::BOTTOM],
+            Location::BottomLeft => &[DecorationParts::BOTTOM, DecorationParts::LEFT],
+            Location::Left => &[DecorationParts::LEFT],
+            Location::TopLeft => &[DecorationParts::TOP, DecorationParts::LEFT],
+            _ => &[],
+        }
+    }
+}
+
+/// The action performed on the double click on the headerbar.
+///
+/// Mirrors the `action-double-click-titlebar` key of `org.gnome.desktop.wm.preferences`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum DoubleClickAction {
+    /// Maximize the window, or unmaximize it when it's maximized.
+    #[default]
+    ToggleMaximize,
+    /// Minimize the window.
+    Minimize,
+    /// Show the window menu.
+    Menu,
+    /// Do nothing.
+    None,
+}
+
+impl DoubleClickAction {
+    /// Parse the value of the `action-double-click-titlebar` setting.
+    ///
+    /// The actions that can't be performed by the clients, like shading or lowering the window,
+    /// are treated as [`Self::None`].
+    pub(crate) fn from_gsettings(value: &str) -> Option<Self> {
+        let action = match value {
+            "toggle-maximize" | "toggle-maximize-horizontally" | "toggle-maximize-vertically" => {
+                Self::ToggleMaximize
+            }
+            "minimize" => Self::Minimize,
+            "menu" => Self::Menu,
+            "none" | "toggle-shade" | "lower" => Self::None,
+            _ => return None,
+        };
+
+        Some(action)
+    }
+}
+
+/// The action performed on the middle click on the headerbar.
+///
+/// Mirrors the `action-middle-click-titlebar` key of `org.gnome.desktop.wm.preferences`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum MiddleClickAction {
+    /// Lower the window below the other windows.
+    #[default]
+    Lower,
+    /// Maximize the window, or unmaximize it when it's maximized.
+    ToggleMaximize,
+    /// Minimize the window.
+    Minimize,
+    /// Show the window menu.
+    Menu,
+    /// Do nothing.
+    None,
+}
+
+impl MiddleClickAction {
+    /// Parse the value of the `action-middle-click-titlebar` setting.
+    ///
+    /// Shading the window can't be performed by the clients, so it's treated as
+    /// [`Self::None`].
+    pub(crate) fn from_gsettings(value: &str) -> Option<Self> {
+        let action = match value {
+            "lower" => Self::Lower,
+            "toggle-maximize" | "toggle-maximize-horizontally" | "toggle-maximize-vertically" => {
+                Self::ToggleMaximize
+            }
+            "minimize" => Self::Minimize,
+            "menu" => Self::Menu,
+            "none" | "toggle-shade" => Self::None,
+            _ => return None,
+        };
+
+        Some(action)
+    }
+}
+
+/// The action on the scroll over the headerbar.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum ScrollAction {
+    /// Do nothing.
+    #[default]
+    None,
+    /// Maximize the window scrolling up, and unmaximize it scrolling down.
+    Maximize,
+    /// Report the scroll with [`HeaderbarAction::Scroll`], e.g. to change the opacity of the
+    /// window.
+    Custom,
+}
+
+/// The action user should perform in response to the middle click or the scroll on the
+/// headerbar.
+#[derive(Debug, Clone, Copy)]
+#[non_exhaustive]
+pub enum HeaderbarAction {
+    /// The regular frame action.
+    Frame(FrameAction),
+    /// The window should be lowered below the other windows.
+    ///
+    /// There's no such request in `xdg_shell`, so it's up to the client to ask the compositor
+    /// for it, if possible.
+    Lower,
+    /// The headerbar was scrolled by the delta in the `wl_pointer` axis units, growing
+    /// downwards, with the [`ScrollAction::Custom`].
+    Scroll(f64),
+}
+
+/// The area of the screen the window would snap to when dropped, see
+/// [`AdwaitaFrame::set_snap_preview`].
+///
+/// [`AdwaitaFrame::set_snap_preview`]: crate::AdwaitaFrame::set_snap_preview
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SnapRegion {
+    /// The whole screen, the window would be maximized.
+    Maximize,
+    /// The left half of the screen.
+    Left,
+    /// The right half of the screen.
+    Right,
+    /// The top left quarter of the screen.
+    TopLeft,
+    /// The top right quarter of the screen.
+    TopRight,
+    /// The bottom left quarter of the screen.
+    BottomLeft,
+    /// The bottom right quarter of the screen.
+    BottomRight,
+}
+
+impl SnapRegion {
+    /// The border parts facing the edges of the screen the window would snap to.
+    pub(crate) fn edge_parts(self) -> &'static [usize] {
+        match self {
+            SnapRegion::Maximize => &[
+                DecorationParts::TOP,
+                DecorationParts::LEFT,
+                DecorationParts::RIGHT,
+                DecorationParts::BOTTOM,
+            ],
+            SnapRegion::Left => &[DecorationParts::LEFT],
+            SnapRegion::Right => &[DecorationParts::RIGHT],
+            SnapRegion::TopLeft => &[DecorationParts::TOP, DecorationParts::LEFT],
+            SnapRegion::TopRight => &[DecorationParts::TOP, DecorationParts::RIGHT],
+            SnapRegion::BottomLeft => &[DecorationParts::BOTTOM, DecorationParts::LEFT],
+            SnapRegion::BottomRight => &[DecorationParts::BOTTOM, DecorationParts::RIGHT],
+        }
+    }
+}
+
+#[test]
+fn double_click_action_from_gsettings() {
+    assert_eq!(
+        DoubleClickAction::from_gsettings("toggle-maximize"),
+        Some(DoubleClickAction::ToggleMaximize)
+    );
+    assert_eq!(
+        DoubleClickAction::from_gsettings("toggle-shade"),
+        Some(DoubleClickAction::None)
+    );
+    assert_eq!(
+        DoubleClickAction::from_gsettings("menu"),
+        Some(DoubleClickAction::Menu)
+    );
+    assert_eq!(DoubleClickAction::from_gsettings("unknown"), None);
+}
+
+#[test]
+fn middle_click_action_from_gsettings() {
+    assert_eq!(
+        MiddleClickAction::from_gsettings("lower"),
+        Some(MiddleClickAction::Lower)
+    );
+    assert_eq!(
+        MiddleClickAction::from_gsettings("toggle-shade"),
+        Some(MiddleClickAction::None)
+    );
+    assert_eq!(MiddleClickAction::from_gsettings(""), None);
+}
+
+#[test]
+fn scroll_maximizes_past_threshold() {
+    let state = WindowState::empty();
+    let wm_capabilities = WindowManagerCapabilities::all();
+    let mut mouse = MouseState::default();
+    mouse.moved(Location::Head, 0., 0., true, &CursorMap::default());
+
+    let scroll = |mouse: &mut MouseState, delta, state, action| {
+        mouse.scroll(delta, &state, &wm_capabilities, action)
+    };
+    assert!(scroll(&mut mouse, -6., state, ScrollAction::Maximize).is_none());
+    // Turning around starts over.
+    assert!(scroll(&mut mouse, 6., state, ScrollAction::Maximize).is_none());
+    assert!(scroll(&mut mouse, -6., state, ScrollAction::Maximize).is_none());
+    assert!(matches!(
+        scroll(&mut mouse, -6., state, ScrollAction::Maximize),
+        Some(HeaderbarAction::Frame(FrameAction::Maximize))
+    ));
+    assert!(matches!(
+        scroll(
+            &mut mouse,
+            10.,
+            WindowState::MAXIMIZED,
+            ScrollAction::Maximize
+        ),
+        Some(HeaderbarAction::Frame(FrameAction::UnMaximize))
+    ));
+
+    assert!(matches!(
+        scroll(&mut mouse, 2.5, state, ScrollAction::Custom),
+        Some(HeaderbarAction::Scroll(delta)) if delta == 2.5
+    ));
+    assert!(scroll(&mut mouse, -20., state, ScrollAction::None).is_none());
+
+    mouse.moved(Location::Top, 0., 0., true, &CursorMap::default());
+    assert!(scroll(&mut mouse, 2.5, state, ScrollAction::Custom).is_none());
+}
+
+#[test]
+fn resize_cursors_have_shapes() {
+    let mut mouse = MouseState::default();
+    let cursor = mouse.moved(Location::TopLeft, 0., 0., true, &CursorMap::default());
+    assert_eq!(cursor_shape(cursor), Shape::NwResize);
+
+    let cursor = mouse.moved(Location::Head, 0., 0., true, &CursorMap::default());
+    assert_eq!(cursor_shape(cursor), Shape::Default);
+}
+
+#[test]
+fn pointers_track_hover_per_seat() {
+    let mut pointers = Pointers::default();
+    pointers.seat(0).moved(
+        Location::Button(ButtonKind::Close),
+        0.,
+        0.,
+        true,
+        &CursorMap::default(),
+    );
+    pointers.seat(1).moved(
+        Location::Button(ButtonKind::Minimize),
+        0.,
+        0.,
+        true,
+        &CursorMap::default(),
+    );
+    assert_eq!(pointers.hovered_buttons().len(), 2);
+
+    // Leaving clears only the hover of the seat.
+    assert_eq!(pointers.left(1), Location::Button(ButtonKind::Minimize));
+    assert_eq!(pointers.hovered_buttons(), [ButtonKind::Close]);
+    assert_eq!(pointers.left(1), Location::None);
+    assert_eq!(pointers.left(2), Location::None);
+}
+
+#[test]
+fn hovered_corners_hint_both_edges() {
+    let mut pointers = Pointers::default();
+    pointers
+        .seat(0)
+        .moved(Location::TopLeft, 0., 0., true, &CursorMap::default());
+    pointers
+        .seat(1)
+        .moved(Location::Head, 0., 0., true, &CursorMap::default());
+    assert_eq!(pointers.hovered_edges(), [Location::TopLeft]);
+    assert_eq!(
+        Location::TopLeft.edge_parts(),
+        [DecorationParts::TOP, DecorationParts::LEFT]
+    );
+}
+
+#[test]
+fn move_waits_for_drag_threshold() {
+    let config = ClickConfig {
+        double_click_action: DoubleClickAction::ToggleMaximize,
+        double_click_time: DOUBLE_CLICK_DURATION,
+        drag_threshold: 8,
+        drag_modifier: DragModifier::Alt,
+    };
+    let state = WindowState::empty();
+    let wm_capabilities = WindowManagerCapabilities::all();
+
+    let mut mouse = MouseState::default();
+    mouse.moved(Location::Head, 10., 10., true, &CursorMap::default());
+    let action = mouse.click(
+        Duration::ZERO,
+        true,
+        true,
+        &state,
+        &wm_capabilities,
+        &config,
+    );
+    assert!(action.is_none());
+
+    mouse.moved(Location::Head, 14., 10., true, &CursorMap::default());
+    assert!(mouse.drag(config.drag_threshold).is_none());
+    mouse.moved(Location::Head, 20., 10., true, &CursorMap::default());
+    assert!(matches!(
+        mouse.drag(config.drag_threshold),
+        Some(FrameAction::Move)
+    ));
+    assert!(mouse.drag(config.drag_threshold).is_none());
+
+    // The release cancels the drag.
+    let later = DOUBLE_CLICK_DURATION * 2;
+    mouse.click(later, true, true, &state, &wm_capabilities, &config);
+    mouse.click(later, false, true, &state, &wm_capabilities, &config);
+    mouse.moved(Location::Head, 40., 10., true, &CursorMap::default());
+    assert!(mouse.drag(config.drag_threshold).is_none());
+}
+
+#[test]
+fn snap_preview_covers_facing_borders() {
+    assert_eq!(SnapRegion::Left.edge_parts(), [DecorationParts::LEFT]);
+    assert_eq!(
+        SnapRegion::BottomRight.edge_parts(),
+        [DecorationParts::BOTTOM, DecorationParts::RIGHT]
+    );
+    assert_eq!(SnapRegion::Maximize.edge_parts().len(), 4);
+}
+
+#[test]
+fn long_press_on_maximize_skips_maximize() {
+    let config = ClickConfig {
+        double_click_action: DoubleClickAction::ToggleMaximize,
+        double_click_time: DOUBLE_CLICK_DURATION,
+        drag_threshold: 0,
+        drag_modifier: DragModifier::Alt,
+    };
+    let state = WindowState::empty();
+    let wm_capabilities = WindowManagerCapabilities::all();
+    let click = |mouse: &mut MouseState, timestamp, pressed| {
+        mouse.held = pressed;
+        mouse.click(timestamp, pressed, true, &state, &wm_capabilities, &config)
+    };
+
+    let mut mouse = MouseState::default();
+    let maximize = Location::Button(ButtonKind::Maximize);
+    mouse.moved(maximize, 0., 0., true, &CursorMap::default());
+
+    // The short press maximizes.
+    assert!(click(&mut mouse, Duration::ZERO, true).is_none());
+    assert!(!mouse.long_press(LONG_PRESS_DURATION / 2));
+    assert!(matches!(
+        click(&mut mouse, LONG_PRESS_DURATION / 2, false),
+        Some(FrameAction::Maximize)
+    ));
+
+    // The long one is reported once, instead of maximizing.
+    let start = LONG_PRESS_DURATION * 2;
+    click(&mut mouse, start, true);
+    assert!(mouse.long_press(start + LONG_PRESS_DURATION));
+    assert!(!mouse.long_press(start + LONG_PRESS_DURATION * 2));
+    assert!(click(&mut mouse, start + LONG_PRESS_DURATION * 2, false).is_none());
+}
+
+#[test]
+fn alt_drag_moves_and_resizes() {
+    let config = ClickConfig {
+        double_click_action: DoubleClickAction::ToggleMaximize,
+        double_click_time: DOUBLE_CLICK_DURATION,
+        drag_threshold: 8,
+        drag_modifier: DragModifier::Alt,
+    };
+    let alt = Modifiers {
+        alt: true,
+        ..Default::default()
+    };
+    let header = (300, HeaderPosition::Top);
+
+    let mut mouse = MouseState::default();
+    let close = Location::Button(ButtonKind::Close);
+    mouse.moved(close, 290., 10., true, &CursorMap::default());
+
+    // The press on the button moves the window, and its release doesn't close it.
+    assert!(matches!(
+        mouse.modifier_click(false, true, alt, true, &config, header),
+        Some(Some(FrameAction::Move))
+    ));
+    assert!(matches!(
+        mouse.modifier_click(false, false, alt, true, &config, header),
+        Some(None)
+    ));
+    assert!(mouse
+        .modifier_click(false, true, Modifiers::default(), true, &config, header)
+        .is_none());
+
+    // The right press resizes from the corner nearest to the pointer.
+    assert!(matches!(
+        mouse.modifier_click(true, true, alt, true, &config, header),
+        Some(Some(FrameAction::Resize(ResizeEdge::TopRight)))
+    ));
+    mouse.moved(Location::Left, 2., 100., true, &CursorMap::default());
+    assert!(matches!(
+        mouse.modifier_click(true, true, alt, true, &config, header),
+        Some(Some(FrameAction::Resize(ResizeEdge::Left)))
+    ));
+    assert!(matches!(
+        mouse.modifier_click(true, true, alt, false, &config, header),
+        Some(None)
+    ));
+    assert_eq!(
+        nearest_edge(Location::Head, 10., 300, HeaderPosition::Left),
+        Some(ResizeEdge::BottomLeft)
+    );
+}
+
+#[cfg(feature = "automation")]
+#[test]
+fn pointers_report_seat_location() {
+    let mut pointers = Pointers::default();
+    let maximize = Location::Button(ButtonKind::Maximize);
+    pointers
+        .seat(0)
+        .moved(maximize, 0., 0., true, &CursorMap::default());
+
+    assert_eq!(pointers.location(0), maximize);
+    assert_eq!(pointers.location(1), Location::None);
+    pointers.left(0);
+    assert_eq!(pointers.location(0), Location::None);
+}
@@ -0,0 +1,341 @@
+//! Drawing of the decoration parts on another thread, see [`FrameConfig::threaded_rendering`].
+//!
+//! The state the parts are drawn from is moved into the job sent to the thread, and moved back
+//! with the drawn pixmaps, which are copied into the buffers of the parts and presented on the
+//! thread of the frame. When the drawing panics, the state is moved back without the pixmaps
+//! and the thread is gone.
+//!
+//! [`FrameConfig::threaded_rendering`]: crate::FrameConfig::threaded_rendering
+use std::fmt;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::sync::Arc;
+use std::thread;
+
+use smithay_client_toolkit::reexports::csd_frame::WindowState;
+use tiny_skia::Pixmap;
+
+use crate::{
+    buttons::Buttons,
+    icon::Icon,
+    shadow::Shadow,
+    theme::ColorTheme,
+    title::{TitlePixmap, TitleText},
+    ButtonKind, HeaderPosition, Location, OutlineStyle, PartPainter, Rect, ShadowPolicy,
+    SnapRegion, TitleAlignment,
+};
+
+/// The callback invoked on the render thread once the job is done, see
+/// [`AdwaitaFrame::on_render_ready`].
+///
+/// [`AdwaitaFrame::on_render_ready`]: crate::AdwaitaFrame::on_render_ready
+#[derive(Clone)]
+pub(crate) struct RenderReadyCallback(pub Arc<dyn Fn() + Send + Sync>);
+
+impl fmt::Debug for RenderReadyCallback {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RenderReadyCallback")
+            .finish_non_exhaustive()
+    }
+}
+
+/// The part to draw on the render thread.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct RenderTarget {
+    pub idx: usize,
+    /// The rectangle of the buffer, see `PartPainter::buffer_rect`.
+    pub rect: Rect,
+    pub scale: u32,
+    pub opaque: bool,
+}
+
+/// The owned state of the [`PartPainter`] with the parts to draw, and their pixmaps once the
+/// job is done.
+#[derive(Debug)]
+pub(crate) struct RenderJob {
+    pub state: WindowState,
+    pub theme: ColorTheme,
+    pub buttons: Buttons,
+    pub shadow: Shadow,
+    pub title_text: Option<TitleText>,
+    pub title_pixmap: Option<TitlePixmap>,
+    pub subtitle_text: Option<TitleText>,
+    pub icon: Option<Icon>,
+    pub hovered: Vec<ButtonKind>,
+    pub pressed: Vec<ButtonKind>,
+    pub resize_hint: Vec<Location>,
+    pub progress: Option<f32>,
+    pub snap_preview: Option<SnapRegion>,
+    pub resizable: bool,
+    pub rtl: bool,
+    pub title_alignment: TitleAlignment,
+    pub header_position: HeaderPosition,
+    pub draw_header: bool,
+    pub draw_borders: bool,
+    pub draw_shadow: bool,
+    pub shadow_policy: ShadowPolicy,
+    pub floating: f32,
+    pub corner_radius: u32,
+    pub activation: f32,
+    pub outline: Option<(OutlineStyle, Rect)>,
+    pub targets: Vec<RenderTarget>,
+    /// The pixmaps of the targets, `None` when the pixmap couldn't be created.
+    pub rendered: Vec<Option<Pixmap>>,
+    /// Whether the drawing panicked, so the targets weren't drawn and the thread is gone.
+    pub panicked: bool,
+    pub on_ready: Option<RenderReadyCallback>,
+}
+
+impl RenderJob {
+    fn painter(&mut self) -> PartPainter<'_> {
+        PartPainter {
+            state: self.state,
+            theme: &self.theme,
+            buttons: &mut self.buttons,
+            shadow: &mut self.shadow,
+            title_text: self.title_text.as_mut(),
+            title_pixmap: self.title_pixmap.as_mut(),
+            subtitle_text: self.subtitle_text.as_mut(),
+            icon: self.icon.as_mut(),
+            hovered: self.hovered.clone(),
+            pressed: self.pressed.clone(),
+            resize_hint: self.resize_hint.clone(),
+            progress: self.progress,
+            snap_preview: self.snap_preview,
+            resizable: self.resizable,
+            rtl: self.rtl,
+            title_alignment: self.title_alignment,
+            header_position: self.header_position,
+            draw_header: self.draw_header,
+            draw_borders: self.draw_borders,
+            draw_shadow: self.draw_shadow,
+            shadow_policy: self.shadow_policy,
+            floating: self.floating,
+            corner_radius: self.corner_radius,
+            activation: self.activation,
+            outline: self.outline,
+            #[cfg(feature = "debug-overlay")]
+            debug: None,
+        }
+    }
+
+    /// Draw the targets into the new pixmaps.
+    fn render(&mut self) {
+        let targets = self.targets.clone();
+        let mut painter = self.painter();
+        let rendered = targets
+            .iter()
+            .map(|target| {
+                let (width, height) = (target.rect.width, target.rect.height);
+                let mut pixmap = Pixmap::new(width * target.scale, height * target.scale)?;
+                painter.draw(target.idx, target.rect, target.scale, &mut pixmap.as_mut());
+                Some(pixmap)
+            })
+            .collect();
+        self.rendered = rendered;
+    }
+}
+
+/// The thread drawing the parts, with at most one job in flight.
+#[derive(Debug)]
+pub(crate) struct RenderThread {
+    jobs: Sender<Box<RenderJob>>,
+    done: Receiver<Box<RenderJob>>,
+    /// Whether the job was sent and not taken back yet.
+    in_flight: bool,
+    /// Set by the thread once the job is done.
+    ready: Arc<AtomicBool>,
+}
+
+impl RenderThread {
+    pub fn spawn() -> Option<Self> {
+        Self::spawn_with(RenderJob::render)
+    }
+
+    /// Spawn the thread doing the jobs with the `render` function.
+    fn spawn_with(render: fn(&mut RenderJob)) -> Option<Self> {
+        let (jobs, jobs_rx) = mpsc::channel::<Box<RenderJob>>();
+        let (done_tx, done) = mpsc::channel();
+        let ready = Arc::new(AtomicBool::new(false));
+
+        let thread_ready = ready.clone();
+        let spawned = thread::Builder::new()
+            .name("sctk-adwaita-render".into())
+            .spawn(move || {
+                for mut job in jobs_rx {
+                    let panicked =
+                        panic::catch_unwind(AssertUnwindSafe(|| render(&mut job))).is_err();
+                    job.panicked = panicked;
+                    let on_ready = job.on_ready.take();
+                    thread_ready.store(true, Ordering::Release);
+                    if done_tx.send(job).is_err() {
+                        break;
+                    }
+                    if let Some(on_ready) = on_ready {
+                        (on_ready.0)();
+                    }
+                    if panicked {
+                        break;
+                    }
+                }
+            });
+        if let Err(err) = spawned {
+            log::warn!("failed to spawn the render thread: {err}");
+            return None;
+        }
+
+        Some(Self {
+            jobs,
+            done,
+            in_flight: false,
+            ready,
+        })
+    }
+
+    /// Send the job to the thread, or give it back when the thread is gone.
+    pub fn send(&mut self, job: Box<RenderJob>) -> Result<(), Box<RenderJob>> {
+        self.jobs.send(job).map_err(|err| err.0)?;
+        self.in_flight = true;
+        Ok(())
+    }
+
+    pub fn in_flight(&self) -> bool {
+        self.in_flight
+    }
+
+    /// Whether the job in flight is done and could be taken without waiting.
+    pub fn is_ready(&self) -> bool {
+        self.in_flight && self.ready.load(Ordering::Acquire)
+    }
+
+    /// Take the job back if it's done.
+    pub fn try_finish(&mut self) -> Option<Box<RenderJob>> {
+        if !self.in_flight {
+            return None;
+        }
+
+        match self.done.try_recv() {
+            Ok(job) => Some(self.finished(job)),
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => {
+                log::error!("the render thread is gone with its job");
+                self.in_flight = false;
+                None
+            }
+        }
+    }
+
+    /// Take the job back, waiting for it if needed.
+    pub fn wait(&mut self) -> Option<Box<RenderJob>> {
+        if !self.in_flight {
+            return None;
+        }
+
+        match self.done.recv() {
+            Ok(job) => Some(self.finished(job)),
+            Err(_) => {
+                log::error!("the render thread is gone with its job");
+                self.in_flight = false;
+                None
+            }
+        }
+    }
+
+    fn finished(&mut self, job: Box<RenderJob>) -> Box<RenderJob> {
+        self.in_flight = false;
+        self.ready.store(false, Ordering::Release);
+        job
+    }
+}
+
+#[cfg(test)]
+fn header_job(width: u32, scale: u32) -> RenderJob {
+    use crate::{parts::DecorationParts, theme::HEADER_SIZE};
+
+    RenderJob {
+        state: WindowState::ACTIVATED,
+        theme: ColorTheme::default(),
+        buttons: Buttons::default(),
+        shadow: Shadow::default(),
+        title_text: None,
+        title_pixmap: None,
+        subtitle_text: None,
+        icon: None,
+        hovered: vec![ButtonKind::Close],
+        pressed: Vec::new(),
+        resize_hint: Vec::new(),
+        progress: Some(0.5),
+        snap_preview: None,
+        resizable: true,
+        rtl: false,
+        title_alignment: TitleAlignment::Center,
+        header_position: HeaderPosition::Top,
+        draw_header: true,
+        draw_borders: true,
+        draw_shadow: true,
+        shadow_policy: ShadowPolicy::Auto,
+        floating: 1.,
+        corner_radius: crate::theme::CORNER_RADIUS,
+        activation: 1.,
+        outline: None,
+        targets: vec![RenderTarget {
+            idx: DecorationParts::HEADER,
+            rect: Rect {
+                x: 0,
+                y: -(HEADER_SIZE as i32),
+                width,
+                height: HEADER_SIZE,
+            },
+            scale,
+            opaque: false,
+        }],
+        rendered: Vec::new(),
+        panicked: false,
+        on_ready: None,
+    }
+}
+
+#[test]
+fn render_thread_draws_targets() {
+    let notified = Arc::new(AtomicBool::new(false));
+    let mut job = header_job(200, 2);
+    let on_ready = notified.clone();
+    job.on_ready = Some(RenderReadyCallback(Arc::new(move || {
+        on_ready.store(true, Ordering::Release)
+    })));
+
+    #[allow(clippy::unwrap_used)]
+    let mut render_thread = RenderThread::spawn().unwrap();
+    assert!(render_thread.send(Box::new(job)).is_ok());
+    assert!(render_thread.in_flight());
+    #[allow(clippy::unwrap_used)]
+    let done = render_thread.wait().unwrap();
+    assert!(!render_thread.in_flight());
+    assert!(!render_thread.is_ready());
+
+    let mut expected = header_job(200, 2);
+    expected.render();
+    assert_eq!(done.rendered.len(), 1);
+    assert_eq!(done.rendered, expected.rendered);
+
+    // The callback is invoked right after the job is sent back.
+    while !notified.load(Ordering::Acquire) {
+        thread::yield_now();
+    }
+}
+
+#[test]
+fn render_thread_gives_back_the_panicked_job() {
+    #[allow(clippy::unwrap_used)]
+    let mut render_thread = RenderThread::spawn_with(|_| panic!("drawing failed")).unwrap();
+    assert!(render_thread.send(Box::new(header_job(200, 2))).is_ok());
+
+    // The state is moved back without the pixmaps.
+    #[allow(clippy::unwrap_used)]
+    let done = render_thread.wait().unwrap();
+    assert!(done.panicked);
+    assert_eq!(done.targets.len(), 1);
+    assert!(done.rendered.is_empty());
+    assert_eq!(done.progress, Some(0.5));
+}
@@ -1,5 +1,6 @@
 use crate::{parts::DecorationParts, theme};
 use std::collections::BTreeMap;
+use std::thread::{self, JoinHandle};
 use tiny_skia::{Pixmap, PixmapMut, PixmapRef, Point, PremultipliedColorU8};
 
 // These values were generated from a screenshot of an libadwaita window using a script.
@@ -8,26 +9,43 @@ pub const SHADOW_SIZE: u32 = 43;
 const SHADOW_PARAMS_ACTIVE: (f32, f32, f32) = (0.206_505_5, 0.104_617_53, -0.000_542_446_2);
 const SHADOW_PARAMS_INACTIVE: (f32, f32, f32) = (0.168_297_29, 0.204_299_8, 0.001_769_798_6);
 
-fn shadow(pixel_dist: f32, scale: u32, active: bool) -> f32 {
+fn shadow(pixel_dist: f32, scale: f32, active: bool) -> f32 {
     let (a, b, c) = if active {
         SHADOW_PARAMS_ACTIVE
     } else {
         SHADOW_PARAMS_INACTIVE
     };
 
-    a * (-b * (pixel_dist / scale as f32)).exp() + c
+    a * (-b * (pixel_dist / scale)).exp() + c
+}
+
+/// The scale in 1/120 units, like the `wp_fractional_scale_v1` ones, to key the caches.
+fn scale_key(scale: f32) -> u32 {
+    (scale * 120.).round() as u32
+}
+
+/// The logical size in pixels at the possibly fractional `scale`.
+fn to_pixels(size: u32, scale: f32) -> u32 {
+    (size as f32 * scale).round() as u32
 }
 
 #[derive(Debug)]
 struct RenderedShadow {
     side: Pixmap,
     edges: Pixmap,
+    /// The corner radius in logical points.
+    corner_radius: u32,
 }
 
 impl RenderedShadow {
-    fn new(scale: u32, active: bool) -> RenderedShadow {
-        let shadow_size = SHADOW_SIZE * scale;
-        let corner_radius = theme::CORNER_RADIUS * scale;
+    /// Render the shadow at the possibly fractional `scale`.
+    ///
+    /// The profile is sampled from the shadow function at the centers of the pixels, so the
+    /// fractional scales get the exact values instead of the resampled integral ones.
+    fn new(scale: f32, active: bool, corner_radius: u32) -> RenderedShadow {
+        let shadow_size = to_pixels(SHADOW_SIZE, scale);
+        let logical_corner_radius = corner_radius;
+        let corner_radius = to_pixels(corner_radius, scale);
 
         #[allow(clippy::unwrap_used)]
         let mut side = Pixmap::new(shadow_size, 1).unwrap();
@@ -43,20 +61,42 @@ impl RenderedShadow {
         #[allow(clippy::unwrap_used)]
         let mut edges = Pixmap::new(edges_size, edges_size).unwrap();
         let edges_middle = Point::from_xy(edges_size as f32 / 2.0, edges_size as f32 / 2.0);
-        for y in 0..edges_size as usize {
+        let shade_row = |(y, row): (usize, &mut [PremultipliedColorU8])| {
             let y_pos = y as f32 + 0.5;
-            for x in 0..edges_size as usize {
+            for (x, pixel) in row.iter_mut().enumerate() {
                 let dist = edges_middle.distance(Point::from_xy(x as f32 + 0.5, y_pos))
                     - corner_radius as f32;
                 let alpha = (shadow(dist, scale, active) * u8::MAX as f32).round() as u8;
 
                 #[allow(clippy::unwrap_used)]
                 let color = PremultipliedColorU8::from_rgba(0, 0, 0, alpha).unwrap();
-                edges.pixels_mut()[y * edges_size as usize + x] = color;
+                *pixel = color;
             }
+        };
+
+        // The rows are independent, so they're shaded in parallel with `rayon`.
+        let row_size = edges_size as usize;
+        #[cfg(feature = "rayon")]
+        {
+            use rayon::prelude::*;
+            edges
+                .pixels_mut()
+                .par_chunks_exact_mut(row_size)
+                .enumerate()
+                .for_each(shade_row);
+        }
+        #[cfg(not(feature = "rayon"))]
+        edges
+            .pixels_mut()
+            .chunks_exact_mut(row_size)
+            .enumerate()
+            .for_each(shade_row);
+
+        RenderedShadow {
+            side,
+            edges,
+            corner_radius: logical_corner_radius,
         }
-
-        RenderedShadow { side, edges }
     }
 
     fn side_draw(
@@ -146,10 +186,10 @@ impl RenderedShadow {
         }
     }
 
-    fn draw(&self, dst_pixmap: &mut PixmapMut, scale: u32, part_idx: usize) {
-        let shadow_size = (SHADOW_SIZE * scale) as usize;
-        let visible_border_size = (theme::VISIBLE_BORDER_SIZE * scale) as usize;
-        let corner_radius = (theme::CORNER_RADIUS * scale) as usize;
+    fn draw(&self, dst_pixmap: &mut PixmapMut, scale: f32, part_idx: usize) {
+        let shadow_size = to_pixels(SHADOW_SIZE, scale) as usize;
+        let visible_border_size = to_pixels(theme::VISIBLE_BORDER_SIZE, scale) as usize;
+        let corner_radius = to_pixels(self.corner_radius, scale) as usize;
         assert!(corner_radius > visible_border_size);
 
         let dst_width = dst_pixmap.width() as usize;
@@ -322,6 +362,7 @@ impl RenderedShadow {
 #[derive(Debug)]
 struct CachedPart {
     pixmap: Pixmap,
+    /// See [`scale_key`].
     scale: u32,
     active: bool,
 }
@@ -330,7 +371,7 @@ impl CachedPart {
     fn new(
         dst_pixmap: &PixmapRef,
         rendered: &RenderedShadow,
-        scale: u32,
+        scale: f32,
         active: bool,
         part_idx: usize,
     ) -> CachedPart {
@@ -340,15 +381,15 @@ impl CachedPart {
 
         CachedPart {
             pixmap,
-            scale,
+            scale: scale_key(scale),
             active,
         }
     }
 
-    fn matches(&self, dst_pixmap: &PixmapRef, dst_scale: u32, dst_active: bool) -> bool {
+    fn matches(&self, dst_pixmap: &PixmapRef, dst_scale: f32, dst_active: bool) -> bool {
         self.pixmap.width() == dst_pixmap.width()
             && self.pixmap.height() == dst_pixmap.height()
-            && self.scale == dst_scale
+            && self.scale == scale_key(dst_scale)
             && self.active == dst_active
     }
 
@@ -358,15 +399,79 @@ impl CachedPart {
     }
 }
 
-#[derive(Default, Debug)]
+#[derive(Debug)]
 pub struct Shadow {
     part_cache: [Option<CachedPart>; 5],
-    // (scale, active) -> RenderedShadow
+    // (scale_key(scale), active) -> RenderedShadow
     rendered: BTreeMap<(u32, bool), RenderedShadow>,
+    /// The corner radius in logical points.
+    corner_radius: u32,
+    /// The active and the inactive shadows rendered off-thread, see [`Self::prerender`].
+    prerendered: Option<(u32, JoinHandle<[RenderedShadow; 2]>)>,
+}
+
+impl Default for Shadow {
+    fn default() -> Self {
+        Self {
+            part_cache: Default::default(),
+            rendered: BTreeMap::new(),
+            corner_radius: theme::CORNER_RADIUS,
+            prerendered: None,
+        }
+    }
 }
 
 impl Shadow {
-    pub fn draw(&mut self, pixmap: &mut PixmapMut, scale: u32, active: bool, part_idx: usize) {
+    /// The corner radius in logical points the shadow is drawn around.
+    pub fn corner_radius(&self) -> u32 {
+        self.corner_radius
+    }
+
+    /// Update the corner radius in logical points, dropping the shadows rendered for the old one.
+    pub fn set_corner_radius(&mut self, corner_radius: u32) {
+        // The shadow corners always cover the visible border.
+        let corner_radius = corner_radius.max(theme::VISIBLE_BORDER_SIZE + 1);
+        if self.corner_radius != corner_radius {
+            self.corner_radius = corner_radius;
+            self.part_cache = Default::default();
+            self.rendered.clear();
+            self.prerendered = None;
+        }
+    }
+
+    /// Render the active and the inactive shadows at the `scale` on another thread, so the
+    /// first draw doesn't wait for them.
+    pub fn prerender(&mut self, scale: f32) {
+        let corner_radius = self.corner_radius;
+        let handle = thread::Builder::new()
+            .name("sctk-adwaita-shadow".into())
+            .spawn(move || {
+                [true, false].map(|active| RenderedShadow::new(scale, active, corner_radius))
+            });
+        match handle {
+            Ok(handle) => self.prerendered = Some((scale_key(scale), handle)),
+            Err(err) => log::warn!("failed to prerender the shadow: {err}"),
+        }
+    }
+
+    /// Take the shadows rendered by [`Self::prerender`], waiting for them if needed.
+    fn join_prerendered(&mut self) {
+        let Some((scale_key, handle)) = self.prerendered.take() else {
+            return;
+        };
+
+        let Ok(rendered) = handle.join() else {
+            log::warn!("failed to prerender the shadow");
+            return;
+        };
+        for (rendered, active) in rendered.into_iter().zip([true, false]) {
+            self.rendered.entry((scale_key, active)).or_insert(rendered);
+        }
+    }
+
+    /// Draw the shadow of the part at the possibly fractional `scale`.
+    pub fn draw(&mut self, pixmap: &mut PixmapMut, scale: f32, active: bool, part_idx: usize) {
+        self.join_prerendered();
         let cache = &mut self.part_cache[part_idx];
 
         if let Some(cache_value) = cache {
@@ -378,8 +483,8 @@ impl Shadow {
         if cache.is_none() {
             let rendered = self
                 .rendered
-                .entry((scale, active))
-                .or_insert_with(|| RenderedShadow::new(scale, active));
+                .entry((scale_key(scale), active))
+                .or_insert_with(|| RenderedShadow::new(scale, active, self.corner_radius));
 
             *cache = Some(CachedPart::new(
                 &pixmap.as_ref(),
@@ -394,4 +499,81 @@ impl Shadow {
         #[allow(clippy::unwrap_used)]
         cache.as_ref().unwrap().draw(pixmap);
     }
+
+    /// Draw the shadow crossfaded from the inactive to the active one by `activation`, with
+    /// the `opacity` applied, used while animating the window state transitions.
+    pub fn draw_animated(
+        &mut self,
+        pixmap: &mut PixmapMut,
+        scale: f32,
+        activation: f32,
+        opacity: f32,
+        part_idx: usize,
+    ) {
+        let active = activation >= 0.5;
+        let crossfade = activation > 0. && activation < 1.;
+        if !crossfade && opacity >= 1. {
+            return self.draw(pixmap, scale, active, part_idx);
+        }
+
+        self.draw(pixmap, scale, false, part_idx);
+        let active_pixmap = crossfade
+            .then(|| {
+                let mut active_pixmap = Pixmap::new(pixmap.width(), pixmap.height())?;
+                self.draw(&mut active_pixmap.as_mut(), scale, true, part_idx);
+                Some(active_pixmap)
+            })
+            .flatten();
+
+        let mix = |inactive: u8, active: Option<u8>| {
+            let value = match active {
+                Some(active) => {
+                    f32::from(inactive) + (f32::from(active) - f32::from(inactive)) * activation
+                }
+                None => f32::from(inactive),
+            };
+            (value * opacity.clamp(0., 1.)).round() as u8
+        };
+
+        for (idx, dst) in pixmap.pixels_mut().iter_mut().enumerate() {
+            let src = active_pixmap.as_ref().map(|pixmap| pixmap.pixels()[idx]);
+            let color = PremultipliedColorU8::from_rgba(
+                mix(dst.red(), src.map(|src| src.red())),
+                mix(dst.green(), src.map(|src| src.green())),
+                mix(dst.blue(), src.map(|src| src.blue())),
+                mix(dst.alpha(), src.map(|src| src.alpha())),
+            );
+            if let Some(color) = color {
+                *dst = color;
+            }
+        }
+    }
+}
+
+#[test]
+fn fractional_scale_shadow() {
+    let rendered = RenderedShadow::new(1.5, true, theme::CORNER_RADIUS);
+    assert_eq!(rendered.side.width(), to_pixels(SHADOW_SIZE, 1.5));
+
+    // The profile fades out from the window.
+    let alpha: Vec<_> = rendered.side.pixels().iter().map(|px| px.alpha()).collect();
+    assert!(alpha.windows(2).all(|pair| pair[0] >= pair[1]));
+
+    // Distinct from the integral scales in the cache.
+    assert_ne!(scale_key(1.5), scale_key(1.));
+    assert_ne!(scale_key(1.5), scale_key(2.));
+}
+
+#[test]
+fn prerendered_shadow_matches_rendered() {
+    let mut shadow = Shadow::default();
+    shadow.prerender(2.);
+    shadow.join_prerendered();
+
+    for active in [true, false] {
+        let prerendered = &shadow.rendered[&(scale_key(2.), active)];
+        let rendered = RenderedShadow::new(2., active, theme::CORNER_RADIUS);
+        assert_eq!(prerendered.edges, rendered.edges);
+        assert_eq!(prerendered.side, rendered.side);
+    }
 }
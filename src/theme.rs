@@ -1,5 +1,10 @@
-pub use tiny_skia::Color;
-use tiny_skia::{Paint, Shader};
+use smithay_client_toolkit::reexports::csd_frame::WindowState;
+pub use tiny_skia::{Color, Shader};
+use tiny_skia::{GradientStop, LinearGradient, Paint, Point, SpreadMode, Transform};
+
+use self::palette::Palette;
+
+pub mod palette;
 
 // https://gitlab.gnome.org/GNOME/gtk/-/blob/1bf88f1d81043fd99740e2f91e56ade7ede7303b/gtk/gtkwindow.c#L165
 pub(crate) const RESIZE_HANDLE_SIZE: u32 = 12;
@@ -10,68 +15,317 @@ pub(crate) const HEADER_SIZE: u32 = 35;
 pub(crate) const CORNER_RADIUS: u32 = 10;
 pub(crate) const VISIBLE_BORDER_SIZE: u32 = 1;
 
+/// The visible border width of the high contrast theme, taken out of the shadow.
+pub(crate) const HIGH_CONTRAST_BORDER_SIZE: u32 = 2;
+pub(crate) const RESIZE_HINT_SIZE: u32 = 3;
+/// How far the headerbar is tinted with the accent color at the peak of the flash.
+const FLASH_TINT: f32 = 0.35;
+/// The default width of the [`OutlineStyle`].
+const OUTLINE_SIZE: u32 = 2;
+
 /// The color theme to use with the decorations frame.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ColorTheme {
     pub active: ColorMap,
     pub inactive: ColorMap,
+    /// Draw the thicker visible borders and outline the buttons.
+    pub high_contrast: bool,
+    /// The colors replacing the active and the inactive ones in the window states, see
+    /// [`Self::for_window_state`].
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub state_colors: Vec<StateColors>,
+}
+
+/// The window state drawn with its own colors, see [`ColorTheme::with_state_colors`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ThemeState {
+    /// Tiled along any of the edges, e.g. to draw the flat borders.
+    Tiled,
+    Maximized,
+    Fullscreen,
+    /// Suspended by the compositor, e.g. on the other workspace or fully covered.
+    Suspended,
+}
+
+impl ThemeState {
+    /// The states in the order of precedence, for the window in more than one of them.
+    const PRECEDENCE: [ThemeState; 4] = [
+        ThemeState::Suspended,
+        ThemeState::Fullscreen,
+        ThemeState::Maximized,
+        ThemeState::Tiled,
+    ];
+
+    fn matches(self, state: &WindowState) -> bool {
+        match self {
+            ThemeState::Tiled => state.intersects(WindowState::TILED),
+            ThemeState::Maximized => state.contains(WindowState::MAXIMIZED),
+            ThemeState::Fullscreen => state.contains(WindowState::FULLSCREEN),
+            ThemeState::Suspended => state.contains(WindowState::SUSPENDED),
+        }
+    }
+}
+
+/// The colors of the window in the [`ThemeState`], replacing the ones of the [`ColorTheme`].
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StateColors {
+    pub state: ThemeState,
+    pub active: ColorMap,
+    pub inactive: ColorMap,
 }
 
 impl ColorTheme {
     /// Automatically choose between light & dark themes based on:
     /// * dbus org.freedesktop.portal.Settings
     ///   <https://flatpak.github.io/xdg-desktop-portal/#gdbus-interface-org-freedesktop-portal-Settings>
+    ///
+    /// The [`Self::high_contrast`] or the [`Self::high_contrast_dark`] variant takes precedence
+    /// when the high contrast is requested.
     pub fn auto() -> Self {
-        match crate::config::prefer_dark() {
-            true => Self::dark(),
-            false => Self::light(),
+        match (crate::config::high_contrast(), crate::config::prefer_dark()) {
+            (true, true) => Self::high_contrast_dark(),
+            (true, false) => Self::high_contrast(),
+            (false, true) => Self::dark(),
+            (false, false) => Self::light(),
         }
     }
 
     /// Predefined light variant, which aims to replecate Adwaita theme.
     pub fn light() -> Self {
+        Palette::LIGHT.theme()
+    }
+
+    /// Predefined dark variant, which aims to replecate Adwaita-dark theme.
+    pub fn dark() -> Self {
+        Palette::DARK.theme()
+    }
+
+    /// The light or the `dark` variant tinted with the hue of the `accent` color, e.g. of the
+    /// wallpaper, see [`Palette::from_accent`].
+    pub fn from_accent(accent: Color, dark: bool) -> Self {
+        Palette::from_accent(accent.to_color_u8(), dark).theme()
+    }
+
+    /// Like [`Self::auto`], but tinted with the accent color of the XDG Settings Portal when
+    /// it's provided, see [`Self::from_accent`].
+    pub fn auto_accent() -> Self {
+        match crate::config::accent_color() {
+            Some(accent) if !crate::config::high_contrast() => {
+                Self::from_accent(accent, crate::config::prefer_dark())
+            }
+            _ => Self::auto(),
+        }
+    }
+
+    /// Predefined high contrast variant, which aims to replecate the Adwaita high contrast theme.
+    pub fn high_contrast() -> Self {
         Self {
             active: ColorMap {
-                headerbar: Color::from_rgba8(235, 235, 235, 255),
-                button_idle: Color::from_rgba8(216, 216, 216, 255),
-                button_hover: Color::from_rgba8(207, 207, 207, 255),
-                button_icon: Color::from_rgba8(42, 42, 42, 255),
-                border_color: Color::from_rgba8(220, 220, 220, 255),
-                font_color: Color::from_rgba8(47, 47, 47, 255),
+                headerbar: Fill::Solid(Color::from_rgba8(255, 255, 255, 255)),
+                button_idle: Color::from_rgba8(224, 224, 224, 255),
+                button_hover: Color::from_rgba8(192, 192, 192, 255),
+                button_icon: Color::from_rgba8(0, 0, 0, 255),
+                border_color: Color::from_rgba8(0, 0, 0, 255),
+                font_color: Color::from_rgba8(0, 0, 0, 255),
+                resize_hint: Color::from_rgba8(0, 0, 0, 255),
+                progress: Color::from_rgba8(0, 0, 0, 255),
+                attention: Color::from_rgba8(255, 245, 157, 255),
+                snap_preview: Color::from_rgba8(0, 0, 0, 96),
             },
             inactive: ColorMap {
-                headerbar: Color::from_rgba8(250, 250, 250, 255),
+                headerbar: Fill::Solid(Color::from_rgba8(255, 255, 255, 255)),
                 button_idle: Color::from_rgba8(240, 240, 240, 255),
-                button_hover: Color::from_rgba8(216, 216, 216, 255),
-                button_icon: Color::from_rgba8(148, 148, 148, 255),
-                border_color: Color::from_rgba8(220, 220, 220, 255),
-                font_color: Color::from_rgba8(150, 150, 150, 255),
+                button_hover: Color::from_rgba8(208, 208, 208, 255),
+                button_icon: Color::from_rgba8(64, 64, 64, 255),
+                border_color: Color::from_rgba8(64, 64, 64, 255),
+                font_color: Color::from_rgba8(64, 64, 64, 255),
+                resize_hint: Color::from_rgba8(64, 64, 64, 255),
+                progress: Color::from_rgba8(64, 64, 64, 255),
+                attention: Color::from_rgba8(255, 250, 200, 255),
+                snap_preview: Color::from_rgba8(64, 64, 64, 96),
             },
+            high_contrast: true,
+            state_colors: Vec::new(),
         }
     }
 
-    /// Predefined dark variant, which aims to replecate Adwaita-dark theme.
-    pub fn dark() -> Self {
+    /// Predefined dark high contrast variant, the [`Self::high_contrast`] one for the dark style.
+    pub fn high_contrast_dark() -> Self {
         Self {
             active: ColorMap {
-                headerbar: Color::from_rgba8(48, 48, 48, 255),
-                button_idle: Color::from_rgba8(69, 69, 69, 255),
-                button_hover: Color::from_rgba8(79, 79, 79, 255),
+                headerbar: Fill::Solid(Color::from_rgba8(0, 0, 0, 255)),
+                button_idle: Color::from_rgba8(48, 48, 48, 255),
+                button_hover: Color::from_rgba8(80, 80, 80, 255),
                 button_icon: Color::from_rgba8(255, 255, 255, 255),
-                border_color: Color::from_rgba8(58, 58, 58, 255),
+                border_color: Color::from_rgba8(255, 255, 255, 255),
                 font_color: Color::from_rgba8(255, 255, 255, 255),
+                resize_hint: Color::from_rgba8(255, 255, 255, 255),
+                progress: Color::from_rgba8(255, 255, 255, 255),
+                attention: Color::from_rgba8(120, 90, 0, 255),
+                snap_preview: Color::from_rgba8(255, 255, 255, 96),
             },
             inactive: ColorMap {
-                headerbar: Color::from_rgba8(36, 36, 36, 255),
-                button_idle: Color::from_rgba8(47, 47, 47, 255),
-                button_hover: Color::from_rgba8(57, 57, 57, 255),
-                button_icon: Color::from_rgba8(144, 144, 144, 255),
-                border_color: Color::from_rgba8(58, 58, 58, 255),
-                font_color: Color::from_rgba8(144, 144, 144, 255),
+                headerbar: Fill::Solid(Color::from_rgba8(0, 0, 0, 255)),
+                button_idle: Color::from_rgba8(32, 32, 32, 255),
+                button_hover: Color::from_rgba8(64, 64, 64, 255),
+                button_icon: Color::from_rgba8(192, 192, 192, 255),
+                border_color: Color::from_rgba8(192, 192, 192, 255),
+                font_color: Color::from_rgba8(192, 192, 192, 255),
+                resize_hint: Color::from_rgba8(192, 192, 192, 255),
+                progress: Color::from_rgba8(192, 192, 192, 255),
+                attention: Color::from_rgba8(80, 60, 0, 255),
+                snap_preview: Color::from_rgba8(192, 192, 192, 96),
             },
+            high_contrast: true,
+            state_colors: Vec::new(),
+        }
+    }
+
+    /// Draw the window in the `state` with the `active` and the `inactive` colors, replacing the
+    /// ones set for the state before.
+    pub fn with_state_colors(
+        mut self,
+        state: ThemeState,
+        active: ColorMap,
+        inactive: ColorMap,
+    ) -> Self {
+        self.state_colors.retain(|colors| colors.state != state);
+        self.state_colors.push(StateColors {
+            state,
+            active,
+            inactive,
+        });
+        self
+    }
+
+    /// The colors of the window in the `state`: the ones set with [`Self::with_state_colors`]
+    /// for the first of the suspended, the fullscreen, the maximized and the tiled states it's
+    /// in, or the active or the inactive ones.
+    pub fn for_window_state(&self, state: &WindowState) -> &ColorMap {
+        let active = state.contains(WindowState::ACTIVATED);
+        ThemeState::PRECEDENCE
+            .into_iter()
+            .filter(|theme_state| theme_state.matches(state))
+            .find_map(|theme_state| self.state_maps(theme_state))
+            .map(|(active_map, inactive_map)| if active { active_map } else { inactive_map })
+            .unwrap_or_else(|| self.for_state(active))
+    }
+
+    /// The active and the inactive colors set for the `state`.
+    fn state_maps(&self, state: ThemeState) -> Option<(&ColorMap, &ColorMap)> {
+        self.state_colors
+            .iter()
+            .find(|colors| colors.state == state)
+            .map(|colors| (&colors.active, &colors.inactive))
+    }
+
+    /// Map all the colors of the theme, including the ones of the states.
+    fn map_colors(&self, map: impl Fn(&ColorMap) -> ColorMap) -> ColorTheme {
+        Self {
+            active: map(&self.active),
+            inactive: map(&self.inactive),
+            high_contrast: self.high_contrast,
+            state_colors: self
+                .state_colors
+                .iter()
+                .map(|colors| StateColors {
+                    state: colors.state,
+                    active: map(&colors.active),
+                    inactive: map(&colors.inactive),
+                })
+                .collect(),
         }
     }
 
+    /// The width of the visible borders in logical points.
+    pub(crate) fn visible_border_size(&self) -> u32 {
+        if self.high_contrast {
+            HIGH_CONTRAST_BORDER_SIZE
+        } else {
+            VISIBLE_BORDER_SIZE
+        }
+    }
+
+    /// Interpolate the colors between `self` at `t = 0` and `other` at `t = 1`.
+    ///
+    /// The [`Self::high_contrast`] borders switch in the middle. The colors of the states set
+    /// in only one of the themes are interpolated with the active and the inactive ones of the
+    /// other.
+    pub fn lerp(&self, other: &ColorTheme, t: f32) -> ColorTheme {
+        let t = t.clamp(0., 1.);
+        let state_colors = ThemeState::PRECEDENCE
+            .into_iter()
+            .filter_map(|state| {
+                let (from, to) = match (self.state_maps(state), other.state_maps(state)) {
+                    (None, None) => return None,
+                    (from, to) => (
+                        from.unwrap_or((&self.active, &self.inactive)),
+                        to.unwrap_or((&other.active, &other.inactive)),
+                    ),
+                };
+                Some(StateColors {
+                    state,
+                    active: from.0.lerp(to.0, t),
+                    inactive: from.1.lerp(to.1, t),
+                })
+            })
+            .collect();
+
+        Self {
+            active: self.active.lerp(&other.active, t),
+            inactive: self.inactive.lerp(&other.inactive, t),
+            high_contrast: if t < 0.5 {
+                self.high_contrast
+            } else {
+                other.high_contrast
+            },
+            state_colors,
+        }
+    }
+
+    /// Tint the headerbar with the attention color by `t` from `0` to `1`.
+    pub(crate) fn with_attention(&self, t: f32) -> ColorTheme {
+        self.map_colors(|colors| ColorMap {
+            headerbar: colors.headerbar.lerp(&Fill::Solid(colors.attention), t),
+            ..colors.clone()
+        })
+    }
+
+    /// Tint the headerbar with the accent color of the progress by `t` from `0` to `1`, to
+    /// flash it.
+    pub(crate) fn with_flash(&self, t: f32) -> ColorTheme {
+        self.map_colors(|colors| ColorMap {
+            headerbar: colors
+                .headerbar
+                .lerp(&Fill::Solid(colors.progress), t * FLASH_TINT),
+            ..colors.clone()
+        })
+    }
+
+    /// The opacity of the title rendered with the active font color, so that over the inactive
+    /// headerbar it's as light as the inactive font color.
+    ///
+    /// The title isn't re-rendered on every focus change then, at the cost of the inactive font
+    /// color being approximated. The colors are the ones of the window in the `state`.
+    pub(crate) fn inactive_title_opacity(&self, state: &WindowState) -> f32 {
+        let active = self.for_window_state(&(*state | WindowState::ACTIVATED));
+        let inactive = self.for_window_state(&(*state - WindowState::ACTIVATED));
+        let Some(background) = inactive.headerbar.average_color() else {
+            return 1.;
+        };
+
+        let background = luminance(background);
+        let distance = background - luminance(active.font_color);
+        if distance.abs() < f32::EPSILON {
+            return 1.;
+        }
+
+        ((background - luminance(inactive.font_color)) / distance).clamp(0., 1.)
+    }
+
     pub(crate) fn for_state(&self, active: bool) -> &ColorMap {
         if active {
             &self.active
@@ -88,26 +342,109 @@ impl Default for ColorTheme {
 }
 
 /// The color map for various decorcation parts.
-#[derive(Debug, Clone)]
+///
+/// Created with [`ColorMap::new`] or from the [`Palette`], so the colors of the new parts could
+/// be added without breaking the applications.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
 pub struct ColorMap {
-    pub headerbar: Color,
+    /// The background of the headerbar, e.g. `Color::WHITE.into()` for the solid color.
+    pub headerbar: Fill,
+    #[cfg_attr(feature = "serde", serde(with = "crate::description::serde_color"))]
     pub button_idle: Color,
+    #[cfg_attr(feature = "serde", serde(with = "crate::description::serde_color"))]
     pub button_hover: Color,
+    #[cfg_attr(feature = "serde", serde(with = "crate::description::serde_color"))]
     pub button_icon: Color,
+    #[cfg_attr(feature = "serde", serde(with = "crate::description::serde_color"))]
     pub border_color: Color,
+    #[cfg_attr(feature = "serde", serde(with = "crate::description::serde_color"))]
     pub font_color: Color,
+    /// The border segment under the pointer ready to resize the window, see
+    /// [`FrameConfig::resize_hint`].
+    ///
+    /// [`FrameConfig::resize_hint`]: crate::FrameConfig::resize_hint
+    #[cfg_attr(feature = "serde", serde(with = "crate::description::serde_color"))]
+    pub resize_hint: Color,
+    /// The progress strip along the bottom of the headerbar, see
+    /// [`AdwaitaFrame::set_progress`].
+    ///
+    /// [`AdwaitaFrame::set_progress`]: crate::AdwaitaFrame::set_progress
+    #[cfg_attr(feature = "serde", serde(with = "crate::description::serde_color"))]
+    pub progress: Color,
+    /// The headerbar of the window needing attention, see
+    /// [`AdwaitaFrame::set_needs_attention`].
+    ///
+    /// [`AdwaitaFrame::set_needs_attention`]: crate::AdwaitaFrame::set_needs_attention
+    #[cfg_attr(feature = "serde", serde(with = "crate::description::serde_color"))]
+    pub attention: Color,
+    /// The overlay on the borders facing the region the window would snap to, see
+    /// [`AdwaitaFrame::set_snap_preview`].
+    ///
+    /// [`AdwaitaFrame::set_snap_preview`]: crate::AdwaitaFrame::set_snap_preview
+    #[cfg_attr(feature = "serde", serde(with = "crate::description::serde_color"))]
+    pub snap_preview: Color,
 }
 
 impl ColorMap {
-    pub(crate) fn headerbar_paint(&self) -> Paint {
+    /// The colors of the headerbar, the buttons, the border and the title.
+    ///
+    /// The rest of them, e.g. the [`Self::resize_hint`] and the [`Self::progress`], follow the
+    /// `font_color` and the `button_hover` color, and could be changed afterwards.
+    pub fn new(
+        headerbar: impl Into<Fill>,
+        button_idle: Color,
+        button_hover: Color,
+        button_icon: Color,
+        border_color: Color,
+        font_color: Color,
+    ) -> Self {
+        let translucent = |opacity| {
+            let mut color = font_color;
+            color.apply_opacity(opacity);
+            color
+        };
+
+        Self {
+            headerbar: headerbar.into(),
+            button_idle,
+            button_hover,
+            button_icon,
+            border_color,
+            font_color,
+            resize_hint: translucent(0.5),
+            progress: font_color,
+            attention: button_hover,
+            snap_preview: translucent(0.25),
+        }
+    }
+
+    fn lerp(&self, other: &ColorMap, t: f32) -> ColorMap {
+        Self {
+            headerbar: self.headerbar.lerp(&other.headerbar, t),
+            button_idle: lerp_color(self.button_idle, other.button_idle, t),
+            button_hover: lerp_color(self.button_hover, other.button_hover, t),
+            button_icon: lerp_color(self.button_icon, other.button_icon, t),
+            border_color: lerp_color(self.border_color, other.border_color, t),
+            font_color: lerp_color(self.font_color, other.font_color, t),
+            resize_hint: lerp_color(self.resize_hint, other.resize_hint, t),
+            progress: lerp_color(self.progress, other.progress, t),
+            attention: lerp_color(self.attention, other.attention, t),
+            snap_preview: lerp_color(self.snap_preview, other.snap_preview, t),
+        }
+    }
+
+    /// The paint of the headerbar in the buffer of the `height` in pixels.
+    pub(crate) fn headerbar_paint(&self, height: f32, scale: f32) -> Paint<'_> {
         Paint {
-            shader: Shader::SolidColor(self.headerbar),
+            shader: self.headerbar.shader(height, scale),
             anti_alias: true,
             ..Default::default()
         }
     }
 
-    pub(crate) fn button_idle_paint(&self) -> Paint {
+    pub(crate) fn button_idle_paint(&self) -> Paint<'_> {
         Paint {
             shader: Shader::SolidColor(self.button_idle),
             anti_alias: true,
@@ -115,7 +452,7 @@ impl ColorMap {
         }
     }
 
-    pub(crate) fn button_hover_paint(&self) -> Paint {
+    pub(crate) fn button_hover_paint(&self) -> Paint<'_> {
         Paint {
             shader: Shader::SolidColor(self.button_hover),
             anti_alias: true,
@@ -123,17 +460,360 @@ impl ColorMap {
         }
     }
 
-    pub(crate) fn button_icon_paint(&self) -> Paint {
+    /// The pressed button, shaded past the hovered one as far again as the hovered one is from the
+    /// idle one.
+    pub(crate) fn button_pressed_paint(&self) -> Paint<'_> {
+        let (idle, hover) = (self.button_idle, self.button_hover);
+        let shade = |idle: f32, hover: f32| (2. * hover - idle).clamp(0., 1.);
+        let color = Color::from_rgba(
+            shade(idle.red(), hover.red()),
+            shade(idle.green(), hover.green()),
+            shade(idle.blue(), hover.blue()),
+            hover.alpha(),
+        )
+        .unwrap_or(hover);
+        Paint {
+            shader: Shader::SolidColor(color),
+            anti_alias: true,
+            ..Default::default()
+        }
+    }
+
+    pub(crate) fn button_icon_paint(&self) -> Paint<'_> {
         Paint {
             shader: Shader::SolidColor(self.button_icon),
             ..Default::default()
         }
     }
 
-    pub(crate) fn border_paint(&self) -> Paint {
+    pub(crate) fn focus_ring_paint(&self) -> Paint<'_> {
+        let mut color = self.button_icon;
+        color.apply_opacity(0.5);
+        Paint {
+            shader: Shader::SolidColor(color),
+            anti_alias: true,
+            ..Default::default()
+        }
+    }
+
+    pub(crate) fn progress_paint(&self) -> Paint<'_> {
+        Paint {
+            shader: Shader::SolidColor(self.progress),
+            ..Default::default()
+        }
+    }
+
+    pub(crate) fn resize_hint_paint(&self) -> Paint<'_> {
+        Paint {
+            shader: Shader::SolidColor(self.resize_hint),
+            ..Default::default()
+        }
+    }
+
+    pub(crate) fn snap_preview_paint(&self) -> Paint<'_> {
+        Paint {
+            shader: Shader::SolidColor(self.snap_preview),
+            ..Default::default()
+        }
+    }
+
+    pub(crate) fn border_paint(&self) -> Paint<'_> {
         Paint {
             shader: Shader::SolidColor(self.border_color),
             ..Default::default()
         }
     }
+
+    /// The paint of the `outline`, contrasting with the visible border unless its color is set.
+    pub(crate) fn outline_paint(&self, outline: &OutlineStyle) -> Paint<'_> {
+        let color = outline
+            .color
+            .unwrap_or(if luminance(self.border_color) < 0.5 {
+                Color::WHITE
+            } else {
+                Color::BLACK
+            });
+        Paint {
+            shader: Shader::SolidColor(color),
+            anti_alias: true,
+            ..Default::default()
+        }
+    }
+}
+
+/// The line outside the visible borders, helping to find the window boundaries, see
+/// [`FrameConfig::outline`].
+///
+/// [`FrameConfig::outline`]: crate::FrameConfig::outline
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct OutlineStyle {
+    /// The width in logical points.
+    pub width: u32,
+    /// The color of the line, `None` for white or black, whichever contrasts with the visible
+    /// border.
+    pub color: Option<Color>,
+}
+
+impl OutlineStyle {
+    /// The contrasting outline of the given `width` in logical points.
+    pub fn new(width: u32) -> Self {
+        Self { width, color: None }
+    }
+
+    /// Draw the outline in the given `color`.
+    pub fn color(mut self, color: Color) -> Self {
+        self.color = Some(color);
+        self
+    }
+}
+
+impl Default for OutlineStyle {
+    fn default() -> Self {
+        Self::new(OUTLINE_SIZE)
+    }
+}
+
+/// How the area, like the headerbar, is filled.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(untagged)
+)]
+pub enum Fill {
+    /// Fill with the single color.
+    Solid(#[cfg_attr(feature = "serde", serde(with = "crate::description::serde_color"))] Color),
+    /// Blend the `top` color into the `bottom` one from the top to the bottom edge.
+    VerticalGradient {
+        #[cfg_attr(feature = "serde", serde(with = "crate::description::serde_color"))]
+        top: Color,
+        #[cfg_attr(feature = "serde", serde(with = "crate::description::serde_color"))]
+        bottom: Color,
+    },
+    /// Fill with the custom shader in logical points, e.g. the [`tiny_skia::RadialGradient`].
+    ///
+    /// It can't be serialized.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    Shader(Shader<'static>),
+}
+
+impl Fill {
+    /// Whether every pixel of the fill is opaque, `false` for the custom shaders.
+    pub fn is_opaque(&self) -> bool {
+        match self {
+            Fill::Solid(color) => color.is_opaque(),
+            Fill::VerticalGradient { top, bottom } => top.is_opaque() && bottom.is_opaque(),
+            Fill::Shader(_) => false,
+        }
+    }
+
+    /// The colors at the top and at the bottom edge, `None` for the custom shaders.
+    fn edge_colors(&self) -> Option<(Color, Color)> {
+        match *self {
+            Fill::Solid(color) => Some((color, color)),
+            Fill::VerticalGradient { top, bottom } => Some((top, bottom)),
+            Fill::Shader(_) => None,
+        }
+    }
+
+    /// The color between the top and the bottom edge, `None` for the custom shaders.
+    fn average_color(&self) -> Option<Color> {
+        let (top, bottom) = self.edge_colors()?;
+        Some(lerp_color(top, bottom, 0.5))
+    }
+
+    /// Interpolate the colors between `self` at `t = 0` and `other` at `t = 1`.
+    ///
+    /// The custom shaders switch in the middle.
+    fn lerp(&self, other: &Fill, t: f32) -> Fill {
+        match (self, other) {
+            (Fill::Solid(from), Fill::Solid(to)) => Fill::Solid(lerp_color(*from, *to, t)),
+            _ => match (self.edge_colors(), other.edge_colors()) {
+                (Some((from_top, from_bottom)), Some((to_top, to_bottom))) => {
+                    Fill::VerticalGradient {
+                        top: lerp_color(from_top, to_top, t),
+                        bottom: lerp_color(from_bottom, to_bottom, t),
+                    }
+                }
+                _ if t < 0.5 => self.clone(),
+                _ => other.clone(),
+            },
+        }
+    }
+
+    /// The shader filling the buffer of the `height` in pixels.
+    fn shader(&self, height: f32, scale: f32) -> Shader<'static> {
+        match self {
+            Fill::Solid(color) => Shader::SolidColor(*color),
+            Fill::VerticalGradient { top, bottom } => LinearGradient::new(
+                Point::from_xy(0., 0.),
+                Point::from_xy(0., height),
+                vec![GradientStop::new(0., *top), GradientStop::new(1., *bottom)],
+                SpreadMode::Pad,
+                Transform::identity(),
+            )
+            .unwrap_or(Shader::SolidColor(*bottom)),
+            Fill::Shader(shader) => {
+                let mut shader = shader.clone();
+                shader.transform(Transform::from_scale(scale, scale));
+                shader
+            }
+        }
+    }
+}
+
+impl From<Color> for Fill {
+    fn from(color: Color) -> Self {
+        Fill::Solid(color)
+    }
+}
+
+/// The relative luminance of the `color`, ignoring its alpha.
+fn luminance(color: Color) -> f32 {
+    0.2126 * color.red() + 0.7152 * color.green() + 0.0722 * color.blue()
+}
+
+fn lerp_color(from: Color, to: Color, t: f32) -> Color {
+    let lerp = |from: f32, to: f32| from * (1. - t) + to * t;
+    Color::from_rgba(
+        lerp(from.red(), to.red()),
+        lerp(from.green(), to.green()),
+        lerp(from.blue(), to.blue()),
+        lerp(from.alpha(), to.alpha()),
+    )
+    .unwrap_or(to)
+}
+
+#[test]
+fn lerp_theme_moves_between_themes() {
+    let light = ColorTheme::light();
+    let dark = ColorTheme::dark();
+    assert_eq!(light.lerp(&dark, 0.), light);
+    assert_eq!(light.lerp(&dark, 1.), dark);
+
+    let middle = light.lerp(&dark, 0.5).active.button_idle;
+    assert!(middle.red() < light.active.button_idle.red());
+    assert!(middle.red() > dark.active.button_idle.red());
+}
+
+#[test]
+fn fill_lerps_into_gradient() {
+    let solid = Fill::from(Color::BLACK);
+    let gradient = Fill::VerticalGradient {
+        top: Color::WHITE,
+        bottom: Color::BLACK,
+    };
+    assert_eq!(solid.lerp(&gradient, 1.), gradient);
+    assert_eq!(
+        solid.lerp(&gradient, 0.),
+        Fill::VerticalGradient {
+            top: Color::BLACK,
+            bottom: Color::BLACK,
+        }
+    );
+    assert!(gradient.is_opaque());
+    assert!(matches!(
+        gradient.shader(35., 1.),
+        Shader::LinearGradient(_)
+    ));
+
+    let shader = Fill::Shader(Shader::SolidColor(Color::WHITE));
+    assert_eq!(solid.lerp(&shader, 0.4), solid);
+    assert_eq!(solid.lerp(&shader, 0.6), shader);
+}
+
+#[test]
+fn window_state_picks_state_colors() {
+    let tiled = ColorTheme::dark();
+    let maximized = ColorTheme::high_contrast();
+    let theme = ColorTheme::light()
+        .with_state_colors(
+            ThemeState::Tiled,
+            tiled.active.clone(),
+            tiled.inactive.clone(),
+        )
+        .with_state_colors(
+            ThemeState::Maximized,
+            maximized.active.clone(),
+            maximized.inactive.clone(),
+        );
+
+    let plain = WindowState::ACTIVATED;
+    assert_eq!(theme.for_window_state(&plain), &theme.active);
+    let state = WindowState::ACTIVATED | WindowState::TILED_TOP;
+    assert_eq!(theme.for_window_state(&state), &tiled.active);
+    // Maximized windows are tiled too, and the maximized colors take precedence.
+    let state = WindowState::MAXIMIZED | WindowState::TILED;
+    assert_eq!(theme.for_window_state(&state), &maximized.inactive);
+
+    // Setting the colors of the state again replaces them.
+    let theme = theme.with_state_colors(
+        ThemeState::Tiled,
+        tiled.inactive.clone(),
+        tiled.inactive.clone(),
+    );
+    assert_eq!(theme.state_colors.len(), 2);
+    let state = WindowState::ACTIVATED | WindowState::TILED_TOP;
+    assert_eq!(theme.for_window_state(&state), &tiled.inactive);
+}
+
+#[test]
+fn color_map_derives_the_other_colors() {
+    let colors = ColorMap::new(
+        Color::WHITE,
+        Color::WHITE,
+        Color::BLACK,
+        Color::BLACK,
+        Color::BLACK,
+        Color::BLACK,
+    );
+    assert_eq!(colors.headerbar, Fill::Solid(Color::WHITE));
+    assert_eq!(colors.progress, Color::BLACK);
+    assert_eq!(colors.attention, Color::BLACK);
+    assert_eq!(colors.resize_hint.alpha(), 0.5);
+    assert!(colors.snap_preview.alpha() < colors.resize_hint.alpha());
+}
+
+#[test]
+fn high_contrast_variants_keep_contrast() {
+    let contrast = |colors: &ColorMap| {
+        let Fill::Solid(headerbar) = colors.headerbar else {
+            return 0.;
+        };
+        (colors.font_color.red() - headerbar.red()).abs()
+    };
+
+    for theme in [
+        ColorTheme::high_contrast(),
+        ColorTheme::high_contrast_dark(),
+    ] {
+        assert!(theme.high_contrast);
+        assert!(contrast(&theme.active) > 0.9);
+        assert!(contrast(&theme.inactive) > 0.7);
+    }
+}
+
+#[test]
+fn inactive_title_fades_into_headerbar() {
+    for theme in [ColorTheme::light(), ColorTheme::dark()] {
+        let opacity = theme.inactive_title_opacity(&WindowState::empty());
+        assert!((opacity - 0.49).abs() < 0.02, "{opacity}");
+    }
+
+    let mut theme = ColorTheme::light();
+    theme.inactive.headerbar = Fill::Shader(Shader::SolidColor(Color::WHITE));
+    assert_eq!(theme.inactive_title_opacity(&WindowState::empty()), 1.);
+}
+
+#[test]
+fn attention_tints_only_headerbar() {
+    let light = ColorTheme::light();
+    let tinted = light.with_attention(1.);
+    assert_eq!(tinted.active.headerbar, Fill::Solid(light.active.attention));
+    assert_eq!(
+        tinted.inactive.headerbar,
+        Fill::Solid(light.inactive.attention)
+    );
+    assert_eq!(tinted.active.button_idle, light.active.button_idle);
+    assert_eq!(light.with_attention(0.), light);
 }
@@ -0,0 +1,267 @@
+//! The named colors of the libadwaita stylesheet the predefined themes are built from.
+//!
+//! <https://gnome.pages.gitlab.gnome.org/libadwaita/doc/main/css-variables.html>
+
+use tiny_skia::Color;
+pub use tiny_skia::ColorU8;
+
+use super::{ColorMap, ColorTheme, Fill};
+
+/// The opacity of the accent color over the resize hint, in the active and the inactive state.
+const RESIZE_HINT_ALPHA: [u8; 2] = [128, 96];
+/// The opacity of the accent color over the progress strip.
+const PROGRESS_ALPHA: [u8; 2] = [255, 160];
+/// The opacity of the accent color over the snap preview.
+const SNAP_PREVIEW_ALPHA: [u8; 2] = [64, 48];
+
+/// A set of the libadwaita named colors.
+///
+/// Override some of the colors of a predefined palette to derive a [`ColorTheme`]:
+///
+/// ```
+/// # use sctk_adwaita::theme::{palette::{ColorU8, Palette}, ColorTheme};
+/// let theme: ColorTheme = Palette {
+///     accent_bg: ColorU8::from_rgba(224, 27, 36, 255),
+///     ..Palette::LIGHT
+/// }
+/// .into();
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Palette {
+    /// `@window_bg_color`
+    pub window_bg: ColorU8,
+    /// `@window_fg_color`
+    pub window_fg: ColorU8,
+    /// `@headerbar_bg_color`
+    pub headerbar_bg: ColorU8,
+    /// `@headerbar_backdrop_color`, the headerbar of the unfocused window.
+    pub headerbar_backdrop: ColorU8,
+    /// `@headerbar_fg_color`, the title of the focused window.
+    pub headerbar_fg: ColorU8,
+    /// The title of the unfocused window.
+    pub headerbar_backdrop_fg: ColorU8,
+    /// The background of the idle button.
+    pub button_bg: ColorU8,
+    /// The background of the button under the pointer.
+    pub button_hover_bg: ColorU8,
+    /// The background of the idle button of the unfocused window.
+    pub button_backdrop_bg: ColorU8,
+    /// The background of the button under the pointer of the unfocused window.
+    pub button_backdrop_hover_bg: ColorU8,
+    /// The button icons of the focused window.
+    pub button_fg: ColorU8,
+    /// The button icons of the unfocused window.
+    pub button_backdrop_fg: ColorU8,
+    /// `@borders`
+    pub borders: ColorU8,
+    /// `@accent_bg_color`, also used for the resize hint, the progress and the snap preview.
+    pub accent_bg: ColorU8,
+    /// The headerbar of the focused window needing attention.
+    pub attention_bg: ColorU8,
+    /// The headerbar of the unfocused window needing attention.
+    pub attention_backdrop_bg: ColorU8,
+}
+
+impl Palette {
+    /// The palette of the Adwaita theme.
+    pub const LIGHT: Self = Self {
+        window_bg: gray(250),
+        window_fg: ColorU8::from_rgba(0, 0, 0, 204),
+        headerbar_bg: gray(235),
+        headerbar_backdrop: gray(250),
+        headerbar_fg: gray(47),
+        headerbar_backdrop_fg: gray(150),
+        button_bg: gray(216),
+        button_hover_bg: gray(207),
+        button_backdrop_bg: gray(240),
+        button_backdrop_hover_bg: gray(216),
+        button_fg: gray(42),
+        button_backdrop_fg: gray(148),
+        borders: gray(220),
+        accent_bg: ColorU8::from_rgba(53, 132, 228, 255),
+        attention_bg: ColorU8::from_rgba(200, 222, 249, 255),
+        attention_backdrop_bg: ColorU8::from_rgba(220, 233, 250, 255),
+    };
+
+    /// The palette of the Adwaita-dark theme.
+    pub const DARK: Self = Self {
+        window_bg: gray(36),
+        window_fg: gray(255),
+        headerbar_bg: gray(48),
+        headerbar_backdrop: gray(36),
+        headerbar_fg: gray(255),
+        headerbar_backdrop_fg: gray(144),
+        button_bg: gray(69),
+        button_hover_bg: gray(79),
+        button_backdrop_bg: gray(47),
+        button_backdrop_hover_bg: gray(57),
+        button_fg: gray(255),
+        button_backdrop_fg: gray(144),
+        borders: gray(58),
+        accent_bg: ColorU8::from_rgba(120, 174, 237, 255),
+        attention_bg: ColorU8::from_rgba(36, 62, 97, 255),
+        attention_backdrop_bg: ColorU8::from_rgba(30, 45, 66, 255),
+    };
+
+    /// The palette tinted with the hue of the `accent` color, over [`Self::LIGHT`] or
+    /// [`Self::DARK`].
+    ///
+    /// The surfaces keep the lightness of the base palette, with a little of the saturation of
+    /// the accent, so the text stays readable whatever the accent is.
+    pub fn from_accent(accent: ColorU8, dark: bool) -> Self {
+        let base = if dark { Self::DARK } else { Self::LIGHT };
+        let (hue, saturation, _) = hsl(accent);
+        let tint = |color: ColorU8, amount: f32| {
+            let (_, _, lightness) = hsl(color);
+            from_hsl(hue, saturation * amount, lightness, color.alpha())
+        };
+        let (surface, button) = if dark { (0.25, 0.2) } else { (0.45, 0.3) };
+
+        Self {
+            window_bg: tint(base.window_bg, surface),
+            headerbar_bg: tint(base.headerbar_bg, surface),
+            headerbar_backdrop: tint(base.headerbar_backdrop, surface),
+            button_bg: tint(base.button_bg, button),
+            button_hover_bg: tint(base.button_hover_bg, button),
+            button_backdrop_bg: tint(base.button_backdrop_bg, button),
+            button_backdrop_hover_bg: tint(base.button_backdrop_hover_bg, button),
+            borders: tint(base.borders, button),
+            accent_bg: accent,
+            attention_bg: tint(base.attention_bg, 1.),
+            attention_backdrop_bg: tint(base.attention_backdrop_bg, 1.),
+            ..base
+        }
+    }
+
+    /// The [`ColorTheme`] drawing the decorations with this palette.
+    pub fn theme(&self) -> ColorTheme {
+        ColorTheme {
+            active: ColorMap {
+                headerbar: Fill::Solid(color(self.headerbar_bg)),
+                button_idle: color(self.button_bg),
+                button_hover: color(self.button_hover_bg),
+                button_icon: color(self.button_fg),
+                border_color: color(self.borders),
+                font_color: color(self.headerbar_fg),
+                resize_hint: accent(self.accent_bg, RESIZE_HINT_ALPHA[0]),
+                progress: accent(self.accent_bg, PROGRESS_ALPHA[0]),
+                attention: color(self.attention_bg),
+                snap_preview: accent(self.accent_bg, SNAP_PREVIEW_ALPHA[0]),
+            },
+            inactive: ColorMap {
+                headerbar: Fill::Solid(color(self.headerbar_backdrop)),
+                button_idle: color(self.button_backdrop_bg),
+                button_hover: color(self.button_backdrop_hover_bg),
+                button_icon: color(self.button_backdrop_fg),
+                border_color: color(self.borders),
+                font_color: color(self.headerbar_backdrop_fg),
+                resize_hint: accent(self.accent_bg, RESIZE_HINT_ALPHA[1]),
+                progress: accent(self.accent_bg, PROGRESS_ALPHA[1]),
+                attention: color(self.attention_backdrop_bg),
+                snap_preview: accent(self.accent_bg, SNAP_PREVIEW_ALPHA[1]),
+            },
+            high_contrast: false,
+            state_colors: Vec::new(),
+        }
+    }
+}
+
+impl From<Palette> for ColorTheme {
+    fn from(palette: Palette) -> Self {
+        palette.theme()
+    }
+}
+
+const fn gray(value: u8) -> ColorU8 {
+    ColorU8::from_rgba(value, value, value, 255)
+}
+
+fn color(color: ColorU8) -> Color {
+    Color::from_rgba8(color.red(), color.green(), color.blue(), color.alpha())
+}
+
+/// The hue in degrees, the saturation and the lightness of the `color`.
+fn hsl(color: ColorU8) -> (f32, f32, f32) {
+    let [red, green, blue] =
+        [color.red(), color.green(), color.blue()].map(|c| f32::from(c) / 255.);
+    let max = red.max(green).max(blue);
+    let min = red.min(green).min(blue);
+    let lightness = (max + min) / 2.;
+    let chroma = max - min;
+    if chroma <= f32::EPSILON {
+        return (0., 0., lightness);
+    }
+
+    let saturation = chroma / (1. - (2. * lightness - 1.).abs());
+    let hue = if max == red {
+        ((green - blue) / chroma).rem_euclid(6.)
+    } else if max == green {
+        (blue - red) / chroma + 2.
+    } else {
+        (red - green) / chroma + 4.
+    };
+
+    (hue * 60., saturation.min(1.), lightness)
+}
+
+/// The color of the `hue` in degrees, the `saturation` and the `lightness`.
+fn from_hsl(hue: f32, saturation: f32, lightness: f32, alpha: u8) -> ColorU8 {
+    let chroma = (1. - (2. * lightness - 1.).abs()) * saturation.clamp(0., 1.);
+    let hue = hue.rem_euclid(360.) / 60.;
+    let x = chroma * (1. - (hue.rem_euclid(2.) - 1.).abs());
+    let (red, green, blue) = match hue as u32 {
+        0 => (chroma, x, 0.),
+        1 => (x, chroma, 0.),
+        2 => (0., chroma, x),
+        3 => (0., x, chroma),
+        4 => (x, 0., chroma),
+        _ => (chroma, 0., x),
+    };
+    let offset = lightness - chroma / 2.;
+    let channel = |value: f32| ((value + offset).clamp(0., 1.) * 255.).round() as u8;
+
+    ColorU8::from_rgba(channel(red), channel(green), channel(blue), alpha)
+}
+
+/// The accent color scaled by the `alpha` of the part it's drawn over.
+fn accent(color: ColorU8, alpha: u8) -> Color {
+    let mut accent = self::color(color);
+    accent.apply_opacity(f32::from(alpha) / 255.);
+    accent
+}
+
+#[test]
+fn accent_tints_surfaces_keeping_lightness() {
+    let purple = ColorU8::from_rgba(145, 65, 172, 255);
+    for (base, dark) in [(Palette::LIGHT, false), (Palette::DARK, true)] {
+        let palette = Palette::from_accent(purple, dark);
+        assert_eq!(palette.accent_bg, purple);
+        assert_eq!(palette.headerbar_fg, base.headerbar_fg);
+
+        let (hue, saturation, lightness) = hsl(palette.headerbar_bg);
+        assert!((hue - hsl(purple).0).abs() < 5., "{hue}");
+        assert!(saturation > 0.);
+        assert!((lightness - hsl(base.headerbar_bg).2).abs() < 0.01);
+    }
+}
+
+#[test]
+fn accent_override_reaches_every_accent_part() {
+    let red = ColorU8::from_rgba(255, 0, 0, 255);
+    let theme = Palette {
+        accent_bg: red,
+        ..Palette::LIGHT
+    }
+    .theme();
+
+    for (colors, state) in [(&theme.active, 0), (&theme.inactive, 1)] {
+        for (part, alpha) in [
+            (colors.resize_hint, RESIZE_HINT_ALPHA[state]),
+            (colors.progress, PROGRESS_ALPHA[state]),
+            (colors.snap_preview, SNAP_PREVIEW_ALPHA[state]),
+        ] {
+            assert_eq!(part.to_color_u8(), ColorU8::from_rgba(255, 0, 0, alpha));
+        }
+    }
+    assert_eq!(theme.active.headerbar, ColorTheme::light().active.headerbar);
+}
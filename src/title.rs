@@ -1,5 +1,16 @@
-use tiny_skia::{Color, Pixmap};
+#[cfg(all(not(feature = "crossfont"), feature = "ab_glyph"))]
+use std::sync::{Arc, OnceLock};
+#[cfg(any(feature = "crossfont", feature = "ab_glyph"))]
+use tiny_skia::IntSize;
+
+use tiny_skia::{Color, FilterQuality, Pixmap, PixmapPaint, Transform};
 
+use crate::FrameError;
+
+#[cfg(any(feature = "crossfont", feature = "ab_glyph"))]
+mod bidi;
+#[cfg(any(feature = "crossfont", feature = "ab_glyph"))]
+mod cache;
 #[cfg(any(feature = "crossfont", feature = "ab_glyph"))]
 mod config;
 #[cfg(any(feature = "crossfont", feature = "ab_glyph"))]
@@ -10,10 +21,56 @@ mod crossfont_renderer;
 
 #[cfg(all(not(feature = "crossfont"), feature = "ab_glyph"))]
 mod ab_glyph_renderer;
+#[cfg(all(not(feature = "crossfont"), feature = "fontconfig-parser"))]
+mod fontconfig;
+#[cfg(all(not(feature = "crossfont"), feature = "ab_glyph"))]
+mod kerning;
+#[cfg(all(not(feature = "crossfont"), feature = "shaping"))]
+mod shaping;
 
 #[cfg(all(not(feature = "crossfont"), not(feature = "ab_glyph")))]
 mod dumb;
 
+/// The possibly fractional scale the titles are rendered at, `numerator / denominator`, like
+/// the 120ths of `wp_fractional_scale_v1`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TitleScale {
+    numerator: u32,
+    denominator: u32,
+}
+
+impl TitleScale {
+    /// The scale reduced to the lowest terms, so the equal scales get the same cache key.
+    pub fn new(numerator: u32, denominator: u32) -> Self {
+        let denominator = denominator.max(1);
+        let (mut a, mut b) = (numerator, denominator);
+        while b != 0 {
+            (a, b) = (b, a % b);
+        }
+        Self {
+            numerator: numerator / a,
+            denominator: denominator / a,
+        }
+    }
+
+    #[cfg(any(feature = "crossfont", feature = "ab_glyph"))]
+    pub fn factor(self) -> f32 {
+        self.numerator as f32 / self.denominator as f32
+    }
+
+    /// The logical `size` in the pixels at the scale.
+    pub fn to_pixels(self, size: u32) -> u32 {
+        ((u64::from(size) * u64::from(self.numerator) + u64::from(self.denominator) / 2)
+            / u64::from(self.denominator)) as u32
+    }
+}
+
+impl From<u32> for TitleScale {
+    fn from(scale: u32) -> Self {
+        Self::new(scale, 1)
+    }
+}
+
 #[derive(Debug)]
 pub struct TitleText {
     #[cfg(feature = "crossfont")]
@@ -24,26 +81,255 @@ pub struct TitleText {
     imp: dumb::DumbTitleText,
 }
 
+/// The statistics of the cache of the rendered titles, see [`AdwaitaFrame::title_cache_stats`].
+///
+/// [`AdwaitaFrame::title_cache_stats`]: crate::AdwaitaFrame::title_cache_stats
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct TitleCacheStats {
+    /// The renders avoided by reusing the cached titles.
+    pub hits: u64,
+    /// The renders the cached titles didn't match.
+    pub misses: u64,
+    /// The cached titles dropped to make room for the other ones.
+    pub evictions: u64,
+}
+
+impl std::ops::Add for TitleCacheStats {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            hits: self.hits + other.hits,
+            misses: self.misses + other.misses,
+            evictions: self.evictions + other.evictions,
+        }
+    }
+}
+
+/// The titlebar font shared by the titles of the frames, so it's matched and mapped into the
+/// memory once for all the windows of the application, see [`FrameConfig::title_font_cache`].
+///
+/// The font is loaded with the first title created with the cache, following the font
+/// preference at that time. Only the `ab_glyph` renderer shares the font, the `crossfont` one
+/// loads it for every title.
+///
+/// [`FrameConfig::title_font_cache`]: crate::FrameConfig::title_font_cache
+#[derive(Debug, Clone, Default)]
+pub struct TitleFontCache {
+    #[cfg(all(not(feature = "crossfont"), feature = "ab_glyph"))]
+    font: Arc<OnceLock<Arc<ab_glyph_renderer::SharedFont>>>,
+}
+
+#[cfg(all(not(feature = "crossfont"), feature = "ab_glyph"))]
+impl TitleFontCache {
+    fn font(&self) -> Arc<ab_glyph_renderer::SharedFont> {
+        self.font
+            .get_or_init(|| Arc::new(ab_glyph_renderer::SharedFont::load()))
+            .clone()
+    }
+}
+
+/// The height the headerbar grows up to with the large titlebar fonts.
+#[cfg(any(feature = "crossfont", feature = "ab_glyph"))]
+const MAX_HEADER_SIZE: u32 = 64;
+
+/// The height of the headerbar fitting the `titlebar_font`, like `Cantarell Bold 11`, with the
+/// sizes of the fonts scaled by the `text_scale`, [`HEADER_SIZE`] for the default one.
+///
+/// [`HEADER_SIZE`]: crate::theme::HEADER_SIZE
+pub fn header_height(titlebar_font: Option<&str>, text_scale: f32) -> u32 {
+    #[cfg(any(feature = "crossfont", feature = "ab_glyph"))]
+    return match (
+        titlebar_font.and_then(font_preference::FontPreference::from_name_style_size),
+        text_scale,
+    ) {
+        (None, 1.) => crate::theme::HEADER_SIZE,
+        (font, factor) => header_height_for(font.unwrap_or_default().pt_size * factor),
+    };
+
+    #[cfg(all(not(feature = "crossfont"), not(feature = "ab_glyph")))]
+    {
+        let _ = (titlebar_font, text_scale);
+        crate::theme::HEADER_SIZE
+    }
+}
+
+/// The height of the headerbar with the title of `pt_size`, padded like the default 11pt font.
+#[cfg(any(feature = "crossfont", feature = "ab_glyph"))]
+fn header_height_for(pt_size: f32) -> u32 {
+    use crate::theme::HEADER_SIZE;
+
+    let height = (pt_size * 96. / 72. + 20.).round();
+    (height.max(0.) as u32).clamp(HEADER_SIZE, MAX_HEADER_SIZE)
+}
+
+/// The font size of the subtitle relative to the title.
+const SUBTITLE_SIZE: f32 = 0.8;
+
+/// The horizontal alignment of the title in the headerbar.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TitleAlignment {
+    /// Center the title in the headerbar, or align it to the start when it collides with the
+    /// buttons.
+    #[default]
+    Center,
+    /// Place the title right after the buttons on the left.
+    Left,
+    /// Place the title right before the buttons on the right.
+    Right,
+}
+
+/// How the title glyphs are fitted to the pixel grid.
+///
+/// Mirrors the `font-hinting` key of `org.gnome.desktop.interface`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Hinting {
+    /// Keep the glyph outlines and positions as designed.
+    #[default]
+    None,
+    /// Snap the x-height and the baseline to the whole pixels, for the sharp horizontal stems.
+    Slight,
+    /// Snap the glyph positions to the whole pixels as well, for the sharp vertical stems at
+    /// the cost of the uneven spacing.
+    Full,
+}
+
+impl Hinting {
+    /// Parse the value of the `font-hinting` setting.
+    pub(crate) fn from_gsettings(value: &str) -> Option<Self> {
+        let hinting = match value {
+            "none" => Self::None,
+            "slight" => Self::Slight,
+            "medium" | "full" => Self::Full,
+            _ => return None,
+        };
+
+        Some(hinting)
+    }
+}
+
+/// The order of the color elements of the LCD pixels the title is rendered for, sharpening it
+/// on the standard density displays.
+///
+/// Mirrors the `rgba` property of fontconfig.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SubpixelOrder {
+    /// Render the grayscale glyphs.
+    #[default]
+    None,
+    /// The red, green and blue elements from the left to the right.
+    Rgb,
+    /// The blue, green and red elements from the left to the right.
+    Bgr,
+    /// The red, green and blue elements from the top to the bottom.
+    Vrgb,
+    /// The blue, green and red elements from the top to the bottom.
+    Vbgr,
+}
+
+impl SubpixelOrder {
+    /// Parse the constant of the fontconfig `rgba` property.
+    pub(crate) fn from_fontconfig(value: &str) -> Option<Self> {
+        let order = match value {
+            // `FC_RGBA_UNKNOWN` is rendered in grayscale as well.
+            "0" | "5" => Self::None,
+            "1" => Self::Rgb,
+            "2" => Self::Bgr,
+            "3" => Self::Vrgb,
+            "4" => Self::Vbgr,
+            _ => return None,
+        };
+
+        Some(order)
+    }
+
+    /// The oversampling of the glyphs horizontally and vertically.
+    #[cfg(all(not(feature = "crossfont"), feature = "ab_glyph"))]
+    fn oversampling(self) -> (u32, u32) {
+        match self {
+            Self::None => (1, 1),
+            Self::Rgb | Self::Bgr => (3, 1),
+            Self::Vrgb | Self::Vbgr => (1, 3),
+        }
+    }
+}
+
+/// The rendering of the title glyphs, see [`FrameConfig::hinting`] and
+/// [`FrameConfig::subpixel_order`].
+///
+/// [`FrameConfig::hinting`]: crate::FrameConfig::hinting
+/// [`FrameConfig::subpixel_order`]: crate::FrameConfig::subpixel_order
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub(crate) struct TextRendering {
+    pub hinting: Hinting,
+    pub subpixel_order: SubpixelOrder,
+}
+
+/// The rendered line of the title, see [`TitleText::line`].
+#[derive(Debug, Copy, Clone)]
+pub(crate) struct TitleLine<'a> {
+    /// The text in its color.
+    pub pixmap: &'a Pixmap,
+    /// The background filter of the subpixel text, keeping `1 - coverage` of each channel,
+    /// multiplied into the background before the `pixmap` is added over it.
+    pub filter: Option<&'a Pixmap>,
+}
+
+impl<'a> From<&'a Pixmap> for TitleLine<'a> {
+    fn from(pixmap: &'a Pixmap) -> Self {
+        Self {
+            pixmap,
+            filter: None,
+        }
+    }
+}
+
 impl TitleText {
-    pub fn new(color: Color) -> Option<Self> {
+    /// Create the title with the titlebar font of the `fonts`, scaled by the `text_scale` of the
+    /// [`SystemPrefs`].
+    ///
+    /// [`SystemPrefs`]: crate::SystemPrefs
+    pub fn new(color: Color, text_scale: f32, fonts: &TitleFontCache) -> Result<Self, FrameError> {
+        Self::with_size(color, text_scale, fonts)
+    }
+
+    /// The text drawn under the title with the smaller font.
+    pub fn new_subtitle(
+        color: Color,
+        text_scale: f32,
+        fonts: &TitleFontCache,
+    ) -> Result<Self, FrameError> {
+        Self::with_size(color, SUBTITLE_SIZE * text_scale, fonts)
+    }
+
+    /// Create the text with the font size scaled by `size` relative to the titlebar font of the
+    /// `fonts`.
+    fn with_size(color: Color, size: f32, fonts: &TitleFontCache) -> Result<Self, FrameError> {
         #[cfg(feature = "crossfont")]
-        return crossfont_renderer::CrossfontTitleText::new(color)
-            .ok()
-            .map(|imp| Self { imp });
+        let _ = fonts;
+        #[cfg(feature = "crossfont")]
+        return crossfont_renderer::CrossfontTitleText::new(color, size)
+            .map(|imp| Self { imp })
+            .map_err(|err| FrameError::FontInit(err.to_string()));
 
         #[cfg(all(not(feature = "crossfont"), feature = "ab_glyph"))]
-        return Some(Self {
-            imp: ab_glyph_renderer::AbGlyphTitleText::new(color),
+        return Ok(Self {
+            imp: ab_glyph_renderer::AbGlyphTitleText::new(color, size, fonts.font()),
         });
 
         #[cfg(all(not(feature = "crossfont"), not(feature = "ab_glyph")))]
         {
-            let _ = color;
-            return None;
+            let _ = (color, size, fonts);
+            Err(FrameError::FontInit(
+                "no text renderer feature is enabled".into(),
+            ))
         }
     }
 
-    pub fn update_scale(&mut self, scale: u32) {
+    pub fn update_scale(&mut self, scale: TitleScale) {
         self.imp.update_scale(scale)
     }
 
@@ -55,7 +341,192 @@ impl TitleText {
         self.imp.update_color(color)
     }
 
-    pub fn pixmap(&self) -> Option<&Pixmap> {
-        self.imp.pixmap()
+    /// Update the width in pixels available for the title, which is ellipsized when it
+    /// doesn't fit.
+    pub fn update_max_width(&mut self, max_width: Option<u32>) {
+        self.imp.update_max_width(max_width)
+    }
+
+    /// Whether the title is ellipsized to fit into the width set by [`Self::update_max_width`].
+    pub fn is_truncated(&self) -> bool {
+        self.imp.is_truncated()
+    }
+
+    pub fn update_rendering(&mut self, rendering: TextRendering) {
+        self.imp.update_rendering(rendering)
+    }
+
+    pub fn cache_stats(&self) -> TitleCacheStats {
+        self.imp.cache_stats()
+    }
+
+    /// The rendered title with the filter of the subpixel text.
+    pub fn line(&self) -> Option<TitleLine<'_>> {
+        Some(TitleLine {
+            pixmap: self.imp.pixmap()?,
+            filter: self.imp.subpixel_filter(),
+        })
+    }
+}
+
+/// The title pre-rendered by the application, drawn in place of the [`TitleText`], see
+/// [`AdwaitaFrame::set_title_pixmap`].
+///
+/// [`AdwaitaFrame::set_title_pixmap`]: crate::AdwaitaFrame::set_title_pixmap
+#[derive(Debug)]
+pub(crate) struct TitlePixmap {
+    source: Pixmap,
+    /// The size of the title in the logical pixels.
+    logical_size: (u32, u32),
+    /// The `source` resized to the scale, with the scale.
+    scaled: Option<(TitleScale, Pixmap)>,
+    max_width: Option<u32>,
+}
+
+impl TitlePixmap {
+    pub fn new(source: Pixmap, logical_size: (u32, u32)) -> Self {
+        Self {
+            source,
+            logical_size,
+            scaled: None,
+            max_width: None,
+        }
+    }
+
+    pub fn update_scale(&mut self, scale: TitleScale) {
+        if self.scaled.as_ref().is_some_and(|(old, _)| *old == scale) {
+            return;
+        }
+
+        let width = scale.to_pixels(self.logical_size.0);
+        let height = scale.to_pixels(self.logical_size.1);
+        let scaled = if (width, height) == (self.source.width(), self.source.height()) {
+            Some(self.source.clone())
+        } else {
+            Pixmap::new(width, height).map(|mut pixmap| {
+                let transform = Transform::from_scale(
+                    width as f32 / self.source.width() as f32,
+                    height as f32 / self.source.height() as f32,
+                );
+                let paint = PixmapPaint {
+                    quality: FilterQuality::Bicubic,
+                    ..Default::default()
+                };
+                pixmap.draw_pixmap(0, 0, self.source.as_ref(), &paint, transform, None);
+                pixmap
+            })
+        };
+        self.scaled = scaled.map(|pixmap| (scale, pixmap));
+    }
+
+    /// Update the width in pixels available for the title, which is clipped when it doesn't
+    /// fit.
+    pub fn update_max_width(&mut self, max_width: Option<u32>) {
+        self.max_width = max_width;
     }
+
+    /// Whether the title is wider than the width set by [`Self::update_max_width`].
+    pub fn is_truncated(&self) -> bool {
+        match (&self.scaled, self.max_width) {
+            (Some((_, pixmap)), Some(max_width)) => pixmap.width() > max_width,
+            _ => false,
+        }
+    }
+
+    /// The title resized to the scale set by [`Self::update_scale`].
+    pub fn line(&self) -> Option<TitleLine<'_>> {
+        self.scaled.as_ref().map(|(_, pixmap)| pixmap.into())
+    }
+}
+
+/// Create the transparent pixmap reusing the allocation of the `old` one, so re-rendering the
+/// title allocates only when it grows.
+#[cfg(any(feature = "crossfont", feature = "ab_glyph"))]
+fn reuse_pixmap(old: Option<Pixmap>, width: u32, height: u32) -> Option<Pixmap> {
+    let size = IntSize::from_wh(width, height)?;
+    let Some(old) = old else {
+        return Pixmap::new(width, height);
+    };
+
+    let mut data = old.take();
+    data.clear();
+    data.resize(width as usize * height as usize * 4, 0);
+    Pixmap::from_vec(data, size)
+}
+
+#[cfg(any(feature = "crossfont", feature = "ab_glyph"))]
+#[test]
+fn header_height_follows_font_size() {
+    use crate::theme::HEADER_SIZE;
+
+    assert_eq!(header_height_for(11.), HEADER_SIZE);
+    assert_eq!(header_height_for(8.), HEADER_SIZE);
+    assert_eq!(header_height_for(16.), 41);
+    assert_eq!(header_height_for(72.), MAX_HEADER_SIZE);
+}
+
+#[cfg(any(feature = "crossfont", feature = "ab_glyph"))]
+#[test]
+fn title_reports_truncation() {
+    #[allow(clippy::unwrap_used)]
+    let mut title = TitleText::new(Color::BLACK, 1., &TitleFontCache::default()).unwrap();
+    title.update_title("The title of the window too long for the headerbar");
+    assert!(!title.is_truncated());
+
+    title.update_max_width(Some(50));
+    assert!(title.is_truncated());
+
+    title.update_max_width(Some(10_000));
+    assert!(!title.is_truncated());
+}
+
+#[cfg(any(feature = "crossfont", feature = "ab_glyph"))]
+#[test]
+fn reuse_pixmap_keeps_allocation() {
+    let mut old = Pixmap::new(10, 2);
+    if let Some(old) = old.as_mut() {
+        old.fill(Color::BLACK);
+    }
+    let ptr = old.as_ref().map(|old| old.data().as_ptr());
+
+    #[allow(clippy::unwrap_used)]
+    let pixmap = reuse_pixmap(old, 4, 5).unwrap();
+    assert_eq!(Some(pixmap.data().as_ptr()), ptr);
+    assert!(pixmap.pixels().iter().all(|pixel| pixel.alpha() == 0));
+    assert_eq!((pixmap.width(), pixmap.height()), (4, 5));
+}
+
+#[cfg(all(not(feature = "crossfont"), feature = "ab_glyph"))]
+#[test]
+fn font_cache_loads_font_once() {
+    let fonts = TitleFontCache::default();
+    let shared = fonts.clone();
+    assert!(Arc::ptr_eq(&fonts.font(), &shared.font()));
+    assert!(!Arc::ptr_eq(
+        &fonts.font(),
+        &TitleFontCache::default().font()
+    ));
+}
+
+#[test]
+#[allow(clippy::unwrap_used)]
+fn title_pixmap_follows_scale() {
+    let mut title = TitlePixmap::new(Pixmap::new(40, 10).unwrap(), (40, 10));
+    assert!(title.line().is_none());
+
+    title.update_scale(1.into());
+    assert_eq!(title.line().unwrap().pixmap.width(), 40);
+
+    title.update_scale(TitleScale::new(180, 120));
+    let line = title.line().unwrap();
+    assert_eq!((line.pixmap.width(), line.pixmap.height()), (60, 15));
+
+    title.update_scale(2.into());
+    let line = title.line().unwrap();
+    assert_eq!((line.pixmap.width(), line.pixmap.height()), (80, 20));
+
+    title.update_max_width(Some(60));
+    assert!(title.is_truncated());
+    title.update_max_width(Some(80));
+    assert!(!title.is_truncated());
 }
@@ -4,67 +4,201 @@
 //!
 //! Can fallback to a embedded Cantarell-Regular.ttf font (SIL Open Font Licence v1.1)
 //! if the system font doesn't work.
-use crate::title::{config, font_preference::FontPreference};
-use ab_glyph::{point, Font, FontRef, Glyph, PxScale, PxScaleFont, ScaleFont, VariableFont};
-use std::{fs::File, process::Command};
+#[cfg(feature = "fontconfig-parser")]
+use crate::title::fontconfig;
+#[cfg(feature = "shaping")]
+use crate::title::shaping;
+use crate::title::{
+    bidi,
+    cache::{TitleCache, TitleKey},
+    config,
+    font_preference::FontPreference,
+    kerning::PairKerning,
+    reuse_pixmap, Hinting, SubpixelOrder, TextRendering, TitleCacheStats, TitleScale,
+};
+use ab_glyph::{
+    point, Font, FontRef, Glyph, GlyphId, PxScale, PxScaleFont, ScaleFont, VariableFont,
+};
+use std::{fs::File, mem, process::Command, sync::Arc};
 use tiny_skia::{Color, Pixmap, PremultipliedColorU8};
 
 const CANTARELL: &[u8] = include_bytes!("Cantarell-Regular.ttf");
 
+/// The rendered title with its subpixel filter, and its natural and maximum widths.
+type Rendered = (Option<Pixmap>, Option<Pixmap>, f32, Option<u32>);
+
+/// The slant of the synthesized italic, as the horizontal shift per pixel of height.
+const SYNTHETIC_SLANT: f32 = 0.2;
+
+/// The system font file matching the [`FontPreference`].
+#[derive(Debug)]
+struct SystemFont {
+    mmap: memmap2::Mmap,
+    /// The index of the face in the font collection.
+    index: u32,
+    pref: FontPreference,
+}
+
+/// The title font matched with the preference once, shared by the titles created with the
+/// same [`TitleFontCache`](crate::TitleFontCache).
+#[derive(Debug)]
+pub struct SharedFont {
+    /// The system font, `None` to fall back to Cantarell.
+    font: Option<SystemFont>,
+    /// Whether the matched face is italic.
+    italic_face: bool,
+    pref: FontPreference,
+}
+
+impl SharedFont {
+    /// Match and map the titlebar font of the system configuration.
+    pub fn load() -> Self {
+        let pref = config::titlebar_font().unwrap_or_default();
+        let (font, italic_face) = match font_file_matching(&pref) {
+            Some((file, index, italic_face)) => (
+                mmap(&file)
+                    .filter(|mmap| {
+                        let parsed = FontRef::try_from_slice_and_index(mmap, index);
+                        if let Err(err) = &parsed {
+                            log::warn!("falling back to Cantarell, the face {index} of the title font: {err}");
+                        }
+                        parsed.is_ok()
+                    })
+                    .map(|mmap| SystemFont {
+                        mmap,
+                        index,
+                        pref: pref.clone(),
+                    }),
+                italic_face,
+            ),
+            None => (None, false),
+        };
+
+        Self {
+            font,
+            italic_face,
+            pref,
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct AbGlyphTitleText {
     title: String,
-    font: Option<(memmap2::Mmap, FontPreference)>,
+    font: Arc<SharedFont>,
+    /// Whether the title is slanted with a shear, for the fonts without an italic.
+    synthetic_italic: bool,
     original_px_size: f32,
     size: PxScale,
+    scale: TitleScale,
     color: Color,
+    /// The width available for the title in pixels.
+    max_width: Option<u32>,
+    /// The width of the title without the ellipsization.
+    natural_width: f32,
     pixmap: Option<Pixmap>,
+    /// The background filter of the subpixel title.
+    filter: Option<Pixmap>,
+    rendering: TextRendering,
+    /// The laid out glyphs, kept to reuse the allocation between the renders.
+    glyphs: Vec<Glyph>,
+    /// The coverage of the glyphs, kept to reuse the allocation between the renders.
+    coverage: Vec<f32>,
+    /// The titles rendered at the other scales, in the other colors or with the other texts,
+    /// with their filters and natural and maximum widths.
+    cache: TitleCache<Rendered>,
 }
 
 impl AbGlyphTitleText {
-    pub fn new(color: Color) -> Self {
-        let font_pref = config::titlebar_font().unwrap_or_default();
-        let font_pref_pt_size = font_pref.pt_size;
-        let font = font_file_matching(&font_pref)
-            .and_then(|f| mmap(&f))
-            .map(|mmap| (mmap, font_pref));
-
-        let size = parse_font(&font)
-            .pt_to_px_scale(font_pref_pt_size)
-            .unwrap_or_else(|| {
-                log::error!("invalid font units_per_em");
-                PxScale { x: 17.6, y: 17.6 }
-            });
+    pub fn new(color: Color, size_factor: f32, font: Arc<SharedFont>) -> Self {
+        let font_pref_pt_size = font.pref.pt_size * size_factor;
+        let italic = font.pref.italic();
+
+        let parsed = parse_font(&font.font);
+        let slanted_axis = parsed
+            .variations()
+            .iter()
+            .any(|axis| matches!(&axis.tag, b"ital" | b"slnt"));
+        let synthetic_italic =
+            italic && !(font.italic_face && font.font.is_some()) && !slanted_axis;
+
+        let size = parsed.pt_to_px_scale(font_pref_pt_size).unwrap_or_else(|| {
+            log::error!("invalid font units_per_em");
+            PxScale { x: 17.6, y: 17.6 }
+        });
 
         Self {
             title: <_>::default(),
             font,
+            synthetic_italic,
             original_px_size: size.x,
             size,
+            scale: 1.into(),
             color,
+            max_width: None,
+            natural_width: 0.,
             pixmap: None,
+            filter: None,
+            rendering: TextRendering::default(),
+            glyphs: Vec::new(),
+            coverage: Vec::new(),
+            cache: TitleCache::default(),
         }
     }
 
-    pub fn update_scale(&mut self, scale: u32) {
-        let new_scale = PxScale::from(self.original_px_size * scale as f32);
-        if (self.size.x - new_scale.x).abs() > f32::EPSILON {
-            self.size = new_scale;
-            self.pixmap = self.render();
+    pub fn update_scale(&mut self, scale: TitleScale) {
+        if scale == self.scale {
+            return;
         }
+
+        let old_key = self.key();
+        self.scale = scale;
+        self.size = PxScale::from(self.original_px_size * scale.factor());
+        self.rerender(old_key);
     }
 
     pub fn update_title(&mut self, title: impl Into<String>) {
         let new_title = title.into();
         if new_title != self.title {
+            let old_key = self.key();
             self.title = new_title;
-            self.pixmap = self.render();
+            self.rerender(old_key);
         }
     }
 
     pub fn update_color(&mut self, color: Color) {
         if color != self.color {
+            let old_key = self.key();
             self.color = color;
+            self.rerender(old_key);
+        }
+    }
+
+    pub fn update_max_width(&mut self, max_width: Option<u32>) {
+        if max_width == self.max_width {
+            return;
+        }
+
+        let old_max_width = mem::replace(&mut self.max_width, max_width);
+
+        // Only re-render if the title doesn't fit in one of the widths.
+        let fits = |max_width: Option<u32>| {
+            max_width.map_or(true, |max_width| self.natural_width <= max_width as f32)
+        };
+        if !fits(old_max_width) || !fits(max_width) {
+            self.pixmap = self.render();
+        }
+    }
+
+    pub fn is_truncated(&self) -> bool {
+        self.max_width
+            .is_some_and(|max_width| self.natural_width > max_width as f32)
+    }
+
+    pub fn update_rendering(&mut self, rendering: TextRendering) {
+        if rendering != self.rendering {
+            self.rendering = rendering;
+            self.cache.clear();
             self.pixmap = self.render();
         }
     }
@@ -73,21 +207,108 @@ impl AbGlyphTitleText {
         self.pixmap.as_ref()
     }
 
-    /// Render returning the new `Pixmap`.
-    fn render(&self) -> Option<Pixmap> {
-        let font = parse_font(&self.font);
-        let font = font.as_scaled(self.size);
+    /// The background filter of the subpixel title, `None` for the grayscale one.
+    pub fn subpixel_filter(&self) -> Option<&Pixmap> {
+        self.filter.as_ref()
+    }
+
+    pub fn cache_stats(&self) -> TitleCacheStats {
+        self.cache.stats()
+    }
+
+    fn key(&self) -> TitleKey {
+        TitleKey::new(self.scale, self.color, &self.title)
+    }
+
+    /// Show the title cached for the current key, or render it, keeping the one rendered for
+    /// the `old_key` in the cache.
+    fn rerender(&mut self, old_key: TitleKey) {
+        let rendered = (
+            self.pixmap.take(),
+            self.filter.take(),
+            self.natural_width,
+            self.max_width,
+        );
+        let evicted = self.cache.insert(old_key, rendered);
+        match self.cache.take(&self.key()) {
+            Some((pixmap, filter, natural_width, max_width)) => {
+                self.pixmap = pixmap;
+                self.filter = filter;
+                self.natural_width = natural_width;
+                self.max_width = max_width;
+            }
+            None => {
+                // Render into the allocation of the evicted title.
+                if let Some((pixmap, filter, ..)) = evicted {
+                    self.pixmap = pixmap;
+                    self.filter = filter;
+                }
+                self.pixmap = self.render();
+            }
+        }
+    }
 
+    /// Render returning the new `Pixmap`, drawn in place of the current one, and the
+    /// [`Self::subpixel_filter`] of it.
+    fn render(&mut self) -> Option<Pixmap> {
+        let font = parse_font(&self.font.font);
+        let size = hinted_size(&font, self.size, self.rendering.hinting);
+        let font = font.as_scaled(size);
+        let (data, index) = font_data(&self.font.font);
+        let face = ttf_parser::Face::parse(data, index).ok();
+        let kerning = face.as_ref().and_then(PairKerning::new);
+
+        self.natural_width = layout(
+            &self.title,
+            &font,
+            &self.font.font,
+            kerning.as_ref(),
+            self.max_width,
+            &mut self.glyphs,
+        );
+
+        let hinting = self.rendering.hinting;
+        let (sx, sy) = self.rendering.subpixel_order.oversampling();
+
+        // The shear of the synthesized italic in the oversampled coverage, leaning the glyphs
+        // right around the baseline.
+        let shear = if self.synthetic_italic {
+            SYNTHETIC_SLANT * sx as f32 / sy as f32
+        } else {
+            0.
+        };
+        let baseline = font.ascent() * sy as f32;
         let glyphs: Vec<_> = self
-            .layout(&font)
-            .into_iter()
+            .glyphs
+            .drain(..)
+            .map(|mut glyph| {
+                if hinting != Hinting::None {
+                    glyph.position.y = glyph.position.y.round();
+                }
+                if hinting == Hinting::Full {
+                    glyph.position.x = glyph.position.x.round();
+                }
+                // The subpixel glyphs are rasterized stretched over the color elements.
+                glyph.scale = PxScale {
+                    x: glyph.scale.x * sx as f32,
+                    y: glyph.scale.y * sy as f32,
+                };
+                glyph.position = point(glyph.position.x * sx as f32, glyph.position.y * sy as f32);
+                glyph
+            })
             .filter_map(|g| font.outline_glyph(g))
             .collect();
 
         // calc combined px bound coordinates of the rendered glyphs
         // Note: It is possible for min.x to be negative, e.g. the first glyph's
         //       outline extends a little out to the left further than the layout x origin 0.0
-        let all_px_bounds = glyphs.iter().map(|g| g.px_bounds()).reduce(|mut b, next| {
+        let sheared_bounds = |glyph: &ab_glyph::OutlinedGlyph| {
+            let mut bounds = glyph.px_bounds();
+            bounds.min.x += (baseline - bounds.max.y) * shear;
+            bounds.max.x = (bounds.max.x + (baseline - bounds.min.y) * shear).ceil();
+            bounds
+        };
+        let all_px_bounds = glyphs.iter().map(sheared_bounds).reduce(|mut b, next| {
             b.min.x = b.min.x.min(next.min.x);
             b.max.x = b.max.x.max(next.max.x);
             // min(0.0): consistently allocate enough for the whole ascent even
@@ -99,87 +320,380 @@ impl AbGlyphTitleText {
             b
         })?;
 
-        let width = all_px_bounds.width() as _;
-        let mut pixmap = Pixmap::new(width, all_px_bounds.height() as _)?;
-        let pixels = pixmap.pixels_mut();
+        // Start the coverage at the whole pixel, so the subpixels keep their colors.
+        let left = (all_px_bounds.min.x / sx as f32).floor() * sx as f32;
+        let top = (all_px_bounds.min.y / sy as f32).floor() * sy as f32;
+        let coverage_w = (all_px_bounds.max.x - left) as usize;
+        let coverage_h = (all_px_bounds.max.y - top) as usize;
+        let coverage = &mut self.coverage;
+        coverage.clear();
+        coverage.resize(coverage_w * coverage_h, 0.);
 
         for glyph in glyphs {
             let bounds = glyph.px_bounds();
-            // calc top/left ords in pixmap space
-            // pixmap-x=0 means the *left most pixel*, equivalent to
-            // px_bounds.min.x which *may be non-zero* (and similarly with y)
-            // so `- px_bounds.min` converts the left-most/top-most to 0
-            let pixmap_left = bounds.min.x as u32 - all_px_bounds.min.x as u32;
-            let pixmap_top = bounds.min.y as u32 - all_px_bounds.min.y as u32;
+            // calc top/left ords in the coverage space
+            let glyph_left = bounds.min.x - left;
+            let glyph_top = (bounds.min.y - top) as usize;
             glyph.draw(|x, y, c| {
-                // `ab_glyph` may return values greater than 1.0, but they are defined to be
-                // same as 1.0. For our purposes, we need to constrain this value.
-                let c = c.min(1.0);
-
-                let p_idx = (pixmap_top + y) * width + pixmap_left + x;
-                let Some(pixel) = pixels.get_mut(p_idx as usize) else {
-                    debug_assert!(
-                        false,
-                        "oob pixel: x={x} y={y} top={pixmap_top} left={pixmap_left}, w={width}"
-                    );
-                    return;
-                };
-
-                let old_alpha_u8 = pixel.alpha();
+                // The sheared pixel covers the two neighbouring ones.
+                let offset = (baseline - bounds.min.y - y as f32 - 0.5) * shear;
+                let x = glyph_left + x as f32 + offset;
+                let (column, fract) = (x.floor(), x - x.floor());
+                let row = (glyph_top + y as usize) * coverage_w;
+                for (column, c) in [(column, c * (1. - fract)), (column + 1., c * fract)] {
+                    if c == 0. {
+                        continue;
+                    }
+                    let Some(value) = coverage.get_mut(row + column as usize) else {
+                        debug_assert!(
+                            false,
+                            "oob pixel: x={x} y={y} top={glyph_top} left={glyph_left}, w={coverage_w}"
+                        );
+                        return;
+                    };
 
-                let new_alpha = c + (old_alpha_u8 as f32 / 255.0);
-                if let Some(px) = PremultipliedColorU8::from_rgba(
-                    (self.color.red() * new_alpha * 255.0) as _,
-                    (self.color.green() * new_alpha * 255.0) as _,
-                    (self.color.blue() * new_alpha * 255.0) as _,
-                    (new_alpha * 255.0) as _,
-                ) {
-                    *pixel = px;
+                    // `ab_glyph` may return values greater than 1.0, but they are defined to be
+                    // same as 1.0. For our purposes, we need to constrain this value.
+                    *value = (*value + c).min(1.0);
                 }
             })
         }
 
+        if (sx, sy) == (1, 1) {
+            self.filter = None;
+            let mut pixmap = reuse_pixmap(self.pixmap.take(), coverage_w as _, coverage_h as _)?;
+            for (pixel, c) in pixmap.pixels_mut().iter_mut().zip(&self.coverage) {
+                *pixel = premultiplied(self.color, [*c; 3]);
+            }
+            return Some(pixmap);
+        }
+
+        // Pad the sides with the pixel the filter spreads the coverage into.
+        let (pad_x, pad_y) = ((sx > 1) as usize, (sy > 1) as usize);
+        let width = coverage_w.div_ceil(sx as usize) + 2 * pad_x;
+        let height = coverage_h.div_ceil(sy as usize) + 2 * pad_y;
+        let mut pixmap = reuse_pixmap(self.pixmap.take(), width as _, height as _)?;
+        let mut filter = reuse_pixmap(self.filter.take(), width as _, height as _)?;
+
+        let sample = |x: isize, y: isize| {
+            let inside =
+                (0..coverage_w as isize).contains(&x) && (0..coverage_h as isize).contains(&y);
+            if inside {
+                self.coverage[y as usize * coverage_w + x as usize]
+            } else {
+                0.
+            }
+        };
+        let (step_x, step_y) = (pad_x as isize, pad_y as isize);
+        let reversed = matches!(
+            self.rendering.subpixel_order,
+            SubpixelOrder::Bgr | SubpixelOrder::Vbgr
+        );
+        for (idx, (pixel, filter)) in pixmap
+            .pixels_mut()
+            .iter_mut()
+            .zip(filter.pixels_mut())
+            .enumerate()
+        {
+            let x = (idx % width) as isize - step_x;
+            let y = (idx / width) as isize - step_y;
+            let mut channels = [0.; 3];
+            for (element, channel) in channels.iter_mut().enumerate() {
+                let element = element as isize;
+                let x = x * sx as isize + element * step_x;
+                let y = y * sy as isize + element * step_y;
+                *channel = LCD_FILTER
+                    .iter()
+                    .zip(-2..)
+                    .map(|(weight, d)| weight * sample(x + d * step_x, y + d * step_y))
+                    .sum::<f32>()
+                    .min(1.);
+            }
+            if reversed {
+                channels.reverse();
+            }
+
+            *pixel = premultiplied(self.color, channels);
+            let [r, g, b] = channels.map(|c| ((1. - c) * 255.) as u8);
+            *filter = PremultipliedColorU8::from_rgba(r, g, b, 255)
+                .unwrap_or(PremultipliedColorU8::TRANSPARENT);
+        }
+
+        self.filter = Some(filter);
         Some(pixmap)
     }
+}
+
+/// The weights of the neighbouring subpixels spreading the coverage to reduce the color
+/// fringes, like the default LCD filter of FreeType.
+const LCD_FILTER: [f32; 5] = [8. / 256., 77. / 256., 86. / 256., 77. / 256., 8. / 256.];
+
+/// The text `color` with the `coverage` of each channel, as opaque as the most covered one.
+fn premultiplied(color: Color, coverage: [f32; 3]) -> PremultipliedColorU8 {
+    let alpha = coverage.into_iter().fold(0., f32::max);
+    PremultipliedColorU8::from_rgba(
+        (color.red() * coverage[0] * 255.0) as _,
+        (color.green() * coverage[1] * 255.0) as _,
+        (color.blue() * coverage[2] * 255.0) as _,
+        (alpha * 255.0) as _,
+    )
+    .unwrap_or(PremultipliedColorU8::TRANSPARENT)
+}
+
+/// The `size` with the x-height of the `font` snapped to the whole pixels by the `hinting`.
+fn hinted_size(font: &FontRef<'_>, size: PxScale, hinting: Hinting) -> PxScale {
+    if hinting == Hinting::None {
+        return size;
+    }
+
+    let Some(outline) = font.outline(font.glyph_id('x')) else {
+        return size;
+    };
+    let x_height = outline.bounds.height().abs() * font.as_scaled(size).v_scale_factor();
+    if x_height <= 0. {
+        return size;
+    }
+
+    let factor = x_height.round().max(1.) / x_height;
+    PxScale {
+        x: size.x * factor,
+        y: size.y * factor,
+    }
+}
+
+/// The glyph of the title in the visual order.
+#[derive(Debug, Clone, Copy)]
+struct RunGlyph {
+    /// The first character of the glyph cluster.
+    c: char,
+    id: GlyphId,
+    /// The advance and the offset of the shaped glyph in font units, `None` to use the advance
+    /// of the font with the pair kerning.
+    shaped: Option<(f32, f32, f32)>,
+}
 
-    /// Simple single-line glyph layout starting from `(0, ascent)`.
-    fn layout(&self, font: &PxScaleFont<impl Font>) -> Vec<Glyph> {
-        let mut caret = point(0.0, font.ascent());
-        let mut last_glyph: Option<Glyph> = None;
-        let mut target = Vec::new();
-        for c in self.title.chars() {
-            if c.is_control() {
-                continue;
+/// Map the title characters to the glyphs of the font in the visual order, without shaping.
+///
+/// Returns the glyphs and whether the paragraph direction is right-to-left.
+fn unshaped_glyph_run(title: &str, font: &PxScaleFont<impl Font>) -> (Vec<RunGlyph>, bool) {
+    let (title, rtl) = bidi::visual_order(title);
+    let run = title
+        .chars()
+        .filter(|c| !c.is_control())
+        .map(|c| RunGlyph {
+            c,
+            id: font.glyph_id(c),
+            shaped: None,
+        })
+        .collect();
+
+    (run, rtl)
+}
+
+/// Map the title characters to the glyphs in the visual order.
+///
+/// Returns the glyphs and whether the paragraph direction is right-to-left.
+#[cfg(not(feature = "shaping"))]
+fn glyph_run(
+    title: &str,
+    font: &PxScaleFont<impl Font>,
+    _sys_font: &Option<SystemFont>,
+) -> (Vec<RunGlyph>, bool) {
+    unshaped_glyph_run(title, font)
+}
+
+/// Shape the runs of the title in a single direction, in the visual order.
+///
+/// Returns the glyphs and whether the paragraph direction is right-to-left.
+#[cfg(feature = "shaping")]
+fn glyph_run(
+    title: &str,
+    font: &PxScaleFont<impl Font>,
+    sys_font: &Option<SystemFont>,
+) -> (Vec<RunGlyph>, bool) {
+    let (data, index) = font_data(sys_font);
+    let Some(mut face) = rustybuzz::Face::from_slice(data, index) else {
+        return unshaped_glyph_run(title, font);
+    };
+    if let Some(SystemFont { pref, .. }) = sys_font {
+        select_instance(pref, |tag, value| {
+            face.set_variation(rustybuzz::ttf_parser::Tag::from_bytes(tag), value)
+                .is_some()
+        });
+    }
+
+    let (runs, rtl) = bidi::visual_runs(title);
+    let run = runs
+        .into_iter()
+        .flat_map(|(range, run_rtl)| {
+            let text = &title[range];
+            shaping::shape(&face, text, run_rtl)
+                .into_iter()
+                .filter_map(move |glyph| {
+                    let c = text[glyph.cluster..].chars().next()?;
+                    Some(RunGlyph {
+                        c,
+                        id: GlyphId(glyph.id),
+                        shaped: Some((
+                            glyph.x_advance as f32,
+                            glyph.x_offset as f32,
+                            glyph.y_offset as f32,
+                        )),
+                    })
+                })
+        })
+        .filter(|glyph| !glyph.c.is_control())
+        .collect();
+
+    (run, rtl)
+}
+
+/// Simple single-line glyph layout starting from `(0, ascent)`.
+///
+/// The title is laid out in the visual order. When it's wider than `max_width`, it's
+/// truncated at the glyph boundary and the ellipsis is added at its logical end. The glyphs
+/// replace the content of `glyphs`, and the width of the untruncated title is returned.
+///
+/// The unshaped glyphs are kerned with the GPOS `kerning` of the font, or with its legacy
+/// `kern` table when it has none.
+fn layout(
+    title: &str,
+    font: &PxScaleFont<impl Font>,
+    sys_font: &Option<SystemFont>,
+    kerning: Option<&PairKerning<'_>>,
+    max_width: Option<u32>,
+    glyphs: &mut Vec<Glyph>,
+) -> f32 {
+    glyphs.clear();
+
+    let (run, rtl) = glyph_run(title, font, sys_font);
+
+    let mut caret = point(0.0, font.ascent());
+    let mut last_glyph: Option<GlyphId> = None;
+    let mut target: Vec<(char, Glyph)> = Vec::with_capacity(run.len());
+    for run_glyph in &run {
+        let glyph = match run_glyph.shaped {
+            Some((advance, dx, dy)) => {
+                let position = point(
+                    caret.x + dx * font.h_scale_factor(),
+                    caret.y - dy * font.v_scale_factor(),
+                );
+                caret.x += advance * font.h_scale_factor();
+                run_glyph.id.with_scale_and_position(font.scale(), position)
             }
-            let mut glyph = font.scaled_glyph(c);
-            if let Some(previous) = last_glyph.take() {
-                caret.x += font.kern(previous.id, glyph.id);
+            None => {
+                if let Some(previous) = last_glyph.take() {
+                    caret.x += match kerning {
+                        Some(kerning) => {
+                            kerning.kern(previous.0, run_glyph.id.0) * font.h_scale_factor()
+                        }
+                        None => font.kern(previous, run_glyph.id),
+                    };
+                }
+
+                let glyph = run_glyph.id.with_scale_and_position(font.scale(), caret);
+                last_glyph = Some(glyph.id);
+                caret.x += font.h_advance(glyph.id);
+                glyph
             }
-            glyph.position = caret;
+        };
+
+        target.push((run_glyph.c, glyph));
+    }
+
+    let natural_width = caret.x;
+    let fits = max_width.map_or(true, |max_width| natural_width <= max_width as f32);
+    let Some(max_width) = max_width
+        .map(|max_width| max_width as f32)
+        .filter(|_| !fits)
+    else {
+        glyphs.extend(target.into_iter().map(|(_, g)| g));
+        return natural_width;
+    };
+
+    let ellipsis = ellipsis_glyphs(font);
+    let ellipsis_width: f32 = ellipsis.iter().map(|g| font.h_advance(g.id)).sum();
 
-            last_glyph = Some(glyph.clone());
-            caret.x += font.h_advance(glyph.id);
+    // The logical end of the right-to-left title is on the left.
+    if rtl {
+        let start = target
+            .iter()
+            .position(|(c, glyph)| {
+                !c.is_whitespace() && natural_width - glyph.position.x + ellipsis_width <= max_width
+            })
+            .unwrap_or(target.len());
+        let shift = target
+            .get(start)
+            .map(|(_, glyph)| glyph.position.x)
+            .unwrap_or(0.0)
+            - ellipsis_width;
+
+        let mut caret_x = 0.0;
+        for mut glyph in ellipsis {
+            glyph.position = point(caret_x, font.ascent());
+            caret_x += font.h_advance(glyph.id);
+            glyphs.push(glyph);
+        }
+        glyphs.extend(target.into_iter().skip(start).map(|(_, mut glyph)| {
+            glyph.position.x -= shift;
+            glyph
+        }));
+
+        return natural_width;
+    }
 
-            target.push(glyph);
+    // Drop the glyphs until the ellipsis fits, and don't leave the whitespace before it.
+    while let Some((c, glyph)) = target.last() {
+        let end_x = glyph.position.x + font.h_advance(glyph.id);
+        if !c.is_whitespace() && end_x + ellipsis_width <= max_width {
+            break;
         }
-        target
+        target.pop();
+    }
+
+    let mut caret_x = target
+        .last()
+        .map(|(_, glyph)| glyph.position.x + font.h_advance(glyph.id))
+        .unwrap_or(0.0);
+
+    glyphs.extend(target.into_iter().map(|(_, g)| g));
+    for mut glyph in ellipsis {
+        glyph.position = point(caret_x, font.ascent());
+        caret_x += font.h_advance(glyph.id);
+        glyphs.push(glyph);
+    }
+
+    natural_width
+}
+
+/// The glyphs for the "…", falling back to "..." when the font doesn't have it.
+fn ellipsis_glyphs(font: &PxScaleFont<impl Font>) -> Vec<Glyph> {
+    let glyph = font.scaled_glyph('…');
+    if glyph.id.0 != 0 {
+        vec![glyph]
+    } else {
+        vec![font.scaled_glyph('.'); 3]
+    }
+}
+
+/// Select the instance of the variable font matching the `pref` with its `set_variation`,
+/// which returns whether the font has the axis.
+fn select_instance(pref: &FontPreference, mut set_variation: impl FnMut(&[u8; 4], f32) -> bool) {
+    if let Some(weight) = pref.weight() {
+        set_variation(b"wght", weight);
+    }
+    if pref.italic() && !set_variation(b"ital", 1.0) {
+        set_variation(b"slnt", -12.0);
     }
 }
 
 /// Parse the memmapped system font or fallback to built-in cantarell.
-fn parse_font(sys_font: &Option<(memmap2::Mmap, FontPreference)>) -> FontRef<'_> {
+fn parse_font(sys_font: &Option<SystemFont>) -> FontRef<'_> {
     match sys_font {
-        Some((mmap, font_pref)) => {
-            FontRef::try_from_slice(mmap)
+        Some(SystemFont { mmap, index, pref }) => {
+            FontRef::try_from_slice_and_index(mmap, *index)
                 .map(|mut f| {
-                    // basic "bold" handling for variable fonts
-                    if font_pref
-                        .style
-                        .as_deref()
-                        .map_or(false, |s| s.eq_ignore_ascii_case("bold"))
-                    {
-                        f.set_variation(b"wght", 700.0);
-                    }
+                    select_instance(pref, |tag, value| f.set_variation(tag, value));
                     f
                 })
                 .unwrap_or_else(|_| {
@@ -194,24 +708,227 @@ fn parse_font(sys_font: &Option<(memmap2::Mmap, FontPreference)>) -> FontRef<'_>
     }
 }
 
-/// Font-config without dynamically linked dependencies
-fn font_file_matching(pref: &FontPreference) -> Option<File> {
-    let mut pattern = pref.name.clone();
-    if let Some(style) = &pref.style {
-        pattern.push(':');
-        pattern.push_str(style);
+/// The data and the face index of the font used for the title, see `parse_font`.
+fn font_data(sys_font: &Option<SystemFont>) -> (&[u8], u32) {
+    match sys_font {
+        Some(SystemFont { mmap, index, .. }) => (mmap, *index),
+        None => (CANTARELL, 0),
     }
-    Command::new("fc-match")
+}
+
+/// Font-config without dynamically linked dependencies
+///
+/// Returns the font file with the index of the face in it, and whether the face is italic.
+fn font_file_matching(pref: &FontPreference) -> Option<(File, u32, bool)> {
+    let out = match Command::new("fc-match")
         .arg("-f")
-        .arg("%{file}")
-        .arg(&pattern)
+        .arg("%{file}\n%{index}\n%{slant}")
+        .arg(pref.fontconfig_pattern())
         .output()
-        .ok()
-        .and_then(|out| String::from_utf8(out.stdout).ok())
-        .and_then(|path| File::open(path.trim()).ok())
+    {
+        Ok(out) => out,
+        // Without the fontconfig tools, read its configuration instead.
+        #[cfg(feature = "fontconfig-parser")]
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+            return fontconfig::font_file_matching(pref);
+        }
+        Err(_) => return None,
+    };
+    let out = String::from_utf8(out.stdout).ok()?;
+    let (file, index, slant) = parse_fc_match(&out)?;
+    Some((File::open(file).ok()?, index, slant))
+}
+
+/// Parse the `fc-match` output of the file, the index and the slant of the face.
+fn parse_fc_match(out: &str) -> Option<(&str, u32, bool)> {
+    let mut lines = out.lines();
+    let file = lines.next()?.trim();
+    // The upper bits are the named instance of the variable fonts.
+    let index = lines
+        .next()
+        .and_then(|index| index.trim().parse::<u32>().ok());
+    let slant = lines
+        .next()
+        .and_then(|slant| slant.trim().parse::<u32>().ok());
+    Some((file, index.unwrap_or(0) & 0xFFFF, slant.unwrap_or(0) > 0))
 }
 
 fn mmap(file: &File) -> Option<memmap2::Mmap> {
     // Safety: System font files are not expected to be mutated during use
     unsafe { memmap2::Mmap::map(file).ok() }
 }
+
+#[test]
+fn layout_ellipsizes_long_title() {
+    #[allow(clippy::unwrap_used)]
+    let font = FontRef::try_from_slice(CANTARELL).unwrap();
+    let font = font.as_scaled(PxScale::from(17.6));
+    let title = "A very long title which certainly doesn't fit";
+
+    let mut glyphs = Vec::new();
+    let natural_width = layout(title, &font, &None, None, None, &mut glyphs);
+    assert_eq!(glyphs.len(), title.chars().count());
+
+    let max_width = natural_width / 2.;
+    layout(
+        title,
+        &font,
+        &None,
+        None,
+        Some(max_width as u32),
+        &mut glyphs,
+    );
+    #[allow(clippy::unwrap_used)]
+    let last = glyphs.last().unwrap();
+    assert_eq!(last.id, font.glyph_id('…'));
+    assert!(last.position.x + font.h_advance(last.id) <= max_width);
+}
+
+// The shaped titles are kerned by rustybuzz.
+#[cfg(not(feature = "shaping"))]
+#[test]
+fn layout_applies_gpos_kerning() {
+    #[allow(clippy::unwrap_used)]
+    let font = FontRef::try_from_slice(CANTARELL).unwrap();
+    let font = font.as_scaled(PxScale::from(17.6));
+    let (a, v) = (font.glyph_id('A'), font.glyph_id('V'));
+    let gpos = crate::title::kerning::pair_gpos(a.0, v.0, -100);
+    let face = crate::title::kerning::face_with_gpos(CANTARELL, &gpos);
+    let kerning = PairKerning::new(&face);
+
+    let mut glyphs = Vec::new();
+    let width = layout("AV", &font, &None, None, None, &mut glyphs);
+    assert!((width - font.h_advance(a) - font.h_advance(v)).abs() < 0.01);
+
+    let kerned_width = layout("AV", &font, &None, kerning.as_ref(), None, &mut glyphs);
+    assert!((width - 100. * font.h_scale_factor() - kerned_width).abs() < 0.01);
+    assert!((glyphs[1].position.x - kerned_width + font.h_advance(v)).abs() < 0.01);
+}
+
+#[test]
+fn layout_ellipsizes_rtl_title_on_the_left() {
+    #[allow(clippy::unwrap_used)]
+    let font = FontRef::try_from_slice(CANTARELL).unwrap();
+    let font = font.as_scaled(PxScale::from(17.6));
+    let title = "שלום עולם שלום עולם שלום עולם";
+
+    let mut glyphs = Vec::new();
+    let natural_width = layout(title, &font, &None, None, None, &mut glyphs);
+    let max_width = natural_width / 2.;
+    layout(
+        title,
+        &font,
+        &None,
+        None,
+        Some(max_width as u32),
+        &mut glyphs,
+    );
+    assert_eq!(glyphs[0].id, font.glyph_id('…'));
+    assert!(glyphs[0].position.x.abs() < f32::EPSILON);
+}
+
+#[test]
+fn rerender_reuses_pixmap() {
+    let mut title = AbGlyphTitleText::new(Color::BLACK, 1., Arc::new(SharedFont::load()));
+    title.update_title("Title");
+    let ptr = title.pixmap().map(|pixmap| pixmap.data().as_ptr());
+    assert!(ptr.is_some());
+
+    // Toggling the activation shows the cached title again.
+    title.update_color(Color::WHITE);
+    assert_ne!(title.pixmap().map(|pixmap| pixmap.data().as_ptr()), ptr);
+    title.update_color(Color::BLACK);
+    assert_eq!(title.pixmap().map(|pixmap| pixmap.data().as_ptr()), ptr);
+    assert_eq!(title.cache_stats().hits, 1);
+
+    // The title evicted from the full cache lends its allocation to the new one.
+    for idx in 0.. {
+        title.update_title(format!("Title {idx}"));
+        if title.cache_stats().evictions > 0 {
+            break;
+        }
+    }
+    let evicted = title.cache_stats().evictions;
+    title.update_title("Another title");
+    assert_eq!(title.cache_stats().evictions, evicted + 1);
+}
+
+#[test]
+fn hinting_snaps_x_height() {
+    #[allow(clippy::unwrap_used)]
+    let font = FontRef::try_from_slice(CANTARELL).unwrap();
+    #[allow(clippy::unwrap_used)]
+    let x_height = font
+        .outline(font.glyph_id('x'))
+        .unwrap()
+        .bounds
+        .height()
+        .abs();
+    let size = PxScale::from(17.6);
+
+    let x_height_at = |size: PxScale| x_height * font.as_scaled(size).v_scale_factor();
+    assert!(x_height_at(size).fract().abs() > 0.01);
+    assert_eq!(hinted_size(&font, size, Hinting::None), size);
+    let hinted = x_height_at(hinted_size(&font, size, Hinting::Slight));
+    assert!((hinted - hinted.round()).abs() < 0.001);
+}
+
+#[test]
+fn title_follows_fractional_scale() {
+    let mut title = AbGlyphTitleText::new(Color::BLACK, 1., Arc::new(SharedFont::load()));
+    title.update_title("Fractional title");
+    let width = |title: &AbGlyphTitleText| title.pixmap().map_or(0, |pixmap| pixmap.width());
+    let normal = width(&title);
+
+    title.update_scale(TitleScale::new(180, 120));
+    let fractional = width(&title);
+    assert!(fractional.abs_diff(normal * 3 / 2) <= 2);
+
+    // The same scale in the lowest terms is taken from the cache.
+    let stats = title.cache_stats();
+    title.update_scale(1.into());
+    title.update_scale(TitleScale::new(3, 2));
+    assert_eq!(width(&title), fractional);
+    assert_eq!(title.cache_stats().hits, stats.hits + 2);
+}
+
+#[test]
+fn subpixel_title_filters_background() {
+    let mut title = AbGlyphTitleText::new(Color::BLACK, 1., Arc::new(SharedFont::load()));
+    title.update_title("Title");
+    let grayscale_width = title.pixmap().map(|pixmap| pixmap.width());
+    assert!(title.subpixel_filter().is_none());
+
+    title.update_rendering(TextRendering {
+        hinting: Hinting::Full,
+        subpixel_order: SubpixelOrder::Rgb,
+    });
+    #[allow(clippy::unwrap_used)]
+    let (pixmap, filter) = (title.pixmap().unwrap(), title.subpixel_filter().unwrap());
+    assert_eq!(pixmap.width(), filter.width());
+    assert!(grayscale_width.is_some_and(|width| pixmap.width().abs_diff(width) <= 3));
+
+    // The edges of the glyphs cover the color elements unevenly.
+    let fringes = filter
+        .pixels()
+        .iter()
+        .filter(|pixel| pixel.red() != pixel.blue())
+        .count();
+    assert!(fringes > 0);
+    assert!(filter.pixels().iter().all(|pixel| pixel.alpha() == 255));
+}
+
+#[test]
+fn fc_match_selects_collection_face() {
+    let out = "/usr/share/fonts/noto/NotoSansCJK-Regular.ttc\n65538\n100";
+    assert_eq!(
+        parse_fc_match(out),
+        Some(("/usr/share/fonts/noto/NotoSansCJK-Regular.ttc", 2, true))
+    );
+
+    let out = "/usr/share/fonts/cantarell/Cantarell-VF.otf\n0\n0";
+    assert_eq!(
+        parse_fc_match(out),
+        Some(("/usr/share/fonts/cantarell/Cantarell-VF.otf", 0, false))
+    );
+}
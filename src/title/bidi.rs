@@ -0,0 +1,149 @@
+//! Bidirectional text reordering for the single-line titles.
+//!
+//! The embedding levels and the reordering come from the Unicode Bidirectional Algorithm
+//! (UAX #9) of the `unicode-bidi` crate, which leaves out the mirroring of the paired
+//! punctuation done here.
+use std::ops::Range;
+
+use unicode_bidi::{BidiInfo, Level};
+
+/// The embedding levels of the characters of the `line` of the paragraph in the logical order.
+fn line_levels(info: &BidiInfo<'_>, line: Range<usize>) -> Vec<Level> {
+    let Some(para) = info
+        .paragraphs
+        .iter()
+        .find(|para| para.range.contains(&line.start))
+    else {
+        return Vec::new();
+    };
+
+    let levels = info.reordered_levels(para, line.clone());
+    info.text[line.clone()]
+        .char_indices()
+        .map(|(idx, _)| levels[line.start + idx])
+        .collect()
+}
+
+/// Whether the direction of the first paragraph of the text is right-to-left.
+fn is_rtl(info: &BidiInfo<'_>) -> bool {
+    info.paragraphs
+        .first()
+        .is_some_and(|para| para.level.is_rtl())
+}
+
+/// Split the `text` into the runs displayed in a single direction, in the visual order.
+///
+/// Returns the byte ranges of the runs with whether they're right-to-left, and whether the
+/// paragraph direction is right-to-left.
+#[cfg(feature = "shaping")]
+pub fn visual_runs(text: &str) -> (Vec<(Range<usize>, bool)>, bool) {
+    let info = BidiInfo::new(text, None);
+    let runs = info
+        .paragraphs
+        .iter()
+        .flat_map(|para| {
+            let (levels, runs) = info.visual_runs(para, para.range.clone());
+            runs.into_iter().map(move |run| {
+                let rtl = levels[run.start].is_rtl();
+                (run, rtl)
+            })
+        })
+        .collect();
+
+    (runs, is_rtl(&info))
+}
+
+/// Mirror the paired punctuation displayed right-to-left.
+pub fn mirror(c: char, level: u8) -> char {
+    if level % 2 == 0 {
+        return c;
+    }
+
+    match c {
+        '(' => ')',
+        ')' => '(',
+        '[' => ']',
+        ']' => '[',
+        '{' => '}',
+        '}' => '{',
+        '<' => '>',
+        '>' => '<',
+        '«' => '»',
+        '»' => '«',
+        '‹' => '›',
+        '›' => '‹',
+        c => c,
+    }
+}
+
+/// Reorder the `text` from the logical to the visual order.
+///
+/// Returns the reordered text and whether the paragraph direction is right-to-left.
+pub fn visual_order(text: &str) -> (String, bool) {
+    let info = BidiInfo::new(text, None);
+    if !info.has_rtl() {
+        return (text.to_owned(), false);
+    }
+
+    let text = info
+        .paragraphs
+        .iter()
+        .flat_map(|para| {
+            let line = para.range.clone();
+            let levels = line_levels(&info, line.clone());
+            let order = BidiInfo::reorder_visual(&levels);
+            let visual: Vec<char> = info.reorder_line(para, line).chars().collect();
+            visual
+                .into_iter()
+                .zip(order)
+                .map(move |(c, idx)| mirror(c, levels[idx].number()))
+        })
+        .collect();
+
+    (text, is_rtl(&info))
+}
+
+#[test]
+fn ltr_is_untouched() {
+    assert_eq!(
+        visual_order("Hello (world)"),
+        ("Hello (world)".into(), false)
+    );
+}
+
+#[test]
+fn rtl_is_reversed() {
+    assert_eq!(visual_order("שלום"), ("םולש".into(), true));
+}
+
+#[test]
+fn rtl_with_numbers_and_latin() {
+    // The latin words and the numbers after them keep their order inside the right-to-left
+    // paragraph.
+    assert_eq!(visual_order("שלום abc 123"), ("abc 123 םולש".into(), true));
+    assert_eq!(visual_order("שלום 123 abc"), ("abc 123 םולש".into(), true));
+}
+
+#[test]
+fn rtl_inside_ltr() {
+    assert_eq!(visual_order("abc שלום (x)"), ("abc םולש (x)".into(), false));
+}
+
+#[test]
+fn numbers_keep_their_order() {
+    assert_eq!(visual_order("abc 1.5 שלום"), ("abc 1.5 םולש".into(), false));
+    assert_eq!(visual_order("שלום 1.5"), ("1.5 םולש".into(), true));
+}
+
+#[cfg(feature = "shaping")]
+#[test]
+fn visual_runs_split_directions() {
+    let (runs, rtl) = visual_runs("abc שלום 1.5");
+    assert!(!rtl);
+    assert_eq!(runs, [(0..4, false), (13..16, false), (4..13, true)]);
+}
+
+#[test]
+fn rtl_mirrors_brackets() {
+    assert_eq!(visual_order("שלום (עולם)"), ("(םלוע) םולש".into(), true));
+}
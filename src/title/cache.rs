@@ -0,0 +1,100 @@
+//! The rendered titles kept for reuse, see [`TitleCache`].
+use std::collections::VecDeque;
+
+use tiny_skia::Color;
+
+use crate::title::{TitleCacheStats, TitleScale};
+
+/// The number of the rendered titles kept besides the shown one.
+const CAPACITY: usize = 8;
+
+/// What the title is rendered for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TitleKey {
+    scale: TitleScale,
+    color: [u8; 4],
+    title: String,
+}
+
+impl TitleKey {
+    pub fn new(scale: TitleScale, color: Color, title: &str) -> Self {
+        let color = color.to_color_u8();
+        Self {
+            scale,
+            color: [color.red(), color.green(), color.blue(), color.alpha()],
+            title: title.to_owned(),
+        }
+    }
+}
+
+/// The least recently used titles rendered at the other scales, in the other colors or with the
+/// other texts, so toggling the activation or moving the window between outputs doesn't
+/// re-render them.
+#[derive(Debug)]
+pub struct TitleCache<V> {
+    /// The entries from the least to the most recently used.
+    entries: VecDeque<(TitleKey, V)>,
+    stats: TitleCacheStats,
+}
+
+impl<V> Default for TitleCache<V> {
+    fn default() -> Self {
+        Self {
+            entries: VecDeque::with_capacity(CAPACITY),
+            stats: TitleCacheStats::default(),
+        }
+    }
+}
+
+impl<V> TitleCache<V> {
+    /// Keep the `value` rendered for the `key`, returning the least recently used one evicted to
+    /// make room for it, so its allocation could be reused.
+    pub fn insert(&mut self, key: TitleKey, value: V) -> Option<V> {
+        self.entries.retain(|(other, _)| *other != key);
+        let evicted = (self.entries.len() >= CAPACITY)
+            .then(|| self.entries.pop_front())
+            .flatten()
+            .map(|(_, value)| value);
+        self.stats.evictions += evicted.is_some() as u64;
+        self.entries.push_back((key, value));
+        evicted
+    }
+
+    /// Take the value rendered for the `key` out of the cache.
+    pub fn take(&mut self, key: &TitleKey) -> Option<V> {
+        let idx = self.entries.iter().position(|(other, _)| other == key);
+        match idx {
+            Some(_) => self.stats.hits += 1,
+            None => self.stats.misses += 1,
+        }
+        self.entries.remove(idx?).map(|(_, value)| value)
+    }
+
+    /// Forget all the values, e.g. when the font rendering changed.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    pub fn stats(&self) -> TitleCacheStats {
+        self.stats
+    }
+}
+
+#[test]
+fn title_cache_evicts_least_recently_used() {
+    let key = |scale: u32| TitleKey::new(scale.into(), Color::BLACK, "Title");
+    let mut cache = TitleCache::default();
+    for scale in 0..CAPACITY as u32 {
+        assert_eq!(cache.insert(key(scale), scale), None);
+    }
+
+    assert_eq!(cache.take(&key(0)), Some(0));
+    assert_eq!(cache.insert(key(0), 0), None);
+    assert_eq!(cache.insert(key(CAPACITY as u32), CAPACITY as u32), Some(1));
+    assert_eq!(cache.take(&key(1)), None);
+    assert_ne!(key(2), TitleKey::new(2.into(), Color::WHITE, "Title"));
+    assert_ne!(key(2), TitleKey::new(2.into(), Color::BLACK, "Other"));
+
+    let stats = cache.stats();
+    assert_eq!((stats.hits, stats.misses, stats.evictions), (1, 1, 1));
+}
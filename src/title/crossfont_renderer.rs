@@ -3,20 +3,33 @@ use std::mem;
 use crossfont::{GlyphKey, Rasterize, RasterizedGlyph};
 use tiny_skia::{Color, Pixmap, PixmapPaint, PixmapRef, Transform};
 
-use crate::title::config;
+use crate::title::{
+    bidi,
+    cache::{TitleCache, TitleKey},
+    config, reuse_pixmap, TextRendering, TitleCacheStats, TitleScale,
+};
 
 pub struct CrossfontTitleText {
     title: String,
 
     font_desc: crossfont::FontDesc,
     font_key: crossfont::FontKey,
+    /// The font size at the scale 1.
+    original_size: f32,
     size: crossfont::Size,
-    scale: u32,
+    scale: TitleScale,
     metrics: crossfont::Metrics,
     rasterizer: crossfont::Rasterizer,
     color: Color,
+    /// The width available for the title in pixels.
+    max_width: Option<u32>,
+    /// The width of the title without the ellipsization.
+    natural_width: i32,
 
     pixmap: Option<Pixmap>,
+    /// The titles rendered at the other scales, in the other colors or with the other texts,
+    /// with their natural and maximum widths.
+    cache: TitleCache<(Option<Pixmap>, i32, Option<u32>)>,
 }
 
 impl std::fmt::Debug for CrossfontTitleText {
@@ -33,7 +46,7 @@ impl std::fmt::Debug for CrossfontTitleText {
 }
 
 impl CrossfontTitleText {
-    pub fn new(color: Color) -> Result<Self, crossfont::Error> {
+    pub fn new(color: Color, size_factor: f32) -> Result<Self, crossfont::Error> {
         let title = "".into();
 
         let font_pref = config::titlebar_font().unwrap_or_default();
@@ -47,7 +60,8 @@ impl CrossfontTitleText {
         let font_desc = crossfont::FontDesc::new(&font_pref.name, font_style);
 
         let mut rasterizer = crossfont::Rasterizer::new()?;
-        let size = crossfont::Size::new(font_pref.pt_size);
+        let original_size = font_pref.pt_size * size_factor;
+        let size = crossfont::Size::new(original_size);
         let font_key = rasterizer.load_font(&font_desc, size)?;
 
         // Need to load at least one glyph for the face before calling metrics.
@@ -66,11 +80,15 @@ impl CrossfontTitleText {
             rasterizer,
             font_desc,
             font_key,
-            scale: 1,
+            scale: 1.into(),
             metrics,
             title,
             color,
+            max_width: None,
+            natural_width: 0,
+            original_size,
             size,
+            cache: TitleCache::default(),
         };
 
         this.rerender();
@@ -88,46 +106,147 @@ impl CrossfontTitleText {
         Ok(())
     }
 
-    pub fn update_scale(&mut self, scale: u32) {
+    pub fn update_scale(&mut self, scale: TitleScale) {
+        let old_key = self.key();
         let old_scale = mem::replace(&mut self.scale, scale);
         if old_scale != self.scale {
-            self.size = self.size.scale(self.scale as f32 / old_scale as f32);
+            // Scaled from the original size, the rounding of the sizes doesn't add up.
+            self.size = crossfont::Size::new(self.original_size * self.scale.factor());
             self.update_metrics().ok();
-            self.rerender();
+            self.switch(old_key);
         }
     }
 
     pub fn update_title<S: Into<String>>(&mut self, title: S) {
         let title = title.into();
         if self.title != title {
+            let old_key = self.key();
             self.title = title;
-            self.rerender();
+            self.switch(old_key);
         }
     }
 
     pub fn update_color(&mut self, color: Color) {
         if self.color != color {
+            let old_key = self.key();
             self.color = color;
+            self.switch(old_key);
+        }
+    }
+
+    pub fn cache_stats(&self) -> TitleCacheStats {
+        self.cache.stats()
+    }
+
+    fn key(&self) -> TitleKey {
+        TitleKey::new(self.scale, self.color, &self.title)
+    }
+
+    /// Show the title cached for the current key, or render it, keeping the one rendered for
+    /// the `old_key` in the cache.
+    fn switch(&mut self, old_key: TitleKey) {
+        let rendered = (self.pixmap.take(), self.natural_width, self.max_width);
+        let evicted = self.cache.insert(old_key, rendered);
+        match self.cache.take(&self.key()) {
+            Some((pixmap, natural_width, max_width)) => {
+                self.pixmap = pixmap;
+                self.natural_width = natural_width;
+                self.max_width = max_width;
+            }
+            None => {
+                // Render into the allocation of the evicted title.
+                if let Some((pixmap, ..)) = evicted {
+                    self.pixmap = pixmap;
+                }
+                self.rerender();
+            }
+        }
+    }
+
+    pub fn update_max_width(&mut self, max_width: Option<u32>) {
+        if max_width == self.max_width {
+            return;
+        }
+
+        let old_max_width = mem::replace(&mut self.max_width, max_width);
+
+        // Only re-render if the title doesn't fit in one of the widths.
+        let fits = |max_width: Option<u32>| {
+            max_width.map_or(true, |max_width| self.natural_width <= max_width as i32)
+        };
+        if !fits(old_max_width) || !fits(max_width) {
             self.rerender();
         }
     }
 
+    pub fn is_truncated(&self) -> bool {
+        self.max_width
+            .is_some_and(|max_width| self.natural_width > max_width as i32)
+    }
+
+    fn rasterize_glyph(&mut self, character: char) -> Option<(GlyphKey, RasterizedGlyph)> {
+        let key = GlyphKey {
+            character,
+            font_key: self.font_key,
+            size: self.size,
+        };
+
+        self.rasterizer
+            .get_glyph(key)
+            .map(|glyph| (key, glyph))
+            .ok()
+    }
+
+    /// Truncate the glyphs at the glyph boundary and add the ellipsis at the logical end of the
+    /// title, so they fit into the `max_width`.
+    fn ellipsize(
+        &mut self,
+        glyphs: &mut Vec<(GlyphKey, RasterizedGlyph)>,
+        max_width: i32,
+        rtl: bool,
+    ) {
+        let ellipsis = match self.rasterize_glyph('…') {
+            Some(ellipsis) => vec![ellipsis],
+            None => self
+                .rasterize_glyph('.')
+                .map(|dot| vec![dot; 3])
+                .unwrap_or_default(),
+        };
+        let ellipsis_width = self.calc_width(&ellipsis);
+
+        // The logical end of the right-to-left title is on the left.
+        if rtl {
+            while let Some((key, _)) = glyphs.first() {
+                if !key.character.is_whitespace()
+                    && self.calc_width(glyphs) + ellipsis_width <= max_width
+                {
+                    break;
+                }
+                glyphs.remove(0);
+            }
+
+            glyphs.splice(0..0, ellipsis);
+            return;
+        }
+
+        // Drop the glyphs until the ellipsis fits, and don't leave the whitespace before it.
+        while let Some((key, _)) = glyphs.last() {
+            if !key.character.is_whitespace()
+                && self.calc_width(glyphs) + ellipsis_width <= max_width
+            {
+                break;
+            }
+            glyphs.pop();
+        }
+
+        glyphs.extend(ellipsis);
+    }
+
     fn rerender(&mut self) {
-        let glyphs: Vec<_> = self
-            .title
+        let (title, rtl) = bidi::visual_order(&self.title);
+        let mut glyphs: Vec<_> = title
             .chars()
-            .filter_map(|character| {
-                let key = GlyphKey {
-                    character,
-                    font_key: self.font_key,
-                    size: self.size,
-                };
-
-                self.rasterizer
-                    .get_glyph(key)
-                    .map(|glyph| (key, glyph))
-                    .ok()
-            })
+            .filter_map(|character| self.rasterize_glyph(character))
             .collect();
 
         if glyphs.is_empty() {
@@ -135,22 +254,31 @@ impl CrossfontTitleText {
             return;
         }
 
+        self.natural_width = self.calc_width(&glyphs);
+        if let Some(max_width) = self.max_width.map(|max_width| max_width as i32) {
+            if self.natural_width > max_width {
+                self.ellipsize(&mut glyphs, max_width, rtl);
+            }
+        }
+
         let width = self.calc_width(&glyphs);
         let height = self.metrics.line_height.round() as i32;
 
-        let mut pixmap = if let Some(p) = Pixmap::new(width as u32, height as u32) {
-            p
-        } else {
-            self.pixmap = None;
-            return;
-        };
+        let mut pixmap =
+            if let Some(p) = reuse_pixmap(self.pixmap.take(), width as u32, height as u32) {
+                p
+            } else {
+                return;
+            };
         // pixmap.fill(Color::from_rgba8(255, 0, 0, 55));
 
         let mut caret = 0;
         let mut last_glyph = None;
 
+        // Shared by the glyphs to allocate only for the largest one.
+        let mut buffer = Vec::new();
         for (key, glyph) in glyphs {
-            let mut buffer = Vec::with_capacity(glyph.width as usize * 4);
+            buffer.clear();
 
             let glyph_buffer = match &glyph.buffer {
                 crossfont::BitmapBuffer::Rgb(v) => v.chunks(3),
@@ -203,10 +331,18 @@ impl CrossfontTitleText {
         self.pixmap = Some(pixmap);
     }
 
+    /// The rasterizer follows the fontconfig hinting of the font itself, and the subpixel
+    /// glyphs are flattened to the grayscale.
+    pub fn update_rendering(&mut self, _rendering: TextRendering) {}
+
     pub fn pixmap(&self) -> Option<&Pixmap> {
         self.pixmap.as_ref()
     }
 
+    pub fn subpixel_filter(&self) -> Option<&Pixmap> {
+        None
+    }
+
     fn calc_width(&mut self, glyphs: &[(GlyphKey, RasterizedGlyph)]) -> i32 {
         let mut caret = 0;
         let mut last_glyph: Option<&GlyphKey> = None;
@@ -1,16 +1,34 @@
 use tiny_skia::{Color, Pixmap};
 
+use crate::title::{TextRendering, TitleCacheStats, TitleScale};
+
 #[derive(Debug)]
 pub struct DumbTitleText {}
 
 impl DumbTitleText {
-    pub fn update_scale(&mut self, _scale: u32) {}
+    pub fn update_scale(&mut self, _scale: TitleScale) {}
 
     pub fn update_title<S: Into<String>>(&mut self, _title: S) {}
 
     pub fn update_color(&mut self, _color: Color) {}
 
+    pub fn update_max_width(&mut self, _max_width: Option<u32>) {}
+
+    pub fn is_truncated(&self) -> bool {
+        false
+    }
+
+    pub fn update_rendering(&mut self, _rendering: TextRendering) {}
+
     pub fn pixmap(&self) -> Option<&Pixmap> {
         None
     }
+
+    pub fn subpixel_filter(&self) -> Option<&Pixmap> {
+        None
+    }
+
+    pub fn cache_stats(&self) -> TitleCacheStats {
+        TitleCacheStats::default()
+    }
 }
@@ -1,4 +1,4 @@
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub(crate) struct FontPreference {
     pub name: String,
     pub style: Option<String>,
@@ -15,39 +15,107 @@ impl Default for FontPreference {
     }
 }
 
+/// A word of the style in the Pango font descriptions.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum StyleWord {
+    /// The weight on the CSS scale, like the `wght` axis.
+    Weight(f32),
+    Italic,
+    Stretch,
+}
+
+/// Parse the style word, ignoring the case and the hyphens like in `Semi-Bold`.
+fn style_word(word: &str) -> Option<StyleWord> {
+    Some(match fontconfig_constant(word).as_str() {
+        "thin" => StyleWord::Weight(100.),
+        "ultralight" | "extralight" => StyleWord::Weight(200.),
+        "light" => StyleWord::Weight(300.),
+        "semilight" | "demilight" => StyleWord::Weight(350.),
+        "book" => StyleWord::Weight(380.),
+        "regular" | "normal" => StyleWord::Weight(400.),
+        "medium" => StyleWord::Weight(500.),
+        "semibold" | "demibold" => StyleWord::Weight(600.),
+        "bold" => StyleWord::Weight(700.),
+        "ultrabold" | "extrabold" => StyleWord::Weight(800.),
+        "heavy" | "black" => StyleWord::Weight(900.),
+        "ultrablack" | "extrablack" => StyleWord::Weight(1000.),
+        "italic" | "oblique" => StyleWord::Italic,
+        "ultracondensed" | "extracondensed" | "condensed" | "semicondensed" | "semiexpanded"
+        | "expanded" | "extraexpanded" | "ultraexpanded" => StyleWord::Stretch,
+        _ => return None,
+    })
+}
+
+/// The style word as the fontconfig constant, `Semi-Bold` is `semibold`.
+fn fontconfig_constant(word: &str) -> String {
+    word.chars()
+        .filter(|c| *c != '-')
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
 impl FontPreference {
-    /// Parse config string like `Cantarell 12`, `Cantarell Bold 11`, `Noto Serif CJK HK Bold 12`.
+    /// Parse config string like `Cantarell 12`, `Cantarell Bold 11`, `Noto Serif CJK HK Bold 12`,
+    /// `Cantarell Semi-Bold Italic 11`.
     pub fn from_name_style_size(conf: &str) -> Option<Self> {
-        // assume last is size, 2nd last is style and the rest is name.
-        match conf.rsplit_once(' ') {
+        // assume last is size, the trailing style words are the style and the rest is name.
+        let (head, pt_size) = match conf.rsplit_once(' ') {
             Some((head, tail)) if tail.chars().all(|c| c.is_numeric()) => {
-                let pt_size: f32 = tail.parse().unwrap_or(10.0);
-                match head.rsplit_once(' ') {
-                    Some((name, style)) if !name.is_empty() => Some(Self {
-                        name: name.into(),
-                        style: Some(style.into()),
-                        pt_size,
-                    }),
-                    None if !head.is_empty() => Some(Self {
-                        name: head.into(),
-                        style: None,
-                        pt_size,
-                    }),
-                    _ => None,
-                }
+                (head, tail.parse().unwrap_or(10.0))
             }
-            Some((head, tail)) if !head.is_empty() => Some(Self {
-                name: head.into(),
-                style: Some(tail.into()),
-                pt_size: 10.0,
-            }),
-            None if !conf.is_empty() => Some(Self {
-                name: conf.into(),
-                style: None,
-                pt_size: 10.0,
-            }),
-            _ => None,
+            _ => (conf, 10.0),
+        };
+
+        let words: Vec<&str> = head.split(' ').collect();
+        let name_len = words
+            .iter()
+            .rposition(|word| style_word(word).is_none())
+            .map_or(1, |idx| idx + 1);
+        let (name, style) = words.split_at(name_len);
+        let name = name.join(" ");
+        if name.is_empty() {
+            return None;
+        }
+
+        Some(Self {
+            name,
+            style: (!style.is_empty()).then(|| style.join(" ")),
+            pt_size,
+        })
+    }
+}
+
+/// The style of the font the ab_glyph renderer resolves itself.
+#[cfg_attr(feature = "crossfont", allow(dead_code))]
+impl FontPreference {
+    fn style_words(&self) -> impl Iterator<Item = StyleWord> + '_ {
+        self.style
+            .iter()
+            .flat_map(|style| style.split(' '))
+            .filter_map(style_word)
+    }
+
+    /// The weight of the style on the CSS scale, `None` for the default one.
+    pub fn weight(&self) -> Option<f32> {
+        self.style_words().fold(None, |weight, word| match word {
+            StyleWord::Weight(word) => Some(word),
+            _ => weight,
+        })
+    }
+
+    /// Whether the style is italic or oblique.
+    pub fn italic(&self) -> bool {
+        self.style_words().any(|word| word == StyleWord::Italic)
+    }
+
+    /// The fontconfig pattern of the font, like `Cantarell:semibold:italic`.
+    pub fn fontconfig_pattern(&self) -> String {
+        let mut pattern = self.name.clone();
+        for word in self.style.iter().flat_map(|style| style.split(' ')) {
+            pattern.push(':');
+            pattern.push_str(&fontconfig_constant(word));
         }
+        pattern
     }
 }
 
@@ -67,6 +135,17 @@ fn pref_from_name_variant_size() {
     assert!((pref.pt_size - 12.0).abs() < f32::EPSILON);
 }
 
+#[test]
+fn pref_from_name_multi_style_size() {
+    let pref = FontPreference::from_name_style_size("Noto Sans Semi-Bold Italic 11").unwrap();
+    assert_eq!(pref.name, "Noto Sans");
+    assert_eq!(pref.style, Some("Semi-Bold Italic".into()));
+    assert!((pref.pt_size - 11.0).abs() < f32::EPSILON);
+    assert_eq!(pref.weight(), Some(600.));
+    assert!(pref.italic());
+    assert_eq!(pref.fontconfig_pattern(), "Noto Sans:semibold:italic");
+}
+
 #[test]
 fn pref_from_name_size() {
     let pref = FontPreference::from_name_style_size("Cantarell 12").unwrap();
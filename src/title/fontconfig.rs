@@ -0,0 +1,210 @@
+//! Font matching without the fontconfig tools.
+//!
+//! Reads the fontconfig configuration for the font directories and the family aliases, and
+//! picks the face of the font files in them closest to the [`FontPreference`]. Used when
+//! `fc-match` isn't installed.
+use crate::title::font_preference::FontPreference;
+use fontconfig_parser::FontConfig;
+use std::{
+    fs::{self, File},
+    path::{Path, PathBuf},
+};
+
+/// The depth of the font directories searched for the font files.
+const MAX_DEPTH: usize = 8;
+
+/// The family every other one falls back to.
+const DEFAULT_FAMILY: &str = "sans-serif";
+
+/// The face of the font file matching the preference.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MatchedFace {
+    pub path: PathBuf,
+    /// The index of the face in the font collection.
+    pub index: u32,
+    pub italic: bool,
+}
+
+/// Load the system fontconfig configuration, `None` when it's missing.
+fn system_config() -> Option<FontConfig> {
+    let path = std::env::var_os("FONTCONFIG_FILE")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from("/etc/fonts/fonts.conf"));
+
+    let mut config = FontConfig::default();
+    if let Err(err) = config.merge_config(&path) {
+        log::warn!("failed to read the fontconfig configuration {path:?}: {err}");
+        return None;
+    }
+    Some(config)
+}
+
+/// Match the preference against the fonts of the system fontconfig configuration.
+pub fn font_file_matching(pref: &FontPreference) -> Option<(File, u32, bool)> {
+    let face = match_face(&system_config()?, pref)?;
+    Some((File::open(face.path).ok()?, face.index, face.italic))
+}
+
+/// The families to look for in the order of preference: the preferred aliases of the family,
+/// the family itself, its accepted and default aliases, then the default families.
+fn families(config: &FontConfig, name: &str) -> Vec<String> {
+    let mut families: Vec<String> = Vec::new();
+    let mut push = |family: &String| {
+        if !families.iter().any(|f| f.eq_ignore_ascii_case(family)) {
+            families.push(family.clone());
+        }
+    };
+
+    for family in [name, DEFAULT_FAMILY] {
+        let aliases: Vec<_> = config
+            .aliases
+            .iter()
+            .filter(|alias| alias.alias.eq_ignore_ascii_case(family))
+            .collect();
+        aliases
+            .iter()
+            .flat_map(|alias| &alias.prefer)
+            .for_each(&mut push);
+        push(&family.to_owned());
+        aliases
+            .iter()
+            .flat_map(|alias| alias.accept.iter().chain(&alias.default))
+            .for_each(&mut push);
+    }
+    families
+}
+
+/// Pick the face closest to the preference in the font directories of the `config`.
+fn match_face(config: &FontConfig, pref: &FontPreference) -> Option<MatchedFace> {
+    let families = families(config, &pref.name);
+    let weight = pref.weight().unwrap_or(400.);
+    let italic = pref.italic();
+
+    let mut files = Vec::new();
+    for dir in &config.dirs {
+        font_files(&dir.path, MAX_DEPTH, &mut files);
+    }
+
+    // Ranked by the family first, then by the style.
+    let mut best: Option<((usize, f32), MatchedFace)> = None;
+    for path in files {
+        let Ok(file) = File::open(&path) else {
+            continue;
+        };
+        // Safety: System font files are not expected to be mutated during use
+        let Ok(data) = (unsafe { memmap2::Mmap::map(&file) }) else {
+            continue;
+        };
+
+        for index in 0..ttf_parser::fonts_in_collection(&data).unwrap_or(1) {
+            let Ok(face) = ttf_parser::Face::parse(&data, index) else {
+                continue;
+            };
+            let Some(rank) = family_names(&face)
+                .filter_map(|name| families.iter().position(|f| f.eq_ignore_ascii_case(&name)))
+                .min()
+            else {
+                continue;
+            };
+
+            let face_italic = face.is_italic() || face.is_oblique();
+            let distance = (f32::from(face.weight().to_number()) - weight).abs()
+                + if face_italic == italic { 0. } else { 1000. }
+                // Prefer the normal width between the faces of the same weight.
+                + if face.width() == ttf_parser::Width::Normal { 0. } else { 1. };
+            if best
+                .as_ref()
+                .map_or(true, |(score, _)| (rank, distance) < *score)
+            {
+                let face = MatchedFace {
+                    path: path.clone(),
+                    index,
+                    italic: face_italic,
+                };
+                best = Some(((rank, distance), face));
+            }
+        }
+    }
+
+    best.map(|(_, face)| face)
+}
+
+/// The family names of the face, the typographic ones and the legacy ones.
+fn family_names<'a>(face: &ttf_parser::Face<'a>) -> impl Iterator<Item = String> + 'a {
+    face.names().into_iter().filter_map(|name| {
+        let family = matches!(
+            name.name_id,
+            ttf_parser::name_id::FAMILY | ttf_parser::name_id::TYPOGRAPHIC_FAMILY
+        );
+        family.then(|| name.to_string()).flatten()
+    })
+}
+
+/// Collect the font files in the `dir` and its subdirectories.
+fn font_files(dir: &Path, depth: usize, files: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            if depth > 0 {
+                font_files(&path, depth - 1, files);
+            }
+        } else if path.extension().is_some_and(|extension| {
+            ["ttf", "otf", "ttc", "otc"]
+                .iter()
+                .any(|font| extension.eq_ignore_ascii_case(font))
+        }) {
+            files.push(path);
+        }
+    }
+}
+
+#[test]
+fn match_face_in_font_dirs() {
+    use fontconfig_parser::{Alias, DirData};
+
+    let dir = std::env::temp_dir().join(format!("sctk-adwaita-fonts-{}", std::process::id()));
+    let fonts = dir.join("cantarell");
+    #[allow(clippy::unwrap_used)]
+    fs::create_dir_all(&fonts).unwrap();
+    #[allow(clippy::unwrap_used)]
+    fs::write(
+        fonts.join("Cantarell-Regular.ttf"),
+        include_bytes!("Cantarell-Regular.ttf"),
+    )
+    .unwrap();
+
+    let config = FontConfig {
+        dirs: vec![DirData {
+            path: dir.clone(),
+            salt: String::new(),
+        }],
+        aliases: vec![Alias {
+            alias: DEFAULT_FAMILY.into(),
+            prefer: vec!["Cantarell".into()],
+            ..Alias::default()
+        }],
+        ..FontConfig::default()
+    };
+
+    let pref = |conf: &str| FontPreference::from_name_style_size(conf).unwrap_or_default();
+    let expected = MatchedFace {
+        path: fonts.join("Cantarell-Regular.ttf"),
+        index: 0,
+        italic: false,
+    };
+    assert_eq!(
+        match_face(&config, &pref("Cantarell 11")),
+        Some(expected.clone())
+    );
+    // The missing family falls back to the default one.
+    assert_eq!(
+        match_face(&config, &pref("Missing Italic 11")),
+        Some(expected)
+    );
+
+    let _ = fs::remove_dir_all(dir);
+}
@@ -0,0 +1,132 @@
+//! Pair kerning of the titles laid out without shaping, read from the GPOS table.
+use ttf_parser::{
+    gpos::{PairAdjustment, PositioningSubtable},
+    opentype_layout::Lookup,
+    Face, GlyphId, Tag,
+};
+
+/// The pair adjustment lookups of the `kern` feature of the font, of all the scripts.
+#[derive(Debug)]
+pub struct PairKerning<'a> {
+    lookups: Vec<Lookup<'a>>,
+}
+
+impl<'a> PairKerning<'a> {
+    /// Returns `None` when the font has no `kern` feature in its GPOS table, so the legacy
+    /// `kern` table should be used instead.
+    pub fn new(face: &Face<'a>) -> Option<Self> {
+        let gpos = face.tables().gpos?;
+        let mut indices: Vec<u16> = gpos
+            .features
+            .into_iter()
+            .filter(|feature| feature.tag == Tag::from_bytes(b"kern"))
+            .flat_map(|feature| feature.lookup_indices)
+            .collect();
+        indices.sort_unstable();
+        indices.dedup();
+
+        let lookups: Vec<_> = indices
+            .into_iter()
+            .filter_map(|index| gpos.lookups.get(index))
+            .collect();
+        (!lookups.is_empty()).then_some(Self { lookups })
+    }
+
+    /// The adjustment of the advance of the `first` glyph followed by the `second` one, in
+    /// font units.
+    pub fn kern(&self, first: u16, second: u16) -> f32 {
+        let (first, second) = (GlyphId(first), GlyphId(second));
+        self.lookups
+            .iter()
+            .filter_map(|lookup| {
+                lookup
+                    .subtables
+                    .into_iter::<PositioningSubtable>()
+                    .find_map(|subtable| match subtable {
+                        PositioningSubtable::Pair(pair) => pair_adjustment(&pair, first, second),
+                        _ => None,
+                    })
+            })
+            .map(f32::from)
+            .sum()
+    }
+}
+
+/// The `x_advance` adjustment of the first glyph of the pair, `None` when the subtable
+/// doesn't cover it.
+fn pair_adjustment(pair: &PairAdjustment<'_>, first: GlyphId, second: GlyphId) -> Option<i16> {
+    let index = pair.coverage().get(first)?;
+    let (record, _) = match pair {
+        PairAdjustment::Format1 { sets, .. } => sets.get(index)?.get(second)?,
+        PairAdjustment::Format2 {
+            classes, matrix, ..
+        } => matrix.get((classes.0.get(first), classes.1.get(second)))?,
+    };
+    Some(record.x_advance)
+}
+
+/// The GPOS table with the `kern` feature adjusting the advance of the `first` glyph followed
+/// by the `second` one.
+#[cfg(test)]
+pub fn pair_gpos(first: u16, second: u16, x_advance: i16) -> Vec<u8> {
+    #[rustfmt::skip]
+    let words = [
+        // The header, with the offsets of the script, the feature and the lookup lists.
+        1, 0, 10, 12, 26,
+        // The scripts.
+        0,
+        // The `kern` feature with the first lookup.
+        1, 0x6b65, 0x726e, 8, 0, 1, 0,
+        // The pair adjustment lookup.
+        1, 4, 2, 0, 1, 8,
+        // The pair set of the first glyph, adjusting its `x_advance`.
+        1, 12, 0x0004, 0, 1, 18,
+        1, 1, first,
+        1, second, x_advance as u16,
+    ];
+    words
+        .iter()
+        .flat_map(|word: &u16| word.to_be_bytes())
+        .collect()
+}
+
+/// The face of the `font` with its GPOS table replaced by the `gpos`.
+#[cfg(test)]
+pub fn face_with_gpos<'a>(font: &'a [u8], gpos: &'a [u8]) -> Face<'a> {
+    #[allow(clippy::unwrap_used)]
+    let raw = ttf_parser::RawFace::parse(font, 0).unwrap();
+    let table = |tag| raw.table(Tag::from_bytes(tag));
+    #[allow(clippy::unwrap_used)]
+    Face::from_raw_tables(ttf_parser::RawFaceTables {
+        head: table(b"head").unwrap(),
+        hhea: table(b"hhea").unwrap(),
+        maxp: table(b"maxp").unwrap(),
+        cmap: table(b"cmap"),
+        hmtx: table(b"hmtx"),
+        gpos: Some(gpos),
+        ..Default::default()
+    })
+    .unwrap()
+}
+
+#[cfg(test)]
+const CANTARELL: &[u8] = include_bytes!("Cantarell-Regular.ttf");
+
+#[test]
+fn kern_adjusts_the_pair() {
+    #[allow(clippy::unwrap_used)]
+    let face = Face::parse(CANTARELL, 0).unwrap();
+    #[allow(clippy::unwrap_used)]
+    let glyph = |c| face.glyph_index(c).unwrap().0;
+
+    // The bundled Cantarell has no GPOS table.
+    assert!(PairKerning::new(&face).is_none());
+
+    let gpos = pair_gpos(glyph('A'), glyph('V'), -100);
+    let face = face_with_gpos(CANTARELL, &gpos);
+    #[allow(clippy::unwrap_used)]
+    let kerning = PairKerning::new(&face).unwrap();
+    assert_eq!(kerning.kern(glyph('A'), glyph('V')), -100.);
+    assert_eq!(kerning.kern(glyph('V'), glyph('A')), 0.);
+    assert_eq!(kerning.kern(glyph('H'), glyph('V')), 0.);
+}
@@ -0,0 +1,177 @@
+//! OpenType shaping of the titles with `rustybuzz`.
+use rustybuzz::{Direction, Face, UnicodeBuffer};
+
+/// The shaped glyph with its position in font units.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ShapedGlyph {
+    pub id: u16,
+    /// The byte index of the first character of the glyph cluster in the shaped text.
+    pub cluster: usize,
+    /// The space to the following glyph, including the kerning.
+    pub x_advance: i32,
+    /// The offset of the glyph from its origin, like the one of the mark from its base.
+    pub x_offset: i32,
+    pub y_offset: i32,
+}
+
+/// Shape the `text` displayed in a single direction.
+///
+/// Returns the glyphs in the visual order, so the right-to-left ones are reversed.
+pub fn shape(face: &Face<'_>, text: &str, rtl: bool) -> Vec<ShapedGlyph> {
+    let mut buffer = UnicodeBuffer::new();
+    buffer.push_str(text);
+    buffer.set_direction(if rtl {
+        Direction::RightToLeft
+    } else {
+        Direction::LeftToRight
+    });
+    buffer.guess_segment_properties();
+
+    let shaped = rustybuzz::shape(face, &[], buffer);
+    shaped
+        .glyph_infos()
+        .iter()
+        .zip(shaped.glyph_positions())
+        .map(|(info, position)| ShapedGlyph {
+            id: info.glyph_id as u16,
+            cluster: info.cluster as usize,
+            x_advance: position.x_advance,
+            x_offset: position.x_offset,
+            y_offset: position.y_offset,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+const CANTARELL: &[u8] = include_bytes!("Cantarell-Regular.ttf");
+
+#[test]
+fn shape_follows_direction() {
+    #[allow(clippy::unwrap_used)]
+    let face = Face::from_slice(CANTARELL, 0).unwrap();
+
+    let clusters = |rtl| {
+        shape(&face, "office", rtl)
+            .iter()
+            .map(|glyph| glyph.cluster)
+            .collect::<Vec<_>>()
+    };
+
+    // The first character starts the first cluster, and the clusters of the right-to-left
+    // glyphs go from the end.
+    let ltr = clusters(false);
+    assert_eq!(ltr[0], 0);
+    assert!(ltr.windows(2).all(|w| w[0] < w[1]));
+    let rtl = clusters(true);
+    assert!(rtl.windows(2).all(|w| w[0] > w[1]));
+}
+
+/// A font with the `A` and `V` glyphs, kerned by -80 units in the GPOS `kern` feature.
+#[cfg(test)]
+fn kerned_font() -> Vec<u8> {
+    fn be(words: &[u16]) -> Vec<u8> {
+        words.iter().flat_map(|word| word.to_be_bytes()).collect()
+    }
+
+    let mut head = vec![0; 54];
+    head[18..20].copy_from_slice(&1000u16.to_be_bytes());
+    let mut hhea = vec![0; 36];
+    hhea[34..36].copy_from_slice(&1u16.to_be_bytes());
+    let maxp = be(&[0, 0x5000, 3]);
+
+    // Format 6 subtable mapping `A` to 1 and `V` to 2.
+    let mut glyphs = vec![0; 22];
+    glyphs[0] = 1;
+    glyphs[21] = 2;
+    let mut cmap = be(&[0, 1, 0, 3, 0, 12, 6, 10 + 2 * 22, 0, u16::from(b'A'), 22]);
+    cmap.extend(be(&glyphs));
+
+    let gpos = be(&[
+        // Header with the script, feature and lookup lists.
+        1,
+        0,
+        10,
+        30,
+        44, //
+        // The `DFLT` script.
+        1,
+        0x4446,
+        0x4c54,
+        8, //
+        // Its default language system with the feature 0.
+        4,
+        0,
+        0,
+        0xffff,
+        1,
+        0, //
+        // The `kern` feature with the lookup 0.
+        1,
+        0x6b65,
+        0x726e,
+        8,
+        0,
+        1,
+        0, //
+        // The pair adjustment lookup.
+        1,
+        4,
+        2,
+        0,
+        1,
+        8, //
+        // Format 1 with the x advance of the first glyph.
+        1,
+        18,
+        4,
+        0,
+        1,
+        12, //
+        // The pair set of `A` kerned with `V`.
+        1,
+        2,
+        (-80i16) as u16, //
+        // The coverage of `A`.
+        1,
+        1,
+        1,
+    ]);
+
+    let tables: [(&[u8; 4], Vec<u8>); 5] = [
+        (b"GPOS", gpos),
+        (b"cmap", cmap),
+        (b"head", head),
+        (b"hhea", hhea),
+        (b"maxp", maxp),
+    ];
+    let mut data = be(&[1, 0, tables.len() as u16, 0, 0, 0]);
+    let mut offset = data.len() + 16 * tables.len();
+    for (tag, table) in &tables {
+        data.extend_from_slice(*tag);
+        data.extend(0u32.to_be_bytes());
+        data.extend((offset as u32).to_be_bytes());
+        data.extend((table.len() as u32).to_be_bytes());
+        offset += table.len();
+    }
+    for (_, table) in tables {
+        data.extend(table);
+    }
+    data
+}
+
+#[test]
+fn shape_applies_kerning() {
+    let font = kerned_font();
+    #[allow(clippy::unwrap_used)]
+    let face = Face::from_slice(&font, 0).unwrap();
+
+    let advances = |text: &str| {
+        shape(&face, text, false)
+            .iter()
+            .map(|glyph| glyph.x_advance)
+            .collect::<Vec<_>>()
+    };
+    let (av, va) = (advances("AV"), advances("VA"));
+    assert_eq!(av[0], va[1] - 80);
+    assert_eq!(av[1], va[0]);
+}
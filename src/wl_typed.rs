@@ -18,6 +18,7 @@ where
         Self(i, PhantomData)
     }
 
+    #[allow(dead_code)]
     pub fn inner(&self) -> &I {
         &self.0
     }